@@ -0,0 +1,148 @@
+use crate::config::Principal;
+use crate::http::Request;
+
+
+/// Finds the principal of a request by its Authorization header, either "Bearer <token>" or
+/// "Basic <base64 of client_id:client_secret>"
+pub struct Authenticator {
+    principals: Vec<Principal>,
+}
+
+
+impl Authenticator {
+    pub fn new(principals: Vec<Principal>) -> Authenticator {
+        Authenticator {
+            principals,
+        }
+    }
+
+    pub fn authenticate(&self, request: &Request) -> Option<&Principal> {
+        let authorization = request.get_header("authorization")?;
+        let (scheme, credentials) = authorization.split_once(' ')?;
+        let credentials = credentials.trim();
+
+        if scheme.eq_ignore_ascii_case("bearer") {
+            return self.principals.iter().find(|principal| {
+                principal.get_token().is_some_and(|token| is_equal(token.as_bytes(), credentials.as_bytes()))
+            });
+        }
+
+        if scheme.eq_ignore_ascii_case("basic") {
+            let decoded = String::from_utf8(decode_base64(credentials)?).ok()?;
+            let (client_id, client_secret) = decoded.split_once(':')?;
+
+            return self.principals.iter().find(|principal| match principal.get_client_credentials() {
+                Some((id, secret)) => id == client_id && is_equal(secret.as_bytes(), client_secret.as_bytes()),
+                None => false,
+            });
+        }
+
+        return None;
+    }
+}
+
+
+/// Takes as long for every secret of the same length, so that it can't be guessed byte by byte
+fn is_equal(expected: &[u8], actual: &[u8]) -> bool {
+    if expected.len() != actual.len() {
+        return false;
+    }
+
+    return expected.iter().zip(actual).fold(0, |difference, (a, b)| difference | (a ^ b)) == 0;
+}
+
+
+/// Standard alphabet with padding, as browsers and curl send it
+fn decode_base64(text: &str) -> Option<Vec<u8>> {
+    let text = text.trim_end_matches('=');
+    let mut decoded = Vec::with_capacity(text.len() * 3 / 4);
+    let mut buffer: u32 = 0;
+    let mut bits = 0;
+
+    for byte in text.bytes() {
+        let value = match byte {
+            b'A'..=b'Z' => byte - b'A',
+            b'a'..=b'z' => byte - b'a' + 26,
+            b'0'..=b'9' => byte - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            _ => return None,
+        };
+
+        buffer = (buffer << 6) | value as u32;
+        bits += 6;
+
+        if bits >= 8 {
+            bits -= 8;
+            decoded.push((buffer >> bits) as u8);
+            buffer &= (1 << bits) - 1;
+        }
+    }
+
+    Some(decoded)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::config::Configuration;
+    use super::*;
+
+    fn build_request(authorization: &str) -> Request {
+        Request {
+            method: String::from("GET"),
+            segments: Vec::new(),
+            query: Vec::new(),
+            headers: vec![(String::from("authorization"), String::from(authorization))],
+        }
+    }
+
+    fn build_authenticator() -> Authenticator {
+        let configuration = Configuration::from_string(String::from(r#"
+            [[principals]]
+            name = "hmi"
+            token = "hmi-token"
+            public_key = "hmi-public"
+            secret_key = "hmi-secret"
+
+            [[principals]]
+            client_id = "dashboard"
+            client_secret = "secret"
+            public_key = "dashboard-public"
+            secret_key = "dashboard-secret"
+        "#)).unwrap();
+
+        Authenticator::new(configuration.get_principals())
+    }
+
+    #[test]
+    fn authenticate_should_map_credentials_to_principal() {
+        let authenticator = build_authenticator();
+
+        let principal = authenticator.authenticate(&build_request("Bearer hmi-token")).unwrap();
+        assert_eq!(principal.get_name(), "hmi");
+
+        // "dashboard:secret"
+        let principal = authenticator.authenticate(&build_request("Basic ZGFzaGJvYXJkOnNlY3JldA==")).unwrap();
+        assert_eq!(principal.get_name(), "dashboard");
+    }
+
+    #[test]
+    fn authenticate_should_refuse_unknown_credentials() {
+        let authenticator = build_authenticator();
+
+        assert!(authenticator.authenticate(&build_request("Bearer other-token")).is_none());
+        assert!(authenticator.authenticate(&build_request("Bearer ")).is_none());
+        // "dashboard:other"
+        assert!(authenticator.authenticate(&build_request("Basic ZGFzaGJvYXJkOm90aGVy")).is_none());
+        assert!(authenticator.authenticate(&build_request("Basic !!")).is_none());
+        assert!(authenticator.authenticate(&Request { headers: Vec::new(), ..build_request("") }).is_none());
+    }
+
+    #[test]
+    fn decode_base64_should_handle_padding() {
+        assert_eq!(decode_base64("YQ==").unwrap(), b"a");
+        assert_eq!(decode_base64("YWI=").unwrap(), b"ab");
+        assert_eq!(decode_base64("YWJj").unwrap(), b"abc");
+    }
+}
@@ -0,0 +1,87 @@
+use std::collections::HashMap;
+use std::sync::{Mutex, MutexGuard};
+use tsm_ipc::{RpcClient, RpcError};
+
+use crate::config::Principal;
+use crate::routes::ClientSettings;
+
+
+/// More requests of one principal at the same time connect clients which are dropped afterwards
+const MAX_IDLE_CLIENTS: usize = 4;
+
+
+/// Clients of earlier requests by the public key of their principal, so that a request reuses the
+/// ZMQ context, the connection and the CURVE handshake of an earlier one
+pub struct ClientPool {
+    client_settings: ClientSettings,
+    idle: Mutex<HashMap<String, Vec<RpcClient>>>,
+}
+
+
+impl ClientPool {
+    pub fn new(client_settings: ClientSettings) -> ClientPool {
+        ClientPool {
+            client_settings,
+            idle: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// An idle client of the principal, or a new one
+    pub fn take(&self, principal: &Principal) -> Result<RpcClient, RpcError> {
+        let (public_key, _) = principal.get_curve_keys();
+
+        if let Some(rpc_client) = self.lock().get_mut(&public_key).and_then(Vec::pop) {
+            return Ok(rpc_client);
+        }
+
+        return self.client_settings.connect(principal);
+    }
+
+    /// A client whose request timed out already replaced its socket, so it is kept as well
+    pub fn give_back(&self, principal: &Principal, rpc_client: RpcClient) {
+        let (public_key, _) = principal.get_curve_keys();
+        let mut idle = self.lock();
+        let rpc_clients = idle.entry(public_key).or_default();
+
+        if rpc_clients.len() < MAX_IDLE_CLIENTS {
+            rpc_clients.push(rpc_client);
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<String, Vec<RpcClient>>> {
+        return self.idle.lock().unwrap_or_else(|error| error.into_inner());
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::config::Configuration;
+    use super::*;
+
+    #[test]
+    fn take_should_reuse_given_back_client() {
+        let configuration = Configuration::from_string(String::from(r#"
+            [[principals]]
+            token = "hmi-token"
+            public_key = "hmi-public"
+            secret_key = "hmi-secret"
+        "#)).unwrap();
+        let principal = &configuration.get_principals()[0];
+
+        let client_settings = ClientSettings {
+            address: format!("unix:///tmp/tsm-gateway-pool-test-{}.sock", std::process::id()),
+            timeout_ms: 100,
+            server_public_key: String::from("server-public"),
+        };
+
+        let pool = ClientPool::new(client_settings);
+        pool.give_back(principal, RpcClient::new(String::from("unix:///tmp/given-back.sock")).unwrap());
+        pool.give_back(principal, RpcClient::new(String::from("unix:///tmp/given-back.sock")).unwrap());
+
+        assert_eq!(pool.lock()["hmi-public"].len(), 2);
+        assert!(pool.take(principal).is_ok());
+        assert!(pool.take(principal).is_ok());
+        assert!(pool.lock()["hmi-public"].is_empty());
+    }
+}
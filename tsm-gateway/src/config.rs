@@ -0,0 +1,189 @@
+use std::fs;
+use std::path::Path;
+use serde::Deserialize;
+
+
+pub const DEFAULT_CONFIG_FILE: &str = "/etc/tsm-gateway.toml";
+
+
+/// Where the gateway listens, how it reaches the unit manager and who may use it
+#[derive(Deserialize, Debug, Default)]
+pub struct Configuration {
+    bind_address: Option<String>,
+    address: Option<String>,
+    events_address: Option<String>,
+    timeout_ms: Option<u32>,
+    log_level: Option<String>,
+    server_public_key: Option<String>,
+    allowed_origins: Option<Vec<String>>,
+    max_connections: Option<usize>,
+    max_event_streams: Option<usize>,
+    #[serde(default)]
+    principals: Vec<Principal>,
+}
+
+
+/// An HTTP client, known by its bearer token or its client id and secret. Its requests are sent
+/// with its own CURVE key pair, so that the access rules of the unit manager for that key apply.
+#[derive(Deserialize, Debug, Clone)]
+pub struct Principal {
+    name: Option<String>,
+    token: Option<String>,
+    client_id: Option<String>,
+    client_secret: Option<String>,
+    public_key: String,
+    secret_key: String,
+}
+
+
+impl Principal {
+    /// For the log, defaults to the client id or the public key
+    pub fn get_name(&self) -> String {
+        return self.name.clone()
+            .or(self.client_id.clone())
+            .unwrap_or(self.public_key.clone());
+    }
+
+    /// Empty tokens and secrets are treated as unset, so that they never match
+    pub fn get_token(&self) -> Option<String> {
+        return self.token.clone().filter(|token| !token.is_empty());
+    }
+
+    pub fn get_client_credentials(&self) -> Option<(String, String)> {
+        return match (&self.client_id, &self.client_secret) {
+            (Some(client_id), Some(client_secret)) if !client_secret.is_empty() => Some((client_id.clone(), client_secret.clone())),
+            _ => None,
+        };
+    }
+
+    /// Z85 encoded public and secret key
+    pub fn get_curve_keys(&self) -> (String, String) {
+        return (self.public_key.clone(), self.secret_key.clone());
+    }
+}
+
+
+impl Configuration {
+    pub fn from_file(file_path: String) -> Result<Configuration, String> {
+        return match fs::read_to_string(file_path) {
+            Ok(content) => {
+                Configuration::from_string(content)
+            },
+            Err(error) => {
+                Err(format!("Error reading configuration file: {}", error))
+            }
+        }
+    }
+
+    pub fn from_string(content: String) -> Result<Configuration, String> {
+        return match toml::from_str(&content) {
+            Ok(configuration) => {
+                Ok(configuration)
+            },
+            Err(error) => {
+                Err(format!("Error parsing configuration file: {}", error))
+            }
+        }
+    }
+
+    /// The given file must exist, without one the default file is read if it exists
+    pub fn load(file_path: String) -> Result<Configuration, String> {
+        if !file_path.is_empty() {
+            return Configuration::from_file(file_path);
+        }
+
+        return match Path::new(DEFAULT_CONFIG_FILE).exists() {
+            true => Configuration::from_file(String::from(DEFAULT_CONFIG_FILE)),
+            false => Ok(Configuration::default()),
+        };
+    }
+
+    /// Loopback only by default, tokens and client secrets are sent in the clear
+    pub fn get_bind_address(&self) -> String {
+        return self.bind_address.clone().unwrap_or("127.0.0.1:8080".to_string());
+    }
+
+    pub fn get_address(&self) -> String {
+        return self.address.clone().unwrap_or("ipc:///tmp/tsm-unitman.sock".to_string());
+    }
+
+    pub fn get_events_address(&self) -> String {
+        return self.events_address.clone().unwrap_or("ipc:///tmp/tsm-unitman-events.sock".to_string());
+    }
+
+    pub fn get_timeout_ms(&self) -> u32 {
+        return self.timeout_ms.unwrap_or(5000);
+    }
+
+    pub fn get_log_level(&self) -> String {
+        return self.log_level.clone().unwrap_or("warn".to_string());
+    }
+
+    /// Z85 encoded public key of the unit manager, required to send requests as the principals
+    pub fn get_server_public_key(&self) -> Option<String> {
+        return self.server_public_key.clone().filter(|server_public_key| !server_public_key.is_empty());
+    }
+
+    /// Origins besides the gateway itself which may POST, e.g. "https://dashboard.example.com"
+    pub fn get_allowed_origins(&self) -> Vec<String> {
+        return self.allowed_origins.clone().unwrap_or_default();
+    }
+
+    /// Connections served at the same time, event streams included
+    pub fn get_max_connections(&self) -> usize {
+        return self.max_connections.unwrap_or(64);
+    }
+
+    /// Event streams stay open, so they may only take part of the connections
+    pub fn get_max_event_streams(&self) -> usize {
+        return self.max_event_streams.unwrap_or(16);
+    }
+
+    pub fn get_principals(&self) -> Vec<Principal> {
+        return self.principals.clone();
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_string_should_fall_back_to_defaults() {
+        let configuration = Configuration::from_string(String::from(r#"
+            bind_address = "0.0.0.0:8081"
+            address = "unix:///run/tsm/unitman.sock"
+        "#)).unwrap();
+
+        assert_eq!(configuration.get_bind_address(), "0.0.0.0:8081");
+        assert_eq!(configuration.get_address(), "unix:///run/tsm/unitman.sock");
+        assert_eq!(configuration.get_events_address(), "ipc:///tmp/tsm-unitman-events.sock");
+        assert_eq!(configuration.get_timeout_ms(), 5000);
+        assert_eq!(configuration.get_server_public_key(), None);
+        assert_eq!(configuration.get_max_connections(), 64);
+        assert!(configuration.get_principals().is_empty());
+
+        assert!(Configuration::from_string(String::from("bind_address = 8080")).is_err());
+    }
+
+    #[test]
+    fn from_string_should_read_principals() {
+        let configuration = Configuration::from_string(String::from(r#"
+            [[principals]]
+            token = ""
+            client_id = "dashboard"
+            client_secret = "secret"
+            public_key = "public"
+            secret_key = "private"
+        "#)).unwrap();
+
+        let principal = &configuration.get_principals()[0];
+        assert_eq!(principal.get_name(), "dashboard");
+        assert_eq!(principal.get_token(), None);
+        assert_eq!(principal.get_client_credentials(), Some((String::from("dashboard"), String::from("secret"))));
+        assert_eq!(principal.get_curve_keys(), (String::from("public"), String::from("private")));
+
+        assert!(Configuration::from_string(String::from("[[principals]]\ntoken = \"a\"")).is_err());
+    }
+}
@@ -0,0 +1,94 @@
+use std::io::Write;
+use tsm_ipc::{EventSubscriber, tsm_unitman_event};
+
+use crate::http::write_event_stream_head;
+use crate::routes::to_json;
+
+
+/// Forwards the events of the unit manager as server-sent events, named after their topic with
+/// the event as JSON in the data line. Events of units which aren't visible are left out, those
+/// of no unit are always sent. A client that went away is noticed with the next event.
+pub fn stream_events(writer: &mut impl Write, subscriber: EventSubscriber, mut is_visible: impl FnMut(&str) -> bool) -> Result<(), String> {
+    if let Err(error) = write_event_stream_head(writer) {
+        return Err(format!("Failed to start event stream: {}", error));
+    }
+
+    loop {
+        let (topic, event): (String, tsm_unitman_event::Event) = subscriber.receive()?;
+
+        if !event.unit_name.is_empty() && !is_visible(&event.unit_name) {
+            continue;
+        }
+
+        // the JSON is printed on a single line, as a data line has to be
+        let message = format!("event: {}\ndata: {}\n\n", topic, to_json(&event)?);
+
+        if writer.write_all(message.as_bytes()).and_then(|_| writer.flush()).is_err() {
+            return Ok(());
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::thread;
+    use std::time::Duration;
+    use tsm_ipc::EventPublisher;
+    use super::*;
+
+    /// Takes what is written until it has the given number of events, then fails like a client
+    /// that went away
+    struct Client {
+        written: String,
+        events: usize,
+    }
+
+    impl Write for Client {
+        fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+            if self.written.matches("event:").count() >= self.events {
+                return Err(io::Error::from(io::ErrorKind::BrokenPipe));
+            }
+
+            self.written.push_str(&String::from_utf8_lossy(bytes));
+            Ok(bytes.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn stream_events_should_leave_out_invisible_units() {
+        let address = format!("unix:///tmp/tsm-gateway-event-stream-test-{}.sock", std::process::id());
+        let publisher = EventPublisher::new(address.clone()).unwrap();
+        let subscriber = EventSubscriber::new(address, Vec::new()).unwrap();
+
+        // published until streamed, the subscriber only gets events once it is connected
+        let streamed = Arc::new(AtomicBool::new(false));
+        let publishing = streamed.clone();
+        let publisher = thread::spawn(move || {
+            while !publishing.load(Ordering::Relaxed) {
+                for unit_name in ["network", "ui-main", ""] {
+                    let mut event = tsm_unitman_event::Event::new();
+                    event.unit_name = String::from(unit_name);
+                    event.set_unit_started(tsm_unitman_event::UnitStarted::new());
+                    publisher.publish("unit_started", &event).unwrap();
+                }
+                thread::sleep(Duration::from_millis(20));
+            }
+        });
+
+        let mut client = Client { written: String::new(), events: 4 };
+        stream_events(&mut client, subscriber, |unit_name| unit_name == "ui-main").unwrap();
+        streamed.store(true, Ordering::Relaxed);
+        publisher.join().unwrap();
+
+        assert!(client.written.contains("ui-main"));
+        assert!(!client.written.contains("network"));
+    }
+}
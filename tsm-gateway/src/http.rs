@@ -0,0 +1,265 @@
+use std::io::{BufRead, Read, Write};
+
+
+/// Request line and headers together, bodies are not used by any route
+const MAX_HEAD_SIZE: usize = 8 * 1024;
+const MAX_BODY_SIZE: usize = 64 * 1024;
+
+
+/// The parts of an HTTP/1.1 request the routes look at
+#[derive(Debug, Clone, PartialEq)]
+pub struct Request {
+    pub method: String,
+    /// Percent-decoded segments of the path, without empty ones
+    pub segments: Vec<String>,
+    pub query: Vec<(String, String)>,
+    /// Names in lower case, values without surrounding whitespace
+    pub headers: Vec<(String, String)>,
+}
+
+
+impl Request {
+    pub fn get_query(&self, name: &str) -> Option<&str> {
+        return self.query.iter().find(|(key, _)| key == name).map(|(_, value)| value.as_str());
+    }
+
+    /// The first header with the given lower case name
+    pub fn get_header(&self, name: &str) -> Option<&str> {
+        return self.headers.iter().find(|(key, _)| key == name).map(|(_, value)| value.as_str());
+    }
+}
+
+
+pub struct Response {
+    status: u16,
+    content_type: &'static str,
+    headers: Vec<(&'static str, String)>,
+    body: Vec<u8>,
+}
+
+
+impl Response {
+    pub fn json(status: u16, body: String) -> Response {
+        Response {
+            status,
+            content_type: "application/json",
+            headers: Vec::new(),
+            body: body.into_bytes(),
+        }
+    }
+
+    pub fn with_header(mut self, name: &'static str, value: String) -> Response {
+        self.headers.push((name, value));
+        self
+    }
+
+    pub fn get_status(&self) -> u16 {
+        return self.status;
+    }
+
+    /// Every response closes the connection, so the body needs no framing beyond its length
+    pub fn write_to(&self, writer: &mut impl Write) -> std::io::Result<()> {
+        let headers: String = self.headers.iter().map(|(name, value)| format!("{}: {}\r\n", name, value)).collect();
+        let head = format!(
+            "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\n{}Connection: close\r\n\r\n",
+            self.status, reason_phrase(self.status), self.content_type, self.body.len(), headers
+        );
+
+        writer.write_all(head.as_bytes())?;
+        writer.write_all(&self.body)?;
+        writer.flush()
+    }
+}
+
+
+/// Head of a server-sent events stream, the events follow until the client goes away
+pub fn write_event_stream_head(writer: &mut impl Write) -> std::io::Result<()> {
+    writer.write_all(b"HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-cache\r\nConnection: close\r\n\r\n")?;
+    writer.flush()
+}
+
+
+/// Reads the head and skips the body. Errors are meant to be answered with 400.
+pub fn read_request(reader: &mut impl BufRead) -> Result<Request, String> {
+    let mut head = Vec::new();
+
+    loop {
+        let mut line = Vec::new();
+        let read = match reader.by_ref().take((MAX_HEAD_SIZE + 1 - head.len()) as u64).read_until(b'\n', &mut line) {
+            Ok(read) => read,
+            Err(error) => return Err(format!("Failed to read request: {}", error)),
+        };
+
+        if read == 0 {
+            return Err(String::from("Connection closed before end of request head"));
+        }
+
+        head.extend_from_slice(&line);
+        if head.len() > MAX_HEAD_SIZE {
+            return Err(format!("Request head exceeds {} bytes", MAX_HEAD_SIZE));
+        }
+
+        if line == b"\r\n" || line == b"\n" {
+            break;
+        }
+    }
+
+    let head = match String::from_utf8(head) {
+        Ok(head) => head,
+        Err(_) => return Err(String::from("Request head is not valid UTF-8")),
+    };
+
+    let mut lines = head.lines();
+    let request_line = lines.next().unwrap_or_default();
+
+    let (method, target) = match request_line.split(' ').collect::<Vec<&str>>().as_slice() {
+        [method, target, version] if version.starts_with("HTTP/1.") => (String::from(*method), *target),
+        _ => return Err(format!("Invalid request line: {}", request_line)),
+    };
+
+    let headers: Vec<(String, String)> = lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(name, value)| (name.trim().to_ascii_lowercase(), String::from(value.trim())))
+        .collect();
+
+    let content_length = headers.iter()
+        .find(|(name, _)| name == "content-length")
+        .map(|(_, value)| value.parse::<usize>());
+
+    let content_length = match content_length {
+        None => 0,
+        Some(Ok(length)) if length <= MAX_BODY_SIZE => length,
+        Some(_) => return Err(String::from("Invalid or too large Content-Length")),
+    };
+
+    let mut body = vec![0u8; content_length];
+    if let Err(error) = reader.read_exact(&mut body) {
+        return Err(format!("Failed to read request body: {}", error));
+    }
+
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+
+    let segments = path.split('/')
+        .filter(|segment| !segment.is_empty())
+        .map(percent_decode)
+        .collect::<Result<Vec<String>, String>>()?;
+
+    let query = query.split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            Ok((percent_decode(key)?, percent_decode(value)?))
+        })
+        .collect::<Result<Vec<(String, String)>, String>>()?;
+
+    Ok(Request {
+        method,
+        segments,
+        query,
+        headers,
+    })
+}
+
+
+fn percent_decode(text: &str) -> Result<String, String> {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+
+    while index < bytes.len() {
+        match bytes[index] {
+            b'%' => {
+                let hex = bytes.get(index + 1..index + 3).and_then(|hex| std::str::from_utf8(hex).ok());
+                match hex.and_then(|hex| u8::from_str_radix(hex, 16).ok()) {
+                    Some(byte) => decoded.push(byte),
+                    None => return Err(format!("Invalid percent encoding in {}", text)),
+                };
+                index += 3;
+            },
+            b'+' => {
+                decoded.push(b' ');
+                index += 1;
+            },
+            byte => {
+                decoded.push(byte);
+                index += 1;
+            },
+        };
+    }
+
+    return match String::from_utf8(decoded) {
+        Ok(decoded) => Ok(decoded),
+        Err(_) => Err(format!("Invalid UTF-8 in {}", text)),
+    };
+}
+
+
+fn reason_phrase(status: u16) -> &'static str {
+    return match status {
+        200 => "OK",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        409 => "Conflict",
+        415 => "Unsupported Media Type",
+        500 => "Internal Server Error",
+        501 => "Not Implemented",
+        502 => "Bad Gateway",
+        503 => "Service Unavailable",
+        504 => "Gateway Timeout",
+        _ => "Unknown",
+    };
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_request_should_split_path_and_query() {
+        let mut reader = "GET /units/ui%2Dmain/?lines=20&x HTTP/1.1\r\nHost: localhost\r\nX-Test:  a: b \r\n\r\n".as_bytes();
+
+        let request = read_request(&mut reader).unwrap();
+        assert_eq!(request.method, "GET");
+        assert_eq!(request.get_header("host"), Some("localhost"));
+        assert_eq!(request.get_header("x-test"), Some("a: b"));
+        assert_eq!(request.segments, vec!["units", "ui-main"]);
+        assert_eq!(request.get_query("lines"), Some("20"));
+        assert_eq!(request.get_query("x"), Some(""));
+        assert_eq!(request.get_query("y"), None);
+    }
+
+    #[test]
+    fn read_request_should_skip_body() {
+        let mut reader = "POST /units/a/start HTTP/1.1\r\nContent-Length: 2\r\n\r\n{}GET".as_bytes();
+
+        assert_eq!(read_request(&mut reader).unwrap().segments, vec!["units", "a", "start"]);
+        assert_eq!(reader, b"GET");
+    }
+
+    #[test]
+    fn read_request_should_refuse_garbage() {
+        assert!(read_request(&mut "\r\n\r\n".as_bytes()).is_err());
+        assert!(read_request(&mut "GET /units%zz HTTP/1.1\r\n\r\n".as_bytes()).is_err());
+        assert!(read_request(&mut "GET / HTTP/1.1\r\nContent-Length: many\r\n\r\n".as_bytes()).is_err());
+        assert!(read_request(&mut "GET / HTTP/1.1\r\n".as_bytes()).is_err());
+
+        let huge = format!("GET / HTTP/1.1\r\nX: {}\r\n\r\n", "a".repeat(MAX_HEAD_SIZE));
+        assert!(read_request(&mut huge.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn write_to_should_write_status_and_length() {
+        let mut bytes = Vec::new();
+        Response::json(404, String::from("{}")).with_header("X-Test", String::from("a")).write_to(&mut bytes).unwrap();
+
+        let text = String::from_utf8(bytes).unwrap();
+        assert!(text.starts_with("HTTP/1.1 404 Not Found\r\n"));
+        assert!(text.contains("X-Test: a\r\n"));
+        assert!(text.contains("Content-Length: 2\r\n"));
+        assert!(text.ends_with("\r\n\r\n{}"));
+    }
+}
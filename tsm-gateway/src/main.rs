@@ -0,0 +1,98 @@
+mod auth;
+mod client_pool;
+mod config;
+mod http;
+mod routes;
+mod event_stream;
+mod server;
+
+use std::process::exit;
+use argparse::{ArgumentParser, Store};
+use log::{error, info};
+
+
+struct CommandLineParameters {
+    config_file: String,
+    bind_address: String,
+}
+
+
+fn parse_args_or_exit() -> CommandLineParameters {
+    let mut config_file = String::new();
+    let mut bind_address = String::new();
+
+    {
+        let mut ap = ArgumentParser::new();
+        ap.set_description("Taschenmesser HTTP Gateway. Serves the unit manager as JSON: GET /units, GET /units/{name}, POST /units/{name}/start|stop|restart and the events as server-sent events on GET /events. Requests need a bearer token or client credentials");
+        ap.refer(&mut config_file).add_option(&["-c", "--config"], Store, "Configuration file. Defaults to /etc/tsm-gateway.toml if it exists");
+        ap.refer(&mut bind_address).add_option(&["--bind"], Store, "Address to listen on, e.g. 0.0.0.0:8080. Defaults to 127.0.0.1:8080");
+        ap.parse_args_or_exit();
+    }
+
+    CommandLineParameters {
+        config_file,
+        bind_address,
+    }
+}
+
+
+fn init_logger(configuration: &config::Configuration) {
+    let env = env_logger::Env::default().filter_or(env_logger::DEFAULT_FILTER_ENV,
+                                                   configuration.get_log_level());
+    env_logger::init_from_env(env);
+}
+
+
+fn main() {
+    let params = parse_args_or_exit();
+
+    let configuration = match config::Configuration::load(params.config_file) {
+        Ok(configuration) => configuration,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            exit(10);
+        }
+    };
+    init_logger(&configuration);
+
+    let bind_address = match params.bind_address.is_empty() {
+        true => configuration.get_bind_address(),
+        false => params.bind_address,
+    };
+
+    // requests are sent with the key of the principal, which needs CURVE
+    let server_public_key = match configuration.get_server_public_key() {
+        Some(server_public_key) => server_public_key,
+        None => {
+            error!("server_public_key is required, requests are sent to the unit manager with the CURVE keys of the principals");
+            exit(10);
+        }
+    };
+
+    if configuration.get_principals().is_empty() {
+        error!("No principals configured, every request would be refused");
+        exit(10);
+    }
+
+    let client_settings = routes::ClientSettings {
+        address: configuration.get_address(),
+        timeout_ms: configuration.get_timeout_ms(),
+        server_public_key,
+    };
+
+    let mut gateway = match server::HttpGateway::bind(&bind_address, client_settings, configuration.get_events_address()) {
+        Ok(gateway) => gateway,
+        Err(e) => {
+            error!("Failed to start HTTP gateway: {}", e);
+            exit(30);
+        }
+    };
+
+    gateway.set_principals(configuration.get_principals());
+    gateway.set_allowed_origins(configuration.get_allowed_origins());
+    gateway.set_limits(configuration.get_max_connections(), configuration.get_max_event_streams());
+
+    info!("Listening on {}", bind_address);
+
+    gateway.run_threaded().join().expect("Error joining HTTP gateway thread");
+}
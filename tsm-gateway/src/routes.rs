@@ -0,0 +1,262 @@
+use protobuf::{Enum, MessageFull};
+use protobuf_json_mapping::PrintOptions;
+use serde_json::json;
+use tsm_ipc::{RpcClient, RpcError, tsm_unitman_rpc};
+use tsm_ipc::tsm_common_rpc::error::Code;
+use tsm_ipc::tsm_unitman_rpc::RpcMethod;
+
+use crate::client_pool::ClientPool;
+use crate::config::Principal;
+use crate::http::{Request, Response};
+
+
+/// Service name the unit manager answers to, see RpcClient::set_service
+const SERVICE_NAME: &str = "tsm-unitman";
+const DEFAULT_LINES: u32 = 10;
+
+
+/// How every request reaches the unit manager. Requests are sent with the CURVE key pair of the
+/// principal, so the unit manager checks its access rules against the HTTP client, not the gateway.
+#[derive(Clone, Debug)]
+pub struct ClientSettings {
+    pub address: String,
+    pub timeout_ms: u32,
+    pub server_public_key: String,
+}
+
+
+impl ClientSettings {
+    pub fn connect(&self, principal: &Principal) -> Result<RpcClient, RpcError> {
+        let mut rpc_client = RpcClient::new(self.address.clone())?;
+        rpc_client.set_timeout(self.timeout_ms.min(i32::MAX as u32) as i32)?;
+        rpc_client.set_service(String::from(SERVICE_NAME));
+
+        let (public_key, secret_key) = principal.get_curve_keys();
+        rpc_client.set_curve(self.server_public_key.clone(), public_key, secret_key)?;
+
+        Ok(rpc_client)
+    }
+}
+
+
+/// What the server does with a request, events are streamed instead of answered once
+pub enum Route {
+    Respond(Response),
+    Events,
+}
+
+
+/// GET /units, GET /units/{name}, POST /units/{name}/start|stop|restart and GET /events, on behalf
+/// of the authenticated principal
+pub fn route(request: &Request, clients: &ClientPool, principal: &Principal) -> Route {
+    let segments: Vec<&str> = request.segments.iter().map(String::as_str).collect();
+
+    let response = match (request.method.as_str(), segments.as_slice()) {
+        ("GET", ["events"]) => return Route::Events,
+        ("GET", ["units"]) => with_client(clients, principal, list_units),
+        ("GET", ["units", unit_name]) => {
+            let lines = match request.get_query("lines").map(|lines| lines.parse::<u32>()) {
+                None => DEFAULT_LINES,
+                Some(Ok(lines)) => lines,
+                Some(Err(_)) => return Route::Respond(error_response(400, Code::InvalidRequest, String::from("lines must be a number"))),
+            };
+            with_client(clients, principal, |rpc_client| get_unit_status(rpc_client, unit_name, lines))
+        },
+        ("POST", ["units", unit_name, "start"]) => with_client(clients, principal, |rpc_client| start_unit(rpc_client, unit_name)),
+        ("POST", ["units", unit_name, "stop"]) => with_client(clients, principal, |rpc_client| stop_unit(rpc_client, unit_name)),
+        ("POST", ["units", unit_name, "restart"]) => with_client(clients, principal, |rpc_client| restart_unit(rpc_client, unit_name)),
+        (_, ["events"] | ["units"] | ["units", _] | ["units", _, "start" | "stop" | "restart"]) => {
+            error_response(405, Code::InvalidRequest, format!("Method {} not allowed", request.method))
+        },
+        _ => error_response(404, Code::NotFound, String::from("No such endpoint")),
+    };
+
+    return Route::Respond(response);
+}
+
+
+fn with_client<F>(clients: &ClientPool, principal: &Principal, handler: F) -> Response
+where
+    F: FnOnce(&RpcClient) -> Result<Response, RpcError>,
+{
+    let rpc_client = match clients.take(principal) {
+        Ok(rpc_client) => rpc_client,
+        Err(error) => return rpc_error_response(&error),
+    };
+
+    let response = match handler(&rpc_client) {
+        Ok(response) => response,
+        Err(error) => rpc_error_response(&error),
+    };
+
+    clients.give_back(principal, rpc_client);
+
+    return response;
+}
+
+
+fn list_units(rpc_client: &RpcClient) -> Result<Response, RpcError> {
+    let response: tsm_unitman_rpc::ListUnitsResponse = rpc_client.call_idempotent(RpcMethod::ListUnits.value(), &tsm_unitman_rpc::ListUnitsRequest::new())?;
+
+    Ok(json_response(&response))
+}
+
+
+fn get_unit_status(rpc_client: &RpcClient, unit_name: &str, lines: u32) -> Result<Response, RpcError> {
+    let mut request = tsm_unitman_rpc::GetUnitStatusRequest::new();
+    request.unit_name = String::from(unit_name);
+    request.lines = lines;
+
+    let response: tsm_unitman_rpc::GetUnitStatusResponse = rpc_client.call_idempotent(RpcMethod::GetUnitStatus.value(), &request)?;
+
+    return match response.status.into_option() {
+        Some(status) => Ok(json_response(&status)),
+        None => Err(RpcError::Parse(String::from("Response contains no unit status"))),
+    };
+}
+
+
+/// Whether the unit manager lets the principal of the client see the unit, by the same access
+/// rules as GET /units/{name}. The rules are checked before anything else, so any other error of
+/// the unit manager means the request was let through. None if the unit manager didn't answer.
+pub fn is_unit_visible(rpc_client: &RpcClient, unit_name: &str) -> Option<bool> {
+    let mut request = tsm_unitman_rpc::GetUnitStatusRequest::new();
+    request.unit_name = String::from(unit_name);
+
+    let response: Result<tsm_unitman_rpc::GetUnitStatusResponse, RpcError> = rpc_client.call_idempotent(RpcMethod::GetUnitStatus.value(), &request);
+
+    return match response {
+        Ok(_) => Some(true),
+        Err(RpcError::Server { code: Code::PermissionDenied, .. }) => Some(false),
+        Err(RpcError::Server { .. }) => Some(true),
+        Err(_) => None,
+    };
+}
+
+
+fn start_unit(rpc_client: &RpcClient, unit_name: &str) -> Result<Response, RpcError> {
+    let mut request = tsm_unitman_rpc::StartUnitRequest::new();
+    request.unit_name = String::from(unit_name);
+
+    let response: tsm_unitman_rpc::StartUnitResponse = rpc_client.call(RpcMethod::StartUnit.value(), &request)?;
+    Ok(json_response(&response))
+}
+
+
+fn stop_unit(rpc_client: &RpcClient, unit_name: &str) -> Result<Response, RpcError> {
+    let mut request = tsm_unitman_rpc::StopUnitRequest::new();
+    request.unit_name = String::from(unit_name);
+
+    let response: tsm_unitman_rpc::StopUnitResponse = rpc_client.call(RpcMethod::StopUnit.value(), &request)?;
+    Ok(json_response(&response))
+}
+
+
+fn restart_unit(rpc_client: &RpcClient, unit_name: &str) -> Result<Response, RpcError> {
+    let mut request = tsm_unitman_rpc::RestartUnitRequest::new();
+    request.unit_name = String::from(unit_name);
+
+    let response: tsm_unitman_rpc::RestartUnitResponse = rpc_client.call(RpcMethod::RestartUnit.value(), &request)?;
+    Ok(json_response(&response))
+}
+
+
+/// The proto3 JSON mapping with lowerCamelCase names and enum names. Fields with default values
+/// are printed too, so that a stopped unit still has "pid": 0.
+pub fn to_json<M: MessageFull>(message: &M) -> Result<String, String> {
+    let options = PrintOptions {
+        always_output_default_values: true,
+        ..PrintOptions::default()
+    };
+
+    return match protobuf_json_mapping::print_to_string_with_options(message, &options) {
+        Ok(json) => Ok(json),
+        Err(error) => Err(format!("Failed to convert to JSON: {}", error)),
+    };
+}
+
+
+fn json_response<M: MessageFull>(message: &M) -> Response {
+    return match to_json(message) {
+        Ok(json) => Response::json(200, json),
+        Err(error) => error_response(500, Code::Failed, error),
+    };
+}
+
+
+/// Errors of the unit manager keep their code, transport errors are a bad gateway
+pub fn rpc_error_response(error: &RpcError) -> Response {
+    let (status, code) = match error {
+        RpcError::Server { code, .. } => (get_status(*code), *code),
+        RpcError::Timeout { .. } => (504, Code::Timeout),
+        RpcError::Incompatible(_) => (502, Code::Incompatible),
+        _ => (502, Code::Unknown),
+    };
+
+    let details = match error {
+        RpcError::Server { details, .. } => details.clone(),
+        _ => Vec::new(),
+    };
+
+    let body = json!({
+        "code": format!("{:?}", code),
+        "message": error.to_string(),
+        "details": details,
+    });
+
+    Response::json(status, body.to_string())
+}
+
+
+pub fn error_response(status: u16, code: Code, message: String) -> Response {
+    let body = json!({
+        "code": format!("{:?}", code),
+        "message": message,
+        "details": [],
+    });
+
+    Response::json(status, body.to_string())
+}
+
+
+fn get_status(code: Code) -> u16 {
+    return match code {
+        Code::InvalidRequest => 400,
+        Code::PermissionDenied => 403,
+        Code::NotFound => 404,
+        Code::AlreadyRunning | Code::NotRunning | Code::Masked | Code::Disabled => 409,
+        Code::UnknownMethod => 501,
+        Code::Incompatible => 502,
+        Code::Busy => 503,
+        Code::Timeout => 504,
+        Code::Unknown | Code::DependencyFailed | Code::Failed => 500,
+    };
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rpc_error_response_should_keep_code_of_server_error() {
+        let response = rpc_error_response(&RpcError::server(Code::NotFound, String::from("Unit foo not found")));
+        assert_eq!(response.get_status(), 404);
+
+        assert_eq!(rpc_error_response(&RpcError::Timeout { attempts: 1 }).get_status(), 504);
+        assert_eq!(rpc_error_response(&RpcError::server(Code::PermissionDenied, String::new())).get_status(), 403);
+    }
+
+    #[test]
+    fn to_json_should_use_canonical_mapping() {
+        let mut unit = tsm_unitman_rpc::Unit::new();
+        unit.name = String::from("ui-main");
+        unit.restart_count = 2;
+
+        let json: serde_json::Value = serde_json::from_str(&to_json(&unit).unwrap()).unwrap();
+        assert_eq!(json["name"], "ui-main");
+        assert_eq!(json["restartCount"], 2);
+        assert_eq!(json["pid"], 0);
+        assert_eq!(json["state"], "Starting");
+    }
+}
@@ -0,0 +1,555 @@
+use std::collections::HashMap;
+use std::io::BufReader;
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::Duration;
+use log::{debug, warn};
+use serde_json::json;
+use tsm_ipc::EventSubscriber;
+use tsm_ipc::tsm_common_rpc::error::Code;
+
+use crate::auth::Authenticator;
+use crate::client_pool::ClientPool;
+use crate::config::Principal;
+use crate::event_stream::stream_events;
+use crate::http::{read_request, Request, Response};
+use crate::routes::{error_response, is_unit_visible, route, rpc_error_response, ClientSettings, Route};
+
+
+/// Slow or idle clients are dropped after this, except while they follow the events
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+const WRITE_TIMEOUT: Duration = Duration::from_secs(10);
+const DEFAULT_MAX_CONNECTIONS: usize = 64;
+const DEFAULT_MAX_EVENT_STREAMS: usize = 16;
+
+
+/// Serves the REST endpoints with a thread per connection, one request per connection. Every
+/// request must carry the credentials of a principal.
+pub struct HttpGateway {
+    listener: TcpListener,
+    context: Context,
+    max_connections: usize,
+}
+
+
+/// What the connection threads share
+struct Context {
+    clients: ClientPool,
+    events_address: String,
+    authenticator: Authenticator,
+    allowed_origins: Vec<String>,
+    event_streams: Arc<AtomicUsize>,
+    max_event_streams: usize,
+}
+
+
+impl HttpGateway {
+    /// Binds right away, so that a used address is reported to the caller. Without principals
+    /// every request is refused.
+    pub fn bind(bind_address: &str, client_settings: ClientSettings, events_address: String) -> Result<HttpGateway, String> {
+        let listener = match TcpListener::bind(bind_address) {
+            Ok(listener) => listener,
+            Err(error) => return Err(format!("Failed to bind to {}: {}", bind_address, error)),
+        };
+
+        Ok(HttpGateway {
+            listener,
+            context: Context {
+                clients: ClientPool::new(client_settings),
+                events_address,
+                authenticator: Authenticator::new(Vec::new()),
+                allowed_origins: Vec::new(),
+                event_streams: Arc::new(AtomicUsize::new(0)),
+                max_event_streams: DEFAULT_MAX_EVENT_STREAMS,
+            },
+            max_connections: DEFAULT_MAX_CONNECTIONS,
+        })
+    }
+
+    pub fn set_principals(&mut self, principals: Vec<Principal>) {
+        self.context.authenticator = Authenticator::new(principals);
+    }
+
+    /// POSTs are only accepted from these origins and the gateway itself, or without an Origin
+    pub fn set_allowed_origins(&mut self, allowed_origins: Vec<String>) {
+        self.context.allowed_origins = allowed_origins;
+    }
+
+    /// Further connections are closed right away, further event streams are answered with 503
+    pub fn set_limits(&mut self, max_connections: usize, max_event_streams: usize) {
+        self.max_connections = max_connections;
+        self.context.max_event_streams = max_event_streams;
+    }
+
+    pub fn get_local_address(&self) -> Result<SocketAddr, String> {
+        return self.listener.local_addr().map_err(|error| format!("Failed to get local address: {}", error));
+    }
+
+    pub fn run_threaded(self) -> thread::JoinHandle<()> {
+        thread::spawn(move || {
+            self.run();
+        })
+    }
+
+    fn run(self) {
+        let context = Arc::new(self.context);
+        let connections = Arc::new(AtomicUsize::new(0));
+
+        for stream in self.listener.incoming() {
+            let stream = match stream {
+                Ok(stream) => stream,
+                Err(error) => {
+                    warn!("Failed to accept connection: {}", error);
+                    continue;
+                },
+            };
+
+            let slot = match Slot::take(&connections, self.max_connections) {
+                Some(slot) => slot,
+                None => {
+                    warn!("Closing connection, already serving {} connections", self.max_connections);
+                    continue;
+                },
+            };
+
+            let context = context.clone();
+
+            thread::spawn(move || {
+                serve_connection(stream, &context);
+                drop(slot);
+            });
+        }
+    }
+}
+
+
+/// One of a limited number of connections or event streams, given back when dropped
+struct Slot {
+    taken: Arc<AtomicUsize>,
+}
+
+
+impl Slot {
+    fn take(taken: &Arc<AtomicUsize>, limit: usize) -> Option<Slot> {
+        return taken
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |count| (count < limit).then_some(count + 1))
+            .ok()
+            .map(|_| Slot { taken: taken.clone() });
+    }
+}
+
+
+impl Drop for Slot {
+    fn drop(&mut self) {
+        self.taken.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+
+fn serve_connection(stream: TcpStream, context: &Context) {
+    let peer = stream.peer_addr().map(|peer| peer.to_string()).unwrap_or_default();
+
+    let options = [
+        stream.set_read_timeout(Some(READ_TIMEOUT)),
+        stream.set_write_timeout(Some(WRITE_TIMEOUT)),
+    ];
+
+    for option in options {
+        if let Err(error) = option {
+            warn!("Failed to set timeout of connection from {}: {}", peer, error);
+            return;
+        }
+    }
+
+    let mut writer = match stream.try_clone() {
+        Ok(writer) => writer,
+        Err(error) => {
+            warn!("Failed to clone connection from {}: {}", peer, error);
+            return;
+        },
+    };
+
+    let request = read_request(&mut BufReader::new(stream));
+
+    let response = match &request {
+        Ok(request) => match check_request(request, context) {
+            Ok(principal) => {
+                debug!("Request from {} as {}", peer, principal.get_name());
+
+                match route(request, &context.clients, principal) {
+                    Route::Respond(response) => response,
+                    Route::Events => match serve_events(&mut writer, &peer, context, principal) {
+                        Ok(_) => return,
+                        Err(response) => response,
+                    },
+                }
+            },
+            Err(response) => response,
+        },
+        Err(error) => {
+            warn!("Invalid request from {}: {}", peer, error);
+            Response::json(400, json!({ "code": "InvalidRequest", "message": error, "details": [] }).to_string())
+        },
+    };
+
+    if let Ok(request) = &request {
+        debug!("{} {} /{} {}", peer, request.method, request.segments.join("/"), response.get_status());
+    }
+
+    if let Err(error) = response.write_to(&mut writer) {
+        warn!("Failed to send response to {}: {}", peer, error);
+    }
+}
+
+
+/// Returns the principal of the request. POSTs must be JSON, which browsers don't send to other
+/// origins without asking first, and must not come from a foreign origin.
+fn check_request<'a>(request: &Request, context: &'a Context) -> Result<&'a Principal, Response> {
+    let principal = match context.authenticator.authenticate(request) {
+        Some(principal) => principal,
+        None => {
+            let response = error_response(401, Code::PermissionDenied, String::from("A bearer token or client credentials are required"));
+            return Err(response.with_header("WWW-Authenticate", String::from("Bearer realm=\"tsm-gateway\"")));
+        },
+    };
+
+    if request.method != "POST" {
+        return Ok(principal);
+    }
+
+    let content_type = request.get_header("content-type").unwrap_or_default();
+    let media_type = content_type.split(';').next().unwrap_or_default().trim();
+
+    if !media_type.eq_ignore_ascii_case("application/json") {
+        return Err(error_response(415, Code::InvalidRequest, String::from("POST requires Content-Type: application/json")));
+    }
+
+    if let Some(origin) = request.get_header("origin") {
+        if !is_allowed_origin(origin, request.get_header("host"), &context.allowed_origins) {
+            return Err(error_response(403, Code::PermissionDenied, format!("Origin {} is not allowed", origin)));
+        }
+    }
+
+    Ok(principal)
+}
+
+
+/// The gateway's own origin is the one whose host and port are in the Host header
+fn is_allowed_origin(origin: &str, host: Option<&str>, allowed_origins: &[String]) -> bool {
+    let origin = origin.trim_end_matches('/');
+
+    if allowed_origins.iter().any(|allowed_origin| allowed_origin.trim_end_matches('/').eq_ignore_ascii_case(origin)) {
+        return true;
+    }
+
+    return match (origin.split_once("://"), host) {
+        (Some((_, authority)), Some(host)) => authority.eq_ignore_ascii_case(host),
+        _ => false,
+    };
+}
+
+
+/// Streams the events of the units the principal may see until the client goes away, or returns
+/// what to answer instead. Whether a unit is visible is asked once per unit and stream.
+fn serve_events(writer: &mut TcpStream, peer: &str, context: &Context, principal: &Principal) -> Result<(), Response> {
+    let _slot = match Slot::take(&context.event_streams, context.max_event_streams) {
+        Some(slot) => slot,
+        None => {
+            warn!("Refusing event stream to {}, already streaming to {} clients", peer, context.max_event_streams);
+            return Err(error_response(503, Code::Busy, String::from("Too many event streams")));
+        },
+    };
+
+    let subscriber = match EventSubscriber::new(context.events_address.clone(), Vec::new()) {
+        Ok(subscriber) => subscriber,
+        Err(error) => {
+            warn!("Failed to subscribe to events: {}", error);
+            return Err(Response::json(502, json!({ "code": "Unknown", "message": error, "details": [] }).to_string()));
+        },
+    };
+
+    let rpc_client = match context.clients.take(principal) {
+        Ok(rpc_client) => rpc_client,
+        Err(error) => return Err(rpc_error_response(&error)),
+    };
+
+    let mut visible_units: HashMap<String, bool> = HashMap::new();
+    let is_visible = |unit_name: &str| {
+        if let Some(visible) = visible_units.get(unit_name) {
+            return *visible;
+        }
+
+        // left out until the unit manager answers
+        return match is_unit_visible(&rpc_client, unit_name) {
+            Some(visible) => *visible_units.entry(String::from(unit_name)).or_insert(visible),
+            None => false,
+        };
+    };
+
+    debug!("Streaming events to {}", peer);
+    match stream_events(writer, subscriber, is_visible) {
+        Ok(_) => debug!("Stopped streaming events to {}", peer),
+        Err(error) => warn!("Failed to stream events to {}: {}", peer, error),
+    };
+
+    context.clients.give_back(principal, rpc_client);
+
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::io::{BufRead, Read, Write};
+    use std::sync::atomic::AtomicBool;
+    use protobuf::Enum;
+    use tsm_ipc::{EventPublisher, RpcError, RpcServer, RpcService, tsm_unitman_event, tsm_unitman_rpc};
+    use tsm_ipc::tsm_unitman_rpc::RpcMethod;
+    use crate::config::Configuration;
+    use super::*;
+
+    const HMI: &str = "Authorization: Bearer hmi-token\r\n";
+    const OTHER: &str = "Authorization: Bearer other-token\r\n";
+
+    fn encode(key: &[u8]) -> String {
+        zmq::z85_encode(key).unwrap()
+    }
+
+    fn build_principal(token: &str, public_key: String, secret_key: String) -> Principal {
+        let configuration = Configuration::from_string(format!(
+            "[[principals]]\ntoken = \"{}\"\npublic_key = \"{}\"\nsecret_key = \"{}\"",
+            token, public_key, secret_key
+        )).unwrap();
+
+        configuration.get_principals().remove(0)
+    }
+
+    /// The hmi principal has hmi_keys, the other one keys the unit manager doesn't know
+    fn build_principals(hmi_keys: &zmq::CurveKeyPair) -> Vec<Principal> {
+        let other_keys = zmq::CurveKeyPair::new().unwrap();
+
+        vec![
+            build_principal("hmi-token", encode(&hmi_keys.public_key), encode(&hmi_keys.secret_key)),
+            build_principal("other-token", encode(&other_keys.public_key), encode(&other_keys.secret_key)),
+        ]
+    }
+
+    /// A TCP address nothing listens on, taken from a port the kernel picked
+    fn get_free_address() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        format!("tcp://{}", listener.local_addr().unwrap())
+    }
+
+    /// A unit manager with the units "ui-main" and "network", which only the key of the hmi
+    /// principal may call. The status of "network" is denied like by an access rule.
+    fn start_unit_manager(server_keys: &zmq::CurveKeyPair, hmi_keys: &zmq::CurveKeyPair) -> String {
+        let mut service = RpcService::new();
+
+        service.register(RpcMethod::ListUnits.value(), |_: tsm_unitman_rpc::ListUnitsRequest| {
+            let mut unit = tsm_unitman_rpc::Unit::new();
+            unit.name = String::from("ui-main");
+            unit.pid = 42;
+
+            let mut response = tsm_unitman_rpc::ListUnitsResponse::new();
+            response.units.push(unit);
+            Ok(response)
+        });
+
+        service.register(RpcMethod::GetUnitStatus.value(), |request: tsm_unitman_rpc::GetUnitStatusRequest| {
+            if request.unit_name != "ui-main" {
+                return Err(RpcError::server(Code::PermissionDenied, String::from("Permission denied for GetUnitStatus")));
+            }
+
+            let mut unit = tsm_unitman_rpc::Unit::new();
+            unit.name = request.unit_name;
+
+            let mut status = tsm_unitman_rpc::UnitStatus::new();
+            status.unit = protobuf::MessageField::some(unit);
+
+            let mut response = tsm_unitman_rpc::GetUnitStatusResponse::new();
+            response.status = protobuf::MessageField::some(status);
+            Ok(response)
+        });
+
+        service.register(RpcMethod::RestartUnit.value(), |request: tsm_unitman_rpc::RestartUnitRequest| {
+            if request.unit_name != "ui-main" {
+                return Err(RpcError::server(Code::NotFound, format!("Unit {} not found", request.unit_name)));
+            }
+
+            let mut response = tsm_unitman_rpc::RestartUnitResponse::new();
+            response.message = format!("Unit {} restarted", request.unit_name);
+            Ok(response)
+        });
+
+        let address = get_free_address();
+        let mut server = RpcServer::new(address.clone(), Arc::new(service));
+        server.set_curve(encode(&server_keys.secret_key), vec![encode(&hmi_keys.public_key)]);
+        server.run_threaded().unwrap();
+
+        address
+    }
+
+    fn start_gateway(address: String, events_address: String, server_public_key: String, principals: Vec<Principal>) -> SocketAddr {
+        let client_settings = ClientSettings {
+            address,
+            timeout_ms: 1000,
+            server_public_key,
+        };
+
+        let mut gateway = HttpGateway::bind("127.0.0.1:0", client_settings, events_address).unwrap();
+        gateway.set_principals(principals);
+        gateway.set_allowed_origins(vec![String::from("https://dashboard.example.com")]);
+        gateway.set_limits(4, 1);
+        let local_address = gateway.get_local_address().unwrap();
+        gateway.run_threaded();
+
+        local_address
+    }
+
+    /// Returns the status and the body
+    fn send(address: SocketAddr, request: &str) -> (u16, String) {
+        let mut stream = TcpStream::connect(address).unwrap();
+        stream.write_all(request.as_bytes()).unwrap();
+
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+
+        let status = response.split(' ').nth(1).unwrap().parse::<u16>().unwrap();
+        let body = response.split_once("\r\n\r\n").unwrap().1;
+
+        (status, String::from(body))
+    }
+
+    #[test]
+    #[ignore = "needs libzmq with CURVE"]
+    fn gateway_should_map_endpoints_to_rpc_methods() {
+        assert!(zmq::has("curve").unwrap_or(false), "libzmq was built without CURVE");
+
+        let (server_keys, hmi_keys) = (zmq::CurveKeyPair::new().unwrap(), zmq::CurveKeyPair::new().unwrap());
+        let address = start_unit_manager(&server_keys, &hmi_keys);
+        let gateway = start_gateway(address, String::new(), encode(&server_keys.public_key), build_principals(&hmi_keys));
+
+        let (status, body) = send(gateway, &format!("GET /units HTTP/1.1\r\nHost: localhost\r\n{}\r\n", HMI));
+        assert_eq!(status, 200);
+        let units: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(units["units"][0]["name"], "ui-main");
+        assert_eq!(units["units"][0]["pid"], 42);
+
+        let (status, body) = send(gateway, &format!("POST /units/ui-main/restart HTTP/1.1\r\nContent-Type: application/json\r\nContent-Length: 0\r\n{}\r\n", HMI));
+        assert_eq!(status, 200);
+        assert!(body.contains("Unit ui-main restarted"));
+
+        let (status, body) = send(gateway, &format!("POST /units/other/restart HTTP/1.1\r\nContent-Type: application/json\r\n{}\r\n", HMI));
+        assert_eq!(status, 404);
+        assert_eq!(serde_json::from_str::<serde_json::Value>(&body).unwrap()["code"], "NotFound");
+
+        let (status, body) = send(gateway, &format!("GET /units/ui-main HTTP/1.1\r\n{}\r\n", HMI));
+        assert_eq!(status, 200);
+        assert_eq!(serde_json::from_str::<serde_json::Value>(&body).unwrap()["unit"]["name"], "ui-main");
+        assert_eq!(send(gateway, &format!("GET /units/network HTTP/1.1\r\n{}\r\n", HMI)).0, 403);
+
+        assert_eq!(send(gateway, &format!("GET /units/ui-main/restart HTTP/1.1\r\n{}\r\n", HMI)).0, 405);
+        assert_eq!(send(gateway, &format!("GET /metrics HTTP/1.1\r\n{}\r\n", HMI)).0, 404);
+        assert_eq!(send(gateway, "nonsense\r\n\r\n").0, 400);
+
+        // the test unit manager has no StopUnit
+        assert_eq!(send(gateway, &format!("POST /units/ui-main/stop HTTP/1.1\r\nContent-Type: application/json\r\n{}\r\n", HMI)).0, 501);
+
+        // allowed origins get through to the unit manager
+        let post = |origin: &str| format!("POST /units/ui-main/restart HTTP/1.1\r\nHost: localhost:8080\r\nContent-Type: application/json\r\nOrigin: {}\r\n{}\r\n", origin, HMI);
+        assert_eq!(send(gateway, &post("http://localhost:8080")).0, 200);
+        assert_eq!(send(gateway, &post("https://dashboard.example.com")).0, 200);
+
+        // the unit manager doesn't accept the key of the other principal
+        assert!(matches!(send(gateway, &format!("GET /units HTTP/1.1\r\n{}\r\n", OTHER)).0, 502 | 504));
+    }
+
+    #[test]
+    fn gateway_should_refuse_unauthenticated_and_cross_origin_requests() {
+        // refused before the unit manager is asked, so neither it nor CURVE are needed
+        let principals = vec![build_principal("hmi-token", String::from("hmi-public"), String::from("hmi-secret"))];
+        let gateway = start_gateway(get_free_address(), String::new(), String::from("server-public"), principals);
+
+        assert_eq!(send(gateway, "GET /units HTTP/1.1\r\n\r\n").0, 401);
+        assert_eq!(send(gateway, "GET /units HTTP/1.1\r\nAuthorization: Bearer nope\r\n\r\n").0, 401);
+        assert_eq!(send(gateway, "GET /events HTTP/1.1\r\n\r\n").0, 401);
+
+        assert_eq!(send(gateway, &format!("POST /units/ui-main/start HTTP/1.1\r\n{}\r\n", HMI)).0, 415);
+        assert_eq!(send(gateway, &format!("POST /units/ui-main/start HTTP/1.1\r\nContent-Type: text/plain\r\n{}\r\n", HMI)).0, 415);
+
+        let post = |origin: &str| format!("POST /units/ui-main/start HTTP/1.1\r\nHost: localhost:8080\r\nContent-Type: application/json\r\nOrigin: {}\r\n{}\r\n", origin, HMI);
+        assert_eq!(send(gateway, &post("https://evil.example.com")).0, 403);
+        assert_eq!(send(gateway, &post("null")).0, 403);
+    }
+
+    #[test]
+    fn is_allowed_origin_should_accept_own_and_listed_origins() {
+        let allowed_origins = vec![String::from("https://dashboard.example.com/")];
+
+        assert!(is_allowed_origin("http://10.0.0.5:8080", Some("10.0.0.5:8080"), &allowed_origins));
+        assert!(is_allowed_origin("https://Dashboard.example.com", None, &allowed_origins));
+        assert!(!is_allowed_origin("http://10.0.0.5:8081", Some("10.0.0.5:8080"), &allowed_origins));
+        assert!(!is_allowed_origin("http://10.0.0.5:8080", None, &allowed_origins));
+    }
+
+    #[test]
+    fn slot_should_be_limited() {
+        let taken = Arc::new(AtomicUsize::new(0));
+        let slot = Slot::take(&taken, 1);
+
+        assert!(slot.is_some());
+        assert!(Slot::take(&taken, 1).is_none());
+
+        drop(slot);
+        assert!(Slot::take(&taken, 1).is_some());
+        assert_eq!(taken.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    #[ignore = "needs libzmq with CURVE"]
+    fn gateway_should_stream_events_of_visible_units() {
+        assert!(zmq::has("curve").unwrap_or(false), "libzmq was built without CURVE");
+
+        let events_address = format!("ipc:///tmp/tsm-gateway-events-test-{}.sock", std::process::id());
+        let publisher = EventPublisher::new(events_address.clone()).unwrap();
+        let (server_keys, hmi_keys) = (zmq::CurveKeyPair::new().unwrap(), zmq::CurveKeyPair::new().unwrap());
+        let address = start_unit_manager(&server_keys, &hmi_keys);
+        let gateway = start_gateway(address, events_address, encode(&server_keys.public_key), build_principals(&hmi_keys));
+
+        // published until received, the subscriber only gets events once it is connected
+        let received = Arc::new(AtomicBool::new(false));
+        let publishing = received.clone();
+        let publisher = thread::spawn(move || {
+            while !publishing.load(Ordering::Relaxed) {
+                for unit_name in ["network", "ui-main"] {
+                    let mut event = tsm_unitman_event::Event::new();
+                    event.unit_name = String::from(unit_name);
+                    event.set_unit_started(tsm_unitman_event::UnitStarted::new());
+                    publisher.publish("unit_started", &event).unwrap();
+                }
+                thread::sleep(Duration::from_millis(20));
+            }
+        });
+
+        let mut stream = TcpStream::connect(gateway).unwrap();
+        stream.write_all(format!("GET /events HTTP/1.1\r\n{}\r\n", HMI).as_bytes()).unwrap();
+
+        let lines: Vec<String> = BufReader::new(stream).lines()
+            .map(|line| line.unwrap())
+            .filter(|line| line.starts_with("data:"))
+            .take(3)
+            .collect();
+        received.store(true, Ordering::Relaxed);
+        publisher.join().unwrap();
+
+        for line in lines {
+            let event: serde_json::Value = serde_json::from_str(line.strip_prefix("data: ").unwrap()).unwrap();
+            assert_eq!(event["unitName"], "ui-main");
+        }
+
+        // the only event stream is still taken by the first client
+        assert_eq!(send(gateway, &format!("GET /events HTTP/1.1\r\n{}\r\n", HMI)).0, 503);
+    }
+}
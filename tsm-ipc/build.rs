@@ -4,6 +4,7 @@ fn main() {
         .includes(&["schema"])
         .input("schema/tsm_common_rpc.proto")
         .input("schema/tsm_unitman_rpc.proto")
+        .input("schema/tsm_unitman_event.proto")
         .cargo_out_dir("protos")
         .run_from_script();
 }
\ No newline at end of file
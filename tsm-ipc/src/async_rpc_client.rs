@@ -0,0 +1,77 @@
+use std::sync::{Arc, Mutex};
+use protobuf::Message;
+
+use crate::tsm_common_rpc;
+use crate::{RpcClient, RpcError};
+
+
+/// Counterpart of RpcClient for tokio applications. Requests are sent by an RpcClient on the
+/// blocking thread pool, one at a time like with a REQ socket, and have the same timeouts,
+/// retries and protocol checks.
+#[derive(Clone)]
+pub struct AsyncRpcClient {
+    rpc_client: Arc<Mutex<RpcClient>>,
+}
+
+
+impl AsyncRpcClient {
+    pub fn new(bind_address: String) -> Result<AsyncRpcClient, RpcError> {
+        Ok(AsyncRpcClient::from_client(RpcClient::new(bind_address)?))
+    }
+
+    /// Takes a configured client, see RpcClient::set_timeout, set_retries, set_service and set_curve
+    pub fn from_client(rpc_client: RpcClient) -> AsyncRpcClient {
+        AsyncRpcClient {
+            rpc_client: Arc::new(Mutex::new(rpc_client)),
+        }
+    }
+
+    pub async fn send(&self, request: tsm_common_rpc::RpcRequest) -> Result<tsm_common_rpc::RpcResponse, RpcError> {
+        self.run(move |rpc_client| rpc_client.send(request)).await
+    }
+
+    pub async fn send_idempotent(&self, request: tsm_common_rpc::RpcRequest) -> Result<tsm_common_rpc::RpcResponse, RpcError> {
+        self.run(move |rpc_client| rpc_client.send_idempotent(request)).await
+    }
+
+    pub async fn call<Req, Resp>(&self, method: i32, request: Req) -> Result<Resp, RpcError>
+    where
+        Req: Message + Send + 'static,
+        Resp: Message + Send + 'static,
+    {
+        self.run(move |rpc_client| rpc_client.call(method, &request)).await
+    }
+
+    pub async fn call_idempotent<Req, Resp>(&self, method: i32, request: Req) -> Result<Resp, RpcError>
+    where
+        Req: Message + Send + 'static,
+        Resp: Message + Send + 'static,
+    {
+        self.run(move |rpc_client| rpc_client.call_idempotent(method, &request)).await
+    }
+
+    pub async fn get_capabilities(&self) -> Result<tsm_common_rpc::Capabilities, RpcError> {
+        self.run(|rpc_client| rpc_client.get_capabilities()).await
+    }
+
+    /// A dropped future doesn't abort the request, it still occupies the client until answered
+    /// or timed out
+    async fn run<T, F>(&self, function: F) -> Result<T, RpcError>
+    where
+        T: Send + 'static,
+        F: FnOnce(&RpcClient) -> Result<T, RpcError> + Send + 'static,
+    {
+        let rpc_client = self.rpc_client.clone();
+
+        let result = tokio::task::spawn_blocking(move || {
+            // taken over like after a timeout, the socket is replaced when the next request times out
+            let rpc_client = rpc_client.lock().unwrap_or_else(|error| error.into_inner());
+            function(&rpc_client)
+        }).await;
+
+        return match result {
+            Ok(result) => result,
+            Err(error) => Err(RpcError::Send(format!("Request task failed: {}", error))),
+        };
+    }
+}
@@ -0,0 +1,267 @@
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use log::{debug, error, warn};
+use tokio::io::Interest;
+use tokio::io::unix::AsyncFd;
+use tokio::task::{JoinHandle, JoinSet};
+use tokio_util::sync::CancellationToken;
+
+use crate::{RpcError, RpcRequestHandler};
+use crate::protocol::ServiceDescription;
+use crate::unix_transport;
+use crate::rpc_server::{configure_security, handle_frames, receive_with_caller, DEFAULT_WORKERS};
+use crate::zap_handler::ZapHandler;
+
+
+const SHUTDOWN_LINGER_MS: i32 = 1000;
+
+
+/// Counterpart of RpcServer for tokio applications, with the same handlers and protocol. The
+/// ROUTER socket is served from a task, handlers run on the blocking thread pool since they may
+/// block, e.g. while waiting for a lock.
+pub struct AsyncRpcServer {
+    bind_address: String,
+    request_handler: Arc<dyn RpcRequestHandler>,
+    workers: usize,
+    service: Arc<ServiceDescription>,
+    curve_secret_key: Option<String>,
+    authorized_keys: Vec<String>,
+}
+
+
+/// The socket notifies through this file descriptor that its events may have changed
+struct SocketFd(RawFd);
+
+
+impl AsRawFd for SocketFd {
+    fn as_raw_fd(&self) -> RawFd {
+        self.0
+    }
+}
+
+
+impl AsyncRpcServer {
+    pub fn new(bind_address: String, request_handler: Arc<dyn RpcRequestHandler>) -> Self {
+        Self {
+            bind_address,
+            request_handler,
+            workers: DEFAULT_WORKERS,
+            service: Arc::new(ServiceDescription::default()),
+            curve_secret_key: None,
+            authorized_keys: Vec::new(),
+        }
+    }
+
+    /// Number of requests handled at the same time, at least one. Further requests wait in the socket.
+    pub fn set_workers(&mut self, workers: usize) {
+        self.workers = workers.max(1);
+    }
+
+    /// Answered to GetCapabilities, requests naming another service are refused
+    pub fn set_service(&mut self, service: ServiceDescription) {
+        self.service = Arc::new(service);
+    }
+
+    /// Same as RpcServer::set_curve
+    pub fn set_curve(&mut self, secret_key: String, authorized_keys: Vec<String>) {
+        self.curve_secret_key = Some(secret_key);
+        self.authorized_keys = authorized_keys;
+    }
+
+    /// Binds right away, so that a used address is reported to the caller, and serves requests
+    /// in a task until shutdown is cancelled. Requests being handled then are still answered.
+    pub fn spawn(self, shutdown: CancellationToken) -> Result<JoinHandle<()>, RpcError> {
+        if unix_transport::get_path(&self.bind_address).is_some() {
+            return Err(RpcError::Socket(format!("Address {} is only served by RpcServer", self.bind_address)));
+        }
+
+        let context = zmq::Context::new();
+        let zap_handler = ZapHandler::bind(&context, self.authorized_keys.clone())?;
+
+        let frontend = match context.socket(zmq::ROUTER) {
+            Ok(socket) => socket,
+            Err(error) => return Err(RpcError::Socket(format!("Failed to create ZMQ socket: {}", error))),
+        };
+        configure_security(&frontend, &self.curve_secret_key)?;
+
+        // replies to clients which went away must not hold up the shutdown
+        match frontend.set_linger(SHUTDOWN_LINGER_MS) {
+            Ok(_) => {},
+            Err(error) => return Err(RpcError::Socket(format!("Failed to set ZMQ socket linger: {}", error))),
+        };
+
+        match frontend.bind(&self.bind_address) {
+            Ok(_) => {},
+            Err(error) => return Err(RpcError::Bind { address: self.bind_address.clone(), error: error.to_string() }),
+        };
+
+        let fd = match frontend.get_fd() {
+            Ok(fd) => fd,
+            Err(error) => return Err(RpcError::Socket(format!("Failed to get file descriptor of ZMQ socket: {}", error))),
+        };
+
+        let fd = match AsyncFd::with_interest(SocketFd(fd), Interest::READABLE) {
+            Ok(fd) => fd,
+            Err(error) => return Err(RpcError::Socket(format!("Failed to register ZMQ socket: {}", error))),
+        };
+
+        let zap_stopped = Arc::new(AtomicBool::new(false));
+        zap_handler.run_threaded(zap_stopped.clone());
+
+        debug!("Handling requests on {} with {} workers", self.bind_address, self.workers);
+
+        Ok(tokio::spawn(async move {
+            self.serve(frontend, fd, shutdown).await;
+            zap_stopped.store(true, Ordering::Relaxed);
+            debug!("Stopped handling requests on {}", self.bind_address);
+        }))
+    }
+
+    /// The file descriptor is edge triggered and also fires for sent messages, so the socket is
+    /// drained after every wakeup until it would block.
+    async fn serve(&self, frontend: zmq::Socket, fd: AsyncFd<SocketFd>, shutdown: CancellationToken) {
+        let mut handlers: JoinSet<Vec<Vec<u8>>> = JoinSet::new();
+
+        loop {
+            while handlers.len() < self.workers {
+                match receive_with_caller(&frontend, zmq::DONTWAIT) {
+                    Ok(Some(frames)) => self.spawn_handler(&mut handlers, frames),
+                    Ok(None) => {},
+                    Err(zmq::Error::EAGAIN) => break,
+                    Err(error) => {
+                        warn!("Failed to receive request: {}", error);
+                        break;
+                    },
+                };
+            }
+
+            tokio::select! {
+                _ = shutdown.cancelled() => break,
+                Some(result) = handlers.join_next(), if !handlers.is_empty() => send_reply(&frontend, result),
+                result = fd.readable() => match result {
+                    Ok(mut guard) => guard.clear_ready(),
+                    Err(error) => {
+                        error!("Failed to wait for requests: {}", error);
+                        break;
+                    },
+                },
+            };
+        }
+
+        while let Some(result) = handlers.join_next().await {
+            send_reply(&frontend, result);
+        }
+
+        // deregistered while the socket still owns the file descriptor
+        drop(fd);
+        drop(frontend);
+    }
+
+    /// The reply gets the envelope of the request, the caller frame behind it is consumed
+    fn spawn_handler(&self, handlers: &mut JoinSet<Vec<Vec<u8>>>, mut frames: Vec<Vec<u8>>) {
+        // receive_with_caller only returns requests with envelope
+        let delimiter = frames.iter().position(|frame| frame.is_empty()).unwrap_or_default();
+        let request = frames.split_off(delimiter + 1);
+
+        let service = self.service.clone();
+        let request_handler = self.request_handler.clone();
+
+        handlers.spawn_blocking(move || {
+            frames.push(handle_frames(&request, &service, request_handler.as_ref()));
+            frames
+        });
+    }
+}
+
+
+/// A panicking handler is answered in handle_frames already, a failed task can't be answered
+fn send_reply(frontend: &zmq::Socket, result: Result<Vec<Vec<u8>>, tokio::task::JoinError>) {
+    let frames = match result {
+        Ok(frames) => frames,
+        Err(error) => {
+            error!("Request handler task failed: {}", error);
+            return;
+        },
+    };
+
+    // a ROUTER socket drops replies to peers which are gone instead of blocking
+    match frontend.send_multipart(frames, zmq::DONTWAIT) {
+        Ok(_) => {},
+        Err(error) => warn!("Failed to send response: {}", error),
+    };
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use crate::{tsm_common_rpc, AsyncRpcClient, Caller, RpcClient};
+    use super::*;
+
+    /// Answers with the method of the request, method 9001 blocks for a while
+    struct EchoHandler {}
+
+    impl RpcRequestHandler for EchoHandler {
+        fn handle_request(&self, request: tsm_common_rpc::RpcRequest, _caller: &Caller) -> tsm_common_rpc::RpcResponse {
+            if request.method == 9001 {
+                std::thread::sleep(Duration::from_millis(500));
+            }
+
+            let mut response = tsm_common_rpc::RpcResponse::new();
+            response.method = request.method;
+            response.status = true;
+            response
+        }
+    }
+
+    fn build_request(method: i32) -> tsm_common_rpc::RpcRequest {
+        let mut request = tsm_common_rpc::RpcRequest::new();
+        request.method = method;
+        request
+    }
+
+    #[tokio::test]
+    async fn spawn_should_answer_async_and_blocking_clients() {
+        let address = format!("ipc:///tmp/tsm-ipc-async-rpc-test-{}.sock", std::process::id());
+        let shutdown = CancellationToken::new();
+        let server = AsyncRpcServer::new(address.clone(), Arc::new(EchoHandler {})).spawn(shutdown.clone()).unwrap();
+
+        let client = AsyncRpcClient::new(address.clone()).unwrap();
+        assert_eq!(client.send(build_request(1)).await.unwrap().method, 1);
+
+        let blocking_address = address.clone();
+        let response = tokio::task::spawn_blocking(move || RpcClient::new(blocking_address).unwrap().send(build_request(2)).unwrap()).await.unwrap();
+        assert_eq!(response.method, 2);
+
+        shutdown.cancel();
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn shutdown_should_answer_requests_being_handled() {
+        let address = format!("ipc:///tmp/tsm-ipc-async-rpc-shutdown-test-{}.sock", std::process::id());
+        let shutdown = CancellationToken::new();
+        let server = AsyncRpcServer::new(address.clone(), Arc::new(EchoHandler {})).spawn(shutdown.clone()).unwrap();
+
+        let client = AsyncRpcClient::new(address).unwrap();
+        let slow_request = tokio::spawn(async move { client.send(build_request(9001)).await });
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        shutdown.cancel();
+        server.await.unwrap();
+
+        assert_eq!(slow_request.await.unwrap().unwrap().method, 9001);
+    }
+
+    #[tokio::test]
+    async fn spawn_should_report_used_address() {
+        let context = zmq::Context::new();
+        let occupant = context.socket(zmq::REP).unwrap();
+        occupant.bind("tcp://127.0.0.1:*").unwrap();
+        let used_address = occupant.get_last_endpoint().unwrap().unwrap();
+
+        let result = AsyncRpcServer::new(used_address.clone(), Arc::new(EchoHandler {})).spawn(CancellationToken::new());
+        assert!(matches!(result, Err(RpcError::Bind { address, .. }) if address == used_address));
+    }
+}
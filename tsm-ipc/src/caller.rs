@@ -0,0 +1,117 @@
+use std::fmt;
+
+
+/// Who sent a request, as established by the transport and not by the request itself. Unix
+/// sockets tell the credentials of the peer process, CURVE connections the public key of the client.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Caller {
+    pub uid: Option<u32>,
+    pub gid: Option<u32>,
+    pub pid: Option<u32>,
+    /// Z85 encoded CURVE public key
+    pub public_key: Option<String>,
+}
+
+
+impl Caller {
+    /// Connections over tcp without CURVE can't be identified
+    pub fn anonymous() -> Caller {
+        Caller::default()
+    }
+
+    pub fn from_credentials(uid: u32, gid: u32, pid: u32) -> Caller {
+        Caller {
+            uid: Some(uid),
+            gid: Some(gid),
+            pid: Some(pid),
+            public_key: None,
+        }
+    }
+
+    pub fn from_public_key(public_key: String) -> Caller {
+        Caller {
+            public_key: Some(public_key),
+            ..Caller::default()
+        }
+    }
+
+    pub fn is_anonymous(&self) -> bool {
+        return self.uid.is_none() && self.public_key.is_none();
+    }
+
+    /// Parses the ZMQ peer address of a unix socket connection, ":uid:gid:pid"
+    pub(crate) fn from_peer_address(address: &str) -> Option<Caller> {
+        let ids: Vec<u32> = match address.strip_prefix(':') {
+            Some(ids) => ids.split(':').map(|id| id.parse::<u32>()).collect::<Result<Vec<u32>, _>>().ok()?,
+            None => return None,
+        };
+
+        return match ids.as_slice() {
+            [uid, gid, pid] => Some(Caller::from_credentials(*uid, *gid, *pid)),
+            _ => None,
+        };
+    }
+
+    /// The user id the ZAP handler attaches to every message of a connection
+    pub(crate) fn to_user_id(&self) -> String {
+        if let Some(public_key) = &self.public_key {
+            return format!("curve:{}", public_key);
+        }
+
+        return match (self.uid, self.gid, self.pid) {
+            (Some(uid), Some(gid), Some(pid)) => format!("unix:{}:{}:{}", uid, gid, pid),
+            _ => String::new(),
+        };
+    }
+
+    pub(crate) fn from_user_id(user_id: &str) -> Caller {
+        if let Some(public_key) = user_id.strip_prefix("curve:") {
+            return Caller::from_public_key(String::from(public_key));
+        }
+
+        return match user_id.strip_prefix("unix") {
+            Some(address) => Caller::from_peer_address(address).unwrap_or_default(),
+            None => Caller::anonymous(),
+        };
+    }
+}
+
+
+impl fmt::Display for Caller {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(public_key) = &self.public_key {
+            return write!(f, "key {}", public_key);
+        }
+
+        match (self.uid, self.gid, self.pid) {
+            (Some(uid), Some(gid), Some(pid)) => write!(f, "uid {}, gid {}, pid {}", uid, gid, pid),
+            _ => write!(f, "anonymous"),
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_user_id_should_parse_to_user_id() {
+        let callers = [
+            Caller::from_credentials(1000, 100, 4242),
+            Caller::from_public_key(String::from("rq:rM>}U?@Lns47E1%kR.o@n%FcmmsL/@{H8]yf7")),
+            Caller::anonymous(),
+        ];
+
+        for caller in callers {
+            assert_eq!(Caller::from_user_id(&caller.to_user_id()), caller);
+        }
+    }
+
+    #[test]
+    fn from_peer_address_should_ignore_ip_address() {
+        assert_eq!(Caller::from_peer_address(":0:0:1"), Some(Caller::from_credentials(0, 0, 1)));
+        assert_eq!(Caller::from_peer_address("127.0.0.1"), None);
+        assert_eq!(Caller::from_peer_address(":1000:100"), None);
+    }
+}
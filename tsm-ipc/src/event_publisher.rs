@@ -0,0 +1,77 @@
+use protobuf::Message;
+
+use crate::unix_events::UnixPublisher;
+use crate::unix_transport;
+
+
+/// Publishing side of an event stream. Every event is sent as a two-part message with the
+/// topic first, so that subscribers can filter by topic prefix. unix:// addresses are served
+/// without ZMQ.
+pub struct EventPublisher {
+    transport: Transport,
+}
+
+
+enum Transport {
+    #[cfg(feature = "zmq")]
+    Zmq(zmq::Socket),
+    Unix(UnixPublisher),
+}
+
+
+impl EventPublisher {
+    pub fn new(bind_address: String) -> Result<EventPublisher, String> {
+        let transport = match unix_transport::get_path(&bind_address) {
+            Some(path) => Transport::Unix(UnixPublisher::bind(path)?),
+            None => Transport::new_zmq(&bind_address)?,
+        };
+
+        Ok(EventPublisher {
+            transport
+        })
+    }
+
+    /// Events published while nobody is subscribed are dropped
+    pub fn publish<M: Message>(&self, topic: &str, event: &M) -> Result<(), String> {
+        let message = match event.write_to_bytes() {
+            Ok(bytes) => bytes,
+            Err(error) => return Err(format!("Failed to serialize event: {}", error))
+        };
+
+        return match &self.transport {
+            #[cfg(feature = "zmq")]
+            Transport::Zmq(zmq_socket) => match zmq_socket.send_multipart([topic.as_bytes(), &message], 0) {
+                Ok(_) => Ok(()),
+                Err(error) => Err(format!("Failed to publish event: {}", error))
+            },
+            Transport::Unix(publisher) => {
+                publisher.publish(topic, &message);
+                Ok(())
+            },
+        };
+    }
+}
+
+
+impl Transport {
+    #[cfg(feature = "zmq")]
+    fn new_zmq(bind_address: &str) -> Result<Transport, String> {
+        let zmq_context = zmq::Context::new();
+        let zmq_socket = match zmq_context.socket(zmq::PUB) {
+            Ok(socket) => socket,
+            Err(error) => return Err(format!("Failed to create ZMQ socket: {}", error))
+        };
+
+        match zmq_socket.bind(bind_address) {
+            Ok(_) => {},
+            Err(error) => return Err(format!("Failed to bind ZMQ socket to {}: {}", bind_address, error))
+        };
+
+        Ok(Transport::Zmq(zmq_socket))
+    }
+
+    #[cfg(not(feature = "zmq"))]
+    fn new_zmq(bind_address: &str) -> Result<Transport, String> {
+        Err(unix_transport::unsupported_address(bind_address).to_string())
+    }
+}
@@ -0,0 +1,139 @@
+use protobuf::Message;
+
+use crate::unix_events::UnixSubscriber;
+use crate::unix_transport;
+
+
+/// Receiving side of an event stream, see EventPublisher
+pub struct EventSubscriber {
+    transport: Transport,
+}
+
+
+enum Transport {
+    #[cfg(feature = "zmq")]
+    Zmq(zmq::Socket),
+    Unix(UnixSubscriber),
+}
+
+
+impl EventSubscriber {
+    /// Subscribes to all topics starting with one of the given prefixes, or to all topics if there are none
+    pub fn new(address: String, topics: Vec<String>) -> Result<EventSubscriber, String> {
+        let topics = match topics.is_empty() {
+            true => vec![String::new()],
+            false => topics,
+        };
+
+        let transport = match unix_transport::get_path(&address) {
+            Some(path) => Transport::Unix(UnixSubscriber::connect(path, topics)?),
+            None => Transport::new_zmq(&address, topics)?,
+        };
+
+        Ok(EventSubscriber {
+            transport
+        })
+    }
+
+    /// Blocks until the next event arrives and returns it with its topic
+    pub fn receive<M: Message>(&self) -> Result<(String, M), String> {
+        let (topic, message) = match &self.transport {
+            #[cfg(feature = "zmq")]
+            Transport::Zmq(zmq_socket) => receive_zmq(zmq_socket)?,
+            Transport::Unix(subscriber) => subscriber.receive()?,
+        };
+
+        return match M::parse_from_bytes(&message) {
+            Ok(event) => Ok((topic, event)),
+            Err(error) => Err(format!("Failed to parse event: {}", error))
+        };
+    }
+}
+
+
+impl Transport {
+    #[cfg(feature = "zmq")]
+    fn new_zmq(address: &str, topics: Vec<String>) -> Result<Transport, String> {
+        let zmq_context = zmq::Context::new();
+        let zmq_socket = match zmq_context.socket(zmq::SUB) {
+            Ok(socket) => socket,
+            Err(error) => return Err(format!("Failed to create ZMQ socket: {}", error))
+        };
+
+        match zmq_socket.connect(address) {
+            Ok(_) => {},
+            Err(error) => return Err(format!("Failed to connect to ZMQ socket: {}", error))
+        };
+
+        for topic in topics {
+            match zmq_socket.set_subscribe(topic.as_bytes()) {
+                Ok(_) => {},
+                Err(error) => return Err(format!("Failed to subscribe to topic {}: {}", topic, error))
+            };
+        }
+
+        Ok(Transport::Zmq(zmq_socket))
+    }
+
+    #[cfg(not(feature = "zmq"))]
+    fn new_zmq(address: &str, _topics: Vec<String>) -> Result<Transport, String> {
+        Err(unix_transport::unsupported_address(address).to_string())
+    }
+}
+
+
+#[cfg(feature = "zmq")]
+fn receive_zmq(zmq_socket: &zmq::Socket) -> Result<(String, Vec<u8>), String> {
+    let mut parts = match zmq_socket.recv_multipart(0) {
+        Ok(parts) => parts,
+        Err(error) => return Err(format!("Failed to receive event: {}", error))
+    };
+
+    if parts.len() != 2 {
+        return Err(format!("Received event with {} parts instead of 2", parts.len()));
+    }
+
+    let message = parts.pop().unwrap_or_default();
+    let topic = String::from_utf8_lossy(&parts[0]).to_string();
+
+    Ok((topic, message))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+    use std::time::Duration;
+    use super::*;
+    use crate::EventPublisher;
+    use crate::tsm_common_rpc;
+
+    fn assert_only_subscribed_topics_received(address: String) {
+        let publisher = EventPublisher::new(address.clone()).unwrap();
+        let subscriber = EventSubscriber::new(address, vec![String::from("unit_")]).unwrap();
+
+        // subscriptions take a moment to reach the publisher
+        thread::sleep(Duration::from_millis(200));
+
+        let mut event = tsm_common_rpc::RpcRequest::new();
+        event.method = 1;
+        publisher.publish("config_reloaded", &event).unwrap();
+        event.method = 2;
+        publisher.publish("unit_started", &event).unwrap();
+
+        let (topic, received): (String, tsm_common_rpc::RpcRequest) = subscriber.receive().unwrap();
+        assert_eq!(topic, "unit_started");
+        assert_eq!(received.method, 2);
+    }
+
+    #[test]
+    #[cfg(feature = "zmq")]
+    fn receive_should_only_return_subscribed_topics() {
+        assert_only_subscribed_topics_received(format!("ipc:///tmp/tsm-ipc-event-test-{}.sock", std::process::id()));
+    }
+
+    #[test]
+    fn receive_should_only_return_subscribed_topics_over_unix_socket() {
+        assert_only_subscribed_topics_received(format!("unix:///tmp/tsm-ipc-unix-event-test-{}.sock", std::process::id()));
+    }
+}
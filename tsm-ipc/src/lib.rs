@@ -3,5 +3,43 @@ include!(concat!(env!("OUT_DIR"), "/protos/mod.rs"));
 mod rpc_server;
 pub use rpc_server::{RpcServer, RpcRequestHandler};
 
+mod rpc_service;
+pub use rpc_service::RpcService;
+
 mod rpc_client;
-pub use rpc_client::RpcClient;
\ No newline at end of file
+pub use rpc_client::RpcClient;
+
+#[cfg(feature = "tokio")]
+mod async_rpc_server;
+#[cfg(feature = "tokio")]
+pub use async_rpc_server::AsyncRpcServer;
+
+#[cfg(feature = "tokio")]
+mod async_rpc_client;
+#[cfg(feature = "tokio")]
+pub use async_rpc_client::AsyncRpcClient;
+
+#[cfg(feature = "tokio")]
+pub use tokio_util::sync::CancellationToken;
+
+mod rpc_error;
+pub use rpc_error::RpcError;
+
+mod protocol;
+pub use protocol::{ServiceDescription, PROTOCOL_VERSION, MIN_PROTOCOL_VERSION};
+
+mod caller;
+pub use caller::Caller;
+
+mod unix_transport;
+
+#[cfg(feature = "zmq")]
+mod zap_handler;
+
+mod unix_events;
+
+mod event_publisher;
+pub use event_publisher::EventPublisher;
+
+mod event_subscriber;
+pub use event_subscriber::EventSubscriber;
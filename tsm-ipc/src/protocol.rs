@@ -0,0 +1,189 @@
+use protobuf::{Enum, Message};
+
+use crate::tsm_common_rpc;
+use crate::tsm_common_rpc::error::Code;
+use crate::RpcError;
+
+
+/// Sent with every request and response. Raise it for changes older peers can't handle and
+/// MIN_PROTOCOL_VERSION once support for the old behaviour is dropped.
+pub const PROTOCOL_VERSION: u32 = 1;
+pub const MIN_PROTOCOL_VERSION: u32 = 1;
+
+
+/// What a server offers, answered to GetCapabilities
+#[derive(Clone, Debug, Default)]
+pub struct ServiceDescription {
+    name: String,
+    version: String,
+    methods: Vec<(i32, String)>,
+}
+
+
+impl ServiceDescription {
+    pub fn new(name: String, version: String, methods: Vec<(i32, String)>) -> ServiceDescription {
+        ServiceDescription {
+            name,
+            version,
+            methods,
+        }
+    }
+
+    pub fn get_name(&self) -> String {
+        return self.name.clone();
+    }
+
+    /// Requests for another service are refused, e.g. when a client was pointed at the wrong socket
+    pub fn check_request(&self, request: &tsm_common_rpc::RpcRequest) -> Result<(), String> {
+        if !request.service.is_empty() && !self.name.is_empty() && request.service != self.name {
+            return Err(format!("Request for service {}, but this is {}", request.service, self.name));
+        }
+
+        return check_protocol_version(request.protocol_version);
+    }
+
+    pub fn build_capabilities(&self) -> tsm_common_rpc::Capabilities {
+        let mut capabilities = tsm_common_rpc::Capabilities::new();
+        capabilities.service = self.name.clone();
+        capabilities.server_version = self.version.clone();
+        capabilities.protocol_version = PROTOCOL_VERSION;
+        capabilities.min_protocol_version = MIN_PROTOCOL_VERSION;
+
+        for (id, name) in &self.methods {
+            let mut method = tsm_common_rpc::capabilities::Method::new();
+            method.id = *id;
+            method.name = name.clone();
+            capabilities.methods.push(method);
+        }
+
+        capabilities
+    }
+
+    /// Answers the methods every server has, None for the methods of the service
+    pub fn handle_common_request(&self, request: &tsm_common_rpc::RpcRequest) -> Option<tsm_common_rpc::RpcResponse> {
+        if request.method >= 0 {
+            return None;
+        }
+
+        let mut response = tsm_common_rpc::RpcResponse::new();
+        response.method = request.method;
+
+        match tsm_common_rpc::CommonMethod::from_i32(request.method) {
+            Some(tsm_common_rpc::CommonMethod::GetCapabilities) => {
+                match self.build_capabilities().write_to_bytes() {
+                    Ok(bytes) => {
+                        response.status = true;
+                        response.data = bytes;
+                    },
+                    Err(error) => set_error(&mut response, Code::Failed, format!("Failed to serialize capabilities: {}", error), Vec::new()),
+                }
+            },
+            _ => set_error(&mut response, Code::UnknownMethod, format!("Unknown method {}", request.method), Vec::new()),
+        };
+
+        return Some(response);
+    }
+}
+
+
+/// Clients from before protocol versions send 0, their requests are still understood
+pub fn check_protocol_version(protocol_version: u32) -> Result<(), String> {
+    if protocol_version != 0 && !(MIN_PROTOCOL_VERSION..=PROTOCOL_VERSION).contains(&protocol_version) {
+        return Err(format!("Protocol version {} is not supported, only {} to {}", protocol_version, MIN_PROTOCOL_VERSION, PROTOCOL_VERSION));
+    }
+
+    Ok(())
+}
+
+
+/// The message is also put into the plain error field for clients which don't know error codes
+pub(crate) fn set_error(response: &mut tsm_common_rpc::RpcResponse, code: Code, message: String, details: Vec<String>) {
+    let mut error = tsm_common_rpc::Error::new();
+    error.code = protobuf::EnumOrUnknown::new(code);
+    error.message = message.clone();
+    error.details = details;
+
+    response.status = false;
+    response.error = message;
+    response.error_info = protobuf::MessageField::some(error);
+}
+
+
+/// Servers from before error codes only send the message
+pub(crate) fn get_error(response: &tsm_common_rpc::RpcResponse) -> RpcError {
+    return match response.error_info.as_ref() {
+        Some(error) => {
+            let code = Code::from_i32(error.code.value()).unwrap_or(Code::Unknown);
+            let message = match error.message.is_empty() {
+                true => response.error.clone(),
+                false => error.message.clone(),
+            };
+            RpcError::Server { code, message, details: error.details.clone() }
+        },
+        None => RpcError::server(Code::Unknown, response.error.clone()),
+    };
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn build_service() -> ServiceDescription {
+        ServiceDescription::new(String::from("tsm-test"), String::from("1.2.3"), vec![(1, String::from("Ping"))])
+    }
+
+    #[test]
+    fn check_request_should_accept_requests_from_before_versions() {
+        // method 2 with data 0x0a 0x03 "foo", written by a client which only knew method and data
+        let legacy_frame = [0x08, 0x02, 0x12, 0x05, 0x0a, 0x03, 0x66, 0x6f, 0x6f];
+        let request = tsm_common_rpc::RpcRequest::parse_from_bytes(&legacy_frame).unwrap();
+
+        assert_eq!(request.method, 2);
+        assert_eq!(request.protocol_version, 0);
+        assert_eq!(request.correlation_id, 0);
+        assert!(build_service().check_request(&request).is_ok());
+    }
+
+    #[test]
+    fn check_request_should_refuse_other_service_and_version() {
+        let mut request = tsm_common_rpc::RpcRequest::new();
+        request.protocol_version = PROTOCOL_VERSION;
+        request.service = String::from("tsm-other");
+        assert_eq!(build_service().check_request(&request).unwrap_err(), "Request for service tsm-other, but this is tsm-test");
+
+        request.service = String::from("tsm-test");
+        assert!(build_service().check_request(&request).is_ok());
+
+        request.protocol_version = PROTOCOL_VERSION + 1;
+        assert!(build_service().check_request(&request).is_err());
+    }
+
+    #[test]
+    fn handle_common_request_should_answer_capabilities() {
+        let mut request = tsm_common_rpc::RpcRequest::new();
+        request.method = tsm_common_rpc::CommonMethod::GetCapabilities.value();
+
+        let response = build_service().handle_common_request(&request).unwrap();
+        let capabilities = tsm_common_rpc::Capabilities::parse_from_bytes(&response.data).unwrap();
+
+        assert_eq!(capabilities.service, "tsm-test");
+        assert_eq!(capabilities.server_version, "1.2.3");
+        assert_eq!(capabilities.protocol_version, PROTOCOL_VERSION);
+        assert_eq!(capabilities.methods[0].name, "Ping");
+
+        request.method = 1;
+        assert!(build_service().handle_common_request(&request).is_none());
+    }
+
+    #[test]
+    fn get_error_should_read_code_or_fall_back_to_message() {
+        let mut response = tsm_common_rpc::RpcResponse::new();
+        set_error(&mut response, Code::NotFound, String::from("Unit foo not found"), vec![String::from("cause")]);
+        assert_eq!(get_error(&response), RpcError::Server { code: Code::NotFound, message: String::from("Unit foo not found"), details: vec![String::from("cause")] });
+
+        let mut response = tsm_common_rpc::RpcResponse::new();
+        response.error = String::from("Something went wrong");
+        assert_eq!(get_error(&response), RpcError::server(Code::Unknown, String::from("Something went wrong")));
+    }
+}
@@ -1,55 +1,445 @@
-use protobuf::Message;
+use std::cell::Cell;
+#[cfg(feature = "zmq")]
+use std::cell::RefCell;
+use log::debug;
+use protobuf::{Enum, Message};
 
 use crate::tsm_common_rpc;
+use crate::tsm_common_rpc::error::Code;
+use crate::protocol::{self, check_protocol_version, PROTOCOL_VERSION};
+use crate::unix_transport::{self, UnixConnection};
+use crate::RpcError;
 
 
-pub struct RpcClient{
-    zmq_socket: zmq::Socket
+pub const DEFAULT_TIMEOUT_MS: i32 = 5000;
+pub const DEFAULT_RETRIES: u32 = 2;
+
+
+/// Client side of the REQ/REP request-reply pattern. A request which timed out leaves the REQ
+/// socket waiting for a reply that might never come, so the socket is replaced (Lazy Pirate).
+/// unix:// addresses are connected without ZMQ, with the same timeouts and retries.
+pub struct RpcClient {
+    bind_address: String,
+    timeout_ms: i32,
+    retries: u32,
+    #[cfg(feature = "zmq")]
+    curve_keys: Option<CurveKeys>,
+    /// Name of the service requests are meant for, empty skips the check on the server
+    service: String,
+    /// Id of the last request, responses have to carry the id of their request
+    correlation_id: Cell<u64>,
+    transport: Transport,
 }
 
 
-impl RpcClient {
-    pub fn new(bind_address: String) -> Result<RpcClient, String> {
-        let zmq_context = zmq::Context::new();
-        let zmq_socket = match zmq_context.socket(zmq::REQ) {
-            Ok(socket) => socket,
-            Err(error) => return Err(format!("Failed to create ZMQ socket: {}", error))
-        };
+enum Transport {
+    #[cfg(feature = "zmq")]
+    Zmq { zmq_context: zmq::Context, zmq_socket: RefCell<zmq::Socket> },
+    Unix(UnixConnection),
+}
 
-        match zmq_socket.connect(&bind_address) {
-            Ok(_) => {},
-            Err(error) => return Err(format!("Failed to connect to ZMQ socket: {}", error))
+
+/// Decoded keys, so that every new socket doesn't have to check them again
+#[cfg(feature = "zmq")]
+struct CurveKeys {
+    server_public_key: Vec<u8>,
+    public_key: Vec<u8>,
+    secret_key: Vec<u8>,
+}
+
+
+impl RpcClient {
+    pub fn new(bind_address: String) -> Result<RpcClient, RpcError> {
+        let transport = match unix_transport::get_path(&bind_address) {
+            Some(path) => Transport::Unix(UnixConnection::new(String::from(path))),
+            None => Transport::new_zmq(&bind_address)?,
         };
 
         Ok(RpcClient {
-            zmq_socket
+            bind_address,
+            timeout_ms: DEFAULT_TIMEOUT_MS,
+            retries: DEFAULT_RETRIES,
+            #[cfg(feature = "zmq")]
+            curve_keys: None,
+            service: String::new(),
+            correlation_id: Cell::new(0),
+            transport,
         })
     }
 
-    pub fn send(&self, request: tsm_common_rpc::RpcRequest) -> Result<tsm_common_rpc::RpcResponse, String> {
-        let message = match request.write_to_bytes() {
+    /// Gives up sending or receiving after timeout_ms, -1 waits forever
+    pub fn set_timeout(&mut self, timeout_ms: i32) -> Result<(), RpcError> {
+        self.timeout_ms = timeout_ms;
+        self.reset()
+    }
+
+    /// How often send_idempotent resends a request after a timeout
+    pub fn set_retries(&mut self, retries: u32) {
+        self.retries = retries;
+    }
+
+    /// The server refuses requests for another service, e.g. when pointed at the wrong socket
+    pub fn set_service(&mut self, service: String) {
+        self.service = service;
+    }
+
+    /// Encrypts the connection with CURVE, all keys Z85 encoded. The server identifies the client
+    /// by its public key. Not supported on unix:// addresses.
+    #[cfg(feature = "zmq")]
+    pub fn set_curve(&mut self, server_public_key: String, public_key: String, secret_key: String) -> Result<(), RpcError> {
+        if matches!(self.transport, Transport::Unix(_)) {
+            return Err(RpcError::Socket(format!("CURVE is not supported on {}", self.bind_address)));
+        }
+
+        let decode = |key: &str| match zmq::z85_decode(key) {
+            Ok(key) => Ok(key),
+            Err(error) => Err(RpcError::Socket(format!("Invalid CURVE key: {}", error))),
+        };
+
+        self.curve_keys = Some(CurveKeys {
+            server_public_key: decode(&server_public_key)?,
+            public_key: decode(&public_key)?,
+            secret_key: decode(&secret_key)?,
+        });
+        self.reset()
+    }
+
+    #[cfg(not(feature = "zmq"))]
+    pub fn set_curve(&mut self, _server_public_key: String, _public_key: String, _secret_key: String) -> Result<(), RpcError> {
+        Err(RpcError::Socket(String::from("CURVE needs the zmq feature")))
+    }
+
+    /// Sends the request once, the client stays usable after a timeout
+    pub fn send(&self, request: tsm_common_rpc::RpcRequest) -> Result<tsm_common_rpc::RpcResponse, RpcError> {
+        self.send_attempts(request, 1)
+    }
+
+    /// Resends the request after a timeout, only for requests that do no harm when handled twice
+    pub fn send_idempotent(&self, request: tsm_common_rpc::RpcRequest) -> Result<tsm_common_rpc::RpcResponse, RpcError> {
+        self.send_attempts(request, self.retries.saturating_add(1))
+    }
+
+    /// Sends the typed request once. Error responses are returned as RpcError::Server.
+    pub fn call<Req: Message, Resp: Message>(&self, method: i32, request: &Req) -> Result<Resp, RpcError> {
+        self.call_attempts(method, request, 1)
+    }
+
+    /// Like call, but resends the request after a timeout as send_idempotent does
+    pub fn call_idempotent<Req: Message, Resp: Message>(&self, method: i32, request: &Req) -> Result<Resp, RpcError> {
+        self.call_attempts(method, request, self.retries.saturating_add(1))
+    }
+
+    fn call_attempts<Req: Message, Resp: Message>(&self, method: i32, request: &Req, attempts: u32) -> Result<Resp, RpcError> {
+        let mut rpc_request = tsm_common_rpc::RpcRequest::new();
+        rpc_request.method = method;
+        rpc_request.data = match request.write_to_bytes() {
             Ok(bytes) => bytes,
-            Err(error) => return Err(format!("Failed to serialize request: {}", error))
+            Err(error) => return Err(RpcError::Serialize(error.to_string())),
         };
 
-        match self.zmq_socket.send(&message, 0) {
-            Ok(_) => {},
-            Err(error) => return Err(format!("Failed to send request: {}", error))
+        let response = self.send_attempts(rpc_request, attempts)?;
+
+        if !response.status {
+            return Err(protocol::get_error(&response));
+        }
+
+        return match Resp::parse_from_bytes(&response.data) {
+            Ok(response) => Ok(response),
+            Err(error) => Err(RpcError::Parse(error.to_string())),
         };
+    }
 
-        let bytes = match self.zmq_socket.recv_bytes(0) {
+    /// Asks the server for its version and methods, servers from before GetCapabilities are
+    /// reported as incompatible
+    pub fn get_capabilities(&self) -> Result<tsm_common_rpc::Capabilities, RpcError> {
+        let mut request = tsm_common_rpc::RpcRequest::new();
+        request.method = tsm_common_rpc::CommonMethod::GetCapabilities.value();
+
+        let response = self.send_idempotent(request)?;
+
+        if !response.status {
+            return Err(RpcError::Incompatible(format!("Server does not support capabilities: {}", response.error)));
+        }
+
+        return match tsm_common_rpc::Capabilities::parse_from_bytes(&response.data) {
+            Ok(capabilities) => Ok(capabilities),
+            Err(error) => Err(RpcError::Parse(error.to_string())),
+        };
+    }
+
+    /// The same correlation id is used for all attempts of a request
+    fn send_attempts(&self, mut request: tsm_common_rpc::RpcRequest, attempts: u32) -> Result<tsm_common_rpc::RpcResponse, RpcError> {
+        let correlation_id = self.correlation_id.get().wrapping_add(1).max(1);
+        self.correlation_id.set(correlation_id);
+
+        request.protocol_version = PROTOCOL_VERSION;
+        request.service = self.service.clone();
+        request.correlation_id = correlation_id;
+
+        let message = match request.write_to_bytes() {
             Ok(bytes) => bytes,
-            Err(error) => return Err(format!("Failed to receive response: {}", error))
+            Err(error) => return Err(RpcError::Serialize(error.to_string()))
+        };
+
+        for attempt in 1..=attempts {
+            match self.send_once(&message).and_then(|response| check_response(response, correlation_id)) {
+                Err(RpcError::Timeout { .. }) => {
+                    debug!("Request to {} timed out, attempt {} of {}", self.bind_address, attempt, attempts);
+                    self.reset()?;
+                },
+                result => return result,
+            };
+        }
+
+        Err(RpcError::Timeout { attempts })
+    }
+
+    fn send_once(&self, message: &[u8]) -> Result<tsm_common_rpc::RpcResponse, RpcError> {
+        let bytes = match &self.transport {
+            #[cfg(feature = "zmq")]
+            Transport::Zmq { zmq_socket, .. } => send_zmq(&zmq_socket.borrow(), message)?,
+            Transport::Unix(connection) => connection.send_once(message, self.timeout_ms)?,
         };
 
         return match bytes.len() {
-            0 => Err(String::from("Received empty response")),
+            0 => Err(RpcError::EmptyResponse),
             _ => {
                 match Message::parse_from_bytes(&bytes) {
                     Ok(message) => Ok(message),
-                    Err(error) => Err(format!("Failed to parse response: {}", error))
+                    Err(error) => Err(RpcError::Parse(error.to_string()))
                 }
             }
         };
     }
-}
\ No newline at end of file
+
+    /// Replaces the socket, a late reply to the old one is discarded with it
+    fn reset(&self) -> Result<(), RpcError> {
+        match &self.transport {
+            #[cfg(feature = "zmq")]
+            Transport::Zmq { zmq_context, zmq_socket } => {
+                zmq_socket.replace(connect(zmq_context, &self.bind_address, self.timeout_ms, &self.curve_keys)?);
+            },
+            Transport::Unix(connection) => connection.reset(),
+        };
+
+        Ok(())
+    }
+}
+
+
+impl Transport {
+    #[cfg(feature = "zmq")]
+    fn new_zmq(bind_address: &str) -> Result<Transport, RpcError> {
+        let zmq_context = zmq::Context::new();
+        let zmq_socket = connect(&zmq_context, bind_address, DEFAULT_TIMEOUT_MS, &None)?;
+
+        Ok(Transport::Zmq { zmq_context, zmq_socket: RefCell::new(zmq_socket) })
+    }
+
+    #[cfg(not(feature = "zmq"))]
+    fn new_zmq(bind_address: &str) -> Result<Transport, RpcError> {
+        Err(unix_transport::unsupported_address(bind_address))
+    }
+}
+
+
+/// Servers from before protocol versions answer with version and correlation id 0
+fn check_response(response: tsm_common_rpc::RpcResponse, correlation_id: u64) -> Result<tsm_common_rpc::RpcResponse, RpcError> {
+    if response.correlation_id != 0 && response.correlation_id != correlation_id {
+        return Err(RpcError::UnexpectedResponse(format!("Correlation id {} instead of {}", response.correlation_id, correlation_id)));
+    }
+
+    if let Err(error) = check_protocol_version(response.protocol_version) {
+        return Err(RpcError::Incompatible(error));
+    }
+
+    let code = response.error_info.as_ref().and_then(|error| Code::from_i32(error.code.value()));
+    if !response.status && code == Some(Code::Incompatible) {
+        return Err(RpcError::Incompatible(response.error));
+    }
+
+    Ok(response)
+}
+
+
+#[cfg(feature = "zmq")]
+fn send_zmq(zmq_socket: &zmq::Socket, message: &[u8]) -> Result<Vec<u8>, RpcError> {
+    match zmq_socket.send(message, 0) {
+        Ok(_) => {},
+        Err(zmq::Error::EAGAIN) => return Err(RpcError::Timeout { attempts: 1 }),
+        Err(error) => return Err(RpcError::Send(error.to_string()))
+    };
+
+    return match zmq_socket.recv_bytes(0) {
+        Ok(bytes) => Ok(bytes),
+        Err(zmq::Error::EAGAIN) => Err(RpcError::Timeout { attempts: 1 }),
+        Err(error) => Err(RpcError::Receive(error.to_string()))
+    };
+}
+
+
+/// Pending requests are dropped on close instead of blocking the exit when the server is down
+#[cfg(feature = "zmq")]
+fn connect(zmq_context: &zmq::Context, bind_address: &str, timeout_ms: i32, curve_keys: &Option<CurveKeys>) -> Result<zmq::Socket, RpcError> {
+    let zmq_socket = match zmq_context.socket(zmq::REQ) {
+        Ok(socket) => socket,
+        Err(error) => return Err(RpcError::Socket(format!("Failed to create ZMQ socket: {}", error)))
+    };
+
+    let options = [
+        zmq_socket.set_sndtimeo(timeout_ms),
+        zmq_socket.set_rcvtimeo(timeout_ms),
+        zmq_socket.set_connect_timeout(timeout_ms.max(0)),
+        zmq_socket.set_linger(0),
+    ];
+
+    for option in options {
+        match option {
+            Ok(_) => {},
+            Err(error) => return Err(RpcError::Socket(format!("Failed to set ZMQ socket timeout: {}", error)))
+        };
+    }
+
+    if let Some(curve_keys) = curve_keys {
+        let options = [
+            zmq_socket.set_curve_serverkey(&curve_keys.server_public_key),
+            zmq_socket.set_curve_publickey(&curve_keys.public_key),
+            zmq_socket.set_curve_secretkey(&curve_keys.secret_key),
+        ];
+
+        for option in options {
+            match option {
+                Ok(_) => {},
+                Err(error) => return Err(RpcError::Socket(format!("Failed to enable CURVE: {}", error)))
+            };
+        }
+    }
+
+    match zmq_socket.connect(bind_address) {
+        Ok(_) => {},
+        Err(error) => return Err(RpcError::Socket(format!("Failed to connect to ZMQ socket: {}", error)))
+    };
+
+    Ok(zmq_socket)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+    use super::*;
+
+    /// Answers requests with their method, but drops the first replies. A ROUTER socket is used
+    /// because a REP socket can't receive again without replying.
+    #[cfg(feature = "zmq")]
+    fn run_flaky_server(address: String, dropped_replies: usize, requests: usize) -> thread::JoinHandle<()> {
+        let zmq_context = zmq::Context::new();
+        let router = zmq_context.socket(zmq::ROUTER).unwrap();
+        router.bind(&address).unwrap();
+
+        thread::spawn(move || {
+            let _zmq_context = zmq_context;
+
+            for index in 0..requests {
+                let parts = router.recv_multipart(0).unwrap();
+
+                if index < dropped_replies {
+                    continue;
+                }
+
+                let request = tsm_common_rpc::RpcRequest::parse_from_bytes(&parts[2]).unwrap();
+                let mut response = tsm_common_rpc::RpcResponse::new();
+                response.method = request.method;
+                response.status = true;
+
+                let bytes = response.write_to_bytes().unwrap();
+                router.send_multipart([&parts[0], &parts[1], &bytes], 0).unwrap();
+            }
+        })
+    }
+
+    fn build_request(method: i32) -> tsm_common_rpc::RpcRequest {
+        let mut request = tsm_common_rpc::RpcRequest::new();
+        request.method = method;
+
+        request
+    }
+
+    fn build_client(address: String) -> RpcClient {
+        let mut client = RpcClient::new(address).unwrap();
+        client.set_timeout(200).unwrap();
+
+        client
+    }
+
+    #[test]
+    #[cfg(feature = "zmq")]
+    fn send_idempotent_should_retry_after_dropped_reply() {
+        let address = format!("ipc:///tmp/tsm-ipc-rpc-retry-test-{}.sock", std::process::id());
+        let server = run_flaky_server(address.clone(), 2, 3);
+        let client = build_client(address);
+
+        let response = client.send_idempotent(build_request(7)).unwrap();
+        assert_eq!(response.method, 7);
+
+        server.join().unwrap();
+    }
+
+    #[test]
+    #[cfg(feature = "zmq")]
+    fn send_should_stay_usable_after_timeout() {
+        let address = format!("ipc:///tmp/tsm-ipc-rpc-reset-test-{}.sock", std::process::id());
+        let server = run_flaky_server(address.clone(), 1, 2);
+        let client = build_client(address);
+
+        assert_eq!(client.send(build_request(1)).unwrap_err(), RpcError::Timeout { attempts: 1 });
+        assert_eq!(client.send(build_request(2)).unwrap().method, 2);
+
+        server.join().unwrap();
+    }
+
+    #[test]
+    #[cfg(feature = "zmq")]
+    fn send_idempotent_should_give_up_without_server() {
+        let address = format!("ipc:///tmp/tsm-ipc-rpc-absent-test-{}.sock", std::process::id());
+        let mut client = build_client(address);
+        client.set_retries(1);
+
+        assert_eq!(client.send_idempotent(build_request(1)).unwrap_err(), RpcError::Timeout { attempts: 2 });
+    }
+
+    #[test]
+    fn send_idempotent_should_give_up_without_unix_server() {
+        let address = format!("unix:///tmp/tsm-ipc-rpc-unix-absent-test-{}.sock", std::process::id());
+        let mut client = build_client(address);
+        client.set_retries(1);
+
+        assert_eq!(client.send_idempotent(build_request(1)).unwrap_err(), RpcError::Timeout { attempts: 2 });
+    }
+
+    #[test]
+    fn send_should_stay_usable_after_unix_server_closed_connection() {
+        let path = format!("/tmp/tsm-ipc-rpc-unix-reset-test-{}.sock", std::process::id());
+        let _ = std::fs::remove_file(&path);
+        let listener = std::os::unix::net::UnixListener::bind(&path).unwrap();
+
+        // the first connection is closed without reply, the second one gets an answer
+        let server = thread::spawn(move || {
+            drop(listener.accept().unwrap());
+
+            let (mut stream, _) = listener.accept().unwrap();
+            let request = tsm_common_rpc::RpcRequest::parse_from_bytes(&unix_transport::read_frame(&mut stream).unwrap()).unwrap();
+            let mut response = tsm_common_rpc::RpcResponse::new();
+            response.method = request.method;
+            response.status = true;
+            unix_transport::write_frame(&mut stream, &response.write_to_bytes().unwrap()).unwrap();
+        });
+
+        let client = build_client(format!("unix://{}", path));
+        assert!(matches!(client.send(build_request(1)), Err(RpcError::Receive(_) | RpcError::Send(_))));
+        assert_eq!(client.send(build_request(2)).unwrap().method, 2);
+
+        server.join().unwrap();
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,57 @@
+use std::fmt;
+
+use crate::tsm_common_rpc::error::Code;
+
+
+/// Errors of RpcClient and RpcServer. Only Timeout may be retried, the request might have reached the server anyway.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RpcError {
+    /// Creating, configuring or connecting the socket failed
+    Socket(String),
+    /// The server could not bind, e.g. because the address is already in use
+    Bind { address: String, error: String },
+    Serialize(String),
+    Send(String),
+    Receive(String),
+    /// No response within the timeout, after this many attempts
+    Timeout { attempts: u32 },
+    EmptyResponse,
+    Parse(String),
+    /// The server refused the protocol version or is another service
+    Incompatible(String),
+    /// The response does not belong to the request, e.g. a different correlation id
+    UnexpectedResponse(String),
+    /// The request was handled, but failed. Returned by handlers of an RpcService, the client
+    /// gets the same error back from call.
+    Server { code: Code, message: String, details: Vec<String> },
+}
+
+
+impl RpcError {
+    pub fn server(code: Code, message: String) -> RpcError {
+        RpcError::Server { code, message, details: Vec::new() }
+    }
+}
+
+
+impl fmt::Display for RpcError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RpcError::Socket(error) => write!(f, "Failed to set up socket: {}", error),
+            RpcError::Bind { address, error } => write!(f, "Failed to bind socket to {}: {}", address, error),
+            RpcError::Serialize(error) => write!(f, "Failed to serialize request: {}", error),
+            RpcError::Send(error) => write!(f, "Failed to send request: {}", error),
+            RpcError::Receive(error) => write!(f, "Failed to receive response: {}", error),
+            RpcError::Timeout { attempts: 1 } => write!(f, "Timed out waiting for response"),
+            RpcError::Timeout { attempts } => write!(f, "Timed out waiting for response after {} attempts", attempts),
+            RpcError::EmptyResponse => write!(f, "Received empty response"),
+            RpcError::Parse(error) => write!(f, "Failed to parse response: {}", error),
+            RpcError::Incompatible(error) => write!(f, "Incompatible server: {}", error),
+            RpcError::UnexpectedResponse(error) => write!(f, "Unexpected response: {}", error),
+            RpcError::Server { message, .. } => write!(f, "{}", message),
+        }
+    }
+}
+
+
+impl std::error::Error for RpcError {}
@@ -1,96 +1,723 @@
+use std::any::Any;
+#[cfg(feature = "zmq")]
+use std::mem;
+#[cfg(feature = "zmq")]
+use std::os::unix::io::RawFd;
+use std::panic::{self, AssertUnwindSafe};
 use std::thread;
-use std::sync::{Arc, Mutex};
-use log::{debug, warn};
+use std::sync::Arc;
+#[cfg(feature = "zmq")]
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use log::{debug, error, warn};
 use protobuf::Message;
 
 use crate::tsm_common_rpc;
+use crate::tsm_common_rpc::error::Code;
+use crate::{Caller, RpcError};
+use crate::protocol::{self, ServiceDescription, PROTOCOL_VERSION};
+use crate::unix_transport;
+#[cfg(feature = "zmq")]
+use crate::zap_handler::{ZapHandler, ZAP_DOMAIN};
 
 
-pub trait RpcRequestHandler: Send {
-    fn handle_request(&self, request: tsm_common_rpc::RpcRequest) -> tsm_common_rpc::RpcResponse;
+pub const DEFAULT_WORKERS: usize = 4;
+
+#[cfg(feature = "zmq")]
+static SERVER_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+/// Message property with the file descriptor a message was read from, deprecated in libzmq but
+/// the only way to learn the peer of an ipc:// connection when libzmq is built without SO_PEERCRED
+#[cfg(feature = "zmq")]
+const ZMQ_SRCFD: i32 = 2;
+
+// zmq::Message only wraps a zmq_msg_t, so a reference to it can be passed to libzmq as one
+#[cfg(feature = "zmq")]
+const _: () = assert!(mem::size_of::<zmq::Message>() == mem::size_of::<zmq_sys::zmq_msg_t>());
+
+
+/// Called by several workers at once, so implementations do their own locking
+pub trait RpcRequestHandler: Send + Sync {
+    fn handle_request(&self, request: tsm_common_rpc::RpcRequest, caller: &Caller) -> tsm_common_rpc::RpcResponse;
 }
 
 
+/// Clients connect to a ROUTER socket, which passes the requests on through a DEALER socket to a
+/// pool of REP workers. While all workers are busy, requests queue up instead of being dropped.
+/// Every request is passed on together with the caller, who is identified when connecting.
+/// unix:// addresses are served without ZMQ over a plain unix stream socket, see unix_transport.
 pub struct RpcServer {
     bind_address: String,
-    request_handler: Arc<Mutex<dyn RpcRequestHandler>>,
+    request_handler: Arc<dyn RpcRequestHandler>,
+    workers: usize,
+    service: Arc<ServiceDescription>,
+    curve_secret_key: Option<String>,
+    authorized_keys: Vec<String>,
 }
 
 
 impl RpcServer {
-    pub fn new(bind_address: String, request_handler: Arc<Mutex<dyn RpcRequestHandler>>) -> Self {
+    pub fn new(bind_address: String, request_handler: Arc<dyn RpcRequestHandler>) -> Self {
         Self {
             bind_address,
             request_handler,
+            workers: DEFAULT_WORKERS,
+            service: Arc::new(ServiceDescription::default()),
+            curve_secret_key: None,
+            authorized_keys: Vec::new(),
         }
     }
 
-    pub fn run_threaded(self) -> thread::JoinHandle<()> {
-        debug!("Spawning thread");
-        thread::spawn(move || {
-            self.run();
-        })
+    /// Number of requests handled at the same time, at least one
+    pub fn set_workers(&mut self, workers: usize) {
+        self.workers = workers.max(1);
+    }
+
+    /// Answered to GetCapabilities, requests naming another service are refused
+    pub fn set_service(&mut self, service: ServiceDescription) {
+        self.service = Arc::new(service);
     }
 
-    fn run(self) {
+    /// Encrypts connections with CURVE and identifies clients by their public key. Only the given
+    /// Z85 encoded client keys may connect, all keys if empty. Meant for tcp endpoints, unix socket
+    /// callers are identified by their credentials anyway. Not supported on unix:// addresses.
+    pub fn set_curve(&mut self, secret_key: String, authorized_keys: Vec<String>) {
+        self.curve_secret_key = Some(secret_key);
+        self.authorized_keys = authorized_keys;
+    }
+
+    /// Binds before spawning the threads, so that a used address is reported to the caller
+    pub fn run_threaded(self) -> Result<thread::JoinHandle<()>, RpcError> {
+        let path = match unix_transport::get_path(&self.bind_address) {
+            Some(path) => String::from(path),
+            None => return self.run_zmq(),
+        };
+
+        if self.curve_secret_key.is_some() || !self.authorized_keys.is_empty() {
+            warn!("CURVE is not supported on {}, callers are identified by their credentials", self.bind_address);
+        }
+
+        let listener = unix_transport::bind(&path)?;
+        debug!("Handling requests on {} with {} workers", self.bind_address, self.workers);
+
+        Ok(unix_transport::run_threaded(listener, self.workers, self.service, self.request_handler))
+    }
+
+    #[cfg(feature = "zmq")]
+    fn run_zmq(self) -> Result<thread::JoinHandle<()>, RpcError> {
         let context = zmq::Context::new();
-        let responder = match context.socket(zmq::REP) {
+        let zap_handler = ZapHandler::bind(&context, self.authorized_keys.clone())?;
+
+        let frontend = match context.socket(zmq::ROUTER) {
             Ok(socket) => socket,
+            Err(error) => return Err(RpcError::Socket(format!("Failed to create ZMQ socket: {}", error))),
+        };
+        configure_security(&frontend, &self.curve_secret_key)?;
+
+        match frontend.bind(&self.bind_address) {
+            Ok(_) => {},
+            Err(error) => return Err(RpcError::Bind { address: self.bind_address.clone(), error: error.to_string() }),
+        };
+
+        // every server needs its own inproc address, there might be several in one process
+        let workers_address = format!("inproc://tsm-ipc-rpc-workers-{}", SERVER_COUNT.fetch_add(1, Ordering::Relaxed));
+        let backend = bind(&context, zmq::DEALER, &workers_address)?;
+
+        // the blocking server runs as long as the process
+        zap_handler.run_threaded(Arc::new(AtomicBool::new(false)));
+
+        for index in 0..self.workers {
+            let context = context.clone();
+            let workers_address = workers_address.clone();
+            let request_handler = self.request_handler.clone();
+            let service = self.service.clone();
+
+            thread::spawn(move || {
+                run_worker(index, context, workers_address, service, request_handler);
+            });
+        }
+
+        debug!("Handling requests on {} with {} workers", self.bind_address, self.workers);
+
+        Ok(thread::spawn(move || {
+            let _context = context;
+            run_proxy(frontend, backend);
+        }))
+    }
+
+    #[cfg(not(feature = "zmq"))]
+    fn run_zmq(self) -> Result<thread::JoinHandle<()>, RpcError> {
+        Err(unix_transport::unsupported_address(&self.bind_address))
+    }
+}
+
+
+/// The ZAP domain makes libzmq authenticate unix socket connections too, to learn their credentials
+#[cfg(feature = "zmq")]
+pub(crate) fn configure_security(socket: &zmq::Socket, curve_secret_key: &Option<String>) -> Result<(), RpcError> {
+    match socket.set_zap_domain(ZAP_DOMAIN) {
+        Ok(_) => {},
+        Err(error) => return Err(RpcError::Socket(format!("Failed to set ZAP domain: {}", error))),
+    };
+
+    let secret_key = match curve_secret_key {
+        Some(secret_key) => secret_key,
+        None => return Ok(()),
+    };
+
+    let secret_key = match zmq::z85_decode(secret_key) {
+        Ok(secret_key) => secret_key,
+        Err(error) => return Err(RpcError::Socket(format!("Invalid CURVE secret key: {}", error))),
+    };
+
+    let options = [
+        socket.set_curve_server(true),
+        socket.set_curve_secretkey(&secret_key),
+    ];
+
+    for option in options {
+        match option {
+            Ok(_) => {},
+            Err(error) => return Err(RpcError::Socket(format!("Failed to enable CURVE: {}", error))),
+        };
+    }
+
+    Ok(())
+}
+
+
+/// Like zmq::proxy, but puts the caller in front of every request. Clients can't forge it, the
+/// workers always take the first frame after the envelope as caller.
+#[cfg(feature = "zmq")]
+fn run_proxy(frontend: zmq::Socket, backend: zmq::Socket) {
+    loop {
+        let mut items = [
+            frontend.as_poll_item(zmq::POLLIN),
+            backend.as_poll_item(zmq::POLLIN),
+        ];
+
+        match zmq::poll(&mut items, -1) {
+            Ok(_) => {},
             Err(error) => {
-                warn!("Failed to create socket: {}", error);
-                return;
+                error!("Failed to poll sockets: {}", error);
+                continue;
             },
         };
 
-        assert!(responder.bind(self.bind_address.as_str()).is_ok());
+        if items[0].is_readable() {
+            match receive_with_caller(&frontend, 0) {
+                Ok(Some(frames)) => forward(&backend, frames),
+                Ok(None) => {},
+                Err(error) => warn!("Failed to receive request: {}", error),
+            };
+        }
 
-        loop {
-            // https://github.com/pronebird/node-rust-zeromq/blob/master/server/src/main.rs
-            let request: Option<tsm_common_rpc::RpcRequest> = match responder.recv_bytes(0) {
-                Ok(bytes) => Some(Message::parse_from_bytes(&bytes).unwrap()),
-                Err(_) => None,
+        if items[1].is_readable() {
+            match backend.recv_multipart(0) {
+                Ok(frames) => forward(&frontend, frames),
+                Err(error) => warn!("Failed to receive response: {}", error),
             };
+        }
+    }
+}
 
-            // handle request
-            let response: Option<tsm_common_rpc::RpcResponse> = match request {
-                Some(request) => {
-                    debug!("Received request: {:?}", request);
-
-                    match self.request_handler.try_lock() {
-                        Ok(request_handler) => {
-                            Some(request_handler.handle_request(request))
-                        },
-                        Err(error) => {
-                            warn!("Failed to lock request handler: {}", error);
-                            None
-                        },
-                    }
-                },
-                None => None,
+
+/// Requests without envelope don't come from REQ or DEALER clients following the protocol, they
+/// are dropped. The flags only apply to the first frame, the others are there once it is.
+#[cfg(feature = "zmq")]
+pub(crate) fn receive_with_caller(frontend: &zmq::Socket, flags: i32) -> Result<Option<Vec<Vec<u8>>>, zmq::Error> {
+    let mut frames: Vec<Vec<u8>> = Vec::new();
+    let mut user_id = String::new();
+    let mut source_fd = None;
+
+    loop {
+        let mut message = zmq::Message::new();
+        let flags = match frames.is_empty() {
+            true => flags,
+            false => 0,
+        };
+        frontend.recv(&mut message, flags)?;
+
+        // the routing id is added locally, only frames which came over the wire carry the user id
+        if let Some(value) = message.gets("User-Id") {
+            user_id = String::from(value);
+        }
+
+        if source_fd.is_none() {
+            source_fd = get_source_fd(&message);
+        }
+
+        let more = message.get_more();
+        frames.push(message.to_vec());
+
+        if !more {
+            break;
+        }
+    }
+
+    let delimiter = match frames.iter().position(|frame| frame.is_empty()) {
+        Some(delimiter) => delimiter,
+        None => {
+            warn!("Dropped request without envelope");
+            return Ok(None);
+        },
+    };
+
+    // without a user id from the ZAP handler unix socket peers are identified by their fd, tcp
+    // peers stay anonymous. libzmq closes the fd only after its I/O thread noticed the peer left.
+    if user_id.is_empty() {
+        if let Some(caller) = source_fd.and_then(unix_transport::get_peer_credentials) {
+            user_id = caller.to_user_id();
+        }
+    }
+
+    frames.insert(delimiter + 1, user_id.into_bytes());
+    Ok(Some(frames))
+}
+
+
+#[cfg(feature = "zmq")]
+fn get_source_fd(message: &zmq::Message) -> Option<RawFd> {
+    let fd = unsafe { zmq_sys::zmq_msg_get(message as *const zmq::Message as *const zmq_sys::zmq_msg_t, ZMQ_SRCFD) };
+
+    return match fd {
+        fd if fd >= 0 => Some(fd),
+        _ => None,
+    };
+}
+
+
+#[cfg(feature = "zmq")]
+fn forward(socket: &zmq::Socket, frames: Vec<Vec<u8>>) {
+    match socket.send_multipart(frames, 0) {
+        Ok(_) => {},
+        Err(error) => warn!("Failed to forward message: {}", error),
+    };
+}
+
+
+#[cfg(feature = "zmq")]
+fn bind(context: &zmq::Context, socket_type: zmq::SocketType, address: &str) -> Result<zmq::Socket, RpcError> {
+    let socket = match context.socket(socket_type) {
+        Ok(socket) => socket,
+        Err(error) => return Err(RpcError::Socket(format!("Failed to create ZMQ socket: {}", error))),
+    };
+
+    return match socket.bind(address) {
+        Ok(_) => Ok(socket),
+        Err(error) => Err(RpcError::Bind { address: String::from(address), error: error.to_string() }),
+    };
+}
+
+
+/// A REP socket only receives the next request after replying, so every request gets a response
+#[cfg(feature = "zmq")]
+fn run_worker(index: usize, context: zmq::Context, workers_address: String, service: Arc<ServiceDescription>, request_handler: Arc<dyn RpcRequestHandler>) {
+    let responder = match context.socket(zmq::REP) {
+        Ok(socket) => socket,
+        Err(error) => {
+            error!("Failed to create socket for worker {}: {}", index, error);
+            return;
+        },
+    };
+
+    match responder.connect(workers_address.as_str()) {
+        Ok(_) => {},
+        Err(error) => {
+            error!("Failed to connect worker {}: {}", index, error);
+            return;
+        },
+    };
+
+    loop {
+        // the whole message is read, a REP socket refuses to reply while parts are left
+        let frames = match responder.recv_multipart(0) {
+            Ok(frames) => frames,
+            Err(error) => {
+                warn!("Failed to receive request: {}", error);
+                continue;
+            },
+        };
+
+        let message = handle_frames(&frames, &service, request_handler.as_ref());
+
+        match responder.send(message, 0) {
+            Ok(_) => (),
+            Err(error) => warn!("Failed to send response: {}", error),
+        };
+    }
+}
+
+
+/// Never fails, anything that goes wrong is answered with an error response. The first frame is
+/// the caller put there by the proxy.
+pub(crate) fn handle_frames(frames: &[Vec<u8>], service: &ServiceDescription, request_handler: &dyn RpcRequestHandler) -> Vec<u8> {
+    let (caller, frames) = match frames.split_first() {
+        Some((user_id, frames)) => (Caller::from_user_id(&String::from_utf8_lossy(user_id)), frames),
+        None => (Caller::anonymous(), frames),
+    };
+
+    let response = match frames {
+        [bytes] => match tsm_common_rpc::RpcRequest::parse_from_bytes(bytes) {
+            Ok(request) => handle_request(request, &caller, service, request_handler),
+            Err(error) => {
+                warn!("Failed to parse request: {}", error);
+                build_error_response(Code::InvalidRequest, format!("Failed to parse request: {}", error))
+            },
+        },
+        _ => {
+            warn!("Received request with {} frames", frames.len());
+            build_error_response(Code::InvalidRequest, format!("Expected a request in a single frame, got {} frames", frames.len()))
+        },
+    };
+
+    debug!("Sending response: {:?}", response);
+
+    return match response.write_to_bytes() {
+        Ok(bytes) => bytes,
+        Err(error) => {
+            warn!("Failed to serialize response: {}", error);
+            build_error_response(Code::Failed, format!("Failed to serialize response: {}", error))
+                .write_to_bytes()
+                .unwrap_or_default()
+        },
+    };
+}
+
+
+/// The response carries the protocol version of the server and the correlation id of the request
+fn handle_request(request: tsm_common_rpc::RpcRequest, caller: &Caller, service: &ServiceDescription, request_handler: &dyn RpcRequestHandler) -> tsm_common_rpc::RpcResponse {
+    debug!("Received request from {}: {:?}", caller, request);
+    let method = request.method;
+    let correlation_id = request.correlation_id;
+
+    let mut response = match service.check_request(&request) {
+        Ok(_) => match service.handle_common_request(&request) {
+            Some(response) => response,
+            None => handle_service_request(request, caller, request_handler),
+        },
+        Err(message) => {
+            warn!("Refused request from {}: {}", caller, message);
+            build_error_response(Code::Incompatible, message)
+        },
+    };
+
+    response.method = method;
+    response.protocol_version = PROTOCOL_VERSION;
+    response.correlation_id = correlation_id;
+    return response;
+}
+
+
+/// A panicking handler is answered with an error, the worker stays alive for the next request
+fn handle_service_request(request: tsm_common_rpc::RpcRequest, caller: &Caller, request_handler: &dyn RpcRequestHandler) -> tsm_common_rpc::RpcResponse {
+    let method = request.method;
+
+    return match panic::catch_unwind(AssertUnwindSafe(|| request_handler.handle_request(request, caller))) {
+        Ok(response) => response,
+        Err(payload) => {
+            let message = format!("Request handler panicked: {}", format_panic_payload(payload.as_ref()));
+            error!("{} (method {})", message, method);
+            build_error_response(Code::Failed, message)
+        },
+    };
+}
+
+
+fn format_panic_payload(payload: &(dyn Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        return String::from(*message);
+    }
+
+    return match payload.downcast_ref::<String>() {
+        Some(message) => message.clone(),
+        None => String::from("unknown cause"),
+    };
+}
+
+
+fn build_error_response(code: Code, message: String) -> tsm_common_rpc::RpcResponse {
+    let mut response = tsm_common_rpc::RpcResponse::new();
+    protocol::set_error(&mut response, code, message, Vec::new());
+
+    response
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+    use proptest::prelude::*;
+    use crate::RpcClient;
+    use super::*;
+
+    /// Answers with the method of the request and the caller as error, method 9001 blocks for a
+    /// while and method 9002 panics
+    struct EchoHandler {}
+
+    impl RpcRequestHandler for EchoHandler {
+        fn handle_request(&self, request: tsm_common_rpc::RpcRequest, caller: &Caller) -> tsm_common_rpc::RpcResponse {
+            let delay = match request.method {
+                9001 => Duration::from_millis(1000),
+                9002 => panic!("unlucky method"),
+                _ => Duration::from_millis(2),
             };
+            thread::sleep(delay);
+
+            let mut response = tsm_common_rpc::RpcResponse::new();
+            response.method = request.method;
+            response.status = true;
+            response.error = caller.to_string();
+            response
+        }
+    }
+
+    fn start_server(address: String, workers: usize) {
+        let mut server = RpcServer::new(address, Arc::new(EchoHandler {}));
+        server.set_workers(workers);
+        server.run_threaded().unwrap();
+    }
+
+    fn build_request(method: i32) -> tsm_common_rpc::RpcRequest {
+        let mut request = tsm_common_rpc::RpcRequest::new();
+        request.method = method;
+
+        request
+    }
+
+    /// Addresses of every transport, the tests have to pass on all of them
+    fn build_addresses(name: &str) -> Vec<String> {
+        let mut addresses = Vec::new();
+
+        if cfg!(feature = "zmq") {
+            addresses.push(format!("ipc:///tmp/tsm-ipc-rpc-{}-test-{}.sock", name, std::process::id()));
+        }
+
+        addresses.push(format!("unix:///tmp/tsm-ipc-rpc-{}-unix-test-{}.sock", name, std::process::id()));
+        addresses
+    }
+
+    #[test]
+    fn run_should_answer_every_request_of_concurrent_clients() {
+        for address in build_addresses("stress") {
+            start_server(address.clone(), 4);
+
+            let clients: Vec<thread::JoinHandle<()>> = (0..32).map(|client| {
+                let address = address.clone();
+
+                thread::spawn(move || {
+                    let client_rpc = RpcClient::new(address).unwrap();
+
+                    for request in 0..25 {
+                        let method = client * 100 + request + 1;
+                        let response = client_rpc.send(build_request(method)).unwrap();
+                        assert_eq!(response.method, method);
+                    }
+                })
+            }).collect();
+
+            for client in clients {
+                client.join().unwrap();
+            }
+        }
+    }
+
+    #[test]
+    fn run_should_not_block_requests_behind_slow_request() {
+        for address in build_addresses("slow") {
+            start_server(address.clone(), 2);
+
+            let slow_address = address.clone();
+            let slow_client = thread::spawn(move || {
+                RpcClient::new(slow_address).unwrap().send(build_request(9001)).unwrap();
+            });
+            thread::sleep(Duration::from_millis(100));
+
+            let started = Instant::now();
+            assert_eq!(RpcClient::new(address).unwrap().send(build_request(1)).unwrap().method, 1);
+            assert!(started.elapsed() < Duration::from_millis(800));
+
+            slow_client.join().unwrap();
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "zmq")]
+    fn run_should_answer_malformed_request() {
+        let address = format!("ipc:///tmp/tsm-ipc-rpc-malformed-test-{}.sock", std::process::id());
+        start_server(address.clone(), 1);
+
+        let context = zmq::Context::new();
+        let requester = context.socket(zmq::REQ).unwrap();
+        requester.connect(&address).unwrap();
+        requester.send(&[0xff, 0xff, 0xff][..], 0).unwrap();
+
+        let response = tsm_common_rpc::RpcResponse::parse_from_bytes(&requester.recv_bytes(0).unwrap()).unwrap();
+        assert_eq!(response.status, false);
+        assert_eq!(response.error_info.code.enum_value(), Ok(Code::InvalidRequest));
+    }
+
+    #[test]
+    fn run_should_answer_malformed_request_on_unix_socket() {
+        let path = format!("/tmp/tsm-ipc-rpc-malformed-unix-test-{}.sock", std::process::id());
+        start_server(format!("unix://{}", path), 1);
+
+        let mut stream = std::os::unix::net::UnixStream::connect(&path).unwrap();
+        unix_transport::write_frame(&mut stream, &[0xff, 0xff, 0xff]).unwrap();
+
+        let response = tsm_common_rpc::RpcResponse::parse_from_bytes(&unix_transport::read_frame(&mut stream).unwrap()).unwrap();
+        assert_eq!(response.status, false);
+        assert_eq!(response.error_info.code.enum_value(), Ok(Code::InvalidRequest));
+
+        // the connection stays usable
+        unix_transport::write_frame(&mut stream, &build_request(1).write_to_bytes().unwrap()).unwrap();
+        let response = tsm_common_rpc::RpcResponse::parse_from_bytes(&unix_transport::read_frame(&mut stream).unwrap()).unwrap();
+        assert_eq!(response.method, 1);
+    }
+
+    #[test]
+    fn run_should_survive_panicking_handler() {
+        for address in build_addresses("panic") {
+            start_server(address.clone(), 1);
+            let client = RpcClient::new(address).unwrap();
+
+            let response = client.send(build_request(9002)).unwrap();
+            assert_eq!(response.status, false);
+            assert_eq!(response.method, 9002);
+            assert_eq!(response.error, "Request handler panicked: unlucky method");
+            assert_eq!(response.error_info.code.enum_value(), Ok(Code::Failed));
+
+            assert_eq!(client.send(build_request(1)).unwrap().method, 1);
+        }
+    }
+
+    #[test]
+    fn run_threaded_should_report_used_unix_socket() {
+        let address = format!("unix:///tmp/tsm-ipc-rpc-used-unix-test-{}.sock", std::process::id());
+        start_server(address.clone(), 1);
+
+        let result = RpcServer::new(address.clone(), Arc::new(EchoHandler {})).run_threaded();
+        assert!(matches!(result, Err(RpcError::Bind { address: used_address, .. }) if used_address == address));
+    }
+
+    #[test]
+    #[cfg(feature = "zmq")]
+    fn run_threaded_should_report_used_address() {
+        // ipc sockets can be taken over by a second bind, tcp ports can't
+        let context = zmq::Context::new();
+        let occupant = bind(&context, zmq::REP, "tcp://127.0.0.1:*").unwrap();
+        let used_address = occupant.get_last_endpoint().unwrap().unwrap();
+
+        let result = RpcServer::new(used_address.clone(), Arc::new(EchoHandler {})).run_threaded();
+        assert!(matches!(result, Err(RpcError::Bind { address, .. }) if address == used_address));
+    }
+
+    #[test]
+    fn run_should_pass_credentials_of_unix_socket_peer() {
+        for address in build_addresses("caller") {
+            start_server(address.clone(), 1);
+
+            let response = RpcClient::new(address).unwrap().send(build_request(1)).unwrap();
+            assert!(response.error.ends_with(&format!("pid {}", std::process::id())));
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "zmq")]
+    fn run_should_identify_curve_client_by_key() {
+        if !zmq::has("curve").unwrap_or(false) {
+            return;
+        }
+
+        let server_keys = zmq::CurveKeyPair::new().unwrap();
+        let client_keys = zmq::CurveKeyPair::new().unwrap();
+        let other_keys = zmq::CurveKeyPair::new().unwrap();
+        let client_public_key = zmq::z85_encode(&client_keys.public_key).unwrap();
 
-            match response {
-                Some(response) => {
-                    debug!("Sending response: {:?}", response);
-
-                    let message = match response.write_to_bytes() {
-                        Ok(bytes) => bytes,
-                        Err(error) => {
-                            warn!("Failed to serialize response: {}", error);
-                            continue;
-                        },
-                    };
-
-                    match responder.send(&message, 0) {
-                        Ok(_) => (),
-                        Err(error) => {
-                            warn!("Failed to send response: {}", error);
-                            continue;
-                        },
-                    };
-                },
-                None => (),
+        let mut server = RpcServer::new(String::from("tcp://127.0.0.1:45931"), Arc::new(EchoHandler {}));
+        server.set_curve(zmq::z85_encode(&server_keys.secret_key).unwrap(), vec![client_public_key.clone()]);
+        server.run_threaded().unwrap();
+
+        let build_client = |keys: &zmq::CurveKeyPair| {
+            let mut client = RpcClient::new(String::from("tcp://127.0.0.1:45931")).unwrap();
+            client.set_timeout(500).unwrap();
+            client.set_curve(
+                zmq::z85_encode(&server_keys.public_key).unwrap(),
+                zmq::z85_encode(&keys.public_key).unwrap(),
+                zmq::z85_encode(&keys.secret_key).unwrap(),
+            ).unwrap();
+            client
+        };
+
+        let response = build_client(&client_keys).send(build_request(1)).unwrap();
+        assert_eq!(response.error, format!("key {}", client_public_key));
+
+        assert!(build_client(&other_keys).send(build_request(1)).is_err());
+    }
+
+    #[test]
+    fn run_should_answer_capabilities_and_refuse_other_service() {
+        for address in build_addresses("service") {
+            let mut server = RpcServer::new(address.clone(), Arc::new(EchoHandler {}));
+            server.set_service(ServiceDescription::new(String::from("tsm-test"), String::from("1.2.3"), vec![(1, String::from("Echo"))]));
+            server.run_threaded().unwrap();
+
+            let mut client = RpcClient::new(address).unwrap();
+            let capabilities = client.get_capabilities().unwrap();
+            assert_eq!(capabilities.service, "tsm-test");
+            assert_eq!(capabilities.methods[0].name, "Echo");
+
+            client.set_service(String::from("tsm-test"));
+            let response = client.send(build_request(1)).unwrap();
+            assert_eq!(response.protocol_version, PROTOCOL_VERSION);
+            assert_ne!(response.correlation_id, 0);
+
+            client.set_service(String::from("tsm-other"));
+            assert!(matches!(client.send(build_request(1)), Err(RpcError::Incompatible(_))));
+        }
+    }
+
+    #[test]
+    fn handle_frames_should_answer_multipart_request() {
+        let frames = vec![Vec::new(), build_request(1).write_to_bytes().unwrap(), Vec::new()];
+
+        let response = tsm_common_rpc::RpcResponse::parse_from_bytes(&handle_frames(&frames, &ServiceDescription::default(), &EchoHandler {})).unwrap();
+        assert_eq!(response.status, false);
+        assert_eq!(response.error_info.code.enum_value(), Ok(Code::InvalidRequest));
+    }
+
+    proptest! {
+        #[test]
+        fn handle_frames_should_answer_random_bytes(bytes in proptest::collection::vec(any::<u8>(), 0..512)) {
+            let message = handle_frames(&[Vec::new(), bytes.clone()], &ServiceDescription::default(), &EchoHandler {});
+            let response = tsm_common_rpc::RpcResponse::parse_from_bytes(&message).unwrap();
+
+            match tsm_common_rpc::RpcRequest::parse_from_bytes(&bytes) {
+                Ok(request) if request.method < 0 || request.method >= 9001 => {},
+                Ok(request) => prop_assert_eq!(response.method, request.method),
+                Err(_) => prop_assert_eq!(response.error_info.code.enum_value(), Ok(Code::InvalidRequest)),
             }
         }
     }
-}
\ No newline at end of file
+
+    #[cfg(feature = "zmq")]
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(64))]
+
+        #[test]
+        fn run_should_keep_answering_random_frames(frames in proptest::collection::vec(proptest::collection::vec(any::<u8>(), 0..64), 1..4)) {
+            let address = format!("ipc:///tmp/tsm-ipc-rpc-fuzz-test-{}.sock", std::process::id());
+            static STARTED: std::sync::Once = std::sync::Once::new();
+            STARTED.call_once(|| start_server(address.clone(), 2));
+
+            let context = zmq::Context::new();
+            let requester = context.socket(zmq::REQ).unwrap();
+            requester.set_rcvtimeo(2000).unwrap();
+            requester.connect(&address).unwrap();
+            requester.send_multipart(frames, 0).unwrap();
+
+            let response = requester.recv_bytes(0);
+            prop_assert!(response.is_ok());
+            prop_assert!(tsm_common_rpc::RpcResponse::parse_from_bytes(&response.unwrap()).is_ok());
+        }
+    }
+}
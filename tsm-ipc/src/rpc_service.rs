@@ -0,0 +1,164 @@
+use std::collections::HashMap;
+use log::debug;
+use protobuf::Message;
+
+use crate::{Caller, RpcError, RpcRequestHandler};
+use crate::tsm_common_rpc;
+use crate::tsm_common_rpc::error::Code;
+use crate::protocol;
+
+
+/// Takes the request data, returns the response data
+type MethodHandler = Box<dyn Fn(&[u8]) -> Result<Vec<u8>, RpcError> + Send + Sync>;
+
+
+/// Dispatches requests to typed handlers by method id. Handlers get the parsed request message
+/// and return the response message, parsing, serializing and error responses are done here.
+#[derive(Default)]
+pub struct RpcService {
+    handlers: HashMap<i32, MethodHandler>,
+}
+
+
+impl RpcService {
+    pub fn new() -> RpcService {
+        RpcService::default()
+    }
+
+    /// Replaces an earlier handler of the method. Errors other than RpcError::Server are
+    /// answered with Code::Failed.
+    pub fn register<Req, Resp, F>(&mut self, method: i32, handler: F)
+    where
+        Req: Message,
+        Resp: Message,
+        F: Fn(Req) -> Result<Resp, RpcError> + Send + Sync + 'static,
+    {
+        let method_handler = move |data: &[u8]| {
+            let request = match Req::parse_from_bytes(data) {
+                Ok(request) => request,
+                Err(error) => return Err(RpcError::server(Code::InvalidRequest, format!("Failed to parse request: {}", error))),
+            };
+
+            return match handler(request)?.write_to_bytes() {
+                Ok(bytes) => Ok(bytes),
+                Err(error) => Err(RpcError::server(Code::Failed, format!("Failed to serialize response: {}", error))),
+            };
+        };
+
+        self.handlers.insert(method, Box::new(method_handler));
+    }
+
+    pub fn has_method(&self, method: i32) -> bool {
+        return self.handlers.contains_key(&method);
+    }
+}
+
+
+impl RpcRequestHandler for RpcService {
+    fn handle_request(&self, request: tsm_common_rpc::RpcRequest, _caller: &Caller) -> tsm_common_rpc::RpcResponse {
+        let mut response = tsm_common_rpc::RpcResponse::new();
+        response.method = request.method;
+
+        let result = match self.handlers.get(&request.method) {
+            Some(handler) => handler(&request.data),
+            None => Err(RpcError::server(Code::UnknownMethod, format!("Unknown method {}", request.method))),
+        };
+
+        match result {
+            Ok(data) => {
+                response.status = true;
+                response.data = data;
+            },
+            Err(RpcError::Server { code, message, details }) => {
+                debug!("Method {} failed: {}", request.method, message);
+                protocol::set_error(&mut response, code, message, details);
+            },
+            Err(error) => {
+                debug!("Method {} failed: {}", request.method, error);
+                protocol::set_error(&mut response, Code::Failed, error.to_string(), Vec::new());
+            },
+        };
+
+        return response;
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use crate::{RpcClient, RpcServer};
+    use super::*;
+
+    fn build_service() -> RpcService {
+        let mut service = RpcService::new();
+        service.register(1, |request: tsm_common_rpc::Error| {
+            match request.message.as_str() {
+                "missing" => Err(RpcError::Server { code: Code::NotFound, message: String::from("Not found"), details: vec![String::from("cause")] }),
+                "broken" => Err(RpcError::Receive(String::from("connection lost"))),
+                _ => {
+                    let mut response = tsm_common_rpc::Error::new();
+                    response.message = request.message.to_uppercase();
+                    Ok(response)
+                },
+            }
+        });
+        service
+    }
+
+    fn handle(service: &RpcService, method: i32, message: &str) -> tsm_common_rpc::RpcResponse {
+        let mut data = tsm_common_rpc::Error::new();
+        data.message = String::from(message);
+
+        let mut request = tsm_common_rpc::RpcRequest::new();
+        request.method = method;
+        request.data = data.write_to_bytes().unwrap();
+
+        service.handle_request(request, &Caller::anonymous())
+    }
+
+    #[test]
+    fn handle_request_should_call_typed_handler() {
+        let response = handle(&build_service(), 1, "foo");
+
+        assert_eq!(response.status, true);
+        assert_eq!(response.method, 1);
+        assert_eq!(tsm_common_rpc::Error::parse_from_bytes(&response.data).unwrap().message, "FOO");
+    }
+
+    #[test]
+    fn handle_request_should_map_errors_to_codes() {
+        let service = build_service();
+
+        let response = handle(&service, 1, "missing");
+        assert_eq!(protocol::get_error(&response), RpcError::Server { code: Code::NotFound, message: String::from("Not found"), details: vec![String::from("cause")] });
+
+        let response = handle(&service, 1, "broken");
+        assert_eq!(response.error_info.code.enum_value(), Ok(Code::Failed));
+
+        let response = handle(&service, 2, "foo");
+        assert_eq!(response.error_info.code.enum_value(), Ok(Code::UnknownMethod));
+
+        let mut request = tsm_common_rpc::RpcRequest::new();
+        request.method = 1;
+        request.data = vec![0xff, 0xff, 0xff];
+        let response = service.handle_request(request, &Caller::anonymous());
+        assert_eq!(response.error_info.code.enum_value(), Ok(Code::InvalidRequest));
+    }
+
+    #[test]
+    fn call_should_return_typed_response_and_error() {
+        let address = format!("unix:///tmp/tsm-ipc-rpc-service-call-test-{}.sock", std::process::id());
+        RpcServer::new(address.clone(), Arc::new(build_service())).run_threaded().unwrap();
+        let client = RpcClient::new(address).unwrap();
+
+        let mut request = tsm_common_rpc::Error::new();
+        request.message = String::from("foo");
+        let response: tsm_common_rpc::Error = client.call(1, &request).unwrap();
+        assert_eq!(response.message, "FOO");
+
+        request.message = String::from("missing");
+        let error = client.call_idempotent::<_, tsm_common_rpc::Error>(1, &request).unwrap_err();
+        assert!(matches!(error, RpcError::Server { code: Code::NotFound, .. }));
+    }
+}
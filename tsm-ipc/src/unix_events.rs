@@ -0,0 +1,115 @@
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use log::{debug, warn};
+
+use crate::unix_transport::{self, read_frame, write_frame};
+
+
+/// Subscribers which don't keep up are dropped instead of holding up the publisher
+const WRITE_TIMEOUT: Duration = Duration::from_millis(500);
+const MAX_SUBSCRIBERS: usize = 64;
+
+
+/// Publishing side of unix:// event streams. Every event is sent to every subscriber as two
+/// frames, the topic first. Unlike a ZMQ SUB socket, subscribers filter the topics themselves.
+pub(crate) struct UnixPublisher {
+    subscribers: Arc<Mutex<Vec<UnixStream>>>,
+}
+
+
+impl UnixPublisher {
+    pub fn bind(path: &str) -> Result<UnixPublisher, String> {
+        let listener = match unix_transport::bind(path) {
+            Ok(listener) => listener,
+            Err(error) => return Err(error.to_string()),
+        };
+
+        let subscribers = Arc::new(Mutex::new(Vec::new()));
+        let accepted = subscribers.clone();
+        thread::spawn(move || accept_subscribers(listener, accepted));
+
+        Ok(UnixPublisher {
+            subscribers
+        })
+    }
+
+    /// Subscribers which can't be written to are gone or too slow, and are dropped
+    pub fn publish(&self, topic: &str, message: &[u8]) {
+        let mut subscribers = self.subscribers.lock().unwrap_or_else(|error| error.into_inner());
+
+        subscribers.retain_mut(|subscriber| match write_frame(subscriber, topic.as_bytes()).and_then(|_| write_frame(subscriber, message)) {
+            Ok(_) => true,
+            Err(error) => {
+                debug!("Dropping event subscriber: {}", error);
+                false
+            },
+        });
+    }
+}
+
+
+fn accept_subscribers(listener: UnixListener, subscribers: Arc<Mutex<Vec<UnixStream>>>) {
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(error) => {
+                warn!("Failed to accept event subscriber: {}", error);
+                continue;
+            },
+        };
+
+        if let Err(error) = stream.set_write_timeout(Some(WRITE_TIMEOUT)) {
+            warn!("Failed to set timeout of event subscriber: {}", error);
+            continue;
+        }
+
+        let mut subscribers = subscribers.lock().unwrap_or_else(|error| error.into_inner());
+
+        if subscribers.len() >= MAX_SUBSCRIBERS {
+            warn!("Closing connection, already publishing to {} subscribers", MAX_SUBSCRIBERS);
+            continue;
+        }
+
+        subscribers.push(stream);
+    }
+}
+
+
+/// Receiving side of unix:// event streams. Other than with ZMQ the publisher must be running
+/// when connecting, and events are not received again after it restarted.
+pub(crate) struct UnixSubscriber {
+    stream: UnixStream,
+    topics: Vec<String>,
+}
+
+
+impl UnixSubscriber {
+    /// An empty topic matches all topics
+    pub fn connect(path: &str, topics: Vec<String>) -> Result<UnixSubscriber, String> {
+        return match UnixStream::connect(path) {
+            Ok(stream) => Ok(UnixSubscriber { stream, topics }),
+            Err(error) => Err(format!("Failed to connect to {}: {}", path, error)),
+        };
+    }
+
+    /// Blocks until the next event with a subscribed topic arrives
+    pub fn receive(&self) -> Result<(String, Vec<u8>), String> {
+        loop {
+            let topic = match read_frame(&mut &self.stream) {
+                Ok(topic) => String::from_utf8_lossy(&topic).to_string(),
+                Err(error) => return Err(format!("Failed to receive event: {}", error)),
+            };
+
+            let message = match read_frame(&mut &self.stream) {
+                Ok(message) => message,
+                Err(error) => return Err(format!("Failed to receive event: {}", error)),
+            };
+
+            if self.topics.iter().any(|prefix| topic.starts_with(prefix.as_str())) {
+                return Ok((topic, message));
+            }
+        }
+    }
+}
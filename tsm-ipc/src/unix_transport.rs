@@ -0,0 +1,475 @@
+use std::cell::RefCell;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::mem;
+use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::sync::{Arc, Condvar, Mutex};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+use log::{debug, warn};
+
+use crate::{Caller, RpcError, RpcRequestHandler};
+use crate::protocol::ServiceDescription;
+use crate::rpc_server::handle_frames;
+
+
+/// Addresses like unix:///run/tsm/unitman.sock use this transport instead of ZMQ
+pub(crate) const SCHEME: &str = "unix://";
+/// Larger frames are taken as garbage, e.g. from a ZMQ client pointed at the socket. Every
+/// connection may allocate this much, so it is kept well below what the memory of a device allows.
+const MAX_FRAME_SIZE: usize = 4 * 1024 * 1024;
+/// Every connection has a thread, further connections are closed right away
+const MAX_CONNECTIONS: usize = 64;
+/// A connection without requests for so long is closed, so that idle clients don't keep threads
+const IDLE_TIMEOUT: Duration = Duration::from_secs(600);
+/// Once the first byte of a request arrived, the rest of it and the response must not take longer
+const FRAME_TIMEOUT: Duration = Duration::from_secs(30);
+/// Owner and group may connect, which is what the access control is set up for
+const SOCKET_MODE: u32 = 0o660;
+const CONNECT_RETRY_INTERVAL: Duration = Duration::from_millis(20);
+
+
+/// The socket path of unix:// addresses
+pub(crate) fn get_path(address: &str) -> Option<&str> {
+    return address.strip_prefix(SCHEME);
+}
+
+
+/// Without the zmq feature only unix:// addresses can be used
+#[cfg(not(feature = "zmq"))]
+pub(crate) fn unsupported_address(address: &str) -> RpcError {
+    RpcError::Socket(format!("Address {} needs the zmq feature, only {} addresses are supported without", address, SCHEME))
+}
+
+
+/// Frames are a protobuf message behind its length as 4 byte big endian
+pub(crate) fn read_frame(stream: &mut impl Read) -> io::Result<Vec<u8>> {
+    let mut length = [0u8; 4];
+    stream.read_exact(&mut length)?;
+
+    let length = u32::from_be_bytes(length) as usize;
+    if length > MAX_FRAME_SIZE {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, format!("Frame of {} bytes exceeds the limit of {} bytes", length, MAX_FRAME_SIZE)));
+    }
+
+    let mut frame = vec![0u8; length];
+    stream.read_exact(&mut frame)?;
+
+    Ok(frame)
+}
+
+
+pub(crate) fn write_frame(stream: &mut impl Write, frame: &[u8]) -> io::Result<()> {
+    let length = match u32::try_from(frame.len()) {
+        Ok(length) if frame.len() <= MAX_FRAME_SIZE => length,
+        _ => return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("Frame of {} bytes exceeds the limit of {} bytes", frame.len(), MAX_FRAME_SIZE))),
+    };
+
+    // one write, so that the length and a short frame go out in the same packet
+    let mut bytes = Vec::with_capacity(4 + frame.len());
+    bytes.extend_from_slice(&length.to_be_bytes());
+    bytes.extend_from_slice(frame);
+
+    stream.write_all(&bytes)?;
+    stream.flush()
+}
+
+
+/// A socket file left behind by a server which didn't shut down cleanly is replaced, one with a
+/// server still listening is reported as used. Anything else at the path, including a symlink to
+/// a socket, is left alone, so that a wrong address never deletes a file. The socket gets
+/// SOCKET_MODE whatever the umask is.
+pub(crate) fn bind(path: &str) -> Result<UnixListener, RpcError> {
+    let bind_error = |error: String| RpcError::Bind { address: format!("{}{}", SCHEME, path), error };
+
+    if let Ok(metadata) = fs::symlink_metadata(path) {
+        if !metadata.file_type().is_socket() {
+            return Err(bind_error(String::from("Path exists and is not a socket")));
+        }
+
+        if UnixStream::connect(path).is_ok() {
+            return Err(bind_error(String::from("Address already in use")));
+        }
+
+        if let Err(error) = fs::remove_file(path) {
+            return Err(bind_error(format!("Failed to remove stale socket: {}", error)));
+        }
+    }
+
+    let listener = match UnixListener::bind(path) {
+        Ok(listener) => listener,
+        Err(error) => return Err(bind_error(error.to_string())),
+    };
+
+    return match fs::set_permissions(path, fs::Permissions::from_mode(SOCKET_MODE)) {
+        Ok(_) => Ok(listener),
+        Err(error) => Err(bind_error(format!("Failed to set mode of socket: {}", error))),
+    };
+}
+
+
+/// Every connection gets a thread, up to MAX_CONNECTIONS of them. The workers limit how many
+/// requests are handled at the same time. A connection carries one request after the other like
+/// a REQ socket.
+pub(crate) fn run_threaded(listener: UnixListener, workers: usize, service: Arc<ServiceDescription>, request_handler: Arc<dyn RpcRequestHandler>) -> thread::JoinHandle<()> {
+    let worker_slots = Arc::new(WorkerSlots::new(workers));
+    let connections = Arc::new(AtomicUsize::new(0));
+
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let stream = match stream {
+                Ok(stream) => stream,
+                Err(error) => {
+                    warn!("Failed to accept connection: {}", error);
+                    continue;
+                },
+            };
+
+            let connection = match Connection::open(&connections) {
+                Some(connection) => connection,
+                None => {
+                    warn!("Closing connection, already serving {} connections", MAX_CONNECTIONS);
+                    continue;
+                },
+            };
+
+            let worker_slots = worker_slots.clone();
+            let service = service.clone();
+            let request_handler = request_handler.clone();
+
+            thread::spawn(move || {
+                serve_connection(stream, &worker_slots, &service, request_handler.as_ref());
+                drop(connection);
+            });
+        }
+    })
+}
+
+
+/// Counted while its thread serves it
+struct Connection {
+    connections: Arc<AtomicUsize>,
+}
+
+
+impl Connection {
+    fn open(connections: &Arc<AtomicUsize>) -> Option<Connection> {
+        return connections
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |open| (open < MAX_CONNECTIONS).then_some(open + 1))
+            .ok()
+            .map(|_| Connection { connections: connections.clone() });
+    }
+}
+
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        self.connections.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+
+/// A frame that can't be read leaves the stream out of step, so the connection is closed. Frames
+/// which aren't a request are answered by handle_frames.
+fn serve_connection(mut stream: UnixStream, worker_slots: &WorkerSlots, service: &ServiceDescription, request_handler: &dyn RpcRequestHandler) {
+    let caller = get_peer_credentials(stream.as_raw_fd()).unwrap_or_default();
+    let user_id = caller.to_user_id().into_bytes();
+    debug!("Accepted connection from {}", caller);
+
+    if let Err(error) = stream.set_write_timeout(Some(FRAME_TIMEOUT)) {
+        warn!("Failed to set timeout of connection from {}: {}", caller, error);
+        return;
+    }
+
+    loop {
+        let request = match read_request(&mut stream) {
+            Ok(request) => request,
+            Err(error) if error.kind() == io::ErrorKind::UnexpectedEof => return,
+            Err(error) if is_timeout(&error) => {
+                debug!("Closing connection from {}, idle for {}s or request not complete after {}s", caller, IDLE_TIMEOUT.as_secs(), FRAME_TIMEOUT.as_secs());
+                return;
+            },
+            Err(error) => {
+                warn!("Failed to receive request from {}: {}", caller, error);
+                return;
+            },
+        };
+
+        worker_slots.acquire();
+        let response = handle_frames(&[user_id.clone(), request], service, request_handler);
+        worker_slots.release();
+
+        if let Err(error) = write_frame(&mut stream, &response) {
+            warn!("Failed to send response to {}: {}", caller, error);
+            return;
+        }
+    }
+}
+
+
+/// Waits up to IDLE_TIMEOUT for a request to begin and FRAME_TIMEOUT for the rest of it
+fn read_request(stream: &mut UnixStream) -> io::Result<Vec<u8>> {
+    stream.set_read_timeout(Some(IDLE_TIMEOUT))?;
+
+    let mut first = [0u8; 1];
+    stream.read_exact(&mut first)?;
+
+    stream.set_read_timeout(Some(FRAME_TIMEOUT))?;
+
+    return read_frame(&mut first.as_slice().chain(stream));
+}
+
+
+/// The kernel tells the credentials of the process which connected. Sockets other than unix
+/// sockets have no credentials.
+pub(crate) fn get_peer_credentials(fd: RawFd) -> Option<Caller> {
+    let mut domain: libc::c_int = 0;
+    let mut length = mem::size_of::<libc::c_int>() as libc::socklen_t;
+
+    let result = unsafe {
+        libc::getsockopt(fd, libc::SOL_SOCKET, libc::SO_DOMAIN, &mut domain as *mut libc::c_int as *mut libc::c_void, &mut length)
+    };
+
+    if result != 0 || domain != libc::AF_UNIX {
+        return None;
+    }
+
+    let mut credentials = libc::ucred { pid: 0, uid: 0, gid: 0 };
+    let mut length = mem::size_of::<libc::ucred>() as libc::socklen_t;
+
+    let result = unsafe {
+        libc::getsockopt(
+            fd,
+            libc::SOL_SOCKET,
+            libc::SO_PEERCRED,
+            &mut credentials as *mut libc::ucred as *mut libc::c_void,
+            &mut length,
+        )
+    };
+
+    if result != 0 {
+        warn!("Failed to get peer credentials: {}", io::Error::last_os_error());
+        return None;
+    }
+
+    return Some(Caller::from_credentials(credentials.uid, credentials.gid, credentials.pid as u32));
+}
+
+
+/// Counts the requests being handled, like the pool of workers of the ZMQ transport
+struct WorkerSlots {
+    free: Mutex<usize>,
+    released: Condvar,
+}
+
+
+impl WorkerSlots {
+    fn new(workers: usize) -> WorkerSlots {
+        WorkerSlots {
+            free: Mutex::new(workers.max(1)),
+            released: Condvar::new(),
+        }
+    }
+
+    fn acquire(&self) {
+        let mut free = self.free.lock().unwrap_or_else(|error| error.into_inner());
+
+        while *free == 0 {
+            free = self.released.wait(free).unwrap_or_else(|error| error.into_inner());
+        }
+
+        *free -= 1;
+    }
+
+    fn release(&self) {
+        let mut free = self.free.lock().unwrap_or_else(|error| error.into_inner());
+        *free += 1;
+        self.released.notify_one();
+    }
+}
+
+
+/// Client side of a connection. After a failed or timed out request the stream is dropped, a
+/// late response on it would be taken for the response of the next request. The server closes
+/// idle connections, so a request failing on a kept stream is sent again on a new one.
+pub(crate) struct UnixConnection {
+    path: String,
+    stream: RefCell<Option<UnixStream>>,
+}
+
+
+impl UnixConnection {
+    /// Connects with the first request, like ZMQ a server which isn't there yet is waited for
+    pub fn new(path: String) -> UnixConnection {
+        UnixConnection {
+            path,
+            stream: RefCell::new(None),
+        }
+    }
+
+    /// Sends one request and returns the bytes of its response, -1 waits forever
+    pub fn send_once(&self, message: &[u8], timeout_ms: i32) -> Result<Vec<u8>, RpcError> {
+        let timeout = match timeout_ms {
+            timeout_ms if timeout_ms < 0 => None,
+            timeout_ms => Some(Duration::from_millis(timeout_ms.max(1) as u64)),
+        };
+
+        let mut stream = self.stream.borrow_mut();
+
+        let (mut connected, kept) = match stream.take() {
+            Some(connected) => (connected, true),
+            None => (connect(&self.path, timeout)?, false),
+        };
+
+        let mut result = exchange(&mut connected, message, timeout);
+
+        if kept && matches!(result, Err(RpcError::Send(_)) | Err(RpcError::Receive(_))) {
+            debug!("Connection to {} was closed, reconnecting", self.path);
+            connected = connect(&self.path, timeout)?;
+            result = exchange(&mut connected, message, timeout);
+        }
+
+        if result.is_ok() {
+            *stream = Some(connected);
+        }
+
+        return result;
+    }
+
+    pub fn reset(&self) {
+        self.stream.replace(None);
+    }
+}
+
+
+fn connect(path: &str, timeout: Option<Duration>) -> Result<UnixStream, RpcError> {
+    let deadline = timeout.map(|timeout| Instant::now() + timeout);
+
+    loop {
+        match UnixStream::connect(path) {
+            Ok(stream) => return Ok(stream),
+            Err(error) if matches!(error.kind(), io::ErrorKind::NotFound | io::ErrorKind::ConnectionRefused) => {
+                if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                    return Err(RpcError::Timeout { attempts: 1 });
+                }
+                thread::sleep(CONNECT_RETRY_INTERVAL);
+            },
+            Err(error) => return Err(RpcError::Socket(format!("Failed to connect to {}: {}", path, error))),
+        };
+    }
+}
+
+
+fn exchange(stream: &mut UnixStream, message: &[u8], timeout: Option<Duration>) -> Result<Vec<u8>, RpcError> {
+    for option in [stream.set_read_timeout(timeout), stream.set_write_timeout(timeout)] {
+        match option {
+            Ok(_) => {},
+            Err(error) => return Err(RpcError::Socket(format!("Failed to set socket timeout: {}", error))),
+        };
+    }
+
+    match write_frame(stream, message) {
+        Ok(_) => {},
+        Err(error) if is_timeout(&error) => return Err(RpcError::Timeout { attempts: 1 }),
+        Err(error) => return Err(RpcError::Send(error.to_string())),
+    };
+
+    return match read_frame(stream) {
+        Ok(frame) => Ok(frame),
+        Err(error) if is_timeout(&error) => Err(RpcError::Timeout { attempts: 1 }),
+        Err(error) if error.kind() == io::ErrorKind::UnexpectedEof => Err(RpcError::Receive(String::from("Connection closed by server"))),
+        Err(error) => Err(RpcError::Receive(error.to_string())),
+    };
+}
+
+
+fn is_timeout(error: &io::Error) -> bool {
+    return matches!(error.kind(), io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut);
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_frame_should_read_written_frame() {
+        let mut bytes = Vec::new();
+        write_frame(&mut bytes, b"request").unwrap();
+        write_frame(&mut bytes, b"").unwrap();
+
+        assert_eq!(&bytes[..4], &[0, 0, 0, 7]);
+
+        let mut reader = bytes.as_slice();
+        assert_eq!(read_frame(&mut reader).unwrap(), b"request");
+        assert_eq!(read_frame(&mut reader).unwrap(), b"");
+        assert_eq!(read_frame(&mut reader).unwrap_err().kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn read_frame_should_refuse_huge_frame() {
+        let mut reader: &[u8] = &[0xff, 0xff, 0xff, 0xff, 0x00];
+
+        assert_eq!(read_frame(&mut reader).unwrap_err().kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn bind_should_replace_stale_socket_only() {
+        let path = format!("/tmp/tsm-ipc-unix-bind-test-{}.sock", std::process::id());
+
+        let listener = bind(&path).unwrap();
+        assert_eq!(fs::metadata(&path).unwrap().permissions().mode() & 0o777, SOCKET_MODE);
+        assert!(matches!(bind(&path), Err(RpcError::Bind { .. })));
+
+        drop(listener);
+        assert!(bind(&path).is_ok());
+    }
+
+    #[test]
+    fn bind_should_not_remove_other_files() {
+        let path = format!("/tmp/tsm-ipc-unix-bind-file-test-{}", std::process::id());
+        fs::write(&path, b"data").unwrap();
+
+        assert!(matches!(bind(&path), Err(RpcError::Bind { .. })));
+        assert_eq!(fs::read(&path).unwrap(), b"data");
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn send_once_should_reconnect_after_server_closed_connection() {
+        let path = format!("/tmp/tsm-ipc-unix-reconnect-test-{}.sock", std::process::id());
+        let listener = bind(&path).unwrap();
+
+        // every connection is closed after one request, like an idle one
+        let server = thread::spawn(move || {
+            for _ in 0..2 {
+                let (mut stream, _) = listener.accept().unwrap();
+                let request = read_frame(&mut stream).unwrap();
+                write_frame(&mut stream, &request).unwrap();
+            }
+        });
+
+        let connection = UnixConnection::new(path.clone());
+        assert_eq!(connection.send_once(b"first", 1000).unwrap(), b"first");
+        assert_eq!(connection.send_once(b"second", 1000).unwrap(), b"second");
+
+        server.join().unwrap();
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn connection_should_be_limited() {
+        let connections = Arc::new(AtomicUsize::new(0));
+        let open: Vec<Connection> = (0..MAX_CONNECTIONS).filter_map(|_| Connection::open(&connections)).collect();
+
+        assert_eq!(open.len(), MAX_CONNECTIONS);
+        assert!(Connection::open(&connections).is_none());
+
+        drop(open);
+        assert_eq!(connections.load(Ordering::SeqCst), 0);
+    }
+}
@@ -0,0 +1,178 @@
+use std::thread;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use log::{debug, warn};
+
+use crate::{Caller, RpcError};
+
+
+/// libzmq asks the socket bound to this address of the same context to authenticate connections
+const ZAP_ADDRESS: &str = "inproc://zeromq.zap.01";
+/// How often the handler checks whether it should stop
+const STOP_INTERVAL_MS: i32 = 200;
+/// Without a domain libzmq skips the handler for connections without CURVE
+pub(crate) const ZAP_DOMAIN: &str = "tsm-ipc";
+
+
+/// Authenticates new connections of a context (ZAP, https://rfc.zeromq.org/spec/27/) and attaches
+/// the identity of the caller to all their messages as User-Id.
+pub(crate) struct ZapHandler {
+    zmq_socket: zmq::Socket,
+    /// Z85 encoded client keys which may connect with CURVE, empty allows all keys
+    authorized_keys: Vec<String>,
+}
+
+
+impl ZapHandler {
+    /// Has to be bound before the sockets it authenticates accept connections
+    pub fn bind(zmq_context: &zmq::Context, authorized_keys: Vec<String>) -> Result<ZapHandler, RpcError> {
+        let zmq_socket = match zmq_context.socket(zmq::REP) {
+            Ok(socket) => socket,
+            Err(error) => return Err(RpcError::Socket(format!("Failed to create ZAP socket: {}", error))),
+        };
+
+        match zmq_socket.bind(ZAP_ADDRESS) {
+            Ok(_) => {},
+            Err(error) => return Err(RpcError::Bind { address: String::from(ZAP_ADDRESS), error: error.to_string() }),
+        };
+
+        Ok(ZapHandler {
+            zmq_socket,
+            authorized_keys,
+        })
+    }
+
+    /// Runs until stopped is set, the socket is closed then so that the context can terminate
+    pub fn run_threaded(self, stopped: Arc<AtomicBool>) -> thread::JoinHandle<()> {
+        thread::spawn(move || {
+            self.run(stopped);
+        })
+    }
+
+    fn run(self, stopped: Arc<AtomicBool>) {
+        while !stopped.load(Ordering::Relaxed) {
+            match self.zmq_socket.poll(zmq::POLLIN, i64::from(STOP_INTERVAL_MS)) {
+                Ok(0) => continue,
+                Ok(_) => {},
+                Err(error) => {
+                    warn!("Failed to poll ZAP socket: {}", error);
+                    continue;
+                },
+            };
+
+            let frames = match self.zmq_socket.recv_multipart(0) {
+                Ok(frames) => frames,
+                Err(error) => {
+                    warn!("Failed to receive ZAP request: {}", error);
+                    continue;
+                },
+            };
+
+            match self.zmq_socket.send_multipart(self.handle_request(&frames), 0) {
+                Ok(_) => {},
+                Err(error) => warn!("Failed to send ZAP reply: {}", error),
+            };
+        }
+    }
+
+    /// Request: version, request id, domain, address, routing id, mechanism, credentials.
+    /// Reply: version, request id, status code, status text, user id, metadata.
+    fn handle_request(&self, frames: &[Vec<u8>]) -> Vec<Vec<u8>> {
+        if frames.len() < 6 {
+            warn!("Received ZAP request with {} frames", frames.len());
+            return build_reply(b"1.0", b"", "500", "Malformed request", "");
+        }
+
+        let version = frames[0].as_slice();
+        let request_id = frames[1].as_slice();
+        let address = String::from_utf8_lossy(&frames[3]);
+        let mechanism = String::from_utf8_lossy(&frames[5]);
+
+        let caller = match mechanism.as_ref() {
+            // unix socket peers are identified by their credentials, tcp peers stay anonymous
+            "NULL" => Caller::from_peer_address(&address).unwrap_or_default(),
+            "CURVE" => {
+                let public_key = match frames.get(6).map(|key| zmq::z85_encode(key)) {
+                    Some(Ok(public_key)) => public_key,
+                    _ => return build_reply(version, request_id, "400", "Invalid client key", ""),
+                };
+
+                if !self.authorized_keys.is_empty() && !self.authorized_keys.contains(&public_key) {
+                    warn!("Rejected connection from {} with unknown key {}", address, public_key);
+                    return build_reply(version, request_id, "400", "Unknown client key", "");
+                }
+
+                Caller::from_public_key(public_key)
+            },
+            _ => {
+                warn!("Rejected connection from {} with mechanism {}", address, mechanism);
+                return build_reply(version, request_id, "400", "Unsupported mechanism", "");
+            },
+        };
+
+        debug!("Accepted connection from {} as {}", address, caller);
+        build_reply(version, request_id, "200", "OK", &caller.to_user_id())
+    }
+}
+
+
+fn build_reply(version: &[u8], request_id: &[u8], status_code: &str, status_text: &str, user_id: &str) -> Vec<Vec<u8>> {
+    vec![
+        version.to_vec(),
+        request_id.to_vec(),
+        status_code.as_bytes().to_vec(),
+        status_text.as_bytes().to_vec(),
+        user_id.as_bytes().to_vec(),
+        Vec::new(),
+    ]
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn build_request(mechanism: &str, address: &str, credentials: Option<Vec<u8>>) -> Vec<Vec<u8>> {
+        let mut frames = vec![
+            b"1.0".to_vec(),
+            b"1".to_vec(),
+            ZAP_DOMAIN.as_bytes().to_vec(),
+            address.as_bytes().to_vec(),
+            Vec::new(),
+            mechanism.as_bytes().to_vec(),
+        ];
+        frames.extend(credentials);
+        frames
+    }
+
+    #[test]
+    fn handle_request_should_identify_unix_socket_peer() {
+        let zmq_context = zmq::Context::new();
+        let handler = ZapHandler::bind(&zmq_context, Vec::new()).unwrap();
+
+        let reply = handler.handle_request(&build_request("NULL", ":1000:100:42", None));
+        assert_eq!(reply[2], b"200");
+        assert_eq!(reply[4], b"unix:1000:100:42");
+
+        let reply = handler.handle_request(&build_request("NULL", "192.168.7.1", None));
+        assert_eq!(reply[2], b"200");
+        assert_eq!(reply[4], b"");
+    }
+
+    #[test]
+    fn handle_request_should_reject_unknown_key() {
+        let zmq_context = zmq::Context::new();
+        let known_key = [1u8; 32];
+        let handler = ZapHandler::bind(&zmq_context, vec![zmq::z85_encode(&known_key).unwrap()]).unwrap();
+
+        let reply = handler.handle_request(&build_request("CURVE", "192.168.7.1", Some(known_key.to_vec())));
+        assert_eq!(reply[2], b"200");
+        assert_eq!(reply[4], format!("curve:{}", zmq::z85_encode(&known_key).unwrap()).as_bytes());
+
+        let reply = handler.handle_request(&build_request("CURVE", "192.168.7.1", Some([2u8; 32].to_vec())));
+        assert_eq!(reply[2], b"400");
+
+        let reply = handler.handle_request(&build_request("PLAIN", "192.168.7.1", None));
+        assert_eq!(reply[2], b"400");
+    }
+}
@@ -1,7 +1,7 @@
 use std::fs;
 use serde::Deserialize;
 
-use crate::config::{Application, Server};
+use crate::config::{Application, ConfigError, Server};
 
 
 #[derive(Deserialize, Debug)]
@@ -12,24 +12,24 @@ pub struct Configuration {
 
 
 impl Configuration {
-    pub fn from_file(file_path: String) -> Result<Configuration, String> {
+    pub fn from_file(file_path: String) -> Result<Configuration, ConfigError> {
         return match fs::read_to_string(file_path) {
             Ok(content) => {
                 Configuration::from_string(content)
             },
             Err(error) => {
-                Err(format!("Error reading configuration file: {}", error))
+                Err(ConfigError::Read(error))
             }
         }
     }
 
-    pub fn from_string(content: String) -> Result<Configuration, String> {
+    pub fn from_string(content: String) -> Result<Configuration, ConfigError> {
         return match toml::from_str(&content) {
             Ok(configuration) => {
                 Ok(configuration)
             },
             Err(error) => {
-                Err(format!("Error parsing configuration file: {}", error))
+                Err(ConfigError::Parse(error))
             }
         }
     }
@@ -1,6 +1,9 @@
 mod configuration;
 pub use configuration::Configuration;
 
+mod config_error;
+pub use config_error::ConfigError;
+
 mod application;
 use application::Application;
 
@@ -8,4 +11,10 @@ mod log_level;
 use log_level::LogLevel;
 
 mod server;
-use server::Server;
\ No newline at end of file
+use server::Server;
+
+mod tcp_server;
+use tcp_server::TcpServer;
+
+mod tls_server;
+use tls_server::TlsServer;
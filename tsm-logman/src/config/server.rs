@@ -1,10 +1,17 @@
 use serde::Deserialize;
+use crate::config::{TcpServer, TlsServer};
 
 
 #[derive(Deserialize, Debug)]
 pub struct Server {
     host: Option<String>,
     port: Option<u16>,
+    max_message_size: Option<usize>,
+    max_connections: Option<usize>,
+    #[serde(default)]
+    tcp: TcpServer,
+    #[serde(default)]
+    tls: TlsServer,
 }
 
 
@@ -13,7 +20,26 @@ impl Server {
         return self.host.clone().unwrap_or(String::from("localhost"));
     }
 
+    /// UDP port
     pub fn get_port(&self) -> u16 {
         return self.port.unwrap_or(514);
     }
+
+    /// Longest message accepted, on UDP at most 65535 bytes
+    pub fn get_max_message_size(&self) -> usize {
+        return self.max_message_size.unwrap_or(65536);
+    }
+
+    /// Connections open at the same time, over TCP and TLS together
+    pub fn get_max_connections(&self) -> usize {
+        return self.max_connections.unwrap_or(256);
+    }
+
+    pub fn get_tcp(&self) -> &TcpServer {
+        return &self.tcp;
+    }
+
+    pub fn get_tls(&self) -> &TlsServer {
+        return &self.tls;
+    }
 }
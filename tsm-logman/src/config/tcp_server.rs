@@ -0,0 +1,19 @@
+use serde::Deserialize;
+
+
+#[derive(Deserialize, Debug, Default)]
+pub struct TcpServer {
+    enabled: Option<bool>,
+    port: Option<u16>,
+}
+
+
+impl TcpServer {
+    pub fn is_enabled(&self) -> bool {
+        return self.enabled.unwrap_or(false);
+    }
+
+    pub fn get_port(&self) -> u16 {
+        return self.port.unwrap_or(601);
+    }
+}
@@ -0,0 +1,35 @@
+use serde::Deserialize;
+
+
+#[derive(Deserialize, Debug, Default)]
+pub struct TlsServer {
+    enabled: Option<bool>,
+    port: Option<u16>,
+    certificate: Option<String>,
+    private_key: Option<String>,
+    client_ca: Option<String>,
+}
+
+
+impl TlsServer {
+    pub fn is_enabled(&self) -> bool {
+        return self.enabled.unwrap_or(false);
+    }
+
+    pub fn get_port(&self) -> u16 {
+        return self.port.unwrap_or(6514);
+    }
+
+    pub fn get_certificate(&self) -> String {
+        return self.certificate.clone().unwrap_or_default();
+    }
+
+    pub fn get_private_key(&self) -> String {
+        return self.private_key.clone().unwrap_or_default();
+    }
+
+    /// None if client certificates are not verified
+    pub fn get_client_ca(&self) -> Option<String> {
+        return self.client_ca.clone().filter(|client_ca| !client_ca.is_empty());
+    }
+}
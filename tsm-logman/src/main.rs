@@ -1,7 +1,9 @@
 use std::error::Error;
 use std::process::exit;
+use std::sync::Arc;
 use log::{error};
-use tokio::net::UdpSocket;
+use tokio::net::{TcpListener, UdpSocket};
+use tokio::sync::Semaphore;
 use argparse::{ArgumentParser, Store};
 
 mod config;
@@ -59,15 +61,45 @@ async fn main() -> Result<(), Box<dyn Error>> {
     let configuration = init_config_or_exit(params.config_file);
     init_logger(&configuration);
 
-    // start server
+    // start servers, all of them feed the same parser
     let host = configuration.get_server().get_host();
+    let max_message_size = configuration.get_server().get_max_message_size();
+    let connections = Arc::new(Semaphore::new(configuration.get_server().get_max_connections()));
+
+    if configuration.get_server().get_tcp().is_enabled() {
+        let addr = format!("{}:{}", host, configuration.get_server().get_tcp().get_port());
+        let listener = TcpListener::bind(addr).await?;
+
+        println!("Listening on: {} (TCP)", listener.local_addr()?);
+
+        tokio::spawn(server::TcpServer::new(listener, max_message_size, connections.clone()).run());
+    }
+
+    if configuration.get_server().get_tls().is_enabled() {
+        let tls = configuration.get_server().get_tls();
+        let acceptor = match server::build_tls_acceptor(&tls.get_certificate(), &tls.get_private_key(), tls.get_client_ca()) {
+            Ok(acceptor) => acceptor,
+            Err(e) => {
+                error!("Error setting up TLS: {}", e);
+                exit(10);
+            }
+        };
+
+        let addr = format!("{}:{}", host, tls.get_port());
+        let listener = TcpListener::bind(addr).await?;
+
+        println!("Listening on: {} (TLS)", listener.local_addr()?);
+
+        tokio::spawn(server::TlsServer::new(listener, acceptor, max_message_size, connections.clone()).run());
+    }
+
     let port = configuration.get_server().get_port();
     let addr = format!("{}:{}", host, port);
     let socket = UdpSocket::bind(addr.clone()).await?;
 
     println!("Listening on: {}", socket.local_addr()?);
 
-    let server = server::UdpServer::new(socket);
+    let server = server::UdpServer::new(socket, max_message_size);
 
     // This starts the server task.
     server.run().await?;
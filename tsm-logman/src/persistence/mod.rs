@@ -1,6 +1,9 @@
 mod repository;
 pub use repository::Repository;
 
+mod repository_error;
+pub use repository_error::RepositoryError;
+
 mod syslog_message;
 pub use syslog_message::SyslogMessage;
 
@@ -1,6 +1,6 @@
 use log::{warn, debug};
 use rusqlite::{Connection, Result};
-use crate::persistence::SyslogMessage;
+use crate::persistence::{RepositoryError, SyslogMessage};
 
 
 pub struct Repository {
@@ -27,7 +27,7 @@ impl Repository {
         }
     }
 
-    pub fn init_table_if_not_exist(&self) -> Result<bool, String> {
+    pub fn init_table_if_not_exist(&self) -> Result<bool, RepositoryError> {
         // Create SQLite table "syslog" if not exists
         debug!("Creating table if not exists");
 
@@ -35,7 +35,7 @@ impl Repository {
             Some(ref conn) => conn,
             None => {
                 warn!("No connection to database");
-                return Err(String::from("No connection to database"))
+                return Err(RepositoryError::NoConnection)
             },
         };
 
@@ -60,17 +60,17 @@ impl Repository {
             Ok(_) => Ok(true),
             Err(err) => {
                 warn!("Error creating table: {}", err.to_string());
-                Err(err.to_string())
+                Err(RepositoryError::Sqlite(err))
             },
         }
     }
 
-    pub fn create(&self, msg: SyslogMessage) -> Result<bool, String> {
+    pub fn create(&self, msg: SyslogMessage) -> Result<bool, RepositoryError> {
         let conn = match self.conn {
             Some(ref conn) => conn,
             None => {
                 warn!("No connection to database");
-                return Err(String::from("No connection to database"))
+                return Err(RepositoryError::NoConnection)
             },
         };
 
@@ -112,7 +112,7 @@ impl Repository {
             Ok(_) => Ok(true),
             Err(err) => {
                 warn!("Error inserting into table: {}", err.to_string());
-                Err(err.to_string())
+                Err(RepositoryError::Sqlite(err))
             },
         }
     }
@@ -185,12 +185,12 @@ impl Repository {
         }
     }
 
-    pub fn delete(&self, id: u32) -> Result<bool, String> {
+    pub fn delete(&self, id: u32) -> Result<bool, RepositoryError> {
         let conn = match self.conn {
             Some(ref conn) => conn,
             None => {
                 warn!("No connection to database");
-                return Err(String::from("No connection to database"))
+                return Err(RepositoryError::NoConnection)
             },
         };
 
@@ -201,7 +201,7 @@ impl Repository {
             Ok(_) => Ok(true),
             Err(err) => {
                 warn!("Error deleting from table: {}", err.to_string());
-                Err(err.to_string())
+                Err(RepositoryError::Sqlite(err))
             },
         }
     }
@@ -0,0 +1,21 @@
+use std::fmt;
+
+
+#[derive(Debug)]
+pub enum RepositoryError {
+    NoConnection,
+    Sqlite(rusqlite::Error),
+}
+
+
+impl fmt::Display for RepositoryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RepositoryError::NoConnection => write!(f, "No connection to database"),
+            RepositoryError::Sqlite(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+
+impl std::error::Error for RepositoryError {}
@@ -0,0 +1,162 @@
+use std::io;
+use std::str;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt};
+
+
+/// Octet counts longer than this can't be below any sensible maximum message size
+const MAX_OCTET_COUNT_DIGITS: u64 = 10;
+
+
+/// Waits until the next frame starts, skipping empty lines, e.g. from senders ending octet counted
+/// frames with LF too. Returns false once the sender closed the connection.
+pub async fn wait_for_frame<R: AsyncBufRead + Unpin>(reader: &mut R) -> Result<bool, io::Error> {
+    loop {
+        let buffer = reader.fill_buf().await?;
+
+        if buffer.is_empty() {
+            return Ok(false);
+        }
+
+        let line_endings = buffer.iter().take_while(|byte| **byte == b'\n' || **byte == b'\r').count();
+
+        if line_endings == 0 {
+            return Ok(true);
+        }
+
+        reader.consume(line_endings);
+    }
+}
+
+
+/// Reads the next message of a syslog stream (RFC 6587). A frame starting with a digit is octet
+/// counted ("<length> <message>"), any other is terminated by LF, so senders may use both on the
+/// same connection. Returns None once the sender closed the connection.
+pub async fn read_frame<R: AsyncBufRead + Unpin>(reader: &mut R, max_message_size: usize) -> Result<Option<Vec<u8>>, io::Error> {
+    loop {
+        let first = match reader.fill_buf().await?.first() {
+            Some(first) => *first,
+            None => return Ok(None),
+        };
+
+        if first.is_ascii_digit() {
+            return read_octet_counted(reader, max_message_size).await.map(Some);
+        }
+
+        // empty lines, e.g. from senders ending octet counted frames with LF too, are skipped
+        match read_delimited(reader, max_message_size).await? {
+            Some(message) if message.is_empty() => continue,
+            Some(message) => return Ok(Some(message)),
+            None => return Ok(None),
+        };
+    }
+}
+
+
+async fn read_octet_counted<R: AsyncBufRead + Unpin>(reader: &mut R, max_message_size: usize) -> Result<Vec<u8>, io::Error> {
+    let mut octet_count = Vec::new();
+    (&mut *reader).take(MAX_OCTET_COUNT_DIGITS + 1).read_until(b' ', &mut octet_count).await?;
+
+    if octet_count.pop() != Some(b' ') {
+        return Err(invalid_data(String::from("Octet count is not followed by a space")));
+    }
+
+    let length: usize = match str::from_utf8(&octet_count).ok().and_then(|octet_count| octet_count.parse().ok()) {
+        Some(length) => length,
+        None => return Err(invalid_data(format!("Invalid octet count {}", String::from_utf8_lossy(&octet_count)))),
+    };
+
+    if length > max_message_size {
+        return Err(invalid_data(format!("Message of {} bytes exceeds the maximum of {} bytes", length, max_message_size)));
+    }
+
+    let mut message = vec![0; length];
+    reader.read_exact(&mut message).await?;
+
+    Ok(message)
+}
+
+
+/// The LF and a CR before it are not part of the message and don't count towards its size. The
+/// last message may end without LF.
+async fn read_delimited<R: AsyncBufRead + Unpin>(reader: &mut R, max_message_size: usize) -> Result<Option<Vec<u8>>, io::Error> {
+    let mut message = Vec::new();
+    let size = (&mut *reader).take(max_message_size as u64 + 2).read_until(b'\n', &mut message).await?;
+
+    if size == 0 {
+        return Ok(None);
+    }
+
+    if message.last() == Some(&b'\n') {
+        message.pop();
+
+        if message.last() == Some(&b'\r') {
+            message.pop();
+        }
+    }
+
+    if message.len() > max_message_size {
+        return Err(invalid_data(format!("Message exceeds the maximum of {} bytes", max_message_size)));
+    }
+
+    Ok(Some(message))
+}
+
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn read_all(mut stream: &[u8], max_message_size: usize) -> Result<Vec<String>, io::Error> {
+        let mut messages = Vec::new();
+
+        while let Some(message) = read_frame(&mut stream, max_message_size).await? {
+            messages.push(String::from_utf8(message).unwrap());
+        }
+
+        Ok(messages)
+    }
+
+    #[tokio::test]
+    async fn read_frame_should_handle_both_framings() {
+        let stream = b"11 <13>1 - a\nb<13>Oct 11 22:14:15 host message\r\n\n5 <13>115 <13>1 - - - - -";
+        let messages = read_all(stream, 1024).await.unwrap();
+
+        assert_eq!(messages, vec!["<13>1 - a\nb", "<13>Oct 11 22:14:15 host message", "<13>1", "<13>1 - - - - -"]);
+    }
+
+    #[tokio::test]
+    async fn read_frame_should_reject_too_long_messages() {
+        assert!(read_all(b"2048 <13>1", 1024).await.is_err());
+        assert!(read_all(&[b'a'; 2048], 1024).await.is_err());
+        assert_eq!(read_all(&[b'a'; 1024], 1024).await.unwrap().len(), 1);
+
+        let mut exactly_max = vec![b'a'; 1024];
+        exactly_max.extend_from_slice(b"\r\n");
+        assert_eq!(read_all(&exactly_max, 1024).await.unwrap(), vec!["a".repeat(1024)]);
+
+        let mut over_max = vec![b'a'; 1025];
+        over_max.push(b'\n');
+        assert!(read_all(&over_max, 1024).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn wait_for_frame_should_skip_empty_lines() {
+        let mut stream: &[u8] = b"\r\n\n5 <13>1";
+        assert!(wait_for_frame(&mut stream).await.unwrap());
+        assert_eq!(stream, b"5 <13>1");
+
+        let mut stream: &[u8] = b"\n\n";
+        assert!(!wait_for_frame(&mut stream).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn read_frame_should_reject_truncated_frames() {
+        assert!(read_all(b"20 <13>1", 1024).await.is_err());
+        assert!(read_all(b"12345678901234 <13>1", 1024).await.is_err());
+    }
+}
@@ -1,4 +1,13 @@
 mod server;
 pub use server::UdpServer;
 
+mod tcp_server;
+pub use tcp_server::TcpServer;
+
+mod tls_server;
+pub use tls_server::{TlsServer, build_tls_acceptor};
+
+mod framing;
+mod pipeline;
+
 pub mod syslog;
@@ -0,0 +1,16 @@
+use std::net::SocketAddr;
+use crate::server::syslog;
+
+
+/// Every listener hands its messages here, one complete message at a time
+pub fn process(from: SocketAddr, buf: &[u8]) {
+    match syslog::parse(from, buf.len(), buf) {
+        Some(msg) => println!("{:?}", msg),
+        None => {
+            match std::str::from_utf8(buf) {
+                Ok(s) => eprintln!("error parsing: {}", s),
+                Err(e) => eprintln!("received message not parseable and not UTF-8: {}", e),
+            }
+        }
+    }
+}
@@ -1,21 +1,31 @@
 use std::net::SocketAddr;
 use std::io;
+use log::warn;
 use tokio::net::UdpSocket;
-use crate::server::syslog;
+use crate::server::pipeline;
+
+
+/// No datagram can be longer than this
+const MAX_DATAGRAM_SIZE: usize = 65535;
 
 
 pub struct UdpServer {
     socket: UdpSocket,
+    max_message_size: usize,
     buf: Vec<u8>,
     to_send: Option<(usize, SocketAddr)>,
 }
 
 
 impl UdpServer {
-    pub fn new(socket: UdpSocket) -> UdpServer {
+    /// The buffer has room for one more byte, so that longer datagrams are recognized instead of truncated
+    pub fn new(socket: UdpSocket, max_message_size: usize) -> UdpServer {
+        let max_message_size = max_message_size.min(MAX_DATAGRAM_SIZE);
+
         UdpServer {
             socket,
-            buf: vec![0; 4096],
+            max_message_size,
+            buf: vec![0; max_message_size + 1],
             to_send: None,
         }
     }
@@ -23,6 +33,7 @@ impl UdpServer {
     pub async fn run(self) -> Result<(), io::Error> {
         let UdpServer {
             socket,
+            max_message_size,
             mut buf,
             mut to_send,
         } = self;
@@ -30,17 +41,10 @@ impl UdpServer {
         loop {
             // First we check to see if there's a message we need to process.
             if let Some((size, peer)) = to_send {
-                let buffer = &mut buf[..size];
-
-                // Parse syslog message
-                match syslog::parse(peer, size, buffer) {
-                    Some(msg) => println!("{:?}", msg),
-                    None => {
-                        match std::str::from_utf8(buffer) {
-                            Ok(s) => eprintln!("error parsing: {}", s),
-                            Err(e) => eprintln!("received message not parseable and not UTF-8: {}", e),
-                        }
-                    }
+                if size > max_message_size {
+                    warn!("Dropping datagram from {}, it exceeds the maximum of {} bytes", peer, max_message_size);
+                } else {
+                    pipeline::process(peer, &buf[..size]);
                 }
             }
 
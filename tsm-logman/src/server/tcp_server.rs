@@ -0,0 +1,131 @@
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use log::{debug, warn};
+use tokio::io::{AsyncRead, BufReader};
+use tokio::net::TcpListener;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tokio::time;
+use crate::server::{framing, pipeline};
+
+
+/// Failing to accept, e.g. while out of file descriptors, is retried after this
+pub const ACCEPT_RETRY_INTERVAL: Duration = Duration::from_millis(100);
+/// Senders may keep a connection open without sending anything for this long
+const IDLE_TIMEOUT: Duration = Duration::from_secs(600);
+/// Once a message started, all of it has to arrive within this time
+const FRAME_TIMEOUT: Duration = Duration::from_secs(30);
+
+
+/// Syslog over TCP (RFC 6587), with a task per connection
+pub struct TcpServer {
+    listener: TcpListener,
+    max_message_size: usize,
+    connections: Arc<Semaphore>,
+}
+
+
+impl TcpServer {
+    /// The connections are shared with the TLS server, a permit is taken for each one
+    pub fn new(listener: TcpListener, max_message_size: usize, connections: Arc<Semaphore>) -> TcpServer {
+        TcpServer {
+            listener,
+            max_message_size,
+            connections,
+        }
+    }
+
+    pub async fn run(self) {
+        loop {
+            let (stream, peer) = match self.listener.accept().await {
+                Ok(connection) => connection,
+                Err(e) => {
+                    warn!("Failed to accept TCP connection: {}", e);
+                    time::sleep(ACCEPT_RETRY_INTERVAL).await;
+                    continue;
+                }
+            };
+
+            let permit = match acquire_connection(&self.connections, peer) {
+                Some(permit) => permit,
+                None => continue,
+            };
+            let max_message_size = self.max_message_size;
+
+            tokio::spawn(async move {
+                serve_connection(stream, peer, max_message_size).await;
+                drop(permit);
+            });
+        }
+    }
+}
+
+
+/// Connections beyond the limit are closed right away
+pub fn acquire_connection(connections: &Arc<Semaphore>, peer: SocketAddr) -> Option<OwnedSemaphorePermit> {
+    return match connections.clone().try_acquire_owned() {
+        Ok(permit) => Some(permit),
+        Err(_) => {
+            warn!("Closing connection from {}, too many connections are open", peer);
+            None
+        }
+    };
+}
+
+
+/// Passes the messages of a TCP or TLS connection on until the sender closes it. A framing error
+/// closes the connection, since the start of the next message can't be found anymore.
+pub async fn serve_connection<S: AsyncRead + Unpin>(stream: S, peer: SocketAddr, max_message_size: usize) {
+    debug!("Connection from {} opened", peer);
+    let mut reader = BufReader::new(stream);
+
+    loop {
+        match time::timeout(IDLE_TIMEOUT, framing::wait_for_frame(&mut reader)).await {
+            Ok(Ok(true)) => {},
+            Ok(Ok(false)) => break,
+            Ok(Err(e)) => {
+                warn!("Closing connection from {}: {}", peer, e);
+                return;
+            },
+            Err(_) => {
+                debug!("Closing connection from {}, idle for {}s", peer, IDLE_TIMEOUT.as_secs());
+                return;
+            }
+        };
+
+        // a sender stalling in the middle of a message holds its buffer, so it gets less time
+        match time::timeout(FRAME_TIMEOUT, framing::read_frame(&mut reader, max_message_size)).await {
+            Ok(Ok(Some(message))) => pipeline::process(peer, &message),
+            Ok(Ok(None)) => break,
+            Ok(Err(e)) => {
+                warn!("Closing connection from {}: {}", peer, e);
+                return;
+            },
+            Err(_) => {
+                warn!("Closing connection from {}, message not complete after {}s", peer, FRAME_TIMEOUT.as_secs());
+                return;
+            }
+        };
+    }
+
+    debug!("Connection from {} closed", peer);
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn acquire_connection_should_be_limited() {
+        let connections = Arc::new(Semaphore::new(1));
+        let peer = SocketAddr::from(([127, 0, 0, 1], 601));
+
+        let permit = acquire_connection(&connections, peer);
+        assert!(permit.is_some());
+        assert!(acquire_connection(&connections, peer).is_none());
+
+        drop(permit);
+        assert!(acquire_connection(&connections, peer).is_some());
+    }
+}
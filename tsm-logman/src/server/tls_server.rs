@@ -0,0 +1,148 @@
+use std::fs::File;
+use std::io;
+use std::io::BufReader;
+use std::sync::Arc;
+use std::time::Duration;
+use log::warn;
+use rustls_pemfile::Item;
+use tokio::net::TcpListener;
+use tokio::sync::Semaphore;
+use tokio::time;
+use tokio_rustls::rustls::{Certificate, PrivateKey, RootCertStore, ServerConfig};
+use tokio_rustls::rustls::server::AllowAnyAuthenticatedClient;
+use tokio_rustls::TlsAcceptor;
+use crate::server::tcp_server;
+
+
+/// Clients which don't finish the handshake in time are dropped
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+
+/// Syslog over TLS (RFC 5425). The messages are framed like on TCP.
+pub struct TlsServer {
+    listener: TcpListener,
+    acceptor: TlsAcceptor,
+    max_message_size: usize,
+    connections: Arc<Semaphore>,
+}
+
+
+impl TlsServer {
+    /// The connections are shared with the TCP server, a permit is taken for each one
+    pub fn new(listener: TcpListener, acceptor: TlsAcceptor, max_message_size: usize, connections: Arc<Semaphore>) -> TlsServer {
+        TlsServer {
+            listener,
+            acceptor,
+            max_message_size,
+            connections,
+        }
+    }
+
+    pub async fn run(self) {
+        loop {
+            let (stream, peer) = match self.listener.accept().await {
+                Ok(connection) => connection,
+                Err(e) => {
+                    warn!("Failed to accept TLS connection: {}", e);
+                    time::sleep(tcp_server::ACCEPT_RETRY_INTERVAL).await;
+                    continue;
+                }
+            };
+
+            // taken before the handshake, which costs more than serving the connection
+            let permit = match tcp_server::acquire_connection(&self.connections, peer) {
+                Some(permit) => permit,
+                None => continue,
+            };
+            let acceptor = self.acceptor.clone();
+            let max_message_size = self.max_message_size;
+
+            tokio::spawn(async move {
+                let stream = match time::timeout(HANDSHAKE_TIMEOUT, acceptor.accept(stream)).await {
+                    Ok(Ok(stream)) => stream,
+                    Ok(Err(e)) => {
+                        warn!("TLS handshake with {} failed: {}", peer, e);
+                        return;
+                    },
+                    Err(_) => {
+                        warn!("TLS handshake with {} timed out", peer);
+                        return;
+                    }
+                };
+
+                tcp_server::serve_connection(stream, peer, max_message_size).await;
+                drop(permit);
+            });
+        }
+    }
+}
+
+
+/// Certificates and keys are PEM files. With a client CA, only clients presenting a certificate
+/// signed by it are accepted.
+pub fn build_tls_acceptor(certificate: &str, private_key: &str, client_ca: Option<String>) -> Result<TlsAcceptor, io::Error> {
+    let certificates = read_certificates(certificate)?;
+    let private_key = read_private_key(private_key)?;
+    let builder = ServerConfig::builder().with_safe_defaults();
+
+    let builder = match client_ca {
+        Some(client_ca) => {
+            let mut roots = RootCertStore::empty();
+
+            for ca_certificate in read_certificates(&client_ca)? {
+                if let Err(e) = roots.add(&ca_certificate) {
+                    return Err(invalid_data(format!("Invalid CA certificate in {}: {}", client_ca, e)));
+                }
+            }
+
+            builder.with_client_cert_verifier(AllowAnyAuthenticatedClient::new(roots).boxed())
+        },
+        None => builder.with_no_client_auth(),
+    };
+
+    return match builder.with_single_cert(certificates, private_key) {
+        Ok(config) => Ok(TlsAcceptor::from(Arc::new(config))),
+        Err(e) => Err(invalid_data(format!("Invalid certificate {}: {}", certificate, e))),
+    };
+}
+
+
+fn read_certificates(path: &str) -> Result<Vec<Certificate>, io::Error> {
+    let certificates = rustls_pemfile::certs(&mut open(path)?)?;
+
+    if certificates.is_empty() {
+        return Err(invalid_data(format!("No certificate found in {}", path)));
+    }
+
+    Ok(certificates.into_iter().map(Certificate).collect())
+}
+
+
+/// PKCS#8, RSA and SEC1 keys are taken, the first one found is used
+fn read_private_key(path: &str) -> Result<PrivateKey, io::Error> {
+    for item in rustls_pemfile::read_all(&mut open(path)?)? {
+        match item {
+            Item::PKCS8Key(key) | Item::RSAKey(key) | Item::ECKey(key) => return Ok(PrivateKey(key)),
+            _ => continue,
+        };
+    }
+
+    Err(invalid_data(format!("No private key found in {}", path)))
+}
+
+
+fn open(path: &str) -> Result<BufReader<File>, io::Error> {
+    if path.is_empty() {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "Certificate or private key not configured"));
+    }
+
+    return match File::open(path) {
+        Ok(file) => Ok(BufReader::new(file)),
+        Err(e) => Err(io::Error::new(e.kind(), format!("Failed to open {}: {}", path, e))),
+    };
+}
+
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
@@ -0,0 +1,27 @@
+use tsm_ipc::{RpcClient, tsm_common_rpc};
+
+use crate::command_error::CommandError;
+
+
+/// Requests name the service, so that the unitctl doesn't talk to e.g. the log manager by mistake
+pub const SERVICE_NAME: &str = "tsm-unitman";
+
+
+pub fn send_capabilities_request(rpc_client: RpcClient) -> Result<tsm_common_rpc::Capabilities, CommandError> {
+    return match rpc_client.get_capabilities() {
+        Ok(capabilities) => Ok(capabilities),
+        Err(error) => Err(CommandError::Rpc(error)),
+    };
+}
+
+
+pub fn print_capabilities(capabilities: tsm_common_rpc::Capabilities) {
+    println!("Service: {}", capabilities.service);
+    println!("Version: {}", capabilities.server_version);
+    println!("Protocol: {} (supports {} to {})", capabilities.protocol_version, capabilities.min_protocol_version, capabilities.protocol_version);
+    println!("Methods:");
+
+    for method in capabilities.methods {
+        println!("  {} ({})", method.name, method.id);
+    }
+}
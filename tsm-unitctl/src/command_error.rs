@@ -0,0 +1,104 @@
+use std::fmt;
+use tsm_ipc::RpcError;
+use tsm_ipc::tsm_common_rpc::error::Code;
+
+
+pub const EXIT_SUCCESS: i32 = 0;
+pub const EXIT_ERROR: i32 = 1;
+pub const EXIT_TIMED_OUT: i32 = 2;
+pub const EXIT_FAILED: i32 = 3;
+pub const EXIT_NOT_FOUND: i32 = 4;
+pub const EXIT_PERMISSION_DENIED: i32 = 5;
+pub const EXIT_BUSY: i32 = 6;
+pub const EXIT_INVALID: i32 = 7;
+pub const EXIT_INCOMPATIBLE: i32 = 8;
+
+
+/// Errors of a command, the kind decides the exit code
+#[derive(Debug)]
+pub enum CommandError {
+    /// The request didn't get through or no response came back
+    Rpc(RpcError),
+    /// The unit manager answered with an error
+    Server(Code, String),
+    /// The response couldn't be read
+    Response(String),
+    /// Invalid arguments, rejected before anything was sent
+    Invalid(String),
+    /// Subscribing to or receiving events failed
+    Events(String),
+}
+
+
+impl CommandError {
+    pub fn exit_code(&self) -> i32 {
+        return match self {
+            CommandError::Rpc(RpcError::Timeout { .. }) => EXIT_TIMED_OUT,
+            CommandError::Rpc(RpcError::Incompatible(_)) => EXIT_INCOMPATIBLE,
+            CommandError::Rpc(_) => EXIT_ERROR,
+            CommandError::Server(code, _) => match code {
+                Code::NotFound => EXIT_NOT_FOUND,
+                Code::PermissionDenied => EXIT_PERMISSION_DENIED,
+                Code::Busy => EXIT_BUSY,
+                Code::Timeout => EXIT_TIMED_OUT,
+                Code::DependencyFailed | Code::Failed => EXIT_FAILED,
+                Code::InvalidRequest | Code::UnknownMethod => EXIT_INVALID,
+                Code::Incompatible => EXIT_INCOMPATIBLE,
+                _ => EXIT_ERROR,
+            },
+            CommandError::Response(_) => EXIT_ERROR,
+            CommandError::Invalid(_) => EXIT_INVALID,
+            CommandError::Events(_) => EXIT_ERROR,
+        };
+    }
+}
+
+
+impl fmt::Display for CommandError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CommandError::Rpc(error) => write!(f, "{}", error),
+            CommandError::Server(_, message) => write!(f, "{}", message),
+            CommandError::Response(message) => write!(f, "{}", message),
+            CommandError::Invalid(message) => write!(f, "{}", message),
+            CommandError::Events(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+
+/// Errors the unit manager answered with keep their code
+impl From<RpcError> for CommandError {
+    fn from(error: RpcError) -> CommandError {
+        return match error {
+            RpcError::Server { code, message, .. } => CommandError::Server(code, message),
+            error => CommandError::Rpc(error),
+        };
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_server_error_should_map_code_to_exit_code() {
+        let error = CommandError::from(RpcError::server(Code::NotFound, String::from("Failed to start unit foo: Unit foo not found")));
+
+        assert_eq!(error.exit_code(), EXIT_NOT_FOUND);
+        assert_eq!(error.to_string(), "Failed to start unit foo: Unit foo not found");
+    }
+
+    #[test]
+    fn from_server_error_without_code_should_be_generic_error() {
+        let error = CommandError::from(RpcError::server(Code::Unknown, String::from("Something went wrong")));
+
+        assert_eq!(error.exit_code(), EXIT_ERROR);
+    }
+
+    #[test]
+    fn from_timeout_should_be_timed_out() {
+        assert_eq!(CommandError::from(RpcError::Timeout { attempts: 3 }).exit_code(), EXIT_TIMED_OUT);
+    }
+}
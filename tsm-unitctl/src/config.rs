@@ -0,0 +1,102 @@
+use std::fs;
+use std::path::Path;
+use serde::Deserialize;
+
+
+pub const DEFAULT_CONFIG_FILE: &str = "/etc/tsm-unitctl.toml";
+
+
+/// Defaults for the command line, every value can be overridden by environment variables and flags
+#[derive(Deserialize, Debug, Default)]
+pub struct Configuration {
+    address: Option<String>,
+    events_address: Option<String>,
+    timeout_ms: Option<u32>,
+    log_level: Option<String>,
+    server_public_key: Option<String>,
+    public_key: Option<String>,
+    secret_key: Option<String>,
+}
+
+
+impl Configuration {
+    pub fn from_file(file_path: String) -> Result<Configuration, String> {
+        return match fs::read_to_string(file_path) {
+            Ok(content) => {
+                Configuration::from_string(content)
+            },
+            Err(error) => {
+                Err(format!("Error reading configuration file: {}", error))
+            }
+        }
+    }
+
+    pub fn from_string(content: String) -> Result<Configuration, String> {
+        return match toml::from_str(&content) {
+            Ok(configuration) => {
+                Ok(configuration)
+            },
+            Err(error) => {
+                Err(format!("Error parsing configuration file: {}", error))
+            }
+        }
+    }
+
+    /// The given file must exist, without one the default file is read if it exists
+    pub fn load(file_path: String) -> Result<Configuration, String> {
+        if !file_path.is_empty() {
+            return Configuration::from_file(file_path);
+        }
+
+        return match Path::new(DEFAULT_CONFIG_FILE).exists() {
+            true => Configuration::from_file(String::from(DEFAULT_CONFIG_FILE)),
+            false => Ok(Configuration::default()),
+        };
+    }
+
+    pub fn get_address(&self) -> String {
+        return self.address.clone().unwrap_or("ipc:///tmp/tsm-unitman.sock".to_string());
+    }
+
+    pub fn get_events_address(&self) -> String {
+        return self.events_address.clone().unwrap_or("ipc:///tmp/tsm-unitman-events.sock".to_string());
+    }
+
+    pub fn get_timeout_ms(&self) -> u32 {
+        return self.timeout_ms.unwrap_or(5000);
+    }
+
+    pub fn get_log_level(&self) -> String {
+        return self.log_level.clone().unwrap_or("warn".to_string());
+    }
+
+    /// Z85 encoded public key of the server, public and secret key of the client, CURVE is only used if all are set
+    pub fn get_curve_keys(&self) -> Option<(String, String, String)> {
+        return match (&self.server_public_key, &self.public_key, &self.secret_key) {
+            (Some(server_public_key), Some(public_key), Some(secret_key)) => Some((server_public_key.clone(), public_key.clone(), secret_key.clone())),
+            _ => None,
+        };
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_string_should_fall_back_to_defaults() {
+        let configuration = Configuration::from_string(String::from(r#"
+            address = "tcp://192.168.7.2:5555"
+            timeout_ms = 1000
+        "#)).unwrap();
+
+        assert_eq!(configuration.get_address(), "tcp://192.168.7.2:5555");
+        assert_eq!(configuration.get_events_address(), "ipc:///tmp/tsm-unitman-events.sock");
+        assert_eq!(configuration.get_timeout_ms(), 1000);
+        assert_eq!(configuration.get_log_level(), "warn");
+        assert_eq!(configuration.get_curve_keys(), None);
+
+        assert!(Configuration::from_string(String::from("timeout_ms = \"soon\"")).is_err());
+    }
+}
@@ -0,0 +1,20 @@
+use tsm_ipc::{RpcClient, tsm_unitman_rpc};
+use protobuf::Enum;
+
+use crate::command_error::CommandError;
+
+
+pub fn send_disable_unit_request(rpc_client: RpcClient, unit_name: String, now: bool) -> Result<tsm_unitman_rpc::DisableUnitResponse, CommandError> {
+    let disable_unit_request = build_disable_unit_request(unit_name, now);
+
+    return rpc_client.call(tsm_unitman_rpc::RpcMethod::DisableUnit.value(), &disable_unit_request).map_err(CommandError::from);
+}
+
+
+fn build_disable_unit_request(unit_name: String, now: bool) -> tsm_unitman_rpc::DisableUnitRequest {
+    let mut disable_unit_request = tsm_unitman_rpc::DisableUnitRequest::new();
+    disable_unit_request.unit_name = unit_name;
+    disable_unit_request.now = now;
+
+    disable_unit_request
+}
\ No newline at end of file
@@ -0,0 +1,20 @@
+use tsm_ipc::{RpcClient, tsm_unitman_rpc};
+use protobuf::Enum;
+
+use crate::command_error::CommandError;
+
+
+pub fn send_enable_unit_request(rpc_client: RpcClient, unit_name: String, now: bool) -> Result<tsm_unitman_rpc::EnableUnitResponse, CommandError> {
+    let enable_unit_request = build_enable_unit_request(unit_name, now);
+
+    return rpc_client.call(tsm_unitman_rpc::RpcMethod::EnableUnit.value(), &enable_unit_request).map_err(CommandError::from);
+}
+
+
+fn build_enable_unit_request(unit_name: String, now: bool) -> tsm_unitman_rpc::EnableUnitRequest {
+    let mut enable_unit_request = tsm_unitman_rpc::EnableUnitRequest::new();
+    enable_unit_request.unit_name = unit_name;
+    enable_unit_request.now = now;
+
+    enable_unit_request
+}
\ No newline at end of file
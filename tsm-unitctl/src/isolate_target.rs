@@ -0,0 +1,19 @@
+use tsm_ipc::{RpcClient, tsm_unitman_rpc};
+use protobuf::Enum;
+
+use crate::command_error::CommandError;
+
+
+pub fn send_isolate_target_request(rpc_client: RpcClient, target_name: String) -> Result<tsm_unitman_rpc::IsolateTargetResponse, CommandError> {
+    let isolate_target_request = build_isolate_target_request(target_name);
+
+    return rpc_client.call(tsm_unitman_rpc::RpcMethod::IsolateTarget.value(), &isolate_target_request).map_err(CommandError::from);
+}
+
+
+fn build_isolate_target_request(target_name: String) -> tsm_unitman_rpc::IsolateTargetRequest {
+    let mut isolate_target_request = tsm_unitman_rpc::IsolateTargetRequest::new();
+    isolate_target_request.target_name = target_name;
+
+    isolate_target_request
+}
\ No newline at end of file
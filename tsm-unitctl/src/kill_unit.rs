@@ -0,0 +1,21 @@
+use tsm_ipc::{RpcClient, tsm_unitman_rpc};
+use protobuf::Enum;
+
+use crate::command_error::CommandError;
+
+
+pub fn send_kill_unit_request(rpc_client: RpcClient, unit_name: String, signal: String, group: bool) -> Result<tsm_unitman_rpc::KillUnitResponse, CommandError> {
+    let kill_unit_request = build_kill_unit_request(unit_name, signal, group);
+
+    return rpc_client.call(tsm_unitman_rpc::RpcMethod::KillUnit.value(), &kill_unit_request).map_err(CommandError::from);
+}
+
+
+fn build_kill_unit_request(unit_name: String, signal: String, group: bool) -> tsm_unitman_rpc::KillUnitRequest {
+    let mut kill_unit_request = tsm_unitman_rpc::KillUnitRequest::new();
+    kill_unit_request.unit_name = unit_name;
+    kill_unit_request.signal = signal;
+    kill_unit_request.group = group;
+
+    kill_unit_request
+}
\ No newline at end of file
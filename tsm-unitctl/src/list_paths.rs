@@ -0,0 +1,57 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tsm_ipc::tsm_unitman_rpc;
+use protobuf::Enum;
+use tabled::{builder::Builder, settings::Style};
+use humantime::{format_duration, format_rfc3339_seconds};
+
+
+pub fn print_paths(units: Vec<tsm_unitman_rpc::Unit>) {
+    let mut builder = Builder::new();
+    builder.set_header(vec!["UNIT", "STATE", "LAST", "PASSED", "TRIGGERED BY", "COUNT", "CONDITIONS"]);
+
+    let now = SystemTime::now();
+
+    for unit in units {
+        let path_trigger = match unit.path_trigger.into_option() {
+            Some(path_trigger) => path_trigger,
+            None => continue,
+        };
+
+        let state = match tsm_unitman_rpc::unit::path_trigger::State::from_i32(path_trigger.state.value()) {
+            Some(tsm_unitman_rpc::unit::path_trigger::State::Waiting) => String::from("waiting"),
+            Some(tsm_unitman_rpc::unit::path_trigger::State::Pending) => String::from("pending"),
+            Some(tsm_unitman_rpc::unit::path_trigger::State::RateLimited) => String::from("rate-limited"),
+            None => String::from("unknown"),
+        };
+
+        let (last, passed) = match path_trigger.last_trigger {
+            0 => (String::from("-"), String::from("-")),
+            timestamp => {
+                let last = UNIX_EPOCH + Duration::from_secs(timestamp as u64);
+                let passed = match now.duration_since(last) {
+                    Ok(duration) => format!("{} ago", format_duration(Duration::from_secs(duration.as_secs()))),
+                    Err(_) => String::from("-"),
+                };
+                (format_rfc3339_seconds(last).to_string(), passed)
+            },
+        };
+
+        let last_path = match path_trigger.last_path.is_empty() {
+            true => String::from("-"),
+            false => path_trigger.last_path,
+        };
+
+        let mut conditions = Vec::new();
+        conditions.extend(path_trigger.on_path_exists.iter().map(|path| format!("exists:{}", path)));
+        conditions.extend(path_trigger.on_path_changed.iter().map(|path| format!("changed:{}", path)));
+        conditions.extend(path_trigger.on_directory_not_empty.iter().map(|path| format!("not-empty:{}", path)));
+
+        builder.push_record([unit.name, state, last, passed, last_path, path_trigger.trigger_count.to_string(), conditions.join(" ")]);
+    }
+
+    let mut table = builder.build();
+    table.with(Style::empty());
+
+    let table = table.to_string();
+    println!("{}", table);
+}
@@ -0,0 +1,38 @@
+use tsm_ipc::{RpcClient, tsm_unitman_rpc};
+use protobuf::Enum;
+use tabled::{builder::Builder, settings::Style};
+
+use crate::command_error::CommandError;
+
+
+pub fn send_list_targets_request(rpc_client: RpcClient) -> Result<tsm_unitman_rpc::ListTargetsResponse, CommandError> {
+    let list_targets_request = tsm_unitman_rpc::ListTargetsRequest::new();
+
+    return rpc_client.call_idempotent(tsm_unitman_rpc::RpcMethod::ListTargets.value(), &list_targets_request).map_err(CommandError::from);
+}
+
+
+pub fn print_targets(list_targets_response: tsm_unitman_rpc::ListTargetsResponse) {
+    let mut builder = Builder::new();
+    builder.set_header(vec!["NAME", "ACTIVE", "DEFAULT", "WANTS", "UNITS"]);
+
+    for target in list_targets_response.targets {
+        let active = match target.name == list_targets_response.active_target {
+            true => String::from("Active"),
+            false => String::from("-"),
+        };
+
+        let default = match target.name == list_targets_response.default_target {
+            true => String::from("Default"),
+            false => String::from("-"),
+        };
+
+        builder.push_record([target.name, active, default, target.wants.join(", "), target.units.join(", ")]);
+    }
+
+    let mut table = builder.build();
+    table.with(Style::empty());
+
+    let table = table.to_string();
+    println!("{}", table);
+}
@@ -0,0 +1,56 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tsm_ipc::tsm_unitman_rpc;
+use tabled::{builder::Builder, settings::Style};
+use humantime::{format_duration, format_rfc3339_seconds};
+
+
+pub fn print_timers(units: Vec<tsm_unitman_rpc::Unit>) {
+    let mut builder = Builder::new();
+    builder.set_header(vec!["NEXT", "LEFT", "LAST", "PASSED", "UNIT", "CALENDAR"]);
+
+    let now = SystemTime::now();
+
+    for unit in units {
+        let timer = match unit.timer.into_option() {
+            Some(timer) => timer,
+            None => continue,
+        };
+
+        let (next, left) = match timer.next_trigger {
+            0 => (String::from("-"), String::from("-")),
+            timestamp => {
+                let next = UNIX_EPOCH + Duration::from_secs(timestamp as u64);
+                let left = match next.duration_since(now) {
+                    Ok(duration) => format_duration(Duration::from_secs(duration.as_secs())).to_string(),
+                    Err(_) => String::from("due"),
+                };
+                (format_rfc3339_seconds(next).to_string(), left)
+            },
+        };
+
+        let (last, passed) = match timer.last_trigger {
+            0 => (String::from("-"), String::from("-")),
+            timestamp => {
+                let last = UNIX_EPOCH + Duration::from_secs(timestamp as u64);
+                let passed = match now.duration_since(last) {
+                    Ok(duration) => format!("{} ago", format_duration(Duration::from_secs(duration.as_secs()))),
+                    Err(_) => String::from("-"),
+                };
+                (format_rfc3339_seconds(last).to_string(), passed)
+            },
+        };
+
+        let calendar = match timer.on_calendar.is_empty() {
+            true => String::from("-"),
+            false => timer.on_calendar,
+        };
+
+        builder.push_record([next, left, last, passed, unit.name, calendar]);
+    }
+
+    let mut table = builder.build();
+    table.with(Style::empty());
+
+    let table = table.to_string();
+    println!("{}", table);
+}
@@ -1,102 +1,125 @@
 use std::time::Duration;
-use tsm_ipc::{RpcClient, tsm_common_rpc, tsm_unitman_rpc};
-use protobuf::{Message, Enum};
-use tabled::{builder::Builder, settings::Style};
+use tsm_ipc::{RpcClient, tsm_unitman_rpc};
+use protobuf::Enum;
 use humantime::format_duration;
+use serde_json::{json, Value};
+use crate::output::{Cell, Column, OutputOptions, Records, matches_pattern};
+use crate::command_error::CommandError;
 
 
-pub fn send_list_units_request(rpc_client: RpcClient) -> Result<tsm_unitman_rpc::ListUnitsResponse, String> {
-    let unit_list_request = build_list_units_request();
+pub fn send_list_units_request(rpc_client: RpcClient) -> Result<tsm_unitman_rpc::ListUnitsResponse, CommandError> {
+    let unit_list_request = tsm_unitman_rpc::ListUnitsRequest::new();
 
-    let response = match rpc_client.send(unit_list_request) {
-        Ok(response) => response,
-        Err(error) =>  return Err(format!("{}", error)),
-    };
-
-    if !response.status {
-        return Err(format!("{}", response.error));
-    }
-
-    return match tsm_unitman_rpc::ListUnitsResponse::parse_from_bytes(&response.data) {
-        Ok(list_units_response) => Ok(list_units_response),
-        Err(error) => Err(format!("Failed to parse response: {}", error)),
-    };
+    return rpc_client.call_idempotent(tsm_unitman_rpc::RpcMethod::ListUnits.value(), &unit_list_request).map_err(CommandError::from);
 }
 
 
-fn build_list_units_request() -> tsm_common_rpc::RpcRequest {
-    let mut request = tsm_common_rpc::RpcRequest::new();
-    request.method = tsm_unitman_rpc::RpcMethod::ListUnits.value();
+/// Units in the order of the unit manager, narrowed down by state and a name pattern if given
+pub fn print_units(units: Vec<tsm_unitman_rpc::Unit>, options: &OutputOptions, state: &str, name: &str) -> Result<(), String> {
+    let mut records = Records::new(vec![
+        Column::new("name", "NAME", true),
+        Column::new("enabled", "IS ENABLED", true),
+        Column::new("restart_policy", "RESTART POLICY", true),
+        Column::new("state", "STATE", true),
+        Column::new("process_probe", "PROCESS", false),
+        Column::new("liveness", "LIVENESS", true),
+        Column::new("pid", "PID", false),
+        Column::new("uptime", "UPTIME", true),
+        Column::new("restart_count", "RESTARTS", false),
+        Column::new("uid", "UID", false),
+        Column::new("gid", "GID", false),
+        Column::new("executable", "EXECUTABLE", false),
+        Column::new("arguments", "ARGUMENTS", false),
+        Column::new("command", "COMMAND", true),
+    ]);
+
+    let state = state.to_lowercase();
 
-    request
-}
+    for unit in units {
+        let (unit_state_text, unit_state) = format_unit_state(unit.state.value());
 
+        if !state.is_empty() && unit_state != state {
+            continue;
+        }
 
-pub fn print_units(units: Vec<tsm_unitman_rpc::Unit>) {
-    let mut builder = Builder::new();
-    builder.set_header(vec!["NAME", "IS ENABLED", "RESTART POLICY", "STATE", "LIVENESS", "UPTIME", "COMMAND"]);
+        if !name.is_empty() && !matches_pattern(name, &unit.name) {
+            continue;
+        }
 
-    for unit in units {
-        let enabled = match unit.enabled {
-            true => String::from("Enabled"),
-            false => String::from("Disabled"),
+        let (enabled_text, enabled) = match (unit.masked, unit.enabled) {
+            (true, _) => ("Masked", "masked"),
+            (false, true) => ("Enabled", "enabled"),
+            (false, false) => ("Disabled", "disabled"),
         };
 
-        let restart_policy = match tsm_unitman_rpc::unit::RestartPolicy::from_i32(unit.restart_policy.value()) {
+        let (restart_policy_text, restart_policy) = match tsm_unitman_rpc::unit::RestartPolicy::from_i32(unit.restart_policy.value()) {
             Some(policy) => match policy {
-                tsm_unitman_rpc::unit::RestartPolicy::Always => String::from("Always"),
-                tsm_unitman_rpc::unit::RestartPolicy::Never => String::from("Never"),
-                tsm_unitman_rpc::unit::RestartPolicy::DisabledTemporarily => String::from("Disabled*"),
-            },
-            None => String::from("Unknown"),
-        };
-
-        let process_probe_state = match tsm_unitman_rpc::unit::ProbeState::from_i32(unit.process_probe_state.value()) {
-            Some(state) => match state {
-                tsm_unitman_rpc::unit::ProbeState::Undefined => String::from("Undefined"),
-                tsm_unitman_rpc::unit::ProbeState::Alive => String::from("Alive"),
-                tsm_unitman_rpc::unit::ProbeState::Dead => String::from("Dead"),
+                tsm_unitman_rpc::unit::RestartPolicy::Always => ("Always", "always"),
+                tsm_unitman_rpc::unit::RestartPolicy::Never => ("Never", "never"),
+                tsm_unitman_rpc::unit::RestartPolicy::DisabledTemporarily => ("Disabled*", "disabled-temporarily"),
             },
-            None => String::from("Unknown"),
+            None => ("Unknown", "unknown"),
         };
 
-        let liveness_probe_state = match tsm_unitman_rpc::unit::ProbeState::from_i32(unit.liveness_probe_state.value()) {
-            Some(state) => match state {
-                tsm_unitman_rpc::unit::ProbeState::Undefined => String::from("Undefined"),
-                tsm_unitman_rpc::unit::ProbeState::Alive => String::from("Alive"),
-                tsm_unitman_rpc::unit::ProbeState::Dead => String::from("Dead"),
-            },
-            None => String::from("Unknown"),
-        };
+        let (process_probe_text, process_probe) = format_probe_state(unit.process_probe_state.value());
+        let (liveness_text, liveness) = format_probe_state(unit.liveness_probe_state.value());
 
         let command = format!("{} {}", unit.executable, unit.arguments.join(" "));
 
         // TODO: Consider that unit state should not based solely on pid. We need these states: Stopped, Running, Stuck
-        let unit_state: String = match tsm_unitman_rpc::unit::UnitState::from_i32(unit.state.value()) {
-            Some(state) => match state {
-                tsm_unitman_rpc::unit::UnitState::Starting => String::from("Starting"),
-                tsm_unitman_rpc::unit::UnitState::Running => String::from("Running"),
-                tsm_unitman_rpc::unit::UnitState::RunningAndHealthy => String::from("Running (Healthy)"),
-                tsm_unitman_rpc::unit::UnitState::RunningButDegraded => String::from("Running (Degraded)"),
-                tsm_unitman_rpc::unit::UnitState::Stopping => String::from("Stopping"),
-                tsm_unitman_rpc::unit::UnitState::Stopped => String::from("Stopped"),
-            },
-            None => String::from("Unknown"),
-        };
-
-        let uptime = if unit.pid > 0 { // if running
+        let (pid, uptime) = if unit.pid > 0 { // if running
             let duration = Duration::from_secs(unit.uptime);
-            format_duration(duration).to_string()
+            (Cell::new(unit.pid.to_string(), json!(unit.pid)), Cell::new(format_duration(duration).to_string(), json!(unit.uptime)))
         } else {
-            String::from("-")
+            (Cell::new(String::from("-"), Value::Null), Cell::new(String::from("-"), Value::Null))
         };
 
-        builder.push_record([unit.name, enabled, restart_policy, unit_state, liveness_probe_state, uptime, command]);
+        records.push(vec![
+            Cell::from_text(unit.name),
+            Cell::new(String::from(enabled_text), json!(enabled)),
+            Cell::new(String::from(restart_policy_text), json!(restart_policy)),
+            Cell::new(String::from(unit_state_text), json!(unit_state)),
+            Cell::new(String::from(process_probe_text), json!(process_probe)),
+            Cell::new(String::from(liveness_text), json!(liveness)),
+            pid,
+            uptime,
+            Cell::new(unit.restart_count.to_string(), json!(unit.restart_count)),
+            Cell::new(unit.uid.to_string(), json!(unit.uid)),
+            Cell::new(unit.gid.to_string(), json!(unit.gid)),
+            Cell::from_text(unit.executable),
+            Cell::new(unit.arguments.join(" "), json!(unit.arguments)),
+            Cell::from_text(command.trim_end().to_string()),
+        ]);
     }
 
-    let mut table = builder.build();
-    table.with(Style::empty()); // Most compact: empty. Pretty but still compact: sharp
+    records.print(options)
+}
+
 
-    let table = table.to_string();
-    println!("{}", table);
-}
\ No newline at end of file
+/// Humanized for the table and the key accepted by --state and --wait
+fn format_unit_state(state: i32) -> (&'static str, &'static str) {
+    return match tsm_unitman_rpc::unit::UnitState::from_i32(state) {
+        Some(state) => match state {
+            tsm_unitman_rpc::unit::UnitState::Starting => ("Starting", "starting"),
+            tsm_unitman_rpc::unit::UnitState::Running => ("Running", "running"),
+            tsm_unitman_rpc::unit::UnitState::RunningAndHealthy => ("Running (Healthy)", "running-healthy"),
+            tsm_unitman_rpc::unit::UnitState::RunningButDegraded => ("Running (Degraded)", "running-degraded"),
+            tsm_unitman_rpc::unit::UnitState::Stopping => ("Stopping", "stopping"),
+            tsm_unitman_rpc::unit::UnitState::Stopped => ("Stopped", "stopped"),
+            tsm_unitman_rpc::unit::UnitState::Failed => ("Failed", "failed"),
+        },
+        None => ("Unknown", "unknown"),
+    };
+}
+
+
+fn format_probe_state(state: i32) -> (&'static str, &'static str) {
+    return match tsm_unitman_rpc::unit::ProbeState::from_i32(state) {
+        Some(state) => match state {
+            tsm_unitman_rpc::unit::ProbeState::Undefined => ("Undefined", "undefined"),
+            tsm_unitman_rpc::unit::ProbeState::Alive => ("Alive", "alive"),
+            tsm_unitman_rpc::unit::ProbeState::Dead => ("Dead", "dead"),
+        },
+        None => ("Unknown", "unknown"),
+    };
+}
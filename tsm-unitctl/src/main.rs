@@ -1,63 +1,265 @@
 mod ping;
 mod list_units;
+mod list_timers;
+mod list_paths;
 mod start_unit;
 mod stop_unit;
+mod list_targets;
+mod start_target;
+mod stop_target;
+mod isolate_target;
+mod enable_unit;
+mod disable_unit;
+mod mask_unit;
+mod unmask_unit;
+mod reexec;
+mod restart_unit;
+mod reload_unit;
+mod kill_unit;
+mod reset_failed;
+mod unit_status;
+mod watch_events;
+mod wait_unit;
+mod output;
+mod config;
+mod command_error;
+mod capabilities;
 
-use argparse::{ArgumentParser, Store, StoreTrue};
+use std::process::exit;
+use std::env;
+use argparse::{ArgumentParser, Store, StoreTrue, IncrBy, DecrBy};
 use tsm_ipc::RpcClient;
+use output::OutputOptions;
+use command_error::CommandError;
 
 
-const BIND_ADDRESS: &str = "ipc:///tmp/tsm-unitman.sock";
+const LOG_LEVELS: [&str; 6] = ["off", "error", "warn", "info", "debug", "trace"];
 
 
-// Interesting resource: https://github.com/erickt/rust-zmq/tree/master/examples/zguide
-fn main() {
-    // init logger
-    let env = env_logger::Env::default().filter_or(env_logger::DEFAULT_FILTER_ENV, "debug");
+/// The configured log level shifted by -v and -q, RUST_LOG takes precedence
+fn init_logger(log_level: &str, verbosity: i32) {
+    let index = LOG_LEVELS.iter().position(|level| *level == log_level.to_lowercase()).unwrap_or(2) as i32;
+    let index = (index + verbosity).clamp(0, LOG_LEVELS.len() as i32 - 1) as usize;
+
+    let env = env_logger::Env::default().filter_or(env_logger::DEFAULT_FILTER_ENV, LOG_LEVELS[index]);
     env_logger::init_from_env(env);
+}
+
+
+/// The flag if given, then the environment variable, then the configuration file
+fn resolve(flag: String, variable: &str, configured: String) -> String {
+    if !flag.is_empty() {
+        return flag;
+    }
+
+    return match env::var(variable) {
+        Ok(value) if !value.is_empty() => value,
+        _ => configured,
+    };
+}
 
+
+// Interesting resource: https://github.com/erickt/rust-zmq/tree/master/examples/zguide
+fn main() {
     // parse command line arguments
+    let mut config_file = String::new();
+    let mut address = String::new();
+    let mut events_address = String::new();
+    let mut rpc_timeout_ms: u32 = 0;
+    let mut verbosity: i32 = 0;
     let mut ping = false;
+    let mut show_capabilities = false;
     let mut list_units = false;
+    let mut list_timers = false;
+    let mut list_paths = false;
+    let mut output = String::from("table");
+    let mut no_header = false;
+    let mut columns = String::new();
+    let mut sort = String::new();
+    let mut name = String::new();
+    let mut watch = false;
+    let mut wait_unit = String::new();
+    let mut state = String::new();
+    let mut wait_timeout_s: u32 = 30;
+    let mut unit_status = String::new();
+    let mut lines: u32 = 10;
     let mut stop_unit = String::new();
     let mut start_unit = String::new();
+    let mut restart_unit = String::new();
+    let mut reload_unit = String::new();
+    let mut kill_unit = String::new();
+    let mut signal = String::from("SIGTERM");
+    let mut group = false;
+    let mut reset_failed = String::new();
+    let mut list_targets = false;
+    let mut start_target = String::new();
+    let mut stop_target = String::new();
+    let mut isolate_target = String::new();
+    let mut enable_unit = String::new();
+    let mut disable_unit = String::new();
+    let mut mask_unit = String::new();
+    let mut unmask_unit = String::new();
+    let mut now = false;
+    let mut reexec = false;
+    let mut executable = String::new();
 
     {
         let mut ap = ArgumentParser::new();
-        ap.set_description("Taschenmesser Unit Control");
+        ap.set_description("Taschenmesser Unit Control. Exit codes: 0 success, 1 error, 2 timed out, 3 failed, 4 not found, 5 permission denied, 6 busy, 7 invalid request, 8 incompatible unit manager");
+        ap.refer(&mut config_file).add_option(&["-c", "--config"], Store, "Configuration file. Defaults to /etc/tsm-unitctl.toml if it exists");
+        ap.refer(&mut address).add_option(&["--address"], Store, "RPC address of the unit manager, e.g. tcp://192.168.7.2:5555. Defaults to TSM_UNITMAN_ADDRESS or ipc:///tmp/tsm-unitman.sock");
+        ap.refer(&mut events_address).add_option(&["--events-address"], Store, "With --watch the event address of the unit manager. Defaults to TSM_UNITMAN_EVENTS_ADDRESS or ipc:///tmp/tsm-unitman-events.sock");
+        ap.refer(&mut rpc_timeout_ms).add_option(&["--rpc-timeout"], Store, "Milliseconds to wait for the unit manager to respond. Defaults to 5000");
+        ap.refer(&mut verbosity)
+            .add_option(&["-v", "--verbose"], IncrBy(1), "Log more, can be repeated")
+            .add_option(&["-q", "--quiet"], DecrBy(1), "Log less, can be repeated");
         ap.refer(&mut ping).add_option(&["--ping"], StoreTrue, "Test connection to unit manager");
+        ap.refer(&mut show_capabilities).add_option(&["--capabilities"], StoreTrue, "Show the version, protocol version and methods of the unit manager");
         ap.refer(&mut list_units).add_option(&["--list"], StoreTrue, "List all configured units");
+        ap.refer(&mut list_timers).add_option(&["--list-timers"], StoreTrue, "List units with timers and their next and last trigger times");
+        ap.refer(&mut list_paths).add_option(&["--list-paths"], StoreTrue, "List units with path triggers and their trigger state");
+        ap.refer(&mut output).add_option(&["--output"], Store, "With --list the output format: table, json, yaml or csv. Defaults to table");
+        ap.refer(&mut no_header).add_option(&["--no-header"], StoreTrue, "With --list omit the header of the table and csv formats");
+        ap.refer(&mut columns).add_option(&["--columns"], Store, "With --list a comma separated list of columns to show, e.g. name,state,pid,uptime");
+        ap.refer(&mut sort).add_option(&["--sort"], Store, "With --list sort by this column");
+        ap.refer(&mut name).add_option(&["--name"], Store, "With --list only show units whose name matches this pattern, * and ? are wildcards");
+        ap.refer(&mut watch).add_option(&["--watch"], StoreTrue, "Follow the events of the unit manager live: units starting, exiting and failing, probe changes and configuration reloads");
+        ap.refer(&mut wait_unit).add_option(&["--wait"], Store, "Wait until a unit reaches a state. Exits with 0 once reached, 2 on timeout and 3 if the unit stops or fails before");
+        ap.refer(&mut state).add_option(&["--state"], Store, "With --wait the state: starting, running, running-healthy, running-degraded, stopping, stopped, failed, or the liveness probe states alive and dead. Defaults to running. With --list only show units in this unit state");
+        ap.refer(&mut wait_timeout_s).add_option(&["--timeout"], Store, "With --wait the timeout in seconds. Defaults to 30");
+        ap.refer(&mut unit_status).add_option(&["--status"], Store, "Show the detailed status of a unit with its recent state transitions, probe results and output");
+        ap.refer(&mut lines).add_option(&["--lines"], Store, "With --status the number of output lines to show. Defaults to 10");
         ap.refer(&mut stop_unit).add_option(&["--stop"], Store, "Stop a unit");
         ap.refer(&mut start_unit).add_option(&["--start"], Store, "Start a unit");
+        ap.refer(&mut restart_unit).add_option(&["--restart"], Store, "Restart a unit");
+        ap.refer(&mut reload_unit).add_option(&["--reload"], Store, "Reload a unit by its reload command or reload signal");
+        ap.refer(&mut kill_unit).add_option(&["--kill"], Store, "Send a signal to a unit");
+        ap.refer(&mut signal).add_option(&["--signal"], Store, "With --kill the signal to send, by name or number. Defaults to SIGTERM");
+        ap.refer(&mut group).add_option(&["--group"], StoreTrue, "With --kill signal all processes of the unit instead of its main process");
+        ap.refer(&mut reset_failed).add_option(&["--reset-failed"], Store, "Reset the failed state and the restart counter of a unit");
+        ap.refer(&mut list_targets).add_option(&["--list-targets"], StoreTrue, "List all targets and the units they pull in");
+        ap.refer(&mut start_target).add_option(&["--start-target"], Store, "Start all units of a target");
+        ap.refer(&mut stop_target).add_option(&["--stop-target"], Store, "Stop all units of a target");
+        ap.refer(&mut isolate_target).add_option(&["--isolate"], Store, "Start a target and stop all units not part of it");
+        ap.refer(&mut enable_unit).add_option(&["--enable"], Store, "Enable a unit, persists across restarts");
+        ap.refer(&mut disable_unit).add_option(&["--disable"], Store, "Disable a unit, persists across restarts");
+        ap.refer(&mut mask_unit).add_option(&["--mask"], Store, "Mask a unit so that it cannot be started at all, persists across restarts");
+        ap.refer(&mut unmask_unit).add_option(&["--unmask"], Store, "Unmask a unit");
+        ap.refer(&mut now).add_option(&["--now"], StoreTrue, "With --enable also start the unit, with --disable and --mask also stop it");
+        ap.refer(&mut reexec).add_option(&["--re-exec"], StoreTrue, "Let the unit manager re-execute itself, its units keep running");
+        ap.refer(&mut executable).add_option(&["--executable"], Store, "With --re-exec execute this binary instead of the running one");
         ap.parse_args_or_exit();
     }
 
-    let rpc_client = match RpcClient::new(String::from(BIND_ADDRESS)) {
+    let configuration = match config::Configuration::load(config_file) {
+        Ok(configuration) => configuration,
+        Err(error) => {
+            eprintln!("{}", error);
+            exit(1);
+        },
+    };
+
+    init_logger(&configuration.get_log_level(), verbosity);
+
+    let address = resolve(address, "TSM_UNITMAN_ADDRESS", configuration.get_address());
+    let events_address = resolve(events_address, "TSM_UNITMAN_EVENTS_ADDRESS", configuration.get_events_address());
+    let rpc_timeout_ms = match rpc_timeout_ms {
+        0 => configuration.get_timeout_ms(),
+        timeout_ms => timeout_ms,
+    };
+
+    if watch {
+        if let Err(error) = watch_events::watch_events(events_address).map_err(CommandError::Events) {
+            eprintln!("{}", error);
+            exit(error.exit_code());
+        }
+        return;
+    }
+
+    let mut rpc_client = match RpcClient::new(address) {
         Ok(rpc) => rpc,
-        Err(error) => panic!("Failed to create RPC client: {}", error),
+        Err(error) => {
+            eprintln!("Failed to create RPC client: {}", error);
+            exit(CommandError::Rpc(error).exit_code());
+        },
     };
 
-    if ping {
-        match ping::send_ping_request(rpc_client, String::from("ping")) {
-            Ok(response) => println!("{}", response.message),
-            Err(error) => println!("{}", error),
-        };
+    rpc_client.set_service(String::from(capabilities::SERVICE_NAME));
+
+    if let Some((server_public_key, public_key, secret_key)) = configuration.get_curve_keys() {
+        if let Err(error) = rpc_client.set_curve(server_public_key, public_key, secret_key) {
+            eprintln!("{}", error);
+            exit(CommandError::Rpc(error).exit_code());
+        }
+    }
+
+    // the unit manager only answers a wait request once the state is reached or the wait timed out
+    let rpc_timeout_ms = match wait_unit.is_empty() {
+        true => rpc_timeout_ms,
+        false => rpc_timeout_ms.saturating_add(wait_timeout_s.saturating_mul(1000)),
+    };
+
+    if let Err(error) = rpc_client.set_timeout(rpc_timeout_ms.min(i32::MAX as u32) as i32) {
+        eprintln!("{}", error);
+        exit(CommandError::Rpc(error).exit_code());
+    }
+
+    let result = if ping {
+        ping::send_ping_request(rpc_client, String::from("ping")).map(|response| println!("{}", response.message))
+    } else if show_capabilities {
+        capabilities::send_capabilities_request(rpc_client).map(capabilities::print_capabilities)
     } else if list_units {
-        match list_units::send_list_units_request(rpc_client) {
-            Ok(list_units_response) => list_units::print_units(list_units_response.units),
-            Err(error) => println!("{}", error),
+        OutputOptions::new(&output, no_header, &columns, &sort).map_err(CommandError::Invalid)
+            .and_then(|options| list_units::send_list_units_request(rpc_client)
+                .and_then(|response| list_units::print_units(response.units, &options, &state, &name).map_err(CommandError::Invalid)))
+    } else if list_timers {
+        list_units::send_list_units_request(rpc_client).map(|response| list_timers::print_timers(response.units))
+    } else if list_paths {
+        list_units::send_list_units_request(rpc_client).map(|response| list_paths::print_paths(response.units))
+    } else if !wait_unit.is_empty() {
+        let wait_state = match state.is_empty() {
+            true => String::from("running"),
+            false => state,
         };
+        exit(wait_unit::wait_unit(rpc_client, wait_unit, wait_state, wait_timeout_s));
+    } else if !unit_status.is_empty() {
+        unit_status::send_unit_status_request(rpc_client, unit_status, lines).map(unit_status::print_unit_status)
     } else if !stop_unit.is_empty() {
-        match stop_unit::send_stop_unit_request(rpc_client, stop_unit) {
-            Ok(response) => println!("{}", response.message),
-            Err(error) => println!("{}", error),
-        };
+        stop_unit::send_stop_unit_request(rpc_client, stop_unit).map(|response| println!("{}", response.message))
     } else if !start_unit.is_empty() {
-        match start_unit::send_start_unit_request(rpc_client, start_unit) {
-            Ok(response) => println!("{}", response.message),
-            Err(error) => println!("{}", error),
-        };
+        start_unit::send_start_unit_request(rpc_client, start_unit).map(|response| println!("{}", response.message))
+    } else if !restart_unit.is_empty() {
+        restart_unit::send_restart_unit_request(rpc_client, restart_unit).map(|response| println!("{}", response.message))
+    } else if !reload_unit.is_empty() {
+        reload_unit::send_reload_unit_request(rpc_client, reload_unit).map(|response| println!("{}", response.message))
+    } else if !kill_unit.is_empty() {
+        kill_unit::send_kill_unit_request(rpc_client, kill_unit, signal, group).map(|response| println!("{}", response.message))
+    } else if !reset_failed.is_empty() {
+        reset_failed::send_reset_failed_request(rpc_client, reset_failed).map(|response| println!("{}", response.message))
+    } else if list_targets {
+        list_targets::send_list_targets_request(rpc_client).map(list_targets::print_targets)
+    } else if !start_target.is_empty() {
+        start_target::send_start_target_request(rpc_client, start_target).map(|response| println!("{}", response.message))
+    } else if !stop_target.is_empty() {
+        stop_target::send_stop_target_request(rpc_client, stop_target).map(|response| println!("{}", response.message))
+    } else if !isolate_target.is_empty() {
+        isolate_target::send_isolate_target_request(rpc_client, isolate_target).map(|response| println!("{}", response.message))
+    } else if !enable_unit.is_empty() {
+        enable_unit::send_enable_unit_request(rpc_client, enable_unit, now).map(|response| println!("{}", response.message))
+    } else if !disable_unit.is_empty() {
+        disable_unit::send_disable_unit_request(rpc_client, disable_unit, now).map(|response| println!("{}", response.message))
+    } else if !mask_unit.is_empty() {
+        mask_unit::send_mask_unit_request(rpc_client, mask_unit, now).map(|response| println!("{}", response.message))
+    } else if !unmask_unit.is_empty() {
+        unmask_unit::send_unmask_unit_request(rpc_client, unmask_unit).map(|response| println!("{}", response.message))
+    } else if reexec {
+        reexec::send_reexec_request(rpc_client, executable).map(|response| println!("{}", response.message))
     } else {
-        println!("No command specified. Use --help for more information.");
+        Err(CommandError::Invalid(String::from("No command specified. Use --help for more information.")))
+    };
+
+    if let Err(error) = result {
+        eprintln!("{}", error);
+        exit(error.exit_code());
     }
 }
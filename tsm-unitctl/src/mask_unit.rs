@@ -0,0 +1,20 @@
+use tsm_ipc::{RpcClient, tsm_unitman_rpc};
+use protobuf::Enum;
+
+use crate::command_error::CommandError;
+
+
+pub fn send_mask_unit_request(rpc_client: RpcClient, unit_name: String, now: bool) -> Result<tsm_unitman_rpc::MaskUnitResponse, CommandError> {
+    let mask_unit_request = build_mask_unit_request(unit_name, now);
+
+    return rpc_client.call(tsm_unitman_rpc::RpcMethod::MaskUnit.value(), &mask_unit_request).map_err(CommandError::from);
+}
+
+
+fn build_mask_unit_request(unit_name: String, now: bool) -> tsm_unitman_rpc::MaskUnitRequest {
+    let mut mask_unit_request = tsm_unitman_rpc::MaskUnitRequest::new();
+    mask_unit_request.unit_name = unit_name;
+    mask_unit_request.now = now;
+
+    mask_unit_request
+}
\ No newline at end of file
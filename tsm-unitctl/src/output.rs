@@ -0,0 +1,333 @@
+use std::cmp::Ordering;
+use std::io;
+use std::str::FromStr;
+use serde_json::{Map, Value};
+use tabled::{builder::Builder, settings::Style};
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum OutputFormat {
+    Table,
+    Json,
+    Yaml,
+    Csv,
+}
+
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(format: &str) -> Result<Self, Self::Err> {
+        match format.to_lowercase().as_str() {
+            "table" => Ok(OutputFormat::Table),
+            "json" => Ok(OutputFormat::Json),
+            "yaml" => Ok(OutputFormat::Yaml),
+            "csv" => Ok(OutputFormat::Csv),
+            _ => Err(format!("Invalid output format: {}", format)),
+        }
+    }
+}
+
+
+/// How lists are printed, from the --output, --no-header, --columns and --sort flags
+#[derive(Debug, Clone)]
+pub struct OutputOptions {
+    format: OutputFormat,
+    no_header: bool,
+    columns: Vec<String>,
+    sort: Option<String>,
+}
+
+
+impl OutputOptions {
+    /// columns: comma separated column keys, empty for the default columns
+    /// sort: column key, empty to keep the order of the unit manager
+    pub fn new(format: &str, no_header: bool, columns: &str, sort: &str) -> Result<OutputOptions, String> {
+        let columns = columns.split(',')
+            .map(|column| column.trim().to_lowercase())
+            .filter(|column| !column.is_empty())
+            .collect();
+
+        let sort = match sort.is_empty() {
+            true => None,
+            false => Some(sort.to_lowercase()),
+        };
+
+        Ok(OutputOptions {
+            format: OutputFormat::from_str(format)?,
+            no_header,
+            columns,
+            sort,
+        })
+    }
+}
+
+
+pub struct Column {
+    key: &'static str,
+    header: &'static str,
+    in_table: bool,
+}
+
+
+impl Column {
+    /// key: name in JSON, YAML and for --columns and --sort
+    /// in_table: shown by the table and CSV formats without --columns, JSON and YAML show all columns
+    pub fn new(key: &'static str, header: &'static str, in_table: bool) -> Column {
+        Column {
+            key,
+            header,
+            in_table,
+        }
+    }
+}
+
+
+/// A field as humans read it in the table, and as machines read it in the other formats
+pub struct Cell {
+    text: String,
+    value: Value,
+}
+
+
+impl Cell {
+    pub fn new(text: String, value: Value) -> Cell {
+        Cell {
+            text,
+            value,
+        }
+    }
+
+    /// A field which reads the same for humans and machines
+    pub fn from_text(text: String) -> Cell {
+        Cell {
+            value: Value::String(text.clone()),
+            text,
+        }
+    }
+}
+
+
+pub struct Records {
+    columns: Vec<Column>,
+    rows: Vec<Vec<Cell>>,
+}
+
+
+impl Records {
+    pub fn new(columns: Vec<Column>) -> Records {
+        Records {
+            columns,
+            rows: Vec::new(),
+        }
+    }
+
+    /// One cell per column in the same order
+    pub fn push(&mut self, row: Vec<Cell>) {
+        self.rows.push(row);
+    }
+
+    pub fn print(mut self, options: &OutputOptions) -> Result<(), String> {
+        if let Some(ref sort) = options.sort {
+            let index = self.find_column(sort)?;
+            self.rows.sort_by(|a, b| compare_values(&a[index].value, &b[index].value));
+        }
+
+        let indices = self.select_columns(options)?;
+
+        return match options.format {
+            OutputFormat::Table => Ok(self.print_table(&indices, options.no_header)),
+            OutputFormat::Json => {
+                match serde_json::to_string_pretty(&self.to_maps(&indices)) {
+                    Ok(json) => Ok(println!("{}", json)),
+                    Err(error) => Err(format!("Failed to serialize to JSON: {}", error)),
+                }
+            },
+            OutputFormat::Yaml => {
+                match serde_yaml::to_string(&self.to_maps(&indices)) {
+                    Ok(yaml) => Ok(print!("{}", yaml)),
+                    Err(error) => Err(format!("Failed to serialize to YAML: {}", error)),
+                }
+            },
+            OutputFormat::Csv => self.print_csv(&indices, options.no_header),
+        };
+    }
+
+    fn find_column(&self, key: &str) -> Result<usize, String> {
+        return match self.columns.iter().position(|column| column.key == key) {
+            Some(index) => Ok(index),
+            None => {
+                let keys: Vec<&str> = self.columns.iter().map(|column| column.key).collect();
+                Err(format!("Invalid column: {}. Possible values: {}", key, keys.join(", ")))
+            },
+        };
+    }
+
+    fn select_columns(&self, options: &OutputOptions) -> Result<Vec<usize>, String> {
+        if !options.columns.is_empty() {
+            return options.columns.iter().map(|key| self.find_column(key)).collect();
+        }
+
+        let all_columns = options.format == OutputFormat::Json || options.format == OutputFormat::Yaml;
+
+        return Ok(self.columns.iter()
+            .enumerate()
+            .filter(|(_, column)| all_columns || column.in_table)
+            .map(|(index, _)| index)
+            .collect());
+    }
+
+    fn print_table(&self, indices: &Vec<usize>, no_header: bool) {
+        let mut builder = Builder::new();
+
+        if !no_header {
+            builder.set_header(indices.iter().map(|index| self.columns[*index].header));
+        }
+
+        for row in &self.rows {
+            builder.push_record(indices.iter().map(|index| row[*index].text.clone()));
+        }
+
+        let mut table = builder.build();
+        table.with(Style::empty()); // Most compact: empty. Pretty but still compact: sharp
+
+        let table = table.to_string();
+        println!("{}", table);
+    }
+
+    fn print_csv(&self, indices: &Vec<usize>, no_header: bool) -> Result<(), String> {
+        let mut writer = csv::Writer::from_writer(io::stdout());
+
+        if !no_header {
+            let header: Vec<&str> = indices.iter().map(|index| self.columns[*index].key).collect();
+
+            if let Err(error) = writer.write_record(&header) {
+                return Err(format!("Failed to write CSV: {}", error));
+            }
+        }
+
+        for row in &self.rows {
+            let record: Vec<String> = indices.iter().map(|index| value_to_text(&row[*index].value)).collect();
+
+            if let Err(error) = writer.write_record(&record) {
+                return Err(format!("Failed to write CSV: {}", error));
+            }
+        }
+
+        return match writer.flush() {
+            Ok(_) => Ok(()),
+            Err(error) => Err(format!("Failed to write CSV: {}", error)),
+        };
+    }
+
+    fn to_maps(&self, indices: &Vec<usize>) -> Vec<Map<String, Value>> {
+        return self.rows.iter()
+            .map(|row| indices.iter()
+                .map(|index| (self.columns[*index].key.to_string(), row[*index].value.clone()))
+                .collect())
+            .collect();
+    }
+}
+
+
+/// Raw values as plain text, lists are separated by spaces
+fn value_to_text(value: &Value) -> String {
+    return match value {
+        Value::Null => String::new(),
+        Value::String(text) => text.clone(),
+        Value::Array(values) => values.iter().map(value_to_text).collect::<Vec<String>>().join(" "),
+        _ => value.to_string(),
+    };
+}
+
+
+/// Numbers compare numerically, missing values first
+fn compare_values(a: &Value, b: &Value) -> Ordering {
+    return match (a, b) {
+        (Value::Number(a), Value::Number(b)) => {
+            a.as_f64().unwrap_or(0.0).partial_cmp(&b.as_f64().unwrap_or(0.0)).unwrap_or(Ordering::Equal)
+        },
+        (Value::Null, Value::Null) => Ordering::Equal,
+        (Value::Null, _) => Ordering::Less,
+        (_, Value::Null) => Ordering::Greater,
+        _ => value_to_text(a).cmp(&value_to_text(b)),
+    };
+}
+
+
+/// Matches names against a pattern with * for any number of characters and ? for exactly one
+pub fn matches_pattern(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+
+    // position after the last * in pattern and the position in name it was matched against
+    let mut star: Option<(usize, usize)> = None;
+    let (mut p, mut n) = (0, 0);
+
+    while n < name.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == name[n]) {
+            p += 1;
+            n += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p + 1, n));
+            p += 1;
+        } else if let Some((star_p, star_n)) = star {
+            // let the last * swallow one more character
+            p = star_p;
+            n = star_n + 1;
+            star = Some((star_p, star_n + 1));
+        } else {
+            return false;
+        }
+    }
+
+    return pattern[p..].iter().all(|c| *c == '*');
+}
+
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use super::*;
+
+    #[test]
+    fn matches_pattern_should_work() {
+        assert!(matches_pattern("can*", "canbus"));
+        assert!(matches_pattern("can*", "can"));
+        assert!(matches_pattern("*bus", "canbus"));
+        assert!(matches_pattern("c?n*s", "canbus"));
+        assert!(matches_pattern("*", ""));
+        assert!(!matches_pattern("can*", "scan"));
+        assert!(!matches_pattern("can", "canbus"));
+        assert!(!matches_pattern("c?n", "cn"));
+    }
+
+    #[test]
+    fn select_columns_should_honor_format_and_selection() {
+        let mut records = Records::new(vec![
+            Column::new("name", "NAME", true),
+            Column::new("pid", "PID", false),
+        ]);
+        records.push(vec![Cell::from_text(String::from("foo")), Cell::new(String::from("12"), json!(12))]);
+
+        let table = OutputOptions::new("table", false, "", "").unwrap();
+        assert_eq!(records.select_columns(&table).unwrap(), vec![0]);
+
+        let json = OutputOptions::new("json", false, "", "").unwrap();
+        assert_eq!(records.select_columns(&json).unwrap(), vec![0, 1]);
+        assert_eq!(records.to_maps(&vec![0, 1])[0]["pid"], json!(12));
+
+        let selected = OutputOptions::new("csv", true, "PID, name", "").unwrap();
+        assert_eq!(records.select_columns(&selected).unwrap(), vec![1, 0]);
+
+        let invalid = OutputOptions::new("table", false, "foo", "").unwrap();
+        assert!(records.select_columns(&invalid).is_err());
+    }
+
+    #[test]
+    fn compare_values_should_sort_numbers_numerically() {
+        assert_eq!(compare_values(&json!(9), &json!(10)), Ordering::Less);
+        assert_eq!(compare_values(&Value::Null, &json!(1)), Ordering::Less);
+        assert_eq!(compare_values(&json!("b"), &json!("a")), Ordering::Greater);
+    }
+}
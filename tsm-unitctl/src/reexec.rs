@@ -0,0 +1,19 @@
+use tsm_ipc::{RpcClient, tsm_unitman_rpc};
+use protobuf::Enum;
+
+use crate::command_error::CommandError;
+
+
+pub fn send_reexec_request(rpc_client: RpcClient, executable: String) -> Result<tsm_unitman_rpc::ReExecResponse, CommandError> {
+    let reexec_request = build_reexec_request(executable);
+
+    return rpc_client.call(tsm_unitman_rpc::RpcMethod::ReExec.value(), &reexec_request).map_err(CommandError::from);
+}
+
+
+fn build_reexec_request(executable: String) -> tsm_unitman_rpc::ReExecRequest {
+    let mut reexec_request = tsm_unitman_rpc::ReExecRequest::new();
+    reexec_request.executable = executable;
+
+    reexec_request
+}
\ No newline at end of file
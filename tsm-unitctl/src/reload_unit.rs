@@ -0,0 +1,19 @@
+use tsm_ipc::{RpcClient, tsm_unitman_rpc};
+use protobuf::Enum;
+
+use crate::command_error::CommandError;
+
+
+pub fn send_reload_unit_request(rpc_client: RpcClient, unit_name: String) -> Result<tsm_unitman_rpc::ReloadUnitResponse, CommandError> {
+    let reload_unit_request = build_reload_unit_request(unit_name);
+
+    return rpc_client.call(tsm_unitman_rpc::RpcMethod::ReloadUnit.value(), &reload_unit_request).map_err(CommandError::from);
+}
+
+
+fn build_reload_unit_request(unit_name: String) -> tsm_unitman_rpc::ReloadUnitRequest {
+    let mut reload_unit_request = tsm_unitman_rpc::ReloadUnitRequest::new();
+    reload_unit_request.unit_name = unit_name;
+
+    reload_unit_request
+}
\ No newline at end of file
@@ -0,0 +1,19 @@
+use tsm_ipc::{RpcClient, tsm_unitman_rpc};
+use protobuf::Enum;
+
+use crate::command_error::CommandError;
+
+
+pub fn send_reset_failed_request(rpc_client: RpcClient, unit_name: String) -> Result<tsm_unitman_rpc::ResetFailedResponse, CommandError> {
+    let reset_failed_request = build_reset_failed_request(unit_name);
+
+    return rpc_client.call(tsm_unitman_rpc::RpcMethod::ResetFailed.value(), &reset_failed_request).map_err(CommandError::from);
+}
+
+
+fn build_reset_failed_request(unit_name: String) -> tsm_unitman_rpc::ResetFailedRequest {
+    let mut reset_failed_request = tsm_unitman_rpc::ResetFailedRequest::new();
+    reset_failed_request.unit_name = unit_name;
+
+    reset_failed_request
+}
\ No newline at end of file
@@ -0,0 +1,19 @@
+use tsm_ipc::{RpcClient, tsm_unitman_rpc};
+use protobuf::Enum;
+
+use crate::command_error::CommandError;
+
+
+pub fn send_restart_unit_request(rpc_client: RpcClient, unit_name: String) -> Result<tsm_unitman_rpc::RestartUnitResponse, CommandError> {
+    let restart_unit_request = build_restart_unit_request(unit_name);
+
+    return rpc_client.call(tsm_unitman_rpc::RpcMethod::RestartUnit.value(), &restart_unit_request).map_err(CommandError::from);
+}
+
+
+fn build_restart_unit_request(unit_name: String) -> tsm_unitman_rpc::RestartUnitRequest {
+    let mut restart_unit_request = tsm_unitman_rpc::RestartUnitRequest::new();
+    restart_unit_request.unit_name = unit_name;
+
+    restart_unit_request
+}
\ No newline at end of file
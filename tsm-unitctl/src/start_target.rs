@@ -0,0 +1,19 @@
+use tsm_ipc::{RpcClient, tsm_unitman_rpc};
+use protobuf::Enum;
+
+use crate::command_error::CommandError;
+
+
+pub fn send_start_target_request(rpc_client: RpcClient, target_name: String) -> Result<tsm_unitman_rpc::StartTargetResponse, CommandError> {
+    let start_target_request = build_start_target_request(target_name);
+
+    return rpc_client.call(tsm_unitman_rpc::RpcMethod::StartTarget.value(), &start_target_request).map_err(CommandError::from);
+}
+
+
+fn build_start_target_request(target_name: String) -> tsm_unitman_rpc::StartTargetRequest {
+    let mut start_target_request = tsm_unitman_rpc::StartTargetRequest::new();
+    start_target_request.target_name = target_name;
+
+    start_target_request
+}
\ No newline at end of file
@@ -1,33 +1,19 @@
-use tsm_ipc::{RpcClient, tsm_common_rpc, tsm_unitman_rpc};
-use protobuf::{Message, Enum};
+use tsm_ipc::{RpcClient, tsm_unitman_rpc};
+use protobuf::Enum;
 
+use crate::command_error::CommandError;
 
-pub fn send_start_unit_request(rpc_client: RpcClient, unit_name: String) -> Result<tsm_unitman_rpc::StartUnitResponse, String> {
-    let start_unit_request = build_start_unit_request(unit_name);
-
-    let response = match rpc_client.send(start_unit_request) {
-        Ok(response) => response,
-        Err(error) =>  return Err(format!("{}", error)),
-    };
 
-    if !response.status {
-        return Err(format!("{}", response.error));
-    }
+pub fn send_start_unit_request(rpc_client: RpcClient, unit_name: String) -> Result<tsm_unitman_rpc::StartUnitResponse, CommandError> {
+    let start_unit_request = build_start_unit_request(unit_name);
 
-    return match tsm_unitman_rpc::StartUnitResponse::parse_from_bytes(&response.data) {
-        Ok(start_unit_response) => Ok(start_unit_response),
-        Err(error) => Err(format!("Failed to parse response: {}", error)),
-    };
+    return rpc_client.call(tsm_unitman_rpc::RpcMethod::StartUnit.value(), &start_unit_request).map_err(CommandError::from);
 }
 
 
-fn build_start_unit_request(unit_name: String) -> tsm_common_rpc::RpcRequest {
+fn build_start_unit_request(unit_name: String) -> tsm_unitman_rpc::StartUnitRequest {
     let mut start_unit_request = tsm_unitman_rpc::StartUnitRequest::new();
     start_unit_request.unit_name = unit_name;
 
-    let mut request = tsm_common_rpc::RpcRequest::new();
-    request.method = tsm_unitman_rpc::RpcMethod::StartUnit.value();
-    request.data = start_unit_request.write_to_bytes().unwrap();
-
-    request
+    start_unit_request
 }
\ No newline at end of file
@@ -0,0 +1,19 @@
+use tsm_ipc::{RpcClient, tsm_unitman_rpc};
+use protobuf::Enum;
+
+use crate::command_error::CommandError;
+
+
+pub fn send_stop_target_request(rpc_client: RpcClient, target_name: String) -> Result<tsm_unitman_rpc::StopTargetResponse, CommandError> {
+    let stop_target_request = build_stop_target_request(target_name);
+
+    return rpc_client.call(tsm_unitman_rpc::RpcMethod::StopTarget.value(), &stop_target_request).map_err(CommandError::from);
+}
+
+
+fn build_stop_target_request(target_name: String) -> tsm_unitman_rpc::StopTargetRequest {
+    let mut stop_target_request = tsm_unitman_rpc::StopTargetRequest::new();
+    stop_target_request.target_name = target_name;
+
+    stop_target_request
+}
\ No newline at end of file
@@ -0,0 +1,194 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tsm_ipc::{RpcClient, tsm_unitman_rpc};
+use protobuf::{Enum, EnumOrUnknown};
+use humantime::{format_duration, format_rfc3339_millis, format_rfc3339_seconds};
+
+use crate::command_error::CommandError;
+
+
+pub fn send_unit_status_request(rpc_client: RpcClient, unit_name: String, lines: u32) -> Result<tsm_unitman_rpc::UnitStatus, CommandError> {
+    let unit_status_request = build_unit_status_request(unit_name, lines);
+
+    let unit_status_response: tsm_unitman_rpc::GetUnitStatusResponse = rpc_client.call_idempotent(tsm_unitman_rpc::RpcMethod::GetUnitStatus.value(), &unit_status_request)?;
+
+    return match unit_status_response.status.into_option() {
+        Some(status) => Ok(status),
+        None => Err(CommandError::Response(String::from("Response contains no unit status"))),
+    };
+}
+
+
+fn build_unit_status_request(unit_name: String, lines: u32) -> tsm_unitman_rpc::GetUnitStatusRequest {
+    let mut unit_status_request = tsm_unitman_rpc::GetUnitStatusRequest::new();
+    unit_status_request.unit_name = unit_name;
+    unit_status_request.lines = lines;
+
+    unit_status_request
+}
+
+
+/// Prints the status in the fashion of systemctl status
+pub fn print_unit_status(status: tsm_unitman_rpc::UnitStatus) {
+    let unit = status.unit.clone().unwrap_or_default();
+    let command = format!("{} {}", unit.executable, unit.arguments.join(" "));
+
+    println!("{} - {}", unit.name, command.trim_end());
+
+    let enabled = match (unit.masked, unit.enabled) {
+        (true, _) => "masked",
+        (false, true) => "enabled",
+        (false, false) => "disabled",
+    };
+    print_field("Loaded", format!("{}; restart policy: {}; restarts: {}", enabled, format_restart_policy(unit.restart_policy), unit.restart_count));
+
+    let active = match unit.pid > 0 {
+        true => {
+            let since = SystemTime::now() - Duration::from_secs(unit.uptime);
+            format!("{} since {}; {} ago", format_unit_state(unit.state), format_rfc3339_seconds(since), format_duration(Duration::from_secs(unit.uptime)))
+        },
+        false => format_unit_state(unit.state),
+    };
+    print_field("Active", active);
+
+    if unit.pid > 0 {
+        print_field("Main PID", unit.pid.to_string());
+    }
+
+    if status.has_exit_status {
+        let exit_status = match status.exit_signal.is_empty() {
+            true => format!("code {}", status.exit_code),
+            false => format!("signal {}", status.exit_signal),
+        };
+        print_field("Last exit", exit_status);
+    }
+
+    print_field("User", format_id(&status.user, unit.uid));
+    print_field("Group", format_id(&status.group, unit.gid));
+
+    if !status.dependencies.is_empty() {
+        print_field("Requires", status.dependencies.join(", "));
+    }
+
+    if !status.wanted_by.is_empty() {
+        print_field("Wanted by", status.wanted_by.join(", "));
+    }
+
+    if !status.reload_command.is_empty() {
+        print_field("Reload", status.reload_command.join(" "));
+    } else if !status.reload_signal.is_empty() {
+        print_field("Reload", status.reload_signal.clone());
+    }
+
+    if let Some(probe) = status.liveness_probe.clone().into_option() {
+        let probe_command = format!("{} {}", probe.executable, probe.arguments.join(" "));
+        print_field("Liveness", format!("{}; every {}s, timeout {}s: {}", format_probe_state(unit.liveness_probe_state), probe.interval_s, probe.timeout_s, probe_command.trim_end()));
+    }
+
+    if !status.sandbox.is_empty() {
+        print_field("Sandbox", status.sandbox.join(", "));
+    }
+
+    if let Some(timer) = unit.timer.into_option() {
+        print_field("Timer", timer.on_calendar);
+    }
+
+    for (index, process) in status.processes.iter().enumerate() {
+        let label = match index {
+            0 => "Processes",
+            _ => "",
+        };
+        let indent = "  ".repeat(process.depth as usize);
+        print_field(label, format!("{}{} {}", indent, process.pid, process.command));
+    }
+
+    if !status.transitions.is_empty() {
+        println!();
+        println!("State transitions:");
+
+        for transition in &status.transitions {
+            println!("  {} {} -> {}: {}", format_timestamp(transition.timestamp), format_unit_state(transition.from), format_unit_state(transition.to), transition.reason);
+        }
+    }
+
+    if !status.probe_results.is_empty() {
+        println!();
+        println!("Liveness probe results:");
+
+        for result in &status.probe_results {
+            let exit_code = match result.has_exit_code {
+                true => format!("exit code {}", result.exit_code),
+                false => String::from("no exit code"),
+            };
+            let message = match result.message.is_empty() {
+                true => String::new(),
+                false => format!(": {}", result.message),
+            };
+            println!("  {} {} after {}ms, {}{}", format_timestamp(result.timestamp), format_probe_state(result.state), result.duration_ms, exit_code, message);
+        }
+    }
+
+    if !status.output.is_empty() {
+        println!();
+        println!("Output:");
+
+        for line in &status.output {
+            println!("  {}", line);
+        }
+    }
+}
+
+
+fn print_field(label: &str, value: String) {
+    match label.is_empty() {
+        true => println!("{:>12}  {}", "", value),
+        false => println!("{:>12}: {}", label, value),
+    }
+}
+
+
+fn format_id(name: &str, id: i32) -> String {
+    return match name.is_empty() {
+        true => id.to_string(),
+        false => format!("{} ({})", name, id),
+    };
+}
+
+
+fn format_timestamp(timestamp_ms: i64) -> String {
+    let timestamp = UNIX_EPOCH + Duration::from_millis(timestamp_ms.max(0) as u64);
+    format_rfc3339_millis(timestamp).to_string()
+}
+
+
+fn format_restart_policy(policy: EnumOrUnknown<tsm_unitman_rpc::unit::RestartPolicy>) -> String {
+    return match tsm_unitman_rpc::unit::RestartPolicy::from_i32(policy.value()) {
+        Some(tsm_unitman_rpc::unit::RestartPolicy::Always) => String::from("Always"),
+        Some(tsm_unitman_rpc::unit::RestartPolicy::Never) => String::from("Never"),
+        Some(tsm_unitman_rpc::unit::RestartPolicy::DisabledTemporarily) => String::from("Disabled*"),
+        None => String::from("Unknown"),
+    };
+}
+
+
+fn format_probe_state(state: EnumOrUnknown<tsm_unitman_rpc::unit::ProbeState>) -> String {
+    return match tsm_unitman_rpc::unit::ProbeState::from_i32(state.value()) {
+        Some(tsm_unitman_rpc::unit::ProbeState::Undefined) => String::from("Undefined"),
+        Some(tsm_unitman_rpc::unit::ProbeState::Alive) => String::from("Alive"),
+        Some(tsm_unitman_rpc::unit::ProbeState::Dead) => String::from("Dead"),
+        None => String::from("Unknown"),
+    };
+}
+
+
+fn format_unit_state(state: EnumOrUnknown<tsm_unitman_rpc::unit::UnitState>) -> String {
+    return match tsm_unitman_rpc::unit::UnitState::from_i32(state.value()) {
+        Some(tsm_unitman_rpc::unit::UnitState::Starting) => String::from("Starting"),
+        Some(tsm_unitman_rpc::unit::UnitState::Running) => String::from("Running"),
+        Some(tsm_unitman_rpc::unit::UnitState::RunningAndHealthy) => String::from("Running (Healthy)"),
+        Some(tsm_unitman_rpc::unit::UnitState::RunningButDegraded) => String::from("Running (Degraded)"),
+        Some(tsm_unitman_rpc::unit::UnitState::Stopping) => String::from("Stopping"),
+        Some(tsm_unitman_rpc::unit::UnitState::Stopped) => String::from("Stopped"),
+        Some(tsm_unitman_rpc::unit::UnitState::Failed) => String::from("Failed"),
+        None => String::from("Unknown"),
+    };
+}
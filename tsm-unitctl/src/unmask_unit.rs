@@ -0,0 +1,19 @@
+use tsm_ipc::{RpcClient, tsm_unitman_rpc};
+use protobuf::Enum;
+
+use crate::command_error::CommandError;
+
+
+pub fn send_unmask_unit_request(rpc_client: RpcClient, unit_name: String) -> Result<tsm_unitman_rpc::UnmaskUnitResponse, CommandError> {
+    let unmask_unit_request = build_unmask_unit_request(unit_name);
+
+    return rpc_client.call(tsm_unitman_rpc::RpcMethod::UnmaskUnit.value(), &unmask_unit_request).map_err(CommandError::from);
+}
+
+
+fn build_unmask_unit_request(unit_name: String) -> tsm_unitman_rpc::UnmaskUnitRequest {
+    let mut unmask_unit_request = tsm_unitman_rpc::UnmaskUnitRequest::new();
+    unmask_unit_request.unit_name = unit_name;
+
+    unmask_unit_request
+}
\ No newline at end of file
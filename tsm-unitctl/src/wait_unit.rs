@@ -0,0 +1,108 @@
+use std::time::{Duration, Instant};
+use tsm_ipc::{RpcClient, tsm_unitman_rpc};
+use protobuf::Enum;
+
+use crate::command_error::{CommandError, EXIT_SUCCESS, EXIT_ERROR, EXIT_TIMED_OUT, EXIT_FAILED};
+
+
+/// Sends the wait request and returns the exit code for its outcome. The unit manager limits how
+/// long a single request waits, so it is sent again until the timeout elapsed.
+pub fn wait_unit(rpc_client: RpcClient, unit_name: String, state: String, timeout_s: u32) -> i32 {
+    let deadline = Instant::now() + Duration::from_secs(timeout_s as u64);
+
+    let response = loop {
+        let remaining_ms = deadline.saturating_duration_since(Instant::now()).as_millis().min(u32::MAX as u128) as u32;
+
+        let request = match build_wait_unit_request(unit_name.clone(), &state, remaining_ms) {
+            Ok(request) => request,
+            Err(error) => {
+                eprintln!("{}", error);
+                return error.exit_code();
+            },
+        };
+
+        let response = match send_wait_unit_request(&rpc_client, request) {
+            Ok(response) => response,
+            Err(error) => {
+                eprintln!("{}", error);
+                return error.exit_code();
+            },
+        };
+
+        let timed_out = response.result.value() == tsm_unitman_rpc::wait_unit_response::Result::TimedOut.value();
+
+        if !timed_out || Instant::now() >= deadline {
+            break response;
+        }
+    };
+
+    return match tsm_unitman_rpc::wait_unit_response::Result::from_i32(response.result.value()) {
+        Some(tsm_unitman_rpc::wait_unit_response::Result::Reached) => {
+            println!("Unit {} reached state {}", unit_name, state);
+            EXIT_SUCCESS
+        },
+        Some(tsm_unitman_rpc::wait_unit_response::Result::TimedOut) => {
+            println!("Timed out waiting for unit {} to reach state {}, it is {}", unit_name, state, format_state(&response));
+            EXIT_TIMED_OUT
+        },
+        Some(tsm_unitman_rpc::wait_unit_response::Result::Failed) => {
+            println!("Unit {} is {} and will not reach state {}", unit_name, format_state(&response), state);
+            EXIT_FAILED
+        },
+        None => {
+            eprintln!("Unknown result waiting for unit {}", unit_name);
+            EXIT_ERROR
+        },
+    };
+}
+
+
+fn send_wait_unit_request(rpc_client: &RpcClient, request: tsm_unitman_rpc::WaitUnitRequest) -> Result<tsm_unitman_rpc::WaitUnitResponse, CommandError> {
+    return rpc_client.call(tsm_unitman_rpc::RpcMethod::WaitUnit.value(), &request).map_err(CommandError::from);
+}
+
+
+/// Unit states, or the liveness probe states alive and dead
+fn build_wait_unit_request(unit_name: String, state: &str, timeout_ms: u32) -> Result<tsm_unitman_rpc::WaitUnitRequest, CommandError> {
+    let mut wait_unit_request = tsm_unitman_rpc::WaitUnitRequest::new();
+    wait_unit_request.unit_name = unit_name;
+    wait_unit_request.timeout_ms = timeout_ms;
+
+    match state.to_lowercase().as_str() {
+        "starting" => wait_unit_request.set_state(tsm_unitman_rpc::unit::UnitState::Starting),
+        "running" => wait_unit_request.set_state(tsm_unitman_rpc::unit::UnitState::Running),
+        "running-healthy" => wait_unit_request.set_state(tsm_unitman_rpc::unit::UnitState::RunningAndHealthy),
+        "running-degraded" => wait_unit_request.set_state(tsm_unitman_rpc::unit::UnitState::RunningButDegraded),
+        "stopping" => wait_unit_request.set_state(tsm_unitman_rpc::unit::UnitState::Stopping),
+        "stopped" => wait_unit_request.set_state(tsm_unitman_rpc::unit::UnitState::Stopped),
+        "failed" => wait_unit_request.set_state(tsm_unitman_rpc::unit::UnitState::Failed),
+        "alive" => wait_unit_request.set_liveness_probe_state(tsm_unitman_rpc::unit::ProbeState::Alive),
+        "dead" => wait_unit_request.set_liveness_probe_state(tsm_unitman_rpc::unit::ProbeState::Dead),
+        _ => return Err(CommandError::Invalid(format!("Invalid state: {}", state))),
+    }
+
+    Ok(wait_unit_request)
+}
+
+
+fn format_state(response: &tsm_unitman_rpc::WaitUnitResponse) -> String {
+    let state = match tsm_unitman_rpc::unit::UnitState::from_i32(response.state.value()) {
+        Some(tsm_unitman_rpc::unit::UnitState::Starting) => "starting",
+        Some(tsm_unitman_rpc::unit::UnitState::Running) => "running",
+        Some(tsm_unitman_rpc::unit::UnitState::RunningAndHealthy) => "running-healthy",
+        Some(tsm_unitman_rpc::unit::UnitState::RunningButDegraded) => "running-degraded",
+        Some(tsm_unitman_rpc::unit::UnitState::Stopping) => "stopping",
+        Some(tsm_unitman_rpc::unit::UnitState::Stopped) => "stopped",
+        Some(tsm_unitman_rpc::unit::UnitState::Failed) => "failed",
+        None => "unknown",
+    };
+
+    let probe_state = match tsm_unitman_rpc::unit::ProbeState::from_i32(response.liveness_probe_state.value()) {
+        Some(tsm_unitman_rpc::unit::ProbeState::Undefined) => "undefined",
+        Some(tsm_unitman_rpc::unit::ProbeState::Alive) => "alive",
+        Some(tsm_unitman_rpc::unit::ProbeState::Dead) => "dead",
+        None => "unknown",
+    };
+
+    format!("{} with liveness probe {}", state, probe_state)
+}
@@ -0,0 +1,65 @@
+use std::time::{Duration, UNIX_EPOCH};
+use tsm_ipc::{EventSubscriber, tsm_unitman_event, tsm_unitman_rpc};
+use protobuf::{Enum, EnumOrUnknown};
+use humantime::format_rfc3339_millis;
+
+
+/// Prints the events of the unit manager as they happen, until interrupted
+pub fn watch_events(address: String) -> Result<(), String> {
+    let subscriber = EventSubscriber::new(address, Vec::new())?;
+
+    loop {
+        let (_topic, event): (String, tsm_unitman_event::Event) = subscriber.receive()?;
+        println!("{}", format_event(&event));
+    }
+}
+
+
+fn format_event(event: &tsm_unitman_event::Event) -> String {
+    let timestamp = UNIX_EPOCH + Duration::from_millis(event.timestamp.max(0) as u64);
+
+    let description = if event.has_unit_started() {
+        format!("started (pid={})", event.unit_started().pid)
+    } else if event.has_unit_exited() {
+        let unit_exited = event.unit_exited();
+
+        if unit_exited.has_exit_code {
+            format!("exited with code {}", unit_exited.exit_code)
+        } else if !unit_exited.signal.is_empty() {
+            format!("killed by {}", unit_exited.signal)
+        } else {
+            String::from("exited")
+        }
+    } else if event.has_probe_changed() {
+        let probe_changed = event.probe_changed();
+
+        let probe = match tsm_unitman_event::probe_changed::Probe::from_i32(probe_changed.probe.value()) {
+            Some(tsm_unitman_event::probe_changed::Probe::Process) => "process",
+            Some(tsm_unitman_event::probe_changed::Probe::Liveness) => "liveness",
+            None => "unknown",
+        };
+
+        format!("{} probe changed from {} to {}", probe, format_probe_state(probe_changed.from), format_probe_state(probe_changed.to))
+    } else if event.has_unit_failed() {
+        format!("failed: {}", event.unit_failed().reason)
+    } else if event.has_config_reloaded() {
+        format!("configuration reloaded with {} units", event.config_reloaded().unit_count)
+    } else {
+        String::from("unknown event")
+    };
+
+    return match event.unit_name.is_empty() {
+        true => format!("{} {}", format_rfc3339_millis(timestamp), description),
+        false => format!("{} {}: {}", format_rfc3339_millis(timestamp), event.unit_name, description),
+    };
+}
+
+
+fn format_probe_state(state: EnumOrUnknown<tsm_unitman_rpc::unit::ProbeState>) -> String {
+    return match tsm_unitman_rpc::unit::ProbeState::from_i32(state.value()) {
+        Some(tsm_unitman_rpc::unit::ProbeState::Undefined) => String::from("Undefined"),
+        Some(tsm_unitman_rpc::unit::ProbeState::Alive) => String::from("Alive"),
+        Some(tsm_unitman_rpc::unit::ProbeState::Dead) => String::from("Dead"),
+        None => String::from("Unknown"),
+    };
+}
@@ -0,0 +1,78 @@
+use log::warn;
+use serde::Deserialize;
+use users::{get_group_by_name, get_user_by_name};
+
+use crate::rpc;
+
+
+#[derive(Deserialize, Debug)]
+pub struct AccessRule {
+    users: Option<Vec<String>>,
+    groups: Option<Vec<String>>,
+    keys: Option<Vec<String>>,
+    methods: Option<Vec<String>>,
+    units: Option<Vec<String>>,
+    targets: Option<Vec<String>>,
+}
+
+
+impl AccessRule {
+    /// Names or numeric ids
+    pub fn get_users(&self) -> Vec<String> {
+        return self.users.clone().unwrap_or(Vec::new());
+    }
+
+    /// Names or numeric ids
+    pub fn get_groups(&self) -> Vec<String> {
+        return self.groups.clone().unwrap_or(Vec::new());
+    }
+
+    /// Z85 encoded CURVE public keys of clients
+    pub fn get_keys(&self) -> Vec<String> {
+        return self.keys.clone().unwrap_or(Vec::new());
+    }
+
+    /// Names of RPC methods like "RestartUnit", all methods if empty
+    pub fn get_methods(&self) -> Vec<String> {
+        return self.methods.clone().unwrap_or(Vec::new());
+    }
+
+    /// Patterns of unit names like "ui*", all units if empty and no targets are given
+    pub fn get_units(&self) -> Vec<String> {
+        return self.units.clone().unwrap_or(Vec::new());
+    }
+
+    /// Patterns of target names, all targets if empty and no units are given
+    pub fn get_targets(&self) -> Vec<String> {
+        return self.targets.clone().unwrap_or(Vec::new());
+    }
+
+    /// Without users, groups and keys the rule applies to all callers. Unknown users and groups
+    /// are left out, so they grant nothing.
+    pub fn build(&self) -> rpc::AccessRule {
+        let mut rule = rpc::AccessRule::new(self.get_methods(), self.get_units(), self.get_targets());
+
+        if self.get_users().is_empty() && self.get_groups().is_empty() && self.get_keys().is_empty() {
+            return rule;
+        }
+
+        let uids = self.get_users().iter().filter_map(|user| {
+            let uid = user.parse::<u32>().ok().or_else(|| get_user_by_name(user).map(|user| user.uid()));
+            if uid.is_none() {
+                warn!("Unknown user {} in access rule", user);
+            }
+            uid
+        }).collect();
+
+        let gids = self.get_groups().iter().filter_map(|group| {
+            let gid = group.parse::<u32>().ok().or_else(|| get_group_by_name(group).map(|group| group.gid()));
+            if gid.is_none() {
+                warn!("Unknown group {} in access rule", group);
+            }
+            gid
+        }).collect();
+
+        rule.restrict_to(uids, gids, self.get_keys());
+        return rule;
+    }
+}
@@ -5,6 +5,8 @@ use crate::config::{LogLevel};
 #[derive(Deserialize, Debug)]
 pub struct Application {
     log_level: Option<LogLevel>,
+    state_dir: Option<String>,
+    default_target: Option<String>,
 }
 
 
@@ -12,4 +14,16 @@ impl Application {
     pub fn get_log_level(&self) -> LogLevel {
         return self.log_level.clone().unwrap_or(LogLevel::Info);
     }
+
+    pub fn get_state_dir(&self) -> String {
+        return self.state_dir.clone().unwrap_or(String::from("/var/lib/tsm-unitman"));
+    }
+
+    /// Empty string means no default target, all units are started at boot
+    pub fn get_default_target(&self) -> Option<String> {
+        return match &self.default_target {
+            Some(default_target) if !default_target.is_empty() => Some(default_target.clone()),
+            _ => None,
+        };
+    }
 }
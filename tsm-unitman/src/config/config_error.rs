@@ -0,0 +1,22 @@
+use std::fmt;
+use std::io;
+
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Read(io::Error),
+    Parse(toml::de::Error),
+}
+
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Read(error) => write!(f, "Error reading configuration file: {}", error),
+            ConfigError::Parse(error) => write!(f, "Error parsing configuration file: {}", error),
+        }
+    }
+}
+
+
+impl std::error::Error for ConfigError {}
@@ -3,7 +3,7 @@ use std::fs;
 use serde::Deserialize;
 use log::{error, warn};
 
-use crate::config::{Application, Unit, RpcServer};
+use crate::config::{Application, ConfigError, Unit, RpcServer, EventPublisher, MetricsExporter, Socket, Target};
 use crate::unit;
 
 
@@ -11,29 +11,35 @@ use crate::unit;
 pub struct Configuration {
     application: Application,
     rpc_server: RpcServer,
+    #[serde(default)]
+    event_publisher: EventPublisher,
+    #[serde(default)]
+    metrics_exporter: MetricsExporter,
     units: Vec<Unit>,
+    sockets: Option<Vec<Socket>>,
+    targets: Option<Vec<Target>>,
 }
 
 
 impl Configuration {
-    pub fn from_file(file_path: String) -> Result<Configuration, String> {
+    pub fn from_file(file_path: String) -> Result<Configuration, ConfigError> {
         return match fs::read_to_string(file_path) {
             Ok(content) => {
                 Configuration::from_string(content)
             },
             Err(error) => {
-                Err(format!("Error reading configuration file: {}", error))
+                Err(ConfigError::Read(error))
             }
         }
     }
 
-    pub fn from_string(content: String) -> Result<Configuration, String> {
+    pub fn from_string(content: String) -> Result<Configuration, ConfigError> {
         return match toml::from_str(&content) {
             Ok(configuration) => {
                 Ok(configuration)
             },
             Err(error) => {
-                Err(format!("Error parsing configuration file: {}", error))
+                Err(ConfigError::Parse(error))
             }
         }
     }
@@ -46,6 +52,14 @@ impl Configuration {
         return &self.rpc_server;
     }
 
+    pub fn get_event_publisher(&self) -> &EventPublisher {
+        return &self.event_publisher;
+    }
+
+    pub fn get_metrics_exporter(&self) -> &MetricsExporter {
+        return &self.metrics_exporter;
+    }
+
     pub fn build_units(&self) -> Vec<unit::UnitRef> {
         let mut units = Vec::new();
 
@@ -54,7 +68,7 @@ impl Configuration {
         let mut unit_map: HashMap<String, unit::UnitRef> = HashMap::new();
 
         for unit_configuration in &self.units {
-            let unit_ref = unit_configuration.build_ref();
+            let unit_ref = unit_configuration.build_ref(self.application.get_state_dir());
 
             units.push(unit_ref.clone());
 
@@ -100,6 +114,40 @@ impl Configuration {
 
         return units;
     }
+
+    /// Targets named in a unit's wanted_by don't need to be declared
+    pub fn build_targets(&self) -> Vec<unit::Target> {
+        let mut targets: Vec<unit::Target> = Vec::new();
+
+        for target_configuration in self.targets.as_ref().unwrap_or(&Vec::new()) {
+            targets.push(target_configuration.build());
+        }
+
+        for unit_configuration in &self.units {
+            for target_name in unit_configuration.get_wanted_by() {
+                match targets.iter_mut().find(|target| target.get_name() == target_name) {
+                    Some(target) => target.add_want(unit_configuration.get_name()),
+                    None => targets.push(unit::Target::new(target_name, vec![unit_configuration.get_name()])),
+                }
+            }
+        }
+
+        return targets;
+    }
+
+    /// Binds the sockets. Sockets which fail to bind are left out.
+    pub fn build_sockets(&self) -> Vec<unit::Socket> {
+        let mut sockets = Vec::new();
+
+        for socket_configuration in self.sockets.as_ref().unwrap_or(&Vec::new()) {
+            match socket_configuration.build() {
+                Ok(socket) => sockets.push(socket),
+                Err(e) => error!("Error building socket {}: {}", socket_configuration.get_name(), e),
+            }
+        }
+
+        return sockets;
+    }
 }
 
 #[cfg(test)]
@@ -177,6 +225,178 @@ mod tests {
     }
 
 
+    fn sample_working_sandbox_conf() -> String {
+        return String::from(
+            r#"
+                [application]
+
+                [rpc_server]
+
+                [[units]]
+                name = "foo"
+                executable = "ls"
+                sandbox.no_new_privileges = true
+                sandbox.private_tmp = true
+                sandbox.read_only_paths = [ "/etc" ]
+                sandbox.syscall_deny_list = [ "reboot" ]
+            "#,
+        );
+    }
+
+    fn sample_working_timer_conf() -> String {
+        return String::from(
+            r#"
+                [application]
+                state_dir = "/tmp/tsm-unitman"
+
+                [rpc_server]
+
+                [[units]]
+                name = "cleanup"
+                executable = "ls"
+                restart_policy = "never"
+                timer.on_calendar = "0 3 * * *"
+                timer.on_boot_s = 60
+                timer.randomized_delay_s = 30
+            "#,
+        );
+    }
+
+    fn sample_working_path_trigger_conf() -> String {
+        return String::from(
+            r#"
+                [application]
+
+                [rpc_server]
+
+                [[units]]
+                name = "update"
+                executable = "ls"
+                path_trigger.on_path_exists = [ "/data/update/firmware.bin" ]
+                path_trigger.on_directory_not_empty = [ "/data/inbox" ]
+                path_trigger.debounce_s = 5
+            "#,
+        );
+    }
+
+    fn sample_working_target_conf() -> String {
+        return String::from(
+            r#"
+                [application]
+                default_target = "normal"
+
+                [rpc_server]
+
+                [[units]]
+                name = "app"
+                executable = "ls"
+                wanted_by = [ "normal" ]
+
+                [[units]]
+                name = "diagnostics"
+                executable = "ls"
+                wanted_by = [ "maintenance", "factory-test" ]
+
+                [[targets]]
+                name = "maintenance"
+                wants = [ "normal" ]
+            "#,
+        );
+    }
+
+    fn sample_working_acl_conf() -> String {
+        return String::from(
+            r#"
+                [application]
+
+                [rpc_server]
+                curve_secret_key = "JTKVSB%%)wK0E.X)V>+}o?pNmC{O&4W4b!Ni{Lh6"
+
+                [[rpc_server.acl]]
+                users = [ "54321" ]
+                keys = [ "rq:rM>}U?@Lns47E1%kR.o@n%FcmmsL/@{H8]yf7" ]
+                methods = [ "ListUnits" ]
+
+                [[units]]
+                name = "app"
+                executable = "ls"
+            "#,
+        );
+    }
+
+    fn sample_working_socket_conf() -> String {
+        return String::from(
+            r#"
+                [application]
+
+                [rpc_server]
+
+                [[units]]
+                name = "echo"
+                executable = "cat"
+
+                [[sockets]]
+                name = "echo"
+                listen = [ "tcp://127.0.0.1:0", "udp://127.0.0.1:0" ]
+
+                [[sockets]]
+                name = "echo-per-connection"
+                unit = "echo"
+                listen = [ "tcp://127.0.0.1:0" ]
+                accept = true
+            "#,
+        );
+    }
+
+    fn sample_invalid_socket_conf() -> String {
+        return String::from(
+            r#"
+                [application]
+
+                [rpc_server]
+
+                [[units]]
+                name = "echo"
+                executable = "cat"
+
+                [[sockets]]
+                name = "echo"
+                listen = [ "127.0.0.1:8080" ]
+            "#,
+        );
+    }
+
+    fn sample_invalid_timer_conf() -> String {
+        return String::from(
+            r#"
+                [application]
+
+                [rpc_server]
+
+                [[units]]
+                name = "cleanup"
+                executable = "ls"
+                timer.on_calendar = "at three"
+            "#,
+        );
+    }
+
+    fn sample_invalid_reload_signal_conf() -> String {
+        return String::from(
+            r#"
+                [application]
+
+                [rpc_server]
+
+                [[units]]
+                name = "web"
+                executable = "ls"
+                reload_signal = "SIGRELOAD"
+            "#,
+        );
+    }
+
+
     #[test]
     fn from_string_should_work() {
         let content= sample_working_complete_conf();
@@ -186,12 +406,116 @@ mod tests {
         assert_eq!(configuration.units.len(), 2);
     }
 
+    #[test]
+    fn build_access_control_should_work() {
+        let content= sample_working_acl_conf();
+        let configuration = Configuration::from_string(content).unwrap();
+        let access_control = configuration.get_rpc_server().build_access_control();
+        let caller = tsm_ipc::Caller::from_credentials(54321, 54321, 4242);
+
+        assert!(configuration.get_rpc_server().get_curve_secret_key().is_some());
+        assert_eq!(access_control.get_keys(), vec!["rq:rM>}U?@Lns47E1%kR.o@n%FcmmsL/@{H8]yf7"]);
+        assert!(access_control.is_allowed(&caller, tsm_ipc::tsm_unitman_rpc::RpcMethod::ListUnits, &[]));
+        assert!(!access_control.is_allowed(&caller, tsm_ipc::tsm_unitman_rpc::RpcMethod::ReExec, &[]));
+    }
+
     #[test]
     fn from_string_when_missing_optional_keys_should_work() {
         let content= sample_working_mandatory_only_conf();
         let configuration = Configuration::from_string(content).unwrap();
 
         assert_eq!(configuration.application.get_log_level(), LogLevel::Info);
+        assert!(!configuration.get_metrics_exporter().is_enabled());
+        assert_eq!(configuration.get_metrics_exporter().get_textfile_path(), None);
+    }
+
+    #[test]
+    fn from_string_with_sandbox_should_work() {
+        let content= sample_working_sandbox_conf();
+        let configuration = Configuration::from_string(content).unwrap();
+
+        assert!(configuration.units[0].get_sandbox().is_some());
+        assert!(!configuration.units[0].get_sandbox().unwrap().is_empty());
+    }
+
+    #[test]
+    fn build_units_with_timer_should_work() {
+        let content= sample_working_timer_conf();
+        let configuration = Configuration::from_string(content).unwrap();
+
+        let units = configuration.build_units();
+
+        assert_eq!(configuration.application.get_state_dir(), "/tmp/tsm-unitman");
+        assert_eq!(units[0].lock().unwrap().has_timer(), true);
+        assert!(units[0].lock().unwrap().get_timer().unwrap().get_next_trigger().is_some());
+    }
+
+    #[test]
+    fn from_string_with_invalid_timer_should_return_error() {
+        let content= sample_invalid_timer_conf();
+        let configuration = Configuration::from_string(content);
+
+        assert!(configuration.is_err());
+    }
+
+    #[test]
+    fn from_string_with_invalid_reload_signal_should_return_error() {
+        let content= sample_invalid_reload_signal_conf();
+        let configuration = Configuration::from_string(content);
+
+        assert!(configuration.is_err());
+    }
+
+    #[test]
+    fn build_units_with_path_trigger_should_work() {
+        let content= sample_working_path_trigger_conf();
+        let configuration = Configuration::from_string(content).unwrap();
+
+        let units = configuration.build_units();
+        let unit = units[0].lock().unwrap();
+
+        assert_eq!(unit.has_path_trigger(), true);
+        assert_eq!(unit.is_started_on_demand(), true);
+        assert_eq!(unit.get_path_trigger().unwrap().get_conditions().len(), 2);
+    }
+
+    #[test]
+    fn build_targets_should_work() {
+        let content= sample_working_target_conf();
+        let configuration = Configuration::from_string(content).unwrap();
+
+        let targets = configuration.build_targets();
+
+        assert_eq!(configuration.application.get_default_target(), Some(String::from("normal")));
+        assert_eq!(targets.len(), 3);
+        assert_eq!(targets[0].get_name(), "maintenance");
+        assert_eq!(targets[0].get_wants(), vec!["normal", "diagnostics"]);
+        assert_eq!(targets[1].get_name(), "normal");
+        assert_eq!(targets[1].get_wants(), vec!["app"]);
+        assert_eq!(targets[2].get_name(), "factory-test");
+    }
+
+    #[test]
+    fn build_sockets_should_work() {
+        let content= sample_working_socket_conf();
+        let configuration = Configuration::from_string(content).unwrap();
+
+        let sockets = configuration.build_sockets();
+
+        assert_eq!(sockets.len(), 2);
+        assert_eq!(sockets[0].get_unit_name(), "echo");
+        assert_eq!(sockets[0].get_addresses().len(), 2);
+        assert_eq!(sockets[0].is_accept(), false);
+        assert_eq!(sockets[1].get_unit_name(), "echo");
+        assert_eq!(sockets[1].is_accept(), true);
+    }
+
+    #[test]
+    fn from_string_with_invalid_socket_should_return_error() {
+        let content= sample_invalid_socket_conf();
+        let configuration = Configuration::from_string(content);
+
+        assert!(configuration.is_err());
     }
 
     #[test]
@@ -201,6 +525,7 @@ mod tests {
 
         assert_eq!(configuration.application.get_log_level(), LogLevel::Trace);
         assert_eq!(configuration.units.len(), 2);
+        assert_eq!(configuration.get_metrics_exporter().get_bind_address(), "127.0.0.1:9464");
     }
 
     #[test]
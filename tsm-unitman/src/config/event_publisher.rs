@@ -0,0 +1,19 @@
+use serde::Deserialize;
+
+
+#[derive(Deserialize, Debug, Default)]
+pub struct EventPublisher {
+    enabled: Option<bool>,
+    bind_address: Option<String>,
+}
+
+
+impl EventPublisher {
+    pub fn is_enabled(&self) -> bool {
+        return self.enabled.unwrap_or(false);
+    }
+
+    pub fn get_bind_address(&self) -> String {
+        return self.bind_address.clone().unwrap_or("ipc:///tmp/tsm-unitman-events.sock".to_string());
+    }
+}
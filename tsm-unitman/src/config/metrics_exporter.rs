@@ -0,0 +1,31 @@
+use serde::Deserialize;
+
+
+#[derive(Deserialize, Debug, Default)]
+pub struct MetricsExporter {
+    enabled: Option<bool>,
+    bind_address: Option<String>,
+    textfile_path: Option<String>,
+    textfile_interval_s: Option<u32>,
+}
+
+
+impl MetricsExporter {
+    pub fn is_enabled(&self) -> bool {
+        return self.enabled.unwrap_or(false);
+    }
+
+    /// Empty if metrics are only written to the textfile
+    pub fn get_bind_address(&self) -> String {
+        return self.bind_address.clone().unwrap_or("127.0.0.1:9464".to_string());
+    }
+
+    /// File for the textfile collector of the node exporter, None if no file is written
+    pub fn get_textfile_path(&self) -> Option<String> {
+        return self.textfile_path.clone().filter(|path| !path.is_empty());
+    }
+
+    pub fn get_textfile_interval_s(&self) -> u32 {
+        return self.textfile_interval_s.unwrap_or(15).max(1);
+    }
+}
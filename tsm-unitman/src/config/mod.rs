@@ -1,6 +1,9 @@
 mod configuration;
 pub use configuration::Configuration;
 
+mod config_error;
+pub use config_error::ConfigError;
+
 mod application;
 use application::Application;
 
@@ -13,5 +16,28 @@ use unit::Unit;
 mod process_probe;
 use process_probe::ProcessProbe;
 
+mod sandbox;
+use sandbox::Sandbox;
+
+mod timer;
+use timer::Timer;
+
+mod path_trigger;
+use path_trigger::PathTrigger;
+
+mod socket;
+use socket::Socket;
+
+mod target;
+use target::Target;
+
 mod rpc_server;
-use rpc_server::RpcServer;
\ No newline at end of file
+use rpc_server::RpcServer;
+
+mod access_rule;
+use access_rule::AccessRule;
+mod event_publisher;
+use event_publisher::EventPublisher;
+
+mod metrics_exporter;
+use metrics_exporter::MetricsExporter;
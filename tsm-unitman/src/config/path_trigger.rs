@@ -0,0 +1,71 @@
+use std::path::PathBuf;
+use serde::Deserialize;
+
+use crate::unit;
+
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct PathTrigger {
+    on_path_exists: Option<Vec<String>>,
+    on_path_changed: Option<Vec<String>>,
+    on_directory_not_empty: Option<Vec<String>>,
+    debounce_s: Option<u64>,
+    rate_limit_interval_s: Option<u64>,
+    rate_limit_burst: Option<u32>,
+}
+
+
+impl PathTrigger {
+    pub fn get_on_path_exists(&self) -> Vec<String> {
+        return self.on_path_exists.clone().unwrap_or(Vec::new());
+    }
+
+    pub fn get_on_path_changed(&self) -> Vec<String> {
+        return self.on_path_changed.clone().unwrap_or(Vec::new());
+    }
+
+    pub fn get_on_directory_not_empty(&self) -> Vec<String> {
+        return self.on_directory_not_empty.clone().unwrap_or(Vec::new());
+    }
+
+    pub fn get_debounce_s(&self) -> u64 {
+        return self.debounce_s.unwrap_or(1);
+    }
+
+    pub fn get_rate_limit_interval_s(&self) -> u64 {
+        return self.rate_limit_interval_s.unwrap_or(10);
+    }
+
+    /// 0 disables rate limiting
+    pub fn get_rate_limit_burst(&self) -> u32 {
+        return self.rate_limit_burst.unwrap_or(5);
+    }
+
+    pub fn get_conditions(&self) -> Vec<unit::PathCondition> {
+        let mut conditions = Vec::new();
+
+        for path in self.get_on_path_exists() {
+            conditions.push(unit::PathCondition::Exists(PathBuf::from(path)));
+        }
+
+        for path in self.get_on_path_changed() {
+            conditions.push(unit::PathCondition::Changed(PathBuf::from(path)));
+        }
+
+        for path in self.get_on_directory_not_empty() {
+            conditions.push(unit::PathCondition::DirectoryNotEmpty(PathBuf::from(path)));
+        }
+
+        return conditions;
+    }
+
+    pub fn build(&self, name: String) -> unit::PathTrigger {
+        return unit::PathTrigger::new(
+            name,
+            self.get_conditions(),
+            self.get_debounce_s(),
+            self.get_rate_limit_interval_s(),
+            self.get_rate_limit_burst(),
+        );
+    }
+}
@@ -1,10 +1,16 @@
 use serde::Deserialize;
 
+use crate::config::AccessRule;
+use crate::rpc;
+
 
 #[derive(Deserialize, Debug)]
 pub struct RpcServer {
     enabled: Option<bool>,
     bind_address: Option<String>,
+    workers: Option<usize>,
+    curve_secret_key: Option<String>,
+    acl: Option<Vec<AccessRule>>,
 }
 
 
@@ -16,4 +22,18 @@ impl RpcServer {
     pub fn get_bind_address(&self) -> String {
         return self.bind_address.clone().unwrap_or("ipc:///tmp/tsm-unitman.sock".to_string());
     }
+
+    pub fn get_workers(&self) -> usize {
+        return self.workers.unwrap_or(4).max(1);
+    }
+
+    /// Z85 encoded, enables CURVE for tcp endpoints
+    pub fn get_curve_secret_key(&self) -> Option<String> {
+        return self.curve_secret_key.clone();
+    }
+
+    pub fn build_access_control(&self) -> rpc::AccessControl {
+        let rules = self.acl.as_ref().unwrap_or(&Vec::new()).iter().map(|rule| rule.build()).collect();
+        return rpc::AccessControl::new(rules);
+    }
 }
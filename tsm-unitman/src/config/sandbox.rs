@@ -0,0 +1,95 @@
+use serde::Deserialize;
+
+use crate::unit;
+
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct Sandbox {
+    supplementary_groups: Option<Vec<String>>,
+    ambient_capabilities: Option<Vec<String>>,
+    capability_bounding_set: Option<Vec<String>>,
+    no_new_privileges: Option<bool>,
+    private_mounts: Option<bool>,
+    private_network: Option<bool>,
+    private_pid: Option<bool>,
+    private_ipc: Option<bool>,
+    private_tmp: Option<bool>,
+    read_only_paths: Option<Vec<String>>,
+    root_directory: Option<String>,
+    syscall_deny_list: Option<Vec<String>>,
+}
+
+
+impl Sandbox {
+    pub fn get_supplementary_groups(&self) -> Vec<String> {
+        return self.supplementary_groups.clone().unwrap_or(Vec::new());
+    }
+
+    pub fn get_ambient_capabilities(&self) -> Vec<String> {
+        return self.ambient_capabilities.clone().unwrap_or(Vec::new());
+    }
+
+    /// None means the bounding set is left untouched
+    pub fn get_capability_bounding_set(&self) -> Option<Vec<String>> {
+        return self.capability_bounding_set.clone();
+    }
+
+    pub fn is_no_new_privileges(&self) -> bool {
+        return self.no_new_privileges.unwrap_or(false);
+    }
+
+    pub fn is_private_mounts(&self) -> bool {
+        return self.private_mounts.unwrap_or(false);
+    }
+
+    pub fn is_private_network(&self) -> bool {
+        return self.private_network.unwrap_or(false);
+    }
+
+    pub fn is_private_pid(&self) -> bool {
+        return self.private_pid.unwrap_or(false);
+    }
+
+    pub fn is_private_ipc(&self) -> bool {
+        return self.private_ipc.unwrap_or(false);
+    }
+
+    pub fn is_private_tmp(&self) -> bool {
+        return self.private_tmp.unwrap_or(false);
+    }
+
+    pub fn get_read_only_paths(&self) -> Vec<String> {
+        return self.read_only_paths.clone().unwrap_or(Vec::new());
+    }
+
+    /// Empty string means no chroot
+    pub fn get_root_directory(&self) -> Option<String> {
+        return match &self.root_directory {
+            Some(root_directory) if !root_directory.is_empty() => Some(root_directory.clone()),
+            _ => None,
+        };
+    }
+
+    pub fn get_syscall_deny_list(&self) -> Vec<String> {
+        return self.syscall_deny_list.clone().unwrap_or(Vec::new());
+    }
+
+    pub fn build(&self) -> unit::Sandbox {
+        let mut sandbox = unit::Sandbox::new();
+
+        sandbox.set_supplementary_groups(self.get_supplementary_groups());
+        sandbox.set_ambient_capabilities(self.get_ambient_capabilities());
+        sandbox.set_capability_bounding_set(self.get_capability_bounding_set());
+        sandbox.set_no_new_privileges(self.is_no_new_privileges());
+        sandbox.set_private_mounts(self.is_private_mounts());
+        sandbox.set_private_network(self.is_private_network());
+        sandbox.set_private_pid(self.is_private_pid());
+        sandbox.set_private_ipc(self.is_private_ipc());
+        sandbox.set_private_tmp(self.is_private_tmp());
+        sandbox.set_read_only_paths(self.get_read_only_paths());
+        sandbox.set_root_directory(self.get_root_directory());
+        sandbox.set_syscall_deny_list(self.get_syscall_deny_list());
+
+        return sandbox;
+    }
+}
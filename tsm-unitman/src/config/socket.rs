@@ -0,0 +1,42 @@
+use serde::Deserialize;
+
+use crate::unit;
+
+
+#[derive(Deserialize, Debug)]
+pub struct Socket {
+    name: String,
+    unit: Option<String>,
+    listen: Vec<unit::ListenAddress>,
+    accept: Option<bool>,
+}
+
+
+impl Socket {
+    pub fn get_name(&self) -> String {
+        return self.name.clone();
+    }
+
+    /// Defaults to the unit named like the socket
+    pub fn get_unit(&self) -> String {
+        return self.unit.clone().unwrap_or(self.get_name());
+    }
+
+    pub fn get_listen(&self) -> Vec<unit::ListenAddress> {
+        return self.listen.clone();
+    }
+
+    pub fn is_accept(&self) -> bool {
+        return self.accept.unwrap_or(false);
+    }
+
+    /// Binds the listen addresses
+    pub fn build(&self) -> Result<unit::Socket, String> {
+        return unit::Socket::new(
+            self.get_name(),
+            self.get_unit(),
+            self.get_listen(),
+            self.is_accept(),
+        );
+    }
+}
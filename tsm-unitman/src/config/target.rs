@@ -0,0 +1,29 @@
+use serde::Deserialize;
+
+use crate::unit;
+
+
+#[derive(Deserialize, Debug)]
+pub struct Target {
+    name: String,
+    wants: Option<Vec<String>>,
+}
+
+
+impl Target {
+    pub fn get_name(&self) -> String {
+        return self.name.clone();
+    }
+
+    /// Names of units and other targets
+    pub fn get_wants(&self) -> Vec<String> {
+        return self.wants.clone().unwrap_or(Vec::new());
+    }
+
+    pub fn build(&self) -> unit::Target {
+        return unit::Target::new(
+            self.get_name(),
+            self.get_wants(),
+        );
+    }
+}
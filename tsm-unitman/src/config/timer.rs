@@ -0,0 +1,48 @@
+use serde::Deserialize;
+
+use crate::unit;
+
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct Timer {
+    on_calendar: Option<unit::Calendar>,
+    on_boot_s: Option<u64>,
+    on_unit_active_s: Option<u64>,
+    randomized_delay_s: Option<u64>,
+    persistent: Option<bool>,
+}
+
+
+impl Timer {
+    pub fn get_on_calendar(&self) -> Option<unit::Calendar> {
+        return self.on_calendar.clone();
+    }
+
+    pub fn get_on_boot_s(&self) -> Option<u64> {
+        return self.on_boot_s;
+    }
+
+    pub fn get_on_unit_active_s(&self) -> Option<u64> {
+        return self.on_unit_active_s;
+    }
+
+    pub fn get_randomized_delay_s(&self) -> u64 {
+        return self.randomized_delay_s.unwrap_or(0);
+    }
+
+    pub fn is_persistent(&self) -> bool {
+        return self.persistent.unwrap_or(false);
+    }
+
+    pub fn build(&self, name: String, state_dir: String) -> unit::Timer {
+        return unit::Timer::new(
+            name,
+            self.get_on_calendar(),
+            self.get_on_boot_s(),
+            self.get_on_unit_active_s(),
+            self.get_randomized_delay_s(),
+            self.is_persistent(),
+            state_dir,
+        );
+    }
+}
@@ -1,8 +1,9 @@
+use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
 use serde::Deserialize;
 use users::{get_current_gid, get_current_uid, get_group_by_name, get_user_by_name};
 
-use crate::config::ProcessProbe;
+use crate::config::{ProcessProbe, Sandbox, Timer, PathTrigger};
 use crate::unit;
 
 
@@ -12,11 +13,17 @@ pub struct Unit {
     executable: String,
     arguments: Option<Vec<String>>,
     dependencies: Option<Vec<String>>,
+    wanted_by: Option<Vec<String>>,
     restart_policy: Option<unit::RestartPolicy>,
     user: Option<String>,
     group: Option<String>,
     enabled: Option<bool>,
+    reload_signal: Option<unit::Signal>,
+    reload_command: Option<Vec<String>>,
     liveness_probe: Option<ProcessProbe>,
+    sandbox: Option<Sandbox>,
+    timer: Option<Timer>,
+    path_trigger: Option<PathTrigger>,
 }
 
 
@@ -37,6 +44,11 @@ impl Unit {
         return self.dependencies.clone().unwrap_or(Vec::new());
     }
 
+    /// Names of the targets the unit is part of
+    pub fn get_wanted_by(&self) -> Vec<String> {
+        return self.wanted_by.clone().unwrap_or(Vec::new());
+    }
+
     pub fn get_restart_policy(&self) -> unit::RestartPolicy {
         return self.restart_policy.clone().unwrap_or(unit::RestartPolicy::Always);
     }
@@ -45,6 +57,15 @@ impl Unit {
         return self.enabled.clone().unwrap_or(true);
     }
 
+    pub fn get_reload_signal(&self) -> Option<unit::Signal> {
+        return self.reload_signal.clone();
+    }
+
+    /// Executable followed by its arguments
+    pub fn get_reload_command(&self) -> Vec<String> {
+        return self.reload_command.clone().unwrap_or(Vec::new());
+    }
+
     pub fn get_liveness_probe(&self) -> Option<unit::LivenessProbe> {
         let name = self.get_name();
         return match &self.liveness_probe {
@@ -53,7 +74,30 @@ impl Unit {
         }
     }
 
-    pub fn build_ref(&self) -> unit::UnitRef {
+    pub fn get_sandbox(&self) -> Option<unit::Sandbox> {
+        return match &self.sandbox {
+            Some(sandbox) => Some(sandbox.build()),
+            None => None,
+        }
+    }
+
+    pub fn get_timer(&self, state_dir: String) -> Option<unit::Timer> {
+        let name = self.get_name();
+        return match &self.timer {
+            Some(timer) => Some(timer.build(name, state_dir)),
+            None => None,
+        }
+    }
+
+    pub fn get_path_trigger(&self) -> Option<unit::PathTrigger> {
+        let name = self.get_name();
+        return match &self.path_trigger {
+            Some(path_trigger) => Some(path_trigger.build(name)),
+            None => None,
+        }
+    }
+
+    pub fn build_ref(&self, state_dir: String) -> unit::UnitRef {
         let mut unit = unit::Unit::new(
             self.get_name(),
             self.get_executable(),
@@ -64,6 +108,18 @@ impl Unit {
             self.is_enabled(),
         );
 
+        match self.get_reload_signal() {
+            Some(signal) => {
+                unit.set_reload_signal(signal);
+            },
+            None => {},
+        }
+
+        unit.set_reload_command(self.get_reload_command());
+
+        let output_path = PathBuf::from(&state_dir).join("output").join(format!("{}.log", self.get_name()));
+        unit.set_output_log(unit::OutputLog::new(output_path));
+
         match self.get_liveness_probe() {
             Some(liveness_probe) => {
                 unit.set_liveness_probe(liveness_probe);
@@ -71,6 +127,27 @@ impl Unit {
             None => {},
         }
 
+        match self.get_sandbox() {
+            Some(sandbox) => {
+                unit.set_sandbox(sandbox);
+            },
+            None => {},
+        }
+
+        match self.get_timer(state_dir) {
+            Some(timer) => {
+                unit.set_timer(timer);
+            },
+            None => {},
+        }
+
+        match self.get_path_trigger() {
+            Some(path_trigger) => {
+                unit.set_path_trigger(path_trigger);
+            },
+            None => {},
+        }
+
         Arc::new(Mutex::new(unit))
     }
 
@@ -5,6 +5,7 @@ use log::{error};
 mod config;
 mod unit;
 mod rpc;
+mod metrics;
 
 
 struct CommandLineParameters {
@@ -52,12 +53,26 @@ fn init_logger(configuration: &config::Configuration) {
 fn init_unit_manager_or_exit(configuration: &config::Configuration) -> unit::UnitManagerRef {
     let manager = unit::UnitManager::new_ref();
     let units = configuration.build_units();
+    let sockets = configuration.build_sockets();
+    let targets = configuration.build_targets();
 
     match manager.try_lock() {
         Ok(mut manager) => {
             for unit in units {
                 manager.add_unit(unit);
             }
+
+            for socket in sockets {
+                manager.add_socket(socket);
+            }
+
+            for target in targets {
+                manager.add_target(target);
+            }
+
+            manager.set_default_target(configuration.get_application().get_default_target());
+            manager.set_state_dir(configuration.get_application().get_state_dir());
+            manager.apply_unit_overrides();
         },
         Err(e) => {
             error!("Error acquiring lock: {}", e);
@@ -77,12 +92,57 @@ fn main() {
     init_logger(&configuration);
     let manager = init_unit_manager_or_exit(&configuration);
 
+    // start event publisher before the units start, so that their first events are published too
+    if configuration.get_event_publisher().is_enabled() {
+        let (event_publisher, event_sender) = rpc::EventPublisher::new(
+            configuration.get_event_publisher().get_bind_address()
+        );
+
+        match manager.try_lock() {
+            Ok(mut manager_lock) => manager_lock.set_event_sender(event_sender),
+            Err(e) => {
+                error!("Error acquiring lock: {}", e);
+                exit(20);
+            }
+        };
+
+        event_publisher.run_threaded();
+    }
+
     // start rpc server
     if configuration.get_rpc_server().is_enabled() {
-        rpc::RpcServer::new(
+        let mut rpc_server = rpc::RpcServer::new(
+            manager.clone(),
+            configuration.get_rpc_server().get_bind_address(),
+            configuration.get_rpc_server().get_workers()
+        );
+        rpc_server.set_access_control(configuration.get_rpc_server().build_access_control());
+
+        if let Some(curve_secret_key) = configuration.get_rpc_server().get_curve_secret_key() {
+            rpc_server.set_curve_secret_key(curve_secret_key);
+        }
+
+        if let Err(e) = rpc_server.run_threaded() {
+            error!("Failed to start RPC server: {}", e);
+            exit(30);
+        }
+    }
+
+    // start metrics exporter
+    if configuration.get_metrics_exporter().is_enabled() {
+        let mut metrics_exporter = metrics::MetricsExporter::new(
             manager.clone(),
-            configuration.get_rpc_server().get_bind_address()
-        ).run_threaded();
+            configuration.get_metrics_exporter().get_bind_address()
+        );
+
+        if let Some(textfile_path) = configuration.get_metrics_exporter().get_textfile_path() {
+            metrics_exporter.set_textfile(textfile_path, configuration.get_metrics_exporter().get_textfile_interval_s());
+        }
+
+        if let Err(e) = metrics_exporter.run_threaded() {
+            error!("Failed to start metrics exporter: {}", e);
+            exit(30);
+        }
     }
 
     // start unit manager
@@ -0,0 +1,219 @@
+use std::fs;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::thread;
+use std::time::Duration;
+use log::{debug, error, warn};
+
+use crate::unit;
+use crate::metrics::unit_metrics;
+
+
+/// Scrapers which don't send their request in time are dropped, so that they don't hold up others
+const READ_TIMEOUT: Duration = Duration::from_secs(5);
+const WRITE_TIMEOUT: Duration = Duration::from_secs(5);
+const MAX_REQUEST_HEAD_SIZE: u64 = 8192;
+const CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+
+/// Serves the metrics of the units on GET /metrics and writes them to a file for the textfile
+/// collector of the node exporter. Either may be left out.
+pub struct MetricsExporter {
+    unit_manager: unit::UnitManagerRef,
+    bind_address: String,
+    textfile_path: Option<String>,
+    textfile_interval: Duration,
+}
+
+
+impl MetricsExporter {
+    /// An empty bind address serves no metrics over HTTP
+    pub fn new(unit_manager: unit::UnitManagerRef, bind_address: String) -> MetricsExporter {
+        MetricsExporter {
+            unit_manager,
+            bind_address,
+            textfile_path: None,
+            textfile_interval: Duration::from_secs(15),
+        }
+    }
+
+    /// The file is replaced atomically every interval, so the collector never reads half of it
+    pub fn set_textfile(&mut self, textfile_path: String, interval_s: u32) {
+        self.textfile_path = Some(textfile_path);
+        self.textfile_interval = Duration::from_secs(interval_s as u64);
+    }
+
+    /// Binds right away, so that a used address is reported to the caller
+    pub fn run_threaded(self) -> Result<(), String> {
+        if !self.bind_address.is_empty() {
+            let listener = match TcpListener::bind(&self.bind_address) {
+                Ok(listener) => listener,
+                Err(error) => return Err(format!("Failed to bind to {}: {}", self.bind_address, error)),
+            };
+
+            debug!("Serving metrics on http://{}/metrics", self.bind_address);
+
+            let unit_manager = self.unit_manager.clone();
+            thread::spawn(move || serve(listener, unit_manager));
+        }
+
+        if let Some(textfile_path) = self.textfile_path {
+            debug!("Writing metrics to {}", textfile_path);
+
+            let unit_manager = self.unit_manager.clone();
+            let interval = self.textfile_interval;
+            thread::spawn(move || write_textfile_loop(&textfile_path, interval, unit_manager));
+        }
+
+        Ok(())
+    }
+}
+
+
+/// Scrapes are rare and quick, so they are answered one after another
+fn serve(listener: TcpListener, unit_manager: unit::UnitManagerRef) {
+    for stream in listener.incoming() {
+        match stream {
+            Ok(stream) => {
+                if let Err(error) = serve_connection(stream, &unit_manager) {
+                    warn!("Failed to serve metrics: {}", error);
+                }
+            },
+            Err(error) => warn!("Failed to accept connection for metrics: {}", error),
+        };
+    }
+}
+
+
+fn serve_connection(mut stream: TcpStream, unit_manager: &unit::UnitManagerRef) -> Result<(), String> {
+    stream.set_read_timeout(Some(READ_TIMEOUT)).map_err(|error| error.to_string())?;
+    stream.set_write_timeout(Some(WRITE_TIMEOUT)).map_err(|error| error.to_string())?;
+
+    let request_line = read_request_line(&stream)?;
+    let mut parts = request_line.split_whitespace();
+
+    let (status, body) = match (parts.next(), parts.next()) {
+        (Some("GET"), Some("/metrics")) => {
+            let samples = unit_metrics::collect(unit_manager);
+            ("200 OK", unit_metrics::render(&samples))
+        },
+        (Some(_), Some("/metrics")) => ("405 Method Not Allowed", String::from("Only GET is allowed\n")),
+        (Some(_), Some(_)) => ("404 Not Found", String::from("Metrics are served on /metrics\n")),
+        _ => ("400 Bad Request", String::from("Invalid request\n")),
+    };
+
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status, CONTENT_TYPE, body.len(), body
+    );
+
+    return stream.write_all(response.as_bytes()).map_err(|error| error.to_string());
+}
+
+
+/// Reads the whole head of the request, so that the client doesn't get a reset, and returns its first line
+fn read_request_line(stream: &TcpStream) -> Result<String, String> {
+    let mut reader = BufReader::new(stream.take(MAX_REQUEST_HEAD_SIZE));
+    let mut request_line = String::new();
+
+    if let Err(error) = reader.read_line(&mut request_line) {
+        return Err(format!("Failed to read request: {}", error));
+    }
+
+    loop {
+        let mut line = String::new();
+
+        match reader.read_line(&mut line) {
+            Ok(0) => break,
+            Ok(_) if line.trim_end().is_empty() => break,
+            Ok(_) => continue,
+            Err(error) => return Err(format!("Failed to read request: {}", error)),
+        };
+    }
+
+    Ok(request_line)
+}
+
+
+fn write_textfile_loop(textfile_path: &str, interval: Duration, unit_manager: unit::UnitManagerRef) {
+    loop {
+        let samples = unit_metrics::collect(&unit_manager);
+
+        if let Err(error) = write_textfile(textfile_path, &unit_metrics::render(&samples)) {
+            error!("Failed to write metrics to {}: {}", textfile_path, error);
+        }
+
+        thread::sleep(interval);
+    }
+}
+
+
+/// The collector only reads *.prom files, the temporary file is renamed over the old one
+fn write_textfile(textfile_path: &str, content: &str) -> Result<(), std::io::Error> {
+    let temporary_path = format!("{}.tmp", textfile_path);
+
+    fs::write(&temporary_path, content)?;
+    return fs::rename(&temporary_path, textfile_path);
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+    use super::*;
+
+    fn build_unit_manager() -> unit::UnitManagerRef {
+        let unit_manager = unit::UnitManager::new_ref();
+        let unit = unit::Unit::new(String::from("ui-main"), String::from("ls"), Vec::new(), unit::RestartPolicy::Never, 0, 0, true);
+        unit_manager.lock().unwrap().add_unit(Arc::new(Mutex::new(unit)));
+        unit_manager
+    }
+
+    fn get(address: &str, request: &str) -> String {
+        let mut stream = TcpStream::connect(address).unwrap();
+        stream.write_all(request.as_bytes()).unwrap();
+
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    #[test]
+    fn serve_should_answer_metrics_requests() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let unit_manager = build_unit_manager();
+        thread::spawn(move || serve(listener, unit_manager));
+
+        let response = get(&address, "GET /metrics HTTP/1.1\r\nHost: localhost\r\n\r\n");
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.contains("tsm_unit_state{unit=\"ui-main\",state=\"stopped\"} 1\n"));
+        assert!(response.contains("tsm_unit_up{unit=\"ui-main\"} 0\n"));
+
+        assert!(get(&address, "GET / HTTP/1.1\r\n\r\n").starts_with("HTTP/1.1 404"));
+        assert!(get(&address, "POST /metrics HTTP/1.1\r\n\r\n").starts_with("HTTP/1.1 405"));
+    }
+
+    #[test]
+    fn run_threaded_should_report_used_address() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+
+        assert!(MetricsExporter::new(build_unit_manager(), address).run_threaded().is_err());
+    }
+
+    #[test]
+    fn write_textfile_should_replace_file() {
+        let textfile_path = format!("/tmp/tsm-unitman-metrics-test-{}.prom", std::process::id());
+        let samples = unit_metrics::collect(&build_unit_manager());
+
+        write_textfile(&textfile_path, "outdated\n").unwrap();
+        write_textfile(&textfile_path, &unit_metrics::render(&samples)).unwrap();
+
+        let content = fs::read_to_string(&textfile_path).unwrap();
+        fs::remove_file(&textfile_path).unwrap();
+
+        assert!(content.contains("tsm_unit_restarts_total{unit=\"ui-main\"} 0\n"));
+        assert!(!content.contains("outdated"));
+    }
+}
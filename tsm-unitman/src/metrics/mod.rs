@@ -0,0 +1,4 @@
+mod metrics_exporter;
+pub use metrics_exporter::MetricsExporter;
+
+mod unit_metrics;
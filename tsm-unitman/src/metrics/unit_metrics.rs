@@ -0,0 +1,254 @@
+use std::fmt::Write;
+use std::time::Duration;
+use log::error;
+
+use crate::unit;
+use crate::unit::{ProbeCounters, ProcStat, UnitState};
+
+
+/// Every state is exported for every unit, with 1 for the one the unit is in
+static STATES: [(UnitState, &str); 7] = [
+    (UnitState::Starting, "starting"),
+    (UnitState::Running, "running"),
+    (UnitState::RunningAndHealthy, "running_healthy"),
+    (UnitState::RunningButDegraded, "running_degraded"),
+    (UnitState::Stopping, "stopping"),
+    (UnitState::Stopped, "stopped"),
+    (UnitState::Failed, "failed"),
+];
+
+
+/// What is exported of a unit, taken while it is locked
+#[derive(Debug, Clone)]
+pub struct UnitSample {
+    name: String,
+    state: UnitState,
+    restart_count: u32,
+    exit_code: Option<i32>,
+    uptime: Option<Duration>,
+    probe_counters: Option<ProbeCounters>,
+    last_probe_duration: Option<Duration>,
+    proc_stat: Option<ProcStat>,
+}
+
+
+impl UnitSample {
+    pub fn new(unit: &unit::Unit) -> UnitSample {
+        UnitSample {
+            name: unit.get_name(),
+            state: unit.get_state(),
+            restart_count: unit.get_restart_count(),
+            // like in the unit status, -1 means the process was killed by a signal
+            exit_code: unit.get_last_exit_status().map(|exit_status| exit_status.code().unwrap_or(-1)),
+            uptime: unit.get_uptime(),
+            probe_counters: unit.get_probe_counters(),
+            last_probe_duration: unit.get_probe_results().last().map(|result| result.get_duration()),
+            proc_stat: unit.get_pid().and_then(ProcStat::read),
+        }
+    }
+
+    fn is_up(&self) -> bool {
+        return matches!(self.state, UnitState::Running | UnitState::RunningAndHealthy | UnitState::RunningButDegraded);
+    }
+}
+
+
+/// Samples all units. The unit manager is only locked to get the units, which are locked one by one.
+pub fn collect(unit_manager: &unit::UnitManagerRef) -> Vec<UnitSample> {
+    let units = match unit_manager.lock() {
+        Ok(unit_manager) => unit_manager.get_units().clone(),
+        Err(e) => {
+            error!("Error acquiring lock while collecting metrics: {}", e);
+            return Vec::new();
+        }
+    };
+
+    let mut samples = Vec::new();
+
+    for unit in units {
+        match unit.lock() {
+            Ok(unit) => samples.push(UnitSample::new(&unit)),
+            Err(e) => error!("Error acquiring lock of unit while collecting metrics: {}", e),
+        };
+    }
+
+    return samples;
+}
+
+
+/// The Prometheus text format, which the textfile collector of the node exporter reads too
+pub fn render(samples: &[UnitSample]) -> String {
+    let mut output = String::new();
+
+    write_family(&mut output, "tsm_unit_state", "gauge", "Current state of the unit, 1 for the state it is in",
+                 samples.iter().flat_map(|sample| STATES.iter().map(move |(state, state_name)| {
+                     (format!("{},state=\"{}\"", unit_label(sample), state_name), bool_value(sample.state == *state))
+                 })).collect());
+
+    write_family(&mut output, "tsm_unit_up", "gauge", "1 if the process of the unit is running, 0 otherwise",
+                 samples.iter().map(|sample| (unit_label(sample), bool_value(sample.is_up()))).collect());
+
+    write_family(&mut output, "tsm_unit_restarts_total", "counter", "Restarts of the unit by its restart policy",
+                 samples.iter().map(|sample| (unit_label(sample), sample.restart_count.to_string())).collect());
+
+    write_family(&mut output, "tsm_unit_last_exit_code", "gauge", "Exit code of the last run of the process, -1 if it was killed by a signal",
+                 samples.iter().filter_map(|sample| sample.exit_code.map(|exit_code| (unit_label(sample), exit_code.to_string()))).collect());
+
+    write_family(&mut output, "tsm_unit_uptime_seconds", "gauge", "Seconds the process of the unit has been running",
+                 samples.iter().filter_map(|sample| sample.uptime.map(|uptime| (unit_label(sample), uptime.as_secs_f64().to_string()))).collect());
+
+    write_family(&mut output, "tsm_unit_probe_success_total", "counter", "Probes which found the unit alive",
+                 samples.iter().filter_map(|sample| sample.probe_counters.as_ref().map(|counters| (probe_label(sample), counters.get_successes().to_string()))).collect());
+
+    write_family(&mut output, "tsm_unit_probe_failure_total", "counter", "Probes which found the unit dead, or could not be run",
+                 samples.iter().filter_map(|sample| sample.probe_counters.as_ref().map(|counters| (probe_label(sample), counters.get_failures().to_string()))).collect());
+
+    let mut probe_durations = Vec::new();
+    for sample in samples {
+        if let Some(counters) = &sample.probe_counters {
+            probe_durations.push((String::from("_sum"), probe_label(sample), counters.get_duration_total().as_secs_f64().to_string()));
+            probe_durations.push((String::from("_count"), probe_label(sample), counters.get_count().to_string()));
+        }
+    }
+    write_summary(&mut output, "tsm_unit_probe_duration_seconds", "Time the probes of the unit took", probe_durations);
+
+    write_family(&mut output, "tsm_unit_probe_last_duration_seconds", "gauge", "Time the last probe of the unit took",
+                 samples.iter().filter_map(|sample| sample.last_probe_duration.map(|duration| (probe_label(sample), duration.as_secs_f64().to_string()))).collect());
+
+    write_family(&mut output, "tsm_unit_memory_rss_bytes", "gauge", "Resident memory of the main process of the unit",
+                 samples.iter().filter_map(|sample| {
+                     let rss_bytes = sample.proc_stat.as_ref()?.get_rss_bytes()?;
+                     Some((unit_label(sample), rss_bytes.to_string()))
+                 }).collect());
+
+    write_family(&mut output, "tsm_unit_cpu_seconds_total", "counter", "CPU time of the main process of the unit in user and kernel mode",
+                 samples.iter().filter_map(|sample| {
+                     let cpu_time_s = sample.proc_stat.as_ref()?.get_cpu_time_s()?;
+                     Some((unit_label(sample), cpu_time_s.to_string()))
+                 }).collect());
+
+    return output;
+}
+
+
+/// Families without samples are left out, e.g. the probe metrics if no unit has a probe
+fn write_family(output: &mut String, name: &str, kind: &str, help: &str, samples: Vec<(String, String)>) {
+    if samples.is_empty() {
+        return;
+    }
+
+    let _ = writeln!(output, "# HELP {} {}", name, help);
+    let _ = writeln!(output, "# TYPE {} {}", name, kind);
+
+    for (labels, value) in samples {
+        let _ = writeln!(output, "{}{{{}}} {}", name, labels, value);
+    }
+}
+
+
+/// Samples are (suffix, labels, value)
+fn write_summary(output: &mut String, name: &str, help: &str, samples: Vec<(String, String, String)>) {
+    if samples.is_empty() {
+        return;
+    }
+
+    let _ = writeln!(output, "# HELP {} {}", name, help);
+    let _ = writeln!(output, "# TYPE {} summary", name);
+
+    for (suffix, labels, value) in samples {
+        let _ = writeln!(output, "{}{}{{{}}} {}", name, suffix, labels, value);
+    }
+}
+
+
+fn unit_label(sample: &UnitSample) -> String {
+    format!("unit=\"{}\"", escape_label_value(&sample.name))
+}
+
+
+fn probe_label(sample: &UnitSample) -> String {
+    format!("{},probe=\"liveness\"", unit_label(sample))
+}
+
+
+fn bool_value(value: bool) -> String {
+    return match value {
+        true => String::from("1"),
+        false => String::from("0"),
+    };
+}
+
+
+fn escape_label_value(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+
+#[cfg(test)]
+mod tests {
+    use chrono::Local;
+    use crate::unit::{ProbeResult, ProbeState};
+    use super::*;
+
+    fn sample(name: &str, state: UnitState) -> UnitSample {
+        UnitSample {
+            name: String::from(name),
+            state,
+            restart_count: 0,
+            exit_code: None,
+            uptime: None,
+            probe_counters: None,
+            last_probe_duration: None,
+            proc_stat: None,
+        }
+    }
+
+    #[test]
+    fn render_should_export_state_of_every_unit() {
+        let mut failed = sample("network", UnitState::Failed);
+        failed.restart_count = 3;
+        failed.exit_code = Some(2);
+
+        let output = render(&[sample("ui-main", UnitState::RunningAndHealthy), failed]);
+
+        assert!(output.contains("# TYPE tsm_unit_state gauge\n"));
+        assert!(output.contains("tsm_unit_state{unit=\"ui-main\",state=\"running_healthy\"} 1\n"));
+        assert!(output.contains("tsm_unit_state{unit=\"ui-main\",state=\"failed\"} 0\n"));
+        assert!(output.contains("tsm_unit_up{unit=\"ui-main\"} 1\n"));
+        assert!(output.contains("tsm_unit_up{unit=\"network\"} 0\n"));
+        assert!(output.contains("tsm_unit_restarts_total{unit=\"network\"} 3\n"));
+        assert!(output.contains("tsm_unit_last_exit_code{unit=\"network\"} 2\n"));
+        assert!(!output.contains("tsm_unit_last_exit_code{unit=\"ui-main\"}"));
+        assert!(!output.contains("tsm_unit_probe"));
+    }
+
+    #[test]
+    fn render_should_export_probes_and_resources() {
+        let mut counters = ProbeCounters::new();
+        counters.record(&ProbeResult::new(Local::now(), ProbeState::Alive, Duration::from_millis(250), Some(0), String::new()));
+        counters.record(&ProbeResult::new(Local::now(), ProbeState::Dead, Duration::from_millis(500), Some(1), String::new()));
+
+        let mut running = sample("ui-main", UnitState::RunningAndHealthy);
+        running.uptime = Some(Duration::from_secs(42));
+        running.probe_counters = Some(counters);
+        running.last_probe_duration = Some(Duration::from_millis(500));
+        running.proc_stat = ProcStat::read(std::process::id());
+
+        let output = render(&[running]);
+
+        assert!(output.contains("tsm_unit_uptime_seconds{unit=\"ui-main\"} 42\n"));
+        assert!(output.contains("tsm_unit_probe_success_total{unit=\"ui-main\",probe=\"liveness\"} 1\n"));
+        assert!(output.contains("tsm_unit_probe_failure_total{unit=\"ui-main\",probe=\"liveness\"} 1\n"));
+        assert!(output.contains("# TYPE tsm_unit_probe_duration_seconds summary\n"));
+        assert!(output.contains("tsm_unit_probe_duration_seconds_sum{unit=\"ui-main\",probe=\"liveness\"} 0.75\n"));
+        assert!(output.contains("tsm_unit_probe_duration_seconds_count{unit=\"ui-main\",probe=\"liveness\"} 2\n"));
+        assert!(output.contains("tsm_unit_probe_last_duration_seconds{unit=\"ui-main\",probe=\"liveness\"} 0.5\n"));
+        assert!(output.contains("tsm_unit_memory_rss_bytes{unit=\"ui-main\"} "));
+        assert!(output.contains("tsm_unit_cpu_seconds_total{unit=\"ui-main\"} "));
+    }
+
+    #[test]
+    fn escape_label_value_should_escape_quotes() {
+        assert_eq!(escape_label_value("a\"b\\c\nd"), "a\\\"b\\\\c\\nd");
+    }
+}
@@ -0,0 +1,312 @@
+use log::warn;
+use protobuf::Message;
+use users::{get_current_uid, get_user_by_uid, get_user_groups};
+use tsm_ipc::{Caller, tsm_unitman_rpc};
+use tsm_ipc::tsm_unitman_rpc::RpcMethod;
+
+
+/// Grants the methods on the units and targets to callers matching one of the principals. Empty
+/// method lists match everything, so do empty unit and target lists together.
+#[derive(Clone, Debug, Default)]
+pub struct AccessRule {
+    everyone: bool,
+    uids: Vec<u32>,
+    gids: Vec<u32>,
+    keys: Vec<String>,
+    methods: Vec<String>,
+    units: Vec<String>,
+    targets: Vec<String>,
+}
+
+
+/// What a request is about
+#[derive(Clone, Debug, PartialEq)]
+enum Subject {
+    Unit(String),
+    Target(String),
+    None,
+}
+
+
+impl AccessRule {
+    /// Applies to all callers until restricted
+    pub fn new(methods: Vec<String>, units: Vec<String>, targets: Vec<String>) -> AccessRule {
+        AccessRule {
+            everyone: true,
+            uids: Vec::new(),
+            gids: Vec::new(),
+            keys: Vec::new(),
+            methods,
+            units,
+            targets,
+        }
+    }
+
+    /// Only callers with one of the uids, gids or keys match afterwards, none if all are empty
+    pub fn restrict_to(&mut self, uids: Vec<u32>, gids: Vec<u32>, keys: Vec<String>) {
+        self.everyone = false;
+        self.uids = uids;
+        self.gids = gids;
+        self.keys = keys;
+    }
+
+    pub fn get_keys(&self) -> Vec<String> {
+        return self.keys.clone();
+    }
+
+    fn matches_caller(&self, caller: &Caller, gids: &[u32]) -> bool {
+        if self.everyone {
+            return true;
+        }
+
+        let uid_matches = caller.uid.is_some_and(|uid| self.uids.contains(&uid));
+        let gid_matches = gids.iter().any(|gid| self.gids.contains(gid));
+        let key_matches = caller.public_key.as_ref().is_some_and(|key| self.keys.contains(key));
+
+        return uid_matches || gid_matches || key_matches;
+    }
+
+    fn matches_method(&self, method: RpcMethod) -> bool {
+        let method_name = format!("{:?}", method);
+        return self.methods.is_empty() || self.methods.iter().any(|name| name.eq_ignore_ascii_case(&method_name));
+    }
+
+    /// Rules limited to some units or targets only grant requests which name one of them. Targets
+    /// are matched by their own patterns, since a target may start and stop any unit.
+    fn matches_subject(&self, subject: &Subject) -> bool {
+        if self.units.is_empty() && self.targets.is_empty() {
+            return true;
+        }
+
+        return match subject {
+            Subject::Unit(unit_name) => self.units.iter().any(|pattern| matches_pattern(unit_name, pattern)),
+            Subject::Target(target_name) => self.targets.iter().any(|pattern| matches_pattern(target_name, pattern)),
+            Subject::None => false,
+        };
+    }
+}
+
+
+/// Decides who may call which method on which unit. Without rules everybody may do everything,
+/// root and the user of the unit manager itself always may.
+#[derive(Clone, Debug, Default)]
+pub struct AccessControl {
+    rules: Vec<AccessRule>,
+}
+
+
+impl AccessControl {
+    pub fn new(rules: Vec<AccessRule>) -> AccessControl {
+        AccessControl {
+            rules,
+        }
+    }
+
+    /// Client keys which may connect with CURVE
+    pub fn get_keys(&self) -> Vec<String> {
+        return self.rules.iter().flat_map(|rule| rule.get_keys()).collect();
+    }
+
+    /// Denied requests are logged
+    pub fn is_allowed(&self, caller: &Caller, method: RpcMethod, data: &[u8]) -> bool {
+        if self.rules.is_empty() {
+            return true;
+        }
+
+        if caller.uid == Some(0) || caller.uid == Some(get_current_uid()) {
+            return true;
+        }
+
+        let gids = get_groups(caller);
+        let subject = get_subject(method, data);
+
+        let allowed = self.rules.iter().any(|rule| {
+            rule.matches_caller(caller, &gids) && rule.matches_method(method) && rule.matches_subject(&subject)
+        });
+
+        if !allowed {
+            let name = match &subject {
+                Subject::Unit(name) | Subject::Target(name) => name.as_str(),
+                Subject::None => "-",
+            };
+            warn!("Denied {:?} on {} for {}", method, name, caller);
+        }
+
+        return allowed;
+    }
+}
+
+
+/// The primary group from the credentials and the supplementary groups of the user
+fn get_groups(caller: &Caller) -> Vec<u32> {
+    let mut gids: Vec<u32> = caller.gid.into_iter().collect();
+
+    if let (Some(uid), Some(gid)) = (caller.uid, caller.gid) {
+        if let Some(user) = get_user_by_uid(uid) {
+            let groups = get_user_groups(user.name(), gid).unwrap_or_default();
+            gids.extend(groups.iter().map(|group| group.gid()));
+        }
+    }
+
+    return gids;
+}
+
+
+/// The unit or target the request is about
+fn get_subject(method: RpcMethod, data: &[u8]) -> Subject {
+    let target_name = match method {
+        RpcMethod::StartTarget => Some(tsm_unitman_rpc::StartTargetRequest::parse_from_bytes(data).map(|request| request.target_name)),
+        RpcMethod::StopTarget => Some(tsm_unitman_rpc::StopTargetRequest::parse_from_bytes(data).map(|request| request.target_name)),
+        RpcMethod::IsolateTarget => Some(tsm_unitman_rpc::IsolateTargetRequest::parse_from_bytes(data).map(|request| request.target_name)),
+        _ => None,
+    };
+
+    if let Some(target_name) = target_name {
+        return match target_name {
+            Ok(target_name) => Subject::Target(target_name),
+            Err(_) => Subject::None,
+        };
+    }
+
+    let unit_name = match method {
+        RpcMethod::StartUnit => tsm_unitman_rpc::StartUnitRequest::parse_from_bytes(data).map(|request| request.unit_name),
+        RpcMethod::StopUnit => tsm_unitman_rpc::StopUnitRequest::parse_from_bytes(data).map(|request| request.unit_name),
+        RpcMethod::RestartUnit => tsm_unitman_rpc::RestartUnitRequest::parse_from_bytes(data).map(|request| request.unit_name),
+        RpcMethod::ReloadUnit => tsm_unitman_rpc::ReloadUnitRequest::parse_from_bytes(data).map(|request| request.unit_name),
+        RpcMethod::KillUnit => tsm_unitman_rpc::KillUnitRequest::parse_from_bytes(data).map(|request| request.unit_name),
+        RpcMethod::ResetFailed => tsm_unitman_rpc::ResetFailedRequest::parse_from_bytes(data).map(|request| request.unit_name),
+        RpcMethod::EnableUnit => tsm_unitman_rpc::EnableUnitRequest::parse_from_bytes(data).map(|request| request.unit_name),
+        RpcMethod::DisableUnit => tsm_unitman_rpc::DisableUnitRequest::parse_from_bytes(data).map(|request| request.unit_name),
+        RpcMethod::MaskUnit => tsm_unitman_rpc::MaskUnitRequest::parse_from_bytes(data).map(|request| request.unit_name),
+        RpcMethod::UnmaskUnit => tsm_unitman_rpc::UnmaskUnitRequest::parse_from_bytes(data).map(|request| request.unit_name),
+        RpcMethod::GetUnitStatus => tsm_unitman_rpc::GetUnitStatusRequest::parse_from_bytes(data).map(|request| request.unit_name),
+        RpcMethod::WaitUnit => tsm_unitman_rpc::WaitUnitRequest::parse_from_bytes(data).map(|request| request.unit_name),
+        _ => return Subject::None,
+    };
+
+    return match unit_name {
+        Ok(unit_name) => Subject::Unit(unit_name),
+        Err(_) => Subject::None,
+    };
+}
+
+
+/// Glob pattern where * matches any number of characters and ? a single one
+fn matches_pattern(text: &str, pattern: &str) -> bool {
+    let text: Vec<char> = text.chars().collect();
+    let pattern: Vec<char> = pattern.chars().collect();
+
+    fn matches(text: &[char], pattern: &[char]) -> bool {
+        return match pattern.split_first() {
+            None => text.is_empty(),
+            Some((&'*', rest)) => (0..=text.len()).any(|index| matches(&text[index..], rest)),
+            Some((&'?', rest)) => !text.is_empty() && matches(&text[1..], rest),
+            Some((character, rest)) => text.first() == Some(character) && matches(&text[1..], rest),
+        };
+    }
+
+    return matches(&text, &pattern);
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn build_request(unit_name: &str) -> Vec<u8> {
+        let mut request = tsm_unitman_rpc::RestartUnitRequest::new();
+        request.unit_name = String::from(unit_name);
+        request.write_to_bytes().unwrap()
+    }
+
+    /// Ids nobody on the test machine has
+    fn build_caller(uid: u32, gid: u32) -> Caller {
+        Caller::from_credentials(uid, gid, 4242)
+    }
+
+    fn build_rule(uids: Vec<u32>, gids: Vec<u32>, keys: Vec<&str>, methods: Vec<&str>, units: Vec<&str>) -> AccessRule {
+        let mut rule = AccessRule::new(methods.into_iter().map(String::from).collect(), units.into_iter().map(String::from).collect(), Vec::new());
+        rule.restrict_to(uids, gids, keys.into_iter().map(String::from).collect());
+        rule
+    }
+
+    #[test]
+    fn is_allowed_without_rules_should_allow_everything() {
+        let access_control = AccessControl::new(Vec::new());
+
+        assert!(access_control.is_allowed(&Caller::anonymous(), RpcMethod::ReExec, &[]));
+    }
+
+    #[test]
+    fn is_allowed_should_limit_group_to_methods_and_units() {
+        let rule = build_rule(Vec::new(), vec![54321], Vec::new(), vec!["RestartUnit"], vec!["ui*"]);
+        let access_control = AccessControl::new(vec![rule]);
+
+        assert!(access_control.is_allowed(&build_caller(54321, 54321), RpcMethod::RestartUnit, &build_request("ui-main")));
+        assert!(!access_control.is_allowed(&build_caller(54321, 54321), RpcMethod::RestartUnit, &build_request("network")));
+        assert!(!access_control.is_allowed(&build_caller(54321, 54321), RpcMethod::StopUnit, &build_request("ui-main")));
+        assert!(!access_control.is_allowed(&build_caller(54321, 12345), RpcMethod::RestartUnit, &build_request("ui-main")));
+        assert!(!access_control.is_allowed(&Caller::anonymous(), RpcMethod::RestartUnit, &build_request("ui-main")));
+    }
+
+    #[test]
+    fn is_allowed_should_match_key() {
+        let rule = build_rule(Vec::new(), Vec::new(), vec!["client-key"], Vec::new(), Vec::new());
+        let access_control = AccessControl::new(vec![rule]);
+
+        assert!(access_control.is_allowed(&Caller::from_public_key(String::from("client-key")), RpcMethod::ReExec, &[]));
+        assert!(!access_control.is_allowed(&Caller::from_public_key(String::from("other-key")), RpcMethod::ReExec, &[]));
+    }
+
+    #[test]
+    fn is_allowed_should_only_grant_requests_without_unit_by_rules_without_units() {
+        let rules = vec![
+            build_rule(vec![54321], Vec::new(), Vec::new(), Vec::new(), vec!["ui*"]),
+            build_rule(vec![54321], Vec::new(), Vec::new(), vec!["listunits"], Vec::new()),
+        ];
+        let access_control = AccessControl::new(rules);
+
+        assert!(access_control.is_allowed(&build_caller(54321, 54321), RpcMethod::ListUnits, &[]));
+        assert!(!access_control.is_allowed(&build_caller(54321, 54321), RpcMethod::ReExec, &[]));
+    }
+
+    #[test]
+    fn is_allowed_should_match_targets_by_their_own_patterns() {
+        let build_target_request = |target_name: &str| {
+            let mut request = tsm_unitman_rpc::IsolateTargetRequest::new();
+            request.target_name = String::from(target_name);
+            request.write_to_bytes().unwrap()
+        };
+
+        let mut target_rule = AccessRule::new(Vec::new(), Vec::new(), vec![String::from("maintenance")]);
+        target_rule.restrict_to(vec![54321], Vec::new(), Vec::new());
+        let rules = vec![
+            build_rule(vec![54321], Vec::new(), Vec::new(), Vec::new(), vec!["ui*"]),
+            target_rule,
+        ];
+        let access_control = AccessControl::new(rules);
+        let caller = build_caller(54321, 54321);
+
+        // a target named like the units may still start and stop any unit
+        assert!(!access_control.is_allowed(&caller, RpcMethod::IsolateTarget, &build_target_request("ui-everything")));
+        assert!(access_control.is_allowed(&caller, RpcMethod::IsolateTarget, &build_target_request("maintenance")));
+        assert!(!access_control.is_allowed(&caller, RpcMethod::RestartUnit, &build_request("maintenance")));
+        assert!(access_control.is_allowed(&caller, RpcMethod::RestartUnit, &build_request("ui-main")));
+    }
+
+    #[test]
+    fn matches_pattern_should_support_wildcards() {
+        assert!(matches_pattern("ui-main", "ui*"));
+        assert!(matches_pattern("ui", "ui*"));
+        assert!(matches_pattern("ui1", "ui?"));
+        assert!(!matches_pattern("gui", "ui*"));
+    }
+
+    #[test]
+    fn is_allowed_should_deny_everyone_if_no_principal_is_known() {
+        let access_control = AccessControl::new(vec![build_rule(Vec::new(), Vec::new(), Vec::new(), Vec::new(), Vec::new())]);
+
+        assert!(!access_control.is_allowed(&build_caller(54321, 54321), RpcMethod::ListUnits, &[]));
+        assert!(AccessControl::new(vec![AccessRule::new(Vec::new(), Vec::new(), Vec::new())]).is_allowed(&Caller::anonymous(), RpcMethod::ListUnits, &[]));
+    }
+}
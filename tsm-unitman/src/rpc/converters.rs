@@ -1,6 +1,8 @@
+use std::os::unix::process::ExitStatusExt;
 use log::{warn};
-use tsm_ipc::tsm_unitman_rpc;
-use protobuf::EnumOrUnknown;
+use tsm_ipc::{tsm_unitman_event, tsm_unitman_rpc};
+use protobuf::{EnumOrUnknown, MessageField};
+use users::{get_group_by_gid, get_user_by_uid};
 
 use crate::unit;
 
@@ -17,8 +19,29 @@ pub fn convert_units_to_proto(units: &Vec<unit::UnitRef>) -> Vec<tsm_unitman_rpc
 }
 
 
-pub fn convert_unit_to_proto(unit: &unit::UnitRef) -> Result<tsm_unitman_rpc::Unit, String> {
-    match unit.try_lock() {
+pub fn convert_targets_to_proto(unit_manager: &unit::UnitManager) -> Vec<tsm_unitman_rpc::Target> {
+    let mut proto_targets = Vec::new();
+
+    for target in unit_manager.get_targets() {
+        let mut proto_target = tsm_unitman_rpc::Target::new();
+
+        proto_target.name = target.get_name();
+        proto_target.wants = target.get_wants();
+
+        match unit_manager.resolve_target(&target.get_name()) {
+            Ok(units) => proto_target.units = units,
+            Err(error) => warn!("{}", error),
+        }
+
+        proto_targets.push(proto_target);
+    }
+
+    proto_targets
+}
+
+
+pub fn convert_unit_to_proto(unit: &unit::UnitRef) -> Result<tsm_unitman_rpc::Unit, unit::UnitError> {
+    match unit::lock(unit) {
         Ok(unit) => {
             let mut proto_unit = tsm_unitman_rpc::Unit::new();
 
@@ -29,6 +52,8 @@ pub fn convert_unit_to_proto(unit: &unit::UnitRef) -> Result<tsm_unitman_rpc::Un
             proto_unit.uid = unit.get_uid() as i32;
             proto_unit.gid = unit.get_gid() as i32;
             proto_unit.enabled = unit.is_enabled();
+            proto_unit.masked = unit.is_masked();
+            proto_unit.restart_count = unit.get_restart_count();
             proto_unit.process_probe_state = EnumOrUnknown::from_i32(unit.get_process_probe_state().clone() as i32);
             proto_unit.liveness_probe_state = EnumOrUnknown::from_i32(unit.get_liveness_probe_state().clone() as i32);
             proto_unit.state = EnumOrUnknown::from_i32(unit.get_state().clone() as i32);
@@ -43,10 +68,277 @@ pub fn convert_unit_to_proto(unit: &unit::UnitRef) -> Result<tsm_unitman_rpc::Un
                 None => proto_unit.uptime = 0,
             }
 
+            match unit.get_timer() {
+                Some(timer) => proto_unit.timer = MessageField::some(convert_timer_to_proto(&timer)),
+                None => proto_unit.timer = MessageField::none(),
+            }
+
+            match unit.get_path_trigger() {
+                Some(path_trigger) => proto_unit.path_trigger = MessageField::some(convert_path_trigger_to_proto(path_trigger)),
+                None => proto_unit.path_trigger = MessageField::none(),
+            }
+
             Ok(proto_unit)
         },
-        Err(_) => {
-            return Err("Failed to lock unit".to_string());
+        Err(e) => Err(e),
+    }
+}
+
+
+/// Everything known about a unit, including the last lines of its output
+pub fn convert_unit_status_to_proto(unit_manager: &unit::UnitManager, unit_name: &str, lines: usize) -> Result<tsm_unitman_rpc::UnitStatus, unit::UnitError> {
+    let unit = match unit_manager.get_unit(unit_name) {
+        Some(unit) => unit,
+        None => return Err(unit::UnitError::UnitNotFound(unit_name.to_string())),
+    };
+
+    let mut proto_status = tsm_unitman_rpc::UnitStatus::new();
+    proto_status.unit = MessageField::some(convert_unit_to_proto(&unit)?);
+
+    proto_status.wanted_by = unit_manager.get_targets().iter()
+        .filter(|target| target.get_wants().iter().any(|want| want == unit_name))
+        .map(|target| target.get_name())
+        .collect();
+
+    match unit::lock(&unit) {
+        Ok(unit) => {
+            proto_status.user = match get_user_by_uid(unit.get_uid()) {
+                Some(user) => user.name().to_string_lossy().to_string(),
+                None => String::new(),
+            };
+
+            proto_status.group = match get_group_by_gid(unit.get_gid()) {
+                Some(group) => group.name().to_string_lossy().to_string(),
+                None => String::new(),
+            };
+
+            for dependency in unit.get_dependencies() {
+                match unit::lock(&dependency) {
+                    Ok(dependency) => proto_status.dependencies.push(dependency.get_name()),
+                    Err(e) => return Err(e.context(format!("Failed to lock dependency of unit {}", unit_name))),
+                }
+            }
+
+            match unit.get_reload_signal() {
+                Some(signal) => proto_status.reload_signal = signal.to_string(),
+                None => proto_status.reload_signal = String::new(),
+            }
+
+            proto_status.reload_command = unit.get_reload_command();
+
+            match unit.get_liveness_probe() {
+                Some(probe) => {
+                    let mut proto_probe = tsm_unitman_rpc::unit_status::LivenessProbe::new();
+                    proto_probe.executable = probe.get_executable();
+                    proto_probe.arguments = probe.get_arguments();
+                    proto_probe.interval_s = probe.get_interval_s();
+                    proto_probe.timeout_s = probe.get_timeout_s();
+                    proto_status.liveness_probe = MessageField::some(proto_probe);
+                },
+                None => proto_status.liveness_probe = MessageField::none(),
+            }
+
+            proto_status.sandbox = unit.get_sandbox().describe();
+
+            match unit.get_last_exit_status() {
+                Some(exit_status) => {
+                    proto_status.has_exit_status = true;
+                    proto_status.exit_code = exit_status.code().unwrap_or(-1);
+
+                    match exit_status.signal() {
+                        Some(signal) => proto_status.exit_signal = unit::Signal::from_number(signal).to_string(),
+                        None => proto_status.exit_signal = String::new(),
+                    }
+                },
+                None => proto_status.has_exit_status = false,
+            }
+
+            match unit.get_pid() {
+                Some(pid) => {
+                    for entry in unit::ProcessTreeEntry::collect(pid) {
+                        let mut proto_process = tsm_unitman_rpc::unit_status::Process::new();
+                        proto_process.pid = entry.get_pid() as i32;
+                        proto_process.depth = entry.get_depth() as u32;
+                        proto_process.command = entry.get_command();
+                        proto_status.processes.push(proto_process);
+                    }
+                },
+                None => {},
+            }
+
+            for transition in unit.get_transitions() {
+                let mut proto_transition = tsm_unitman_rpc::unit_status::StateTransition::new();
+                proto_transition.timestamp = transition.get_timestamp().timestamp_millis();
+                proto_transition.from = EnumOrUnknown::from_i32(transition.get_from() as i32);
+                proto_transition.to = EnumOrUnknown::from_i32(transition.get_to() as i32);
+                proto_transition.reason = transition.get_reason();
+                proto_status.transitions.push(proto_transition);
+            }
+
+            for result in unit.get_probe_results() {
+                let mut proto_result = tsm_unitman_rpc::unit_status::ProbeResult::new();
+                proto_result.timestamp = result.get_timestamp().timestamp_millis();
+                proto_result.state = EnumOrUnknown::from_i32(result.get_state() as i32);
+                proto_result.duration_ms = result.get_duration().as_millis() as u64;
+
+                match result.get_exit_code() {
+                    Some(exit_code) => {
+                        proto_result.has_exit_code = true;
+                        proto_result.exit_code = exit_code;
+                    },
+                    None => proto_result.has_exit_code = false,
+                }
+
+                proto_result.message = result.get_message();
+                proto_status.probe_results.push(proto_result);
+            }
+
+            proto_status.output = unit.get_output(lines);
         },
+        Err(e) => return Err(e),
+    }
+
+    Ok(proto_status)
+}
+
+
+pub fn convert_proto_to_unit_state(state: tsm_unitman_rpc::unit::UnitState) -> unit::UnitState {
+    return match state {
+        tsm_unitman_rpc::unit::UnitState::Starting => unit::UnitState::Starting,
+        tsm_unitman_rpc::unit::UnitState::Running => unit::UnitState::Running,
+        tsm_unitman_rpc::unit::UnitState::RunningAndHealthy => unit::UnitState::RunningAndHealthy,
+        tsm_unitman_rpc::unit::UnitState::RunningButDegraded => unit::UnitState::RunningButDegraded,
+        tsm_unitman_rpc::unit::UnitState::Stopping => unit::UnitState::Stopping,
+        tsm_unitman_rpc::unit::UnitState::Stopped => unit::UnitState::Stopped,
+        tsm_unitman_rpc::unit::UnitState::Failed => unit::UnitState::Failed,
+    };
+}
+
+
+pub fn convert_proto_to_probe_state(state: tsm_unitman_rpc::unit::ProbeState) -> unit::ProbeState {
+    return match state {
+        tsm_unitman_rpc::unit::ProbeState::Undefined => unit::ProbeState::Undefined,
+        tsm_unitman_rpc::unit::ProbeState::Alive => unit::ProbeState::Alive,
+        tsm_unitman_rpc::unit::ProbeState::Dead => unit::ProbeState::Dead,
+    };
+}
+
+
+pub fn convert_timer_to_proto(timer: &unit::Timer) -> tsm_unitman_rpc::unit::Timer {
+    let mut proto_timer = tsm_unitman_rpc::unit::Timer::new();
+
+    proto_timer.on_calendar = timer.get_on_calendar().unwrap_or(String::new());
+
+    match timer.get_next_trigger() {
+        Some(next_trigger) => proto_timer.next_trigger = next_trigger.timestamp(),
+        None => proto_timer.next_trigger = 0,
+    }
+
+    match timer.get_last_trigger() {
+        Some(last_trigger) => proto_timer.last_trigger = last_trigger.timestamp(),
+        None => proto_timer.last_trigger = 0,
+    }
+
+    proto_timer
+}
+
+
+pub fn convert_path_trigger_to_proto(path_trigger: &unit::PathTrigger) -> tsm_unitman_rpc::unit::PathTrigger {
+    let mut proto_path_trigger = tsm_unitman_rpc::unit::PathTrigger::new();
+
+    for condition in path_trigger.get_conditions() {
+        let path = condition.get_path().to_string_lossy().to_string();
+
+        match condition {
+            unit::PathCondition::Exists(_) => proto_path_trigger.on_path_exists.push(path),
+            unit::PathCondition::Changed(_) => proto_path_trigger.on_path_changed.push(path),
+            unit::PathCondition::DirectoryNotEmpty(_) => proto_path_trigger.on_directory_not_empty.push(path),
+        }
+    }
+
+    let state = match path_trigger.get_state() {
+        unit::PathTriggerState::Waiting => tsm_unitman_rpc::unit::path_trigger::State::Waiting,
+        unit::PathTriggerState::Pending => tsm_unitman_rpc::unit::path_trigger::State::Pending,
+        unit::PathTriggerState::RateLimited => tsm_unitman_rpc::unit::path_trigger::State::RateLimited,
+    };
+    proto_path_trigger.state = EnumOrUnknown::new(state);
+
+    match path_trigger.get_last_trigger() {
+        Some(last_trigger) => proto_path_trigger.last_trigger = last_trigger.timestamp(),
+        None => proto_path_trigger.last_trigger = 0,
     }
-}
\ No newline at end of file
+
+    match path_trigger.get_last_path() {
+        Some(last_path) => proto_path_trigger.last_path = last_path.to_string_lossy().to_string(),
+        None => proto_path_trigger.last_path = String::new(),
+    }
+
+    proto_path_trigger.trigger_count = path_trigger.get_trigger_count();
+
+    proto_path_trigger
+}
+
+
+/// Returns the topic the event is published with, too
+pub fn convert_event_to_proto(event: &unit::UnitEvent) -> (&'static str, tsm_unitman_event::Event) {
+    let mut proto_event = tsm_unitman_event::Event::new();
+
+    proto_event.timestamp = event.get_timestamp().timestamp_millis();
+    proto_event.unit_name = event.get_unit_name();
+
+    let topic = match event.get_kind() {
+        unit::UnitEventKind::Started { pid } => {
+            let mut unit_started = tsm_unitman_event::UnitStarted::new();
+            unit_started.pid = pid as i32;
+            proto_event.set_unit_started(unit_started);
+            "unit_started"
+        },
+        unit::UnitEventKind::Exited { exit_code, signal } => {
+            let mut unit_exited = tsm_unitman_event::UnitExited::new();
+
+            match exit_code {
+                Some(exit_code) => {
+                    unit_exited.has_exit_code = true;
+                    unit_exited.exit_code = exit_code;
+                },
+                None => unit_exited.has_exit_code = false,
+            }
+
+            match signal {
+                Some(signal) => unit_exited.signal = signal.to_string(),
+                None => unit_exited.signal = String::new(),
+            }
+
+            proto_event.set_unit_exited(unit_exited);
+            "unit_exited"
+        },
+        unit::UnitEventKind::ProbeChanged { probe, from, to } => {
+            let mut probe_changed = tsm_unitman_event::ProbeChanged::new();
+
+            let probe = match probe {
+                unit::ProbeType::Process => tsm_unitman_event::probe_changed::Probe::Process,
+                unit::ProbeType::Liveness => tsm_unitman_event::probe_changed::Probe::Liveness,
+            };
+            probe_changed.probe = EnumOrUnknown::new(probe);
+            probe_changed.from = EnumOrUnknown::from_i32(from as i32);
+            probe_changed.to = EnumOrUnknown::from_i32(to as i32);
+
+            proto_event.set_probe_changed(probe_changed);
+            "probe_changed"
+        },
+        unit::UnitEventKind::Failed { reason } => {
+            let mut unit_failed = tsm_unitman_event::UnitFailed::new();
+            unit_failed.reason = reason;
+            proto_event.set_unit_failed(unit_failed);
+            "unit_failed"
+        },
+        unit::UnitEventKind::ConfigReloaded { unit_count } => {
+            let mut config_reloaded = tsm_unitman_event::ConfigReloaded::new();
+            config_reloaded.unit_count = unit_count as u32;
+            proto_event.set_config_reloaded(config_reloaded);
+            "config_reloaded"
+        },
+    };
+
+    (topic, proto_event)
+}
@@ -0,0 +1,25 @@
+use tsm_ipc::{tsm_unitman_rpc, RpcError};
+use log::debug;
+
+use crate::unit;
+use crate::rpc::error_response;
+use crate::rpc::unit_manager_snapshot;
+
+
+pub fn handle_enable_unit(request: tsm_unitman_rpc::EnableUnitRequest, unit_manager: &unit::UnitManagerRef) -> Result<tsm_unitman_rpc::EnableUnitResponse, RpcError> {
+    debug!("Received enable unit request: {} (now: {})", request.unit_name, request.now);
+
+    let unit_manager = unit_manager_snapshot::snapshot_unit_manager(unit_manager)?;
+
+    return match unit_manager.enable_unit(request.unit_name.clone(), request.now) {
+        Ok(_) => {
+            let mut response = tsm_unitman_rpc::EnableUnitResponse::new();
+            response.message = match request.now {
+                true => format!("Unit {} enabled and started", request.unit_name),
+                false => format!("Unit {} enabled", request.unit_name),
+            };
+            Ok(response)
+        },
+        Err(error) => Err(error_response::unit_error("Failed to enable unit", &error)),
+    };
+}
@@ -0,0 +1,73 @@
+use tsm_ipc::{tsm_common_rpc, RpcError};
+use tsm_ipc::tsm_common_rpc::error::Code;
+use protobuf::{EnumOrUnknown, MessageField};
+
+use crate::unit::UnitError;
+
+
+/// Marks the response as failed. The message is also put into the plain error field for
+/// clients which don't know about error codes yet.
+pub fn set_error(rpc_response: &mut tsm_common_rpc::RpcResponse, code: Code, message: String, details: Vec<String>) {
+    let mut error = tsm_common_rpc::Error::new();
+    error.code = EnumOrUnknown::new(code);
+    error.message = message.clone();
+    error.details = details;
+
+    rpc_response.status = false;
+    rpc_response.error = message;
+    rpc_response.error_info = MessageField::some(error);
+}
+
+
+/// The code is taken from the root cause of the error
+pub fn unit_error(context: &str, error: &UnitError) -> RpcError {
+    let details = match error {
+        UnitError::Multiple(_, errors) => errors.iter().map(|error| error.to_string()).collect(),
+        _ => match error.root_cause() {
+            UnitError::DependencyFailed(_, cause) => vec![cause.to_string()],
+            root_cause if root_cause != error => vec![root_cause.to_string()],
+            _ => Vec::new(),
+        },
+    };
+
+    return RpcError::Server {
+        code: convert_unit_error_to_code(error),
+        message: format!("{}: {}", context, error),
+        details,
+    };
+}
+
+
+fn convert_unit_error_to_code(error: &UnitError) -> Code {
+    return match error.root_cause() {
+        UnitError::UnitNotFound(_) | UnitError::TargetNotFound(_) => Code::NotFound,
+        UnitError::Masked(_) => Code::Masked,
+        UnitError::Disabled(_) => Code::Disabled,
+        UnitError::AlreadyRunning(_) => Code::AlreadyRunning,
+        UnitError::NotRunning(_) => Code::NotRunning,
+        UnitError::InvalidArgument(_) => Code::InvalidRequest,
+        UnitError::PermissionDenied(_) => Code::PermissionDenied,
+        UnitError::DependencyFailed(_, _) => Code::DependencyFailed,
+        UnitError::Timeout(_) => Code::Timeout,
+        UnitError::Busy(_) => Code::Busy,
+        UnitError::Failed(_) => Code::Failed,
+        UnitError::Context(_, _) | UnitError::Multiple(_, _) => Code::Unknown,
+    };
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unit_error_should_use_code_of_root_cause() {
+        let error = UnitError::Masked(String::from("foo")).context(String::from("Error starting unit foo"));
+
+        assert_eq!(unit_error("Failed to start unit", &error), RpcError::Server {
+            code: Code::Masked,
+            message: String::from("Failed to start unit: Error starting unit foo: Unit foo is masked"),
+            details: vec![String::from("Unit foo is masked")],
+        });
+    }
+}
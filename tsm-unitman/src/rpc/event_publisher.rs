@@ -0,0 +1,63 @@
+use std::sync::mpsc;
+use std::thread;
+use std::thread::JoinHandle;
+use std::time::Duration;
+use log::{debug, error, warn};
+
+use crate::unit;
+use crate::rpc::converters;
+
+
+/// Subscribers waiting for the socket reconnect within 100 ms by default. Events are held back
+/// meanwhile, so that they don't miss the first events after startup or a re-exec.
+const RECONNECT_GRACE_PERIOD: Duration = Duration::from_millis(300);
+
+/// Publishes the events of the unit manager and its units. Events are passed through a channel,
+/// since they happen in many threads while the socket may only be used by one.
+pub struct EventPublisher {
+    bind_address: String,
+    receiver: mpsc::Receiver<unit::UnitEvent>,
+}
+
+
+impl EventPublisher {
+    /// Returns the publisher and the sender to hand to the unit manager
+    pub fn new(bind_address: String) -> (EventPublisher, unit::EventSender) {
+        let (sender, receiver) = mpsc::channel();
+
+        let event_publisher = EventPublisher {
+            bind_address,
+            receiver,
+        };
+
+        (event_publisher, unit::EventSender::new(sender))
+    }
+
+    pub fn run_threaded(self) -> JoinHandle<()> {
+        thread::spawn(move || {
+            self.run();
+        })
+    }
+
+    fn run(self) {
+        let publisher = match tsm_ipc::EventPublisher::new(self.bind_address.clone()) {
+            Ok(publisher) => publisher,
+            Err(error) => {
+                error!("Failed to start event publisher: {}", error);
+                return;
+            },
+        };
+
+        debug!("Publishing events on {}", self.bind_address);
+        thread::sleep(RECONNECT_GRACE_PERIOD);
+
+        for event in self.receiver {
+            let (topic, proto_event) = converters::convert_event_to_proto(&event);
+
+            match publisher.publish(topic, &proto_event) {
+                Ok(_) => debug!("Published event {}: {:?}", topic, event),
+                Err(error) => warn!("Failed to publish event {}: {}", topic, error),
+            }
+        }
+    }
+}
@@ -0,0 +1,26 @@
+use tsm_ipc::{tsm_unitman_rpc, RpcError};
+use protobuf::MessageField;
+use log::debug;
+
+use crate::unit;
+use crate::rpc::error_response;
+use crate::rpc::unit_manager_snapshot;
+use crate::rpc::converters;
+
+
+pub fn handle_get_unit_status(request: tsm_unitman_rpc::GetUnitStatusRequest, unit_manager: &unit::UnitManagerRef) -> Result<tsm_unitman_rpc::GetUnitStatusResponse, RpcError> {
+    debug!("Received get unit status request: {}", request.unit_name);
+
+    let unit_manager = unit_manager_snapshot::snapshot_unit_manager(unit_manager)?;
+    let lines = request.lines as usize;
+
+    let status = match converters::convert_unit_status_to_proto(&unit_manager, &request.unit_name, lines) {
+        Ok(status) => status,
+        Err(error) => return Err(error_response::unit_error("Failed to get unit status", &error)),
+    };
+
+    let mut get_unit_status_response = tsm_unitman_rpc::GetUnitStatusResponse::new();
+    get_unit_status_response.status = MessageField::some(status);
+
+    return Ok(get_unit_status_response);
+}
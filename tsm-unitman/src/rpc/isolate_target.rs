@@ -0,0 +1,22 @@
+use tsm_ipc::{tsm_unitman_rpc, RpcError};
+use log::debug;
+
+use crate::unit;
+use crate::rpc::error_response;
+use crate::rpc::unit_manager_snapshot;
+
+
+pub fn handle_isolate_target(request: tsm_unitman_rpc::IsolateTargetRequest, unit_manager: &unit::UnitManagerRef) -> Result<tsm_unitman_rpc::IsolateTargetResponse, RpcError> {
+    debug!("Received isolate target request: {}", request.target_name);
+
+    let unit_manager = unit_manager_snapshot::snapshot_unit_manager(unit_manager)?;
+
+    return match unit_manager.isolate_target(request.target_name.clone()) {
+        Ok(_) => {
+            let mut response = tsm_unitman_rpc::IsolateTargetResponse::new();
+            response.message = format!("Target {} isolated", request.target_name);
+            Ok(response)
+        },
+        Err(error) => Err(error_response::unit_error("Failed to isolate target", &error)),
+    };
+}
@@ -0,0 +1,29 @@
+use std::str::FromStr;
+use tsm_ipc::{tsm_unitman_rpc, RpcError};
+use tsm_ipc::tsm_common_rpc::error::Code;
+use log::debug;
+
+use crate::unit;
+use crate::rpc::error_response;
+use crate::rpc::unit_manager_snapshot;
+
+
+pub fn handle_kill_unit(request: tsm_unitman_rpc::KillUnitRequest, unit_manager: &unit::UnitManagerRef) -> Result<tsm_unitman_rpc::KillUnitResponse, RpcError> {
+    debug!("Received kill unit request: {} {} (group={})", request.unit_name, request.signal, request.group);
+
+    let signal = match unit::Signal::from_str(&request.signal) {
+        Ok(signal) => signal,
+        Err(error) => return Err(RpcError::server(Code::InvalidRequest, format!("Failed to kill unit: {}", error))),
+    };
+
+    let unit_manager = unit_manager_snapshot::snapshot_unit_manager(unit_manager)?;
+
+    return match unit_manager.kill_unit(request.unit_name.clone(), signal, request.group) {
+        Ok(_) => {
+            let mut response = tsm_unitman_rpc::KillUnitResponse::new();
+            response.message = format!("Sent {} to unit {}", signal, request.unit_name);
+            Ok(response)
+        },
+        Err(error) => Err(error_response::unit_error("Failed to kill unit", &error)),
+    };
+}
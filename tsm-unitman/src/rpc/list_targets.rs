@@ -0,0 +1,20 @@
+use tsm_ipc::{tsm_unitman_rpc, RpcError};
+use log::debug;
+
+use crate::unit;
+use crate::rpc::unit_manager_snapshot;
+use crate::rpc::converters;
+
+
+pub fn handle_list_targets(_request: tsm_unitman_rpc::ListTargetsRequest, unit_manager: &unit::UnitManagerRef) -> Result<tsm_unitman_rpc::ListTargetsResponse, RpcError> {
+    debug!("Received list targets request");
+
+    let unit_manager = unit_manager_snapshot::snapshot_unit_manager(unit_manager)?;
+
+    let mut list_targets_response = tsm_unitman_rpc::ListTargetsResponse::new();
+    list_targets_response.targets = converters::convert_targets_to_proto(&unit_manager);
+    list_targets_response.active_target = unit_manager.get_active_target().unwrap_or(String::new());
+    list_targets_response.default_target = unit_manager.get_default_target().unwrap_or(String::new());
+
+    return Ok(list_targets_response);
+}
@@ -0,0 +1,25 @@
+use tsm_ipc::{tsm_unitman_rpc, RpcError};
+use log::debug;
+
+use crate::unit;
+use crate::rpc::error_response;
+use crate::rpc::unit_manager_snapshot;
+
+
+pub fn handle_mask_unit(request: tsm_unitman_rpc::MaskUnitRequest, unit_manager: &unit::UnitManagerRef) -> Result<tsm_unitman_rpc::MaskUnitResponse, RpcError> {
+    debug!("Received mask unit request: {} (now: {})", request.unit_name, request.now);
+
+    let unit_manager = unit_manager_snapshot::snapshot_unit_manager(unit_manager)?;
+
+    return match unit_manager.mask_unit(request.unit_name.clone(), request.now) {
+        Ok(_) => {
+            let mut response = tsm_unitman_rpc::MaskUnitResponse::new();
+            response.message = match request.now {
+                true => format!("Unit {} masked and stopped", request.unit_name),
+                false => format!("Unit {} masked", request.unit_name),
+            };
+            Ok(response)
+        },
+        Err(error) => Err(error_response::unit_error("Failed to mask unit", &error)),
+    };
+}
@@ -1,8 +1,31 @@
 mod rpc_server;
 pub use rpc_server::RpcServer;
 
+mod event_publisher;
+pub use event_publisher::EventPublisher;
+
+mod access_control;
+pub use access_control::{AccessControl, AccessRule};
+
 mod converters;
+mod error_response;
+mod unit_manager_snapshot;
 mod ping;
 mod list_units;
 mod start_unit;
 mod stop_unit;
+mod list_targets;
+mod start_target;
+mod stop_target;
+mod isolate_target;
+mod enable_unit;
+mod disable_unit;
+mod mask_unit;
+mod unmask_unit;
+mod reexec;
+mod restart_unit;
+mod reload_unit;
+mod kill_unit;
+mod reset_failed;
+mod get_unit_status;
+mod wait_unit;
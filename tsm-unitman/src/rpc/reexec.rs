@@ -0,0 +1,27 @@
+use tsm_ipc::{tsm_unitman_rpc, RpcError};
+use log::debug;
+
+use crate::unit;
+use crate::rpc::error_response;
+use crate::rpc::unit_manager_snapshot;
+
+
+pub fn handle_reexec(request: tsm_unitman_rpc::ReExecRequest, unit_manager: &unit::UnitManagerRef) -> Result<tsm_unitman_rpc::ReExecResponse, RpcError> {
+    debug!("Received re-exec request: {}", request.executable);
+
+    let executable = match request.executable.is_empty() {
+        true => None,
+        false => Some(request.executable),
+    };
+
+    let unit_manager = unit_manager_snapshot::snapshot_unit_manager(unit_manager)?;
+
+    return match unit_manager.request_reexec(executable) {
+        Ok(_) => {
+            let mut response = tsm_unitman_rpc::ReExecResponse::new();
+            response.message = String::from("Re-exec requested, units keep running");
+            Ok(response)
+        },
+        Err(error) => Err(error_response::unit_error("Failed to re-exec", &error)),
+    };
+}
@@ -0,0 +1,22 @@
+use tsm_ipc::{tsm_unitman_rpc, RpcError};
+use log::debug;
+
+use crate::unit;
+use crate::rpc::error_response;
+use crate::rpc::unit_manager_snapshot;
+
+
+pub fn handle_reset_failed(request: tsm_unitman_rpc::ResetFailedRequest, unit_manager: &unit::UnitManagerRef) -> Result<tsm_unitman_rpc::ResetFailedResponse, RpcError> {
+    debug!("Received reset failed request: {}", request.unit_name);
+
+    let unit_manager = unit_manager_snapshot::snapshot_unit_manager(unit_manager)?;
+
+    return match unit_manager.reset_failed_unit(request.unit_name.clone()) {
+        Ok(_) => {
+            let mut response = tsm_unitman_rpc::ResetFailedResponse::new();
+            response.message = format!("Reset failed state of unit {}", request.unit_name);
+            Ok(response)
+        },
+        Err(error) => Err(error_response::unit_error("Failed to reset failed state", &error)),
+    };
+}
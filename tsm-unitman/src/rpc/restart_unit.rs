@@ -0,0 +1,22 @@
+use tsm_ipc::{tsm_unitman_rpc, RpcError};
+use log::debug;
+
+use crate::unit;
+use crate::rpc::error_response;
+use crate::rpc::unit_manager_snapshot;
+
+
+pub fn handle_restart_unit(request: tsm_unitman_rpc::RestartUnitRequest, unit_manager: &unit::UnitManagerRef) -> Result<tsm_unitman_rpc::RestartUnitResponse, RpcError> {
+    debug!("Received restart unit request: {}", request.unit_name);
+
+    let unit_manager = unit_manager_snapshot::snapshot_unit_manager(unit_manager)?;
+
+    return match unit_manager.restart_unit(request.unit_name.clone()) {
+        Ok(_) => {
+            let mut response = tsm_unitman_rpc::RestartUnitResponse::new();
+            response.message = format!("Unit {} restarted", request.unit_name);
+            Ok(response)
+        },
+        Err(error) => Err(error_response::unit_error("Failed to restart unit", &error)),
+    };
+}
@@ -1,73 +1,161 @@
-use std::sync::{Arc, Mutex};
+use std::sync::Arc;
 use log::warn;
 use protobuf::Enum;
 
-use tsm_ipc::{tsm_common_rpc, tsm_unitman_rpc};
-use crate::rpc::{stop_unit, start_unit, list_units, ping};
+use tsm_ipc::{Caller, RpcError, RpcRequestHandler, tsm_common_rpc};
+use tsm_ipc::tsm_common_rpc::error::Code;
+use tsm_ipc::tsm_unitman_rpc::RpcMethod;
+use crate::rpc::{stop_unit, start_unit, list_units, ping, list_targets, start_target, stop_target, isolate_target, enable_unit, disable_unit, mask_unit, unmask_unit, reexec, restart_unit, reload_unit, kill_unit, reset_failed, get_unit_status, wait_unit};
 
 use crate::unit;
+use crate::rpc::{error_response, AccessControl};
+
+
+/// Clients name it in their requests, so they can't talk to another service by mistake
+pub const SERVICE_NAME: &str = "tsm-unitman";
 
 
 pub struct RpcServer {
-    rpc_server: tsm_ipc::RpcServer,
+    unit_manager: unit::UnitManagerRef,
+    bind_address: String,
+    workers: usize,
+    access_control: AccessControl,
+    curve_secret_key: Option<String>,
 }
 
 
 impl RpcServer {
-    pub fn new(unit_manager: unit::UnitManagerRef, bind_address: String) -> Self {
-        let request_handler = Arc::new(Mutex::new(
-            ResponseHandler::new(unit_manager.clone())
-        ));
-        let rpc_server = tsm_ipc::RpcServer::new(bind_address.clone(), request_handler);
-
+    pub fn new(unit_manager: unit::UnitManagerRef, bind_address: String, workers: usize) -> Self {
         Self {
-            rpc_server,
+            unit_manager,
+            bind_address,
+            workers,
+            access_control: AccessControl::default(),
+            curve_secret_key: None,
         }
     }
 
-    pub fn run_threaded(self) {
-        self.rpc_server.run_threaded();
+    pub fn set_access_control(&mut self, access_control: AccessControl) {
+        self.access_control = access_control;
+    }
+
+    /// Only the client keys named in the access rules may connect, any key if there are none
+    pub fn set_curve_secret_key(&mut self, curve_secret_key: String) {
+        self.curve_secret_key = Some(curve_secret_key);
+    }
+
+    pub fn run_threaded(self) -> Result<(), tsm_ipc::RpcError> {
+        let authorized_keys = self.access_control.get_keys();
+        let request_handler = Arc::new(ResponseHandler::new(self.unit_manager, self.access_control, self.workers));
+
+        let mut rpc_server = tsm_ipc::RpcServer::new(self.bind_address, request_handler);
+        rpc_server.set_workers(self.workers);
+        rpc_server.set_service(build_service_description());
+
+        if let Some(curve_secret_key) = self.curve_secret_key {
+            rpc_server.set_curve(curve_secret_key, authorized_keys);
+        }
+
+        rpc_server.run_threaded()?;
+        Ok(())
     }
 }
 
 
+fn build_service_description() -> tsm_ipc::ServiceDescription {
+    let methods = RpcMethod::VALUES.iter()
+        .filter(|method| **method != RpcMethod::Unknown)
+        .map(|method| (method.value(), format!("{:?}", method)))
+        .collect();
+
+    tsm_ipc::ServiceDescription::new(String::from(SERVICE_NAME), String::from(env!("CARGO_PKG_VERSION")), methods)
+}
+
+
+/// Adding a method takes its proto messages, a handler and a line here
+fn build_service(unit_manager: &unit::UnitManagerRef, workers: usize) -> tsm_ipc::RpcService {
+    let mut service = tsm_ipc::RpcService::new();
+
+    service.register(RpcMethod::Ping.value(), ping::handle_ping);
+    service.register(RpcMethod::ListUnits.value(), with_unit_manager(unit_manager, list_units::handle_list_units));
+    service.register(RpcMethod::StartUnit.value(), with_unit_manager(unit_manager, start_unit::handle_start_unit));
+    service.register(RpcMethod::StopUnit.value(), with_unit_manager(unit_manager, stop_unit::handle_stop_unit));
+    service.register(RpcMethod::ListTargets.value(), with_unit_manager(unit_manager, list_targets::handle_list_targets));
+    service.register(RpcMethod::StartTarget.value(), with_unit_manager(unit_manager, start_target::handle_start_target));
+    service.register(RpcMethod::StopTarget.value(), with_unit_manager(unit_manager, stop_target::handle_stop_target));
+    service.register(RpcMethod::IsolateTarget.value(), with_unit_manager(unit_manager, isolate_target::handle_isolate_target));
+    service.register(RpcMethod::EnableUnit.value(), with_unit_manager(unit_manager, enable_unit::handle_enable_unit));
+    service.register(RpcMethod::DisableUnit.value(), with_unit_manager(unit_manager, disable_unit::handle_disable_unit));
+    service.register(RpcMethod::MaskUnit.value(), with_unit_manager(unit_manager, mask_unit::handle_mask_unit));
+    service.register(RpcMethod::UnmaskUnit.value(), with_unit_manager(unit_manager, unmask_unit::handle_unmask_unit));
+    service.register(RpcMethod::ReExec.value(), with_unit_manager(unit_manager, reexec::handle_reexec));
+    service.register(RpcMethod::RestartUnit.value(), with_unit_manager(unit_manager, restart_unit::handle_restart_unit));
+    service.register(RpcMethod::ReloadUnit.value(), with_unit_manager(unit_manager, reload_unit::handle_reload_unit));
+    service.register(RpcMethod::KillUnit.value(), with_unit_manager(unit_manager, kill_unit::handle_kill_unit));
+    service.register(RpcMethod::ResetFailed.value(), with_unit_manager(unit_manager, reset_failed::handle_reset_failed));
+    service.register(RpcMethod::GetUnitStatus.value(), with_unit_manager(unit_manager, get_unit_status::handle_get_unit_status));
+
+    let unit_manager_for_wait = unit_manager.clone();
+    let wait_slots = wait_unit::WaitSlots::new(workers);
+    service.register(RpcMethod::WaitUnit.value(), move |request| wait_unit::handle_wait_unit(request, &unit_manager_for_wait, &wait_slots));
+
+    return service;
+}
+
+
+/// Binds a handler to the unit manager, so that it can be registered
+fn with_unit_manager<Req, Resp>(
+    unit_manager: &unit::UnitManagerRef,
+    handler: fn(Req, &unit::UnitManagerRef) -> Result<Resp, RpcError>,
+) -> impl Fn(Req) -> Result<Resp, RpcError> + Send + Sync + 'static
+where
+    Req: 'static,
+    Resp: 'static,
+{
+    let unit_manager = unit_manager.clone();
+    move |request| handler(request, &unit_manager)
+}
+
+
+/// Checks the access rules before the request reaches the service
 struct ResponseHandler {
-    unit_manager: unit::UnitManagerRef,
+    service: tsm_ipc::RpcService,
+    access_control: AccessControl,
 }
 
 
-impl tsm_ipc::RpcRequestHandler for ResponseHandler {
-    fn handle_request(&self, request: tsm_common_rpc::RpcRequest) -> tsm_common_rpc::RpcResponse {
-        let request_method= match tsm_unitman_rpc::RpcMethod::from_i32(request.method) {
+impl RpcRequestHandler for ResponseHandler {
+    fn handle_request(&self, request: tsm_common_rpc::RpcRequest, caller: &Caller) -> tsm_common_rpc::RpcResponse {
+        let request_method = match RpcMethod::from_i32(request.method) {
             Some(method) => method,
-            None => tsm_unitman_rpc::RpcMethod::Unknown,
+            None => RpcMethod::Unknown,
         };
 
-        // Handle request based on method
-        match request_method {
-            tsm_unitman_rpc::RpcMethod::Ping => ping::handle_ping(request),
-            tsm_unitman_rpc::RpcMethod::ListUnits => list_units::handle_list_units(request, self.unit_manager.clone()),
-            tsm_unitman_rpc::RpcMethod::StartUnit => start_unit::handle_start_unit(request, self.unit_manager.clone()),
-            tsm_unitman_rpc::RpcMethod::StopUnit => stop_unit::handle_stop_unit(request, self.unit_manager.clone()),
-            _ => self.handle_unknown(),
+        if !self.access_control.is_allowed(caller, request_method, &request.data) {
+            return self.handle_denied(request_method);
+        }
+
+        if !self.service.has_method(request.method) {
+            warn!("Cannot handle unknown method {}", request.method);
         }
+
+        return self.service.handle_request(request, caller);
     }
 }
 
 
 impl ResponseHandler {
-    pub fn new(unit_manager: unit::UnitManagerRef) -> Self {
+    pub fn new(unit_manager: unit::UnitManagerRef, access_control: AccessControl, workers: usize) -> Self {
         Self {
-            unit_manager,
+            service: build_service(&unit_manager, workers),
+            access_control,
         }
     }
 
-    fn handle_unknown(&self) -> tsm_common_rpc::RpcResponse {
-        warn!("Cannot handle unknown method");
+    fn handle_denied(&self, method: RpcMethod) -> tsm_common_rpc::RpcResponse {
         let mut rpc_response = tsm_common_rpc::RpcResponse::new();
-        rpc_response.method = tsm_unitman_rpc::RpcMethod::Unknown.value();
-        rpc_response.status = false;
-        rpc_response.error = format!("Unknown method");
+        rpc_response.method = method.value();
+        error_response::set_error(&mut rpc_response, Code::PermissionDenied, format!("Permission denied for {:?}", method), Vec::new());
         return rpc_response;
     }
-}
\ No newline at end of file
+}
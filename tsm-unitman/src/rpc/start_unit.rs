@@ -1,47 +1,22 @@
-use tsm_ipc::{tsm_common_rpc, tsm_unitman_rpc};
-use protobuf::{Message, Enum};
-use log::{debug, warn};
+use tsm_ipc::{tsm_unitman_rpc, RpcError};
+use log::debug;
 
 use crate::unit;
+use crate::rpc::error_response;
+use crate::rpc::unit_manager_snapshot;
 
 
-pub fn handle_start_unit(request: tsm_common_rpc::RpcRequest, unit_manager: unit::UnitManagerRef) -> tsm_common_rpc::RpcResponse {
-    let mut rpc_response = tsm_common_rpc::RpcResponse::new();
+pub fn handle_start_unit(request: tsm_unitman_rpc::StartUnitRequest, unit_manager: &unit::UnitManagerRef) -> Result<tsm_unitman_rpc::StartUnitResponse, RpcError> {
+    debug!("Received start unit request: {}", request.unit_name);
 
-    let start_unit_request: tsm_unitman_rpc::StartUnitRequest = match Message::parse_from_bytes(&request.data) {
-        Ok(request) => {
-            rpc_response.method = tsm_unitman_rpc::RpcMethod::StartUnit.value();
-            request
-        },
-        Err(error) => {
-            warn!("Failed to parse start unit request: {}", error);
-            rpc_response.method = tsm_unitman_rpc::RpcMethod::StartUnit.value();
-            rpc_response.status = false;
-            rpc_response.error = format!("Failed to parse start unit request: {}", error);
-            return rpc_response;
-        },
-    };
-
-    debug!("Received start unit request: {}", start_unit_request.unit_name);
+    let unit_manager = unit_manager_snapshot::snapshot_unit_manager(unit_manager)?;
 
-    match unit_manager.try_lock() {
-        Ok(unit_manager) => {
-            match unit_manager.start_unit(String::from(start_unit_request.unit_name)) {
-                Ok(_) => {
-                    rpc_response.status = true;
-                },
-                Err(error) => {
-                    rpc_response.status = false;
-                    rpc_response.error = format!("Failed to start unit: {}", error);
-                },
-            }
+    return match unit_manager.start_unit(request.unit_name.clone()) {
+        Ok(_) => {
+            let mut response = tsm_unitman_rpc::StartUnitResponse::new();
+            response.message = format!("Unit {} started", request.unit_name);
+            Ok(response)
         },
-        Err(error) => {
-            warn!("Failed to lock unit manager: {}", error);
-            rpc_response.status = false;
-            rpc_response.error = format!("Failed to lock unit manager: {}", error);
-        },
-    }
-
-    return rpc_response;
-}
\ No newline at end of file
+        Err(error) => Err(error_response::unit_error("Failed to start unit", &error)),
+    };
+}
@@ -0,0 +1,22 @@
+use tsm_ipc::{tsm_unitman_rpc, RpcError};
+use log::debug;
+
+use crate::unit;
+use crate::rpc::error_response;
+use crate::rpc::unit_manager_snapshot;
+
+
+pub fn handle_stop_target(request: tsm_unitman_rpc::StopTargetRequest, unit_manager: &unit::UnitManagerRef) -> Result<tsm_unitman_rpc::StopTargetResponse, RpcError> {
+    debug!("Received stop target request: {}", request.target_name);
+
+    let unit_manager = unit_manager_snapshot::snapshot_unit_manager(unit_manager)?;
+
+    return match unit_manager.stop_target(request.target_name.clone()) {
+        Ok(_) => {
+            let mut response = tsm_unitman_rpc::StopTargetResponse::new();
+            response.message = format!("Target {} stopped", request.target_name);
+            Ok(response)
+        },
+        Err(error) => Err(error_response::unit_error("Failed to stop target", &error)),
+    };
+}
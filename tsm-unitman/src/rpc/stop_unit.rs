@@ -1,47 +1,22 @@
-use tsm_ipc::{tsm_common_rpc, tsm_unitman_rpc};
-use protobuf::{Message, Enum};
-use log::{debug, warn};
+use tsm_ipc::{tsm_unitman_rpc, RpcError};
+use log::debug;
 
 use crate::unit;
+use crate::rpc::error_response;
+use crate::rpc::unit_manager_snapshot;
 
 
-pub fn handle_stop_unit(request: tsm_common_rpc::RpcRequest, unit_manager: unit::UnitManagerRef) -> tsm_common_rpc::RpcResponse {
-    let mut rpc_response = tsm_common_rpc::RpcResponse::new();
+pub fn handle_stop_unit(request: tsm_unitman_rpc::StopUnitRequest, unit_manager: &unit::UnitManagerRef) -> Result<tsm_unitman_rpc::StopUnitResponse, RpcError> {
+    debug!("Received stop unit request: {}", request.unit_name);
 
-    let stop_unit_request: tsm_unitman_rpc::StopUnitRequest = match Message::parse_from_bytes(&request.data) {
-        Ok(request) => {
-            rpc_response.method = tsm_unitman_rpc::RpcMethod::StopUnit.value();
-            request
-        },
-        Err(error) => {
-            warn!("Failed to parse stop unit request: {}", error);
-            rpc_response.method = tsm_unitman_rpc::RpcMethod::StopUnit.value();
-            rpc_response.status = false;
-            rpc_response.error = format!("Failed to parse stop unit request: {}", error);
-            return rpc_response;
-        },
-    };
-
-    debug!("Received stop unit request: {}", stop_unit_request.unit_name);
+    let unit_manager = unit_manager_snapshot::snapshot_unit_manager(unit_manager)?;
 
-    match unit_manager.try_lock() {
-        Ok(unit_manager) => {
-            match unit_manager.stop_unit(String::from(stop_unit_request.unit_name), false) {
-                Ok(_) => {
-                    rpc_response.status = true;
-                },
-                Err(error) => {
-                    rpc_response.status = false;
-                    rpc_response.error = format!("Failed to stop unit: {}", error);
-                },
-            }
+    return match unit_manager.stop_unit(request.unit_name.clone(), false) {
+        Ok(_) => {
+            let mut response = tsm_unitman_rpc::StopUnitResponse::new();
+            response.message = format!("Unit {} stopped", request.unit_name);
+            Ok(response)
         },
-        Err(error) => {
-            warn!("Failed to lock unit manager: {}", error);
-            rpc_response.status = false;
-            rpc_response.error = format!("Failed to lock unit manager: {}", error);
-        },
-    }
-
-    return rpc_response;
-}
\ No newline at end of file
+        Err(error) => Err(error_response::unit_error("Failed to stop unit", &error)),
+    };
+}
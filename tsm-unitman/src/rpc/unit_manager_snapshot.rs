@@ -0,0 +1,37 @@
+use log::warn;
+use tsm_ipc::RpcError;
+
+use crate::unit;
+use crate::rpc::error_response;
+
+
+/// A copy of the unit manager shares its units, targets and flags with the original. Requests
+/// hold the global lock only while copying and then run at the same time, each unit is still
+/// changed by one of them at a time under its own lock.
+pub fn snapshot_unit_manager(unit_manager: &unit::UnitManagerRef) -> Result<unit::UnitManager, RpcError> {
+    return match unit::lock(unit_manager) {
+        Ok(unit_manager) => Ok(unit_manager.clone()),
+        Err(error) => {
+            warn!("Failed to lock unit manager: {}", error);
+            Err(error_response::unit_error("Failed to lock unit manager", &error))
+        },
+    };
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+    use super::*;
+
+    #[test]
+    fn snapshot_unit_manager_should_not_hold_lock() {
+        let unit_manager = unit::UnitManager::new_ref();
+        let unit = unit::Unit::new(String::from("test"), String::from("ls"), Vec::new(), unit::RestartPolicy::Never, 0, 0, true);
+        unit_manager.lock().unwrap().add_unit(Arc::new(Mutex::new(unit)));
+
+        let snapshot = snapshot_unit_manager(&unit_manager).unwrap();
+        assert!(unit_manager.try_lock().is_ok());
+        assert!(Arc::ptr_eq(&snapshot.get_unit("test").unwrap(), &unit_manager.lock().unwrap().get_unit("test").unwrap()));
+    }
+}
@@ -0,0 +1,22 @@
+use tsm_ipc::{tsm_unitman_rpc, RpcError};
+use log::debug;
+
+use crate::unit;
+use crate::rpc::error_response;
+use crate::rpc::unit_manager_snapshot;
+
+
+pub fn handle_unmask_unit(request: tsm_unitman_rpc::UnmaskUnitRequest, unit_manager: &unit::UnitManagerRef) -> Result<tsm_unitman_rpc::UnmaskUnitResponse, RpcError> {
+    debug!("Received unmask unit request: {}", request.unit_name);
+
+    let unit_manager = unit_manager_snapshot::snapshot_unit_manager(unit_manager)?;
+
+    return match unit_manager.unmask_unit(request.unit_name.clone()) {
+        Ok(_) => {
+            let mut response = tsm_unitman_rpc::UnmaskUnitResponse::new();
+            response.message = format!("Unit {} unmasked", request.unit_name);
+            Ok(response)
+        },
+        Err(error) => Err(error_response::unit_error("Failed to unmask unit", &error)),
+    };
+}
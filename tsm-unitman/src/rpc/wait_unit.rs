@@ -0,0 +1,138 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use tsm_ipc::{tsm_unitman_rpc, RpcError};
+use tsm_ipc::tsm_common_rpc::error::Code;
+use protobuf::EnumOrUnknown;
+use log::{debug, warn};
+
+use crate::unit;
+use crate::rpc::error_response;
+use crate::rpc::unit_manager_snapshot;
+use crate::rpc::converters;
+
+
+/// A wait occupies one of the RPC workers for its whole duration, so it is kept short. Clients
+/// waiting longer send the request again after it timed out.
+const MAX_TIMEOUT: Duration = Duration::from_secs(30);
+
+
+/// Counts the waits in flight, so that they never take the last worker from other requests. With
+/// a single worker there is nothing to spare and WaitUnit is refused.
+pub struct WaitSlots {
+    in_flight: AtomicUsize,
+    limit: usize,
+}
+
+
+impl WaitSlots {
+    pub fn new(workers: usize) -> WaitSlots {
+        if workers < 2 {
+            warn!("WaitUnit requests will be refused, they need at least 2 RPC workers and {} is configured", workers);
+        }
+
+        WaitSlots {
+            in_flight: AtomicUsize::new(0),
+            limit: workers.saturating_sub(1),
+        }
+    }
+
+    fn acquire(&self) -> Option<WaitSlot<'_>> {
+        return self.in_flight
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |in_flight| (in_flight < self.limit).then_some(in_flight + 1))
+            .ok()
+            .map(|_| WaitSlot { slots: self });
+    }
+}
+
+
+/// Released when the wait is answered
+struct WaitSlot<'a> {
+    slots: &'a WaitSlots,
+}
+
+
+impl Drop for WaitSlot<'_> {
+    fn drop(&mut self) {
+        self.slots.in_flight.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+
+pub fn handle_wait_unit(request: tsm_unitman_rpc::WaitUnitRequest, unit_manager: &unit::UnitManagerRef, wait_slots: &WaitSlots) -> Result<tsm_unitman_rpc::WaitUnitResponse, RpcError> {
+    debug!("Received wait unit request: {}", request.unit_name);
+
+    if wait_slots.limit == 0 {
+        return Err(RpcError::server(Code::Failed, String::from("WaitUnit needs at least 2 RPC workers, a wait would block all other requests")));
+    }
+
+    let _wait_slot = match wait_slots.acquire() {
+        Some(wait_slot) => wait_slot,
+        None => return Err(RpcError::server(Code::Busy, format!("Already waiting for {} units, all other workers must stay free", wait_slots.limit))),
+    };
+
+    let condition = if request.has_state() {
+        unit::WaitCondition::State(converters::convert_proto_to_unit_state(request.state()))
+    } else if request.has_liveness_probe_state() {
+        unit::WaitCondition::LivenessProbeState(converters::convert_proto_to_probe_state(request.liveness_probe_state()))
+    } else {
+        return Err(RpcError::server(Code::InvalidRequest, String::from("Neither a unit state nor a probe state to wait for was given")));
+    };
+
+    // the manager must not stay locked while waiting, otherwise the unit could never change
+    let unit = unit_manager_snapshot::snapshot_unit_manager(unit_manager)?.get_unit(&request.unit_name);
+
+    let unit = match unit {
+        Some(unit) => unit,
+        None => return Err(RpcError::server(Code::NotFound, format!("Unit {} not found", request.unit_name))),
+    };
+
+    let timeout = Duration::from_millis(request.timeout_ms as u64).min(MAX_TIMEOUT);
+
+    let (outcome, state, probe_state) = match condition.wait(&unit, timeout) {
+        Ok(result) => result,
+        Err(error) => return Err(error_response::unit_error(&format!("Failed to wait for unit {}", request.unit_name), &error)),
+    };
+
+    let result = match outcome {
+        unit::WaitOutcome::Reached => tsm_unitman_rpc::wait_unit_response::Result::Reached,
+        unit::WaitOutcome::TimedOut => tsm_unitman_rpc::wait_unit_response::Result::TimedOut,
+        unit::WaitOutcome::Failed => tsm_unitman_rpc::wait_unit_response::Result::Failed,
+    };
+
+    let mut wait_unit_response = tsm_unitman_rpc::WaitUnitResponse::new();
+    wait_unit_response.result = EnumOrUnknown::new(result);
+    wait_unit_response.state = EnumOrUnknown::from_i32(state as i32);
+    wait_unit_response.liveness_probe_state = EnumOrUnknown::from_i32(probe_state as i32);
+
+    return Ok(wait_unit_response);
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wait_slots_should_leave_one_worker_free() {
+        let wait_slots = WaitSlots::new(3);
+
+        let first = wait_slots.acquire();
+        let second = wait_slots.acquire();
+        assert!(first.is_some());
+        assert!(second.is_some());
+        assert!(wait_slots.acquire().is_none());
+
+        drop(first);
+        assert!(wait_slots.acquire().is_some());
+    }
+
+    #[test]
+    fn handle_wait_unit_should_refuse_with_single_worker() {
+        let unit_manager = crate::unit::UnitManager::new_ref();
+        let mut request = tsm_unitman_rpc::WaitUnitRequest::new();
+        request.unit_name = String::from("test");
+
+        let error = handle_wait_unit(request, &unit_manager, &WaitSlots::new(1)).unwrap_err();
+        assert!(matches!(error, RpcError::Server { code: Code::Failed, .. }));
+    }
+}
@@ -0,0 +1,60 @@
+use std::str::FromStr;
+use chrono::{DateTime, Local};
+use cron::Schedule;
+use serde::Deserialize;
+use serde::de::Error;
+
+
+/// A cron expression. Accepts the classic 5 fields (minute hour day-of-month month day-of-week),
+/// the extended form with seconds (and optionally years) in front, and aliases like "@daily".
+#[derive(Debug, Clone)]
+pub struct Calendar {
+    expression: String,
+    schedule: Schedule,
+}
+
+
+impl Calendar {
+    pub fn get_expression(&self) -> String {
+        self.expression.clone()
+    }
+
+    /// Next elapse strictly after the given time
+    pub fn next_after(&self, after: &DateTime<Local>) -> Option<DateTime<Local>> {
+        return self.schedule.after(after).next();
+    }
+}
+
+
+impl<'de> Deserialize<'de> for Calendar {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: serde::Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        Calendar::from_str(&s).map_err(Error::custom)
+    }
+}
+
+
+impl FromStr for Calendar {
+    type Err = String;
+
+    fn from_str(expression: &str) -> Result<Self, Self::Err> {
+        let trimmed = expression.trim();
+
+        // the cron crate always wants seconds, classic cron expressions start at minutes
+        let normalized = match trimmed.split_whitespace().count() {
+            5 => format!("0 {}", trimmed),
+            _ => trimmed.to_string(),
+        };
+
+        match Schedule::from_str(&normalized) {
+            Ok(schedule) => Ok(Calendar {
+                expression: trimmed.to_string(),
+                schedule,
+            }),
+            Err(error) => Err(format!("Invalid calendar expression {}: {}", expression, error)),
+        }
+    }
+}
@@ -0,0 +1,161 @@
+use std::fs;
+use std::io;
+
+
+const CAPABILITY_NAMES: [&str; 41] = [
+    "CAP_CHOWN",
+    "CAP_DAC_OVERRIDE",
+    "CAP_DAC_READ_SEARCH",
+    "CAP_FOWNER",
+    "CAP_FSETID",
+    "CAP_KILL",
+    "CAP_SETGID",
+    "CAP_SETUID",
+    "CAP_SETPCAP",
+    "CAP_LINUX_IMMUTABLE",
+    "CAP_NET_BIND_SERVICE",
+    "CAP_NET_BROADCAST",
+    "CAP_NET_ADMIN",
+    "CAP_NET_RAW",
+    "CAP_IPC_LOCK",
+    "CAP_IPC_OWNER",
+    "CAP_SYS_MODULE",
+    "CAP_SYS_RAWIO",
+    "CAP_SYS_CHROOT",
+    "CAP_SYS_PTRACE",
+    "CAP_SYS_PACCT",
+    "CAP_SYS_ADMIN",
+    "CAP_SYS_BOOT",
+    "CAP_SYS_NICE",
+    "CAP_SYS_RESOURCE",
+    "CAP_SYS_TIME",
+    "CAP_SYS_TTY_CONFIG",
+    "CAP_MKNOD",
+    "CAP_LEASE",
+    "CAP_AUDIT_WRITE",
+    "CAP_AUDIT_CONTROL",
+    "CAP_SETFCAP",
+    "CAP_MAC_OVERRIDE",
+    "CAP_MAC_ADMIN",
+    "CAP_SYSLOG",
+    "CAP_WAKE_ALARM",
+    "CAP_BLOCK_SUSPEND",
+    "CAP_AUDIT_READ",
+    "CAP_PERFMON",
+    "CAP_BPF",
+    "CAP_CHECKPOINT_RESTORE",
+];
+
+const LINUX_CAPABILITY_VERSION_3: u32 = 0x20080522;
+
+
+#[repr(C)]
+struct CapUserHeader {
+    version: u32,
+    pid: i32,
+}
+
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct CapUserData {
+    effective: u32,
+    permitted: u32,
+    inheritable: u32,
+}
+
+
+/// Resolves a capability name like "CAP_NET_BIND_SERVICE" or "net_bind_service" to its number
+pub fn capability_from_name(name: &str) -> Option<u32> {
+    let upper = name.to_uppercase();
+    let full_name = match upper.starts_with("CAP_") {
+        true => upper,
+        false => format!("CAP_{}", upper),
+    };
+
+    return CAPABILITY_NAMES.iter()
+        .position(|capability| *capability == full_name)
+        .map(|position| position as u32);
+}
+
+
+pub fn capability_name(capability: u32) -> String {
+    return match CAPABILITY_NAMES.get(capability as usize) {
+        Some(name) => name.to_string(),
+        None => format!("CAP_{}", capability),
+    };
+}
+
+
+/// Highest capability supported by the running kernel
+pub fn last_capability() -> u32 {
+    return match fs::read_to_string("/proc/sys/kernel/cap_last_cap") {
+        Ok(content) => content.trim().parse().unwrap_or(CAPABILITY_NAMES.len() as u32 - 1),
+        Err(_) => CAPABILITY_NAMES.len() as u32 - 1,
+    };
+}
+
+
+/// Sets effective, permitted and inheritable sets of the calling thread to exactly the given capabilities.
+/// Only async-signal-safe calls are made here because it runs between fork and exec.
+pub fn set_capabilities(capabilities: &[u32]) -> io::Result<()> {
+    let mut data = [CapUserData { effective: 0, permitted: 0, inheritable: 0 }; 2];
+
+    for capability in capabilities {
+        let index = (*capability / 32) as usize;
+        let bit = 1u32 << (*capability % 32);
+        data[index].effective |= bit;
+        data[index].permitted |= bit;
+        data[index].inheritable |= bit;
+    }
+
+    let mut header = CapUserHeader {
+        version: LINUX_CAPABILITY_VERSION_3,
+        pid: 0,
+    };
+
+    let result = unsafe {
+        libc::syscall(libc::SYS_capset, &mut header as *mut CapUserHeader, data.as_ptr())
+    };
+
+    return match result {
+        0 => Ok(()),
+        _ => Err(io::Error::last_os_error()),
+    };
+}
+
+
+pub fn raise_ambient_capability(capability: u32) -> io::Result<()> {
+    let result = unsafe {
+        libc::prctl(libc::PR_CAP_AMBIENT, libc::PR_CAP_AMBIENT_RAISE as libc::c_ulong, capability as libc::c_ulong, 0, 0)
+    };
+
+    return match result {
+        0 => Ok(()),
+        _ => Err(io::Error::last_os_error()),
+    };
+}
+
+
+/// Drops every capability up to last_capability from the bounding set except the ones to keep.
+/// Capabilities unknown to the kernel (EINVAL) are skipped.
+pub fn drop_bounding_set(keep: &[u32], last_capability: u32) -> io::Result<()> {
+    for capability in 0..=last_capability {
+        if keep.contains(&capability) {
+            continue;
+        }
+
+        let result = unsafe {
+            libc::prctl(libc::PR_CAPBSET_DROP, capability as libc::c_ulong, 0, 0, 0)
+        };
+
+        if result != 0 {
+            let error = io::Error::last_os_error();
+            if error.raw_os_error() != Some(libc::EINVAL) {
+                return Err(error);
+            }
+        }
+    }
+
+    Ok(())
+}
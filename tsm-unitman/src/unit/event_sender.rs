@@ -0,0 +1,30 @@
+use std::sync::mpsc::Sender;
+use log::debug;
+
+use crate::unit::{UnitEvent, UnitEventKind};
+
+
+/// Passes events of units and their probes on to whoever publishes them. Sending is a no-op
+/// as long as no channel is set, so units and probes don't need to care whether events are
+/// published at all.
+#[derive(Debug, Clone, Default)]
+pub struct EventSender {
+    sender: Option<Sender<UnitEvent>>,
+}
+
+
+impl EventSender {
+    pub fn new(sender: Sender<UnitEvent>) -> EventSender {
+        EventSender {
+            sender: Some(sender),
+        }
+    }
+
+    pub fn send(&self, unit_name: String, kind: UnitEventKind) {
+        if let Some(ref sender) = self.sender {
+            if let Err(error) = sender.send(UnitEvent::new(unit_name, kind)) {
+                debug!("Dropping event, nobody publishes it anymore: {:?}", error.0);
+            }
+        }
+    }
+}
@@ -0,0 +1,187 @@
+use std::ffi::CString;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::net::{SocketAddr, TcpListener, UdpSocket};
+use std::os::fd::{FromRawFd, OwnedFd};
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::FileTypeExt;
+use std::os::unix::net::{UnixDatagram, UnixListener};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use serde::Deserialize;
+use serde::de::Error;
+
+
+/// An address unitman listens on for a socket activated unit.
+/// Written as tcp://host:port, udp://host:port, unix:///path, unix-dgram:///path or fifo:///path.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ListenAddress {
+    Tcp(SocketAddr),
+    Udp(SocketAddr),
+    UnixStream(PathBuf),
+    UnixDatagram(PathBuf),
+    Fifo(PathBuf),
+}
+
+
+impl ListenAddress {
+    /// Only stream sockets can accept connections
+    pub fn is_stream(&self) -> bool {
+        return match self {
+            ListenAddress::Tcp(_) | ListenAddress::UnixStream(_) => true,
+            _ => false,
+        };
+    }
+
+    /// Binds the address. All returned fds are close-on-exec, so they only end up in a unit
+    /// process if they are explicitly passed to it.
+    pub fn bind(&self) -> io::Result<OwnedFd> {
+        return match self {
+            ListenAddress::Tcp(address) => Ok(OwnedFd::from(TcpListener::bind(address)?)),
+            ListenAddress::Udp(address) => Ok(OwnedFd::from(UdpSocket::bind(address)?)),
+            ListenAddress::UnixStream(path) => {
+                remove_stale_socket(path)?;
+                Ok(OwnedFd::from(UnixListener::bind(path)?))
+            },
+            ListenAddress::UnixDatagram(path) => {
+                remove_stale_socket(path)?;
+                Ok(OwnedFd::from(UnixDatagram::bind(path)?))
+            },
+            ListenAddress::Fifo(path) => open_fifo(path),
+        };
+    }
+}
+
+
+/// A socket file left behind by a previous run would make bind fail with EADDRINUSE
+fn remove_stale_socket(path: &Path) -> io::Result<()> {
+    return match fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.file_type().is_socket() => fs::remove_file(path),
+        Ok(_) => Err(io::Error::new(io::ErrorKind::AlreadyExists, format!("{:?} exists and is not a socket", path))),
+        Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(()),
+        Err(error) => Err(error),
+    };
+}
+
+
+fn open_fifo(path: &Path) -> io::Result<OwnedFd> {
+    let c_path = CString::new(path.as_os_str().as_bytes())
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "path contains a nul byte"))?;
+
+    match fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.file_type().is_fifo() => {},
+        Ok(_) => return Err(io::Error::new(io::ErrorKind::AlreadyExists, format!("{:?} exists and is not a fifo", path))),
+        Err(error) if error.kind() == io::ErrorKind::NotFound => {
+            if unsafe { libc::mkfifo(c_path.as_ptr(), 0o666) } != 0 {
+                return Err(io::Error::last_os_error());
+            }
+        },
+        Err(error) => return Err(error),
+    }
+
+    // Opening read-write never blocks and keeps the fifo from reporting EOF while no writer is attached
+    let fd = unsafe { libc::open(c_path.as_ptr(), libc::O_RDWR | libc::O_NONBLOCK | libc::O_CLOEXEC) };
+    if fd < 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    return Ok(unsafe { OwnedFd::from_raw_fd(fd) });
+}
+
+
+impl fmt::Display for ListenAddress {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ListenAddress::Tcp(address) => write!(f, "tcp://{}", address),
+            ListenAddress::Udp(address) => write!(f, "udp://{}", address),
+            ListenAddress::UnixStream(path) => write!(f, "unix://{}", path.display()),
+            ListenAddress::UnixDatagram(path) => write!(f, "unix-dgram://{}", path.display()),
+            ListenAddress::Fifo(path) => write!(f, "fifo://{}", path.display()),
+        }
+    }
+}
+
+
+impl<'de> Deserialize<'de> for ListenAddress {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: serde::Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        ListenAddress::from_str(&s).map_err(Error::custom)
+    }
+}
+
+
+impl FromStr for ListenAddress {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (scheme, rest) = match s.split_once("://") {
+            Some(parts) => parts,
+            None => return Err(format!("Invalid listen address {}: missing scheme", s)),
+        };
+
+        let parse_socket_address = |rest: &str| {
+            SocketAddr::from_str(rest).map_err(|error| format!("Invalid listen address {}: {}", s, error))
+        };
+
+        let parse_path = |rest: &str| {
+            match rest.starts_with('/') {
+                true => Ok(PathBuf::from(rest)),
+                false => Err(format!("Invalid listen address {}: path must be absolute", s)),
+            }
+        };
+
+        return match scheme.to_lowercase().as_str() {
+            "tcp" => Ok(ListenAddress::Tcp(parse_socket_address(rest)?)),
+            "udp" => Ok(ListenAddress::Udp(parse_socket_address(rest)?)),
+            "unix" => Ok(ListenAddress::UnixStream(parse_path(rest)?)),
+            "unix-dgram" => Ok(ListenAddress::UnixDatagram(parse_path(rest)?)),
+            "fifo" => Ok(ListenAddress::Fifo(parse_path(rest)?)),
+            _ => Err(format!("Invalid listen address {}: unknown scheme {}", s, scheme)),
+        };
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_str_should_work() {
+        assert_eq!(ListenAddress::from_str("tcp://127.0.0.1:8080").unwrap(), ListenAddress::Tcp("127.0.0.1:8080".parse().unwrap()));
+        assert_eq!(ListenAddress::from_str("udp://[::1]:53").unwrap(), ListenAddress::Udp("[::1]:53".parse().unwrap()));
+        assert_eq!(ListenAddress::from_str("unix:///run/foo.sock").unwrap(), ListenAddress::UnixStream(PathBuf::from("/run/foo.sock")));
+        assert_eq!(ListenAddress::from_str("unix-dgram:///run/foo.sock").unwrap(), ListenAddress::UnixDatagram(PathBuf::from("/run/foo.sock")));
+        assert_eq!(ListenAddress::from_str("fifo:///run/foo.fifo").unwrap(), ListenAddress::Fifo(PathBuf::from("/run/foo.fifo")));
+    }
+
+    #[test]
+    fn from_str_with_invalid_address_should_fail() {
+        assert!(ListenAddress::from_str("127.0.0.1:8080").is_err());
+        assert!(ListenAddress::from_str("sctp://127.0.0.1:8080").is_err());
+        assert!(ListenAddress::from_str("tcp://localhost").is_err());
+        assert!(ListenAddress::from_str("unix://relative.sock").is_err());
+    }
+
+    #[test]
+    fn bind_fifo_and_unix_socket_should_work() {
+        let dir = std::env::temp_dir().join(format!("tsm-unitman-listen-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        let fifo = ListenAddress::Fifo(dir.join("fifo"));
+        assert!(fifo.bind().is_ok());
+        // an existing fifo is reused
+        assert!(fifo.bind().is_ok());
+
+        let socket = ListenAddress::UnixStream(dir.join("socket"));
+        assert!(socket.bind().is_ok());
+        // a stale socket file is replaced
+        assert!(socket.bind().is_ok());
+
+        let _ = fs::remove_dir_all(dir);
+    }
+}
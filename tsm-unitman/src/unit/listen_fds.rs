@@ -0,0 +1,262 @@
+use std::env;
+use std::ffi::{CString, OsStr};
+use std::io;
+use std::os::fd::RawFd;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::PermissionsExt;
+use std::path::PathBuf;
+use libc::{c_char, c_int};
+
+
+const SD_LISTEN_FDS_START: c_int = 3;
+const LISTEN_PID_PREFIX: &str = "LISTEN_PID=";
+
+
+/// File descriptors handed to a unit process following the sd_listen_fds() convention.
+/// They are placed at fd 3 onwards, LISTEN_FDS holds their count, LISTEN_FDNAMES their colon
+/// separated names and LISTEN_PID the pid of the process they are meant for.
+/// The fds themselves are owned by whoever bound them, this only refers to them.
+#[derive(Debug, Clone)]
+pub struct ListenFds {
+    fds: Vec<RawFd>,
+    names: Vec<String>,
+}
+
+
+impl ListenFds {
+    pub fn new() -> ListenFds {
+        ListenFds {
+            fds: Vec::new(),
+            names: Vec::new(),
+        }
+    }
+
+    pub fn push(&mut self, fd: RawFd, name: String) {
+        self.fds.push(fd);
+        self.names.push(name);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.fds.is_empty()
+    }
+
+    /// Everything execve needs is allocated here in the parent, since the forked child must not allocate.
+    pub fn prepare(&self, executable: &str, arguments: &[String]) -> Result<PreparedExec, String> {
+        let path = resolve_executable(executable)?;
+
+        let to_cstring = |bytes: &[u8]| {
+            CString::new(bytes).map_err(|_| format!("{:?} contains a nul byte", String::from_utf8_lossy(bytes)))
+        };
+
+        let mut argv_strings = vec![to_cstring(executable.as_bytes())?];
+        for argument in arguments {
+            argv_strings.push(to_cstring(argument.as_bytes())?);
+        }
+
+        let mut envp_strings = Vec::new();
+        for (key, value) in env::vars_os() {
+            if key.as_bytes().starts_with(b"LISTEN_") {
+                continue;
+            }
+
+            let mut variable = key.as_bytes().to_vec();
+            variable.push(b'=');
+            variable.extend_from_slice(value.as_bytes());
+            envp_strings.push(to_cstring(&variable)?);
+        }
+        envp_strings.push(to_cstring(format!("LISTEN_FDS={}", self.fds.len()).as_bytes())?);
+        envp_strings.push(to_cstring(format!("LISTEN_FDNAMES={}", self.names.join(":")).as_bytes())?);
+
+        // The pid is only known in the child, it is written into this buffer right before execve
+        let mut listen_pid = LISTEN_PID_PREFIX.as_bytes().to_vec();
+        listen_pid.resize(LISTEN_PID_PREFIX.len() + 21, 0);
+
+        let mut argv: Vec<*const c_char> = argv_strings.iter().map(|argument| argument.as_ptr()).collect();
+        argv.push(std::ptr::null());
+
+        let mut envp: Vec<*const c_char> = envp_strings.iter().map(|variable| variable.as_ptr()).collect();
+        envp.push(listen_pid.as_ptr() as *const c_char);
+        envp.push(std::ptr::null());
+
+        return Ok(PreparedExec {
+            fds: self.fds.clone(),
+            temporary_fds: vec![-1; self.fds.len()],
+            occupants: vec![(-1, 0); self.fds.len()],
+            path: to_cstring(path.as_os_str().as_bytes())?,
+            _argv_strings: argv_strings,
+            _envp_strings: envp_strings,
+            listen_pid,
+            argv,
+            envp,
+        });
+    }
+}
+
+
+/// Looks up the executable in PATH like execvp would, which is not safe to call after fork
+fn resolve_executable(executable: &str) -> Result<PathBuf, String> {
+    if executable.contains('/') {
+        return Ok(PathBuf::from(executable));
+    }
+
+    let path = env::var_os("PATH").unwrap_or(OsStr::new("/usr/local/bin:/usr/bin:/bin").to_os_string());
+
+    for directory in env::split_paths(&path) {
+        let candidate = directory.join(executable);
+        match candidate.metadata() {
+            Ok(metadata) if metadata.is_file() && metadata.permissions().mode() & 0o111 != 0 => return Ok(candidate),
+            _ => continue,
+        }
+    }
+
+    return Err(format!("{} not found in PATH", executable));
+}
+
+
+/// Moves the listen fds into place and execs the unit process. Runs in the forked child as the
+/// last pre_exec hook, so that LISTEN_PID can carry the pid of the process actually being exec'd.
+pub struct PreparedExec {
+    fds: Vec<c_int>,
+    temporary_fds: Vec<c_int>,
+    /// Copies of what was open in the target range and its fd flags, put back if execve fails
+    occupants: Vec<(c_int, c_int)>,
+    path: CString,
+    _argv_strings: Vec<CString>,
+    _envp_strings: Vec<CString>,
+    listen_pid: Vec<u8>,
+    argv: Vec<*const c_char>,
+    envp: Vec<*const c_char>,
+}
+
+
+// The raw pointers only point into the buffers owned by the struct itself
+unsafe impl Send for PreparedExec {}
+unsafe impl Sync for PreparedExec {}
+
+
+impl PreparedExec {
+    /// Only returns if execve failed
+    pub fn exec(&mut self) -> io::Error {
+        let count = self.fds.len() as c_int;
+
+        // Move all fds above the target range first, so that dup2 never clobbers a fd still to be moved
+        for index in 0..self.fds.len() {
+            let temporary = unsafe { libc::fcntl(self.fds[index], libc::F_DUPFD_CLOEXEC, SD_LISTEN_FDS_START + count) };
+            if temporary < 0 {
+                return io::Error::last_os_error();
+            }
+            self.temporary_fds[index] = temporary;
+        }
+
+        // Whatever else is open in the target range, e.g. the pipe std reports a failed execve on,
+        // is kept open above it. The copies are closed by a successful execve like the originals.
+        for index in 0..self.fds.len() {
+            let target = SD_LISTEN_FDS_START + index as c_int;
+            let flags = unsafe { libc::fcntl(target, libc::F_GETFD) };
+
+            self.occupants[index] = match flags {
+                flags if flags < 0 => (-1, 0),
+                flags => match unsafe { libc::fcntl(target, libc::F_DUPFD_CLOEXEC, SD_LISTEN_FDS_START + count) } {
+                    copy if copy < 0 => return io::Error::last_os_error(),
+                    copy => (copy, flags),
+                },
+            };
+        }
+
+        // dup2 clears FD_CLOEXEC on the target, the temporary copies are closed by execve
+        for (index, temporary) in self.temporary_fds.iter().enumerate() {
+            if unsafe { libc::dup2(*temporary, SD_LISTEN_FDS_START + index as c_int) } < 0 {
+                let error = io::Error::last_os_error();
+                self.restore_occupants();
+                return error;
+            }
+        }
+
+        self.write_listen_pid(unsafe { libc::getpid() } as u32);
+
+        unsafe { libc::execve(self.path.as_ptr(), self.argv.as_ptr(), self.envp.as_ptr()) };
+        let error = io::Error::last_os_error();
+        self.restore_occupants();
+        return error;
+    }
+
+    /// Puts back what was open in the target range, so that std can report the error
+    fn restore_occupants(&self) {
+        for (index, (copy, flags)) in self.occupants.iter().enumerate() {
+            let target = SD_LISTEN_FDS_START + index as c_int;
+
+            if *copy < 0 {
+                unsafe { libc::close(target) };
+                continue;
+            }
+
+            unsafe {
+                libc::dup2(*copy, target);
+                libc::fcntl(target, libc::F_SETFD, *flags);
+            }
+        }
+    }
+
+    fn write_listen_pid(&mut self, pid: u32) {
+        let mut digits = [0u8; 10];
+        let mut length = 0;
+        let mut remainder = pid;
+
+        loop {
+            digits[length] = b'0' + (remainder % 10) as u8;
+            remainder /= 10;
+            length += 1;
+
+            if remainder == 0 {
+                break;
+            }
+        }
+
+        let offset = LISTEN_PID_PREFIX.len();
+        for index in 0..length {
+            self.listen_pid[offset + index] = digits[length - 1 - index];
+        }
+        self.listen_pid[offset + length] = 0;
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::os::unix::process::CommandExt;
+    use super::*;
+
+    #[test]
+    fn resolve_executable_should_search_path() {
+        assert!(resolve_executable("sh").unwrap().is_absolute());
+        assert_eq!(resolve_executable("./foo").unwrap(), PathBuf::from("./foo"));
+        assert!(resolve_executable("does-not-exist-anywhere").is_err());
+    }
+
+    #[test]
+    fn exec_should_report_failed_execve() {
+        // enough fds for the target range to cover the pipe std reports the error on
+        let mut listen_fds = ListenFds::new();
+        for _ in 0..64 {
+            listen_fds.push(0, String::from("stdin"));
+        }
+
+        let mut prepared = listen_fds.prepare("/does-not-exist/unit", &[]).unwrap();
+        let mut command = std::process::Command::new("/bin/true");
+        unsafe { command.pre_exec(move || Err(prepared.exec())) };
+
+        assert_eq!(command.spawn().unwrap_err().kind(), io::ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn write_listen_pid_should_work() {
+        let mut listen_fds = ListenFds::new();
+        listen_fds.push(0, String::from("stdin"));
+
+        let mut prepared = listen_fds.prepare("sh", &[]).unwrap();
+        prepared.write_listen_pid(4711);
+
+        let variable = unsafe { std::ffi::CStr::from_ptr(prepared.listen_pid.as_ptr() as *const c_char) };
+        assert_eq!(variable.to_str().unwrap(), "LISTEN_PID=4711");
+    }
+}
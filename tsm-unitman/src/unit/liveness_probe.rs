@@ -3,10 +3,15 @@ use std::process::{Command, Stdio};
 use std::time::{Duration, Instant};
 use process_control::{ChildExt, Control, ExitStatus};
 use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::thread;
 use std::thread::JoinHandle;
+use chrono::Local;
 use log::{debug, warn, error};
-use crate::unit::ProbeState;
+use crate::unit::{EventSender, ProbeCounters, ProbeResult, ProbeState, ProbeType, RingBuffer, UnitEventKind};
+
+
+const RESULTS_CAPACITY: usize = 20;
 
 
 #[derive(Debug, Clone)]
@@ -17,8 +22,11 @@ pub struct LivenessProbe {
     timeout_s: i32,
     interval_s: i32,
     state: Arc<Mutex<ProbeState>>,
-    stop_requested: bool,
+    results: Arc<Mutex<RingBuffer<ProbeResult>>>,
+    counters: Arc<Mutex<ProbeCounters>>,
+    stop_requested: Arc<AtomicBool>,
     probe_timestamp: Instant,
+    events: EventSender,
 }
 
 
@@ -39,8 +47,49 @@ impl LivenessProbe {
             timeout_s,
             interval_s,
             state: Arc::new(Mutex::new(ProbeState::Undefined)),
-            stop_requested: false,
+            results: Arc::new(Mutex::new(RingBuffer::new(RESULTS_CAPACITY))),
+            counters: Arc::new(Mutex::new(ProbeCounters::new())),
+            stop_requested: Arc::new(AtomicBool::new(false)),
             probe_timestamp: Instant::now(),
+            events: EventSender::default(),
+        };
+    }
+
+    pub fn get_executable(&self) -> String {
+        self.executable.clone()
+    }
+
+    pub fn get_arguments(&self) -> Vec<String> {
+        self.arguments.clone()
+    }
+
+    pub fn get_timeout_s(&self) -> i32 {
+        self.timeout_s
+    }
+
+    pub fn get_interval_s(&self) -> i32 {
+        self.interval_s
+    }
+
+    /// The results of the last probes, oldest first
+    pub fn get_results(&self) -> Vec<ProbeResult> {
+        return match self.results.try_lock() {
+            Ok(results) => results.to_vec(),
+            Err(e) => {
+                error!("Liveness probe for unit {} failed to lock results: {}", self.name, e);
+                Vec::new()
+            }
+        };
+    }
+
+    /// Totals of all probes, also of those whose results were dropped already
+    pub fn get_counters(&self) -> ProbeCounters {
+        return match self.counters.try_lock() {
+            Ok(counters) => counters.clone(),
+            Err(e) => {
+                error!("Liveness probe for unit {} failed to lock counters: {}", self.name, e);
+                ProbeCounters::new()
+            }
         };
     }
 
@@ -54,9 +103,23 @@ impl LivenessProbe {
         }
     }
 
+    pub fn set_event_sender(&mut self, events: EventSender) {
+        self.events = events;
+    }
+
     fn set_state(&mut self, new_state: ProbeState) {
         match self.state.try_lock() {
-            Ok(mut state) => *state = new_state.clone(),
+            Ok(mut state) => {
+                if *state != new_state {
+                    self.events.send(self.name.clone(), UnitEventKind::ProbeChanged {
+                        probe: ProbeType::Liveness,
+                        from: state.clone(),
+                        to: new_state.clone(),
+                    });
+                }
+
+                *state = new_state.clone();
+            },
             Err(e) => {
                 error!("Liveness probe for unit {} failed to lock state: {}", self.name, e)
             },
@@ -65,10 +128,14 @@ impl LivenessProbe {
 
     /// Set stop_requested flag to true
     pub fn request_stop(&mut self) {
-        self.stop_requested = true;
+        self.stop_requested.store(true, Ordering::Relaxed);
     }
 
-    pub fn run(&self) -> JoinHandle<()> {
+    /// Every run gets its own stop flag, so that a stopped run never picks up a later start.
+    /// A previous run which was not stopped is stopped now.
+    pub fn run(&mut self) -> JoinHandle<()> {
+        self.request_stop();
+        self.stop_requested = Arc::new(AtomicBool::new(false));
         let mut self_clone = self.clone();
         return thread::spawn(move || self_clone.run_loop());
     }
@@ -78,7 +145,7 @@ impl LivenessProbe {
         debug!("Liveness probe for unit {} starting", self.name);
 
         loop {
-            if self.stop_requested {
+            if self.stop_requested.load(Ordering::Relaxed) {
                 debug!("Liveness probe for unit {} stop requested", self.name);
                 break;
             }
@@ -92,7 +159,6 @@ impl LivenessProbe {
         }
 
         self.set_state(ProbeState::Dead);
-        self.stop_requested = false;
 
         debug!("Liveness probe for unit {} stopped", self.name);
     }
@@ -107,13 +173,16 @@ impl LivenessProbe {
     /// Ok: true if process executed successfully, false if it is still not time to probe
     /// Error: process failed to execute, or timed out, or exited with non-zero exit code
     pub fn probe(&mut self) {
+        let timestamp = Local::now();
+        let started = Instant::now();
+
         let process = Command::new(&self.executable)
             .args(&self.arguments)
             .stdout(Stdio::null())
             .stderr(Stdio::null())
             .spawn();
 
-        match process {
+        let (state, exit_code, message) = match process {
             Ok(mut child) => {
                 let output_result: Result<Option<ExitStatus>, Error> = child
                     .controlled()
@@ -127,28 +196,43 @@ impl LivenessProbe {
                             Some(exit_status) => {
                                 if exit_status.success() {
                                     debug!("Liveness probe for unit {} succeeded. Setting probe state to Alive.", self.name);
-                                    self.set_state(ProbeState::Alive);
+                                    (ProbeState::Alive, exit_status.code().map(|code| code as i32), String::new())
                                 } else {
                                     warn!("Liveness probe for unit {} failed with exit status {}. Setting probe state to Dead.", self.name, exit_status);
-                                    self.set_state(ProbeState::Dead);
+                                    (ProbeState::Dead, exit_status.code().map(|code| code as i32), format!("exit status {}", exit_status))
                                 }
                             }
                             None => {
                                 warn!("Liveness probe for unit {} succeeded before timeout. Setting probe state to Alive.", self.name);
-                                self.set_state(ProbeState::Alive);
+                                (ProbeState::Alive, None, String::from("timed out"))
                             }
                         }
                     },
                     Err(e) => {
                         warn!("Liveness probe for unit {} failed: {}. Setting probe state to Undefined.", self.name, e);
-                        self.set_state(ProbeState::Undefined);
+                        (ProbeState::Undefined, None, e.to_string())
                     }
                 }
             }
             Err(e) => {
                 warn!("Liveness probe for unit {} failed when executing command {}: {}. Setting probe state to Undefined.", self.name, self.executable, e);
-                self.set_state(ProbeState::Undefined);
+                (ProbeState::Undefined, None, e.to_string())
             }
-        }
+        };
+
+        self.set_state(state.clone());
+        self.add_result(ProbeResult::new(timestamp, state, started.elapsed(), exit_code, message));
+    }
+
+    fn add_result(&self, result: ProbeResult) {
+        match self.counters.try_lock() {
+            Ok(mut counters) => counters.record(&result),
+            Err(e) => error!("Liveness probe for unit {} failed to lock counters: {}", self.name, e),
+        };
+
+        match self.results.try_lock() {
+            Ok(mut results) => results.push(result),
+            Err(e) => error!("Liveness probe for unit {} failed to lock results: {}", self.name, e),
+        };
     }
 }
\ No newline at end of file
@@ -0,0 +1,91 @@
+use std::sync::{Mutex, MutexGuard, TryLockError};
+use std::thread;
+use std::time::{Duration, Instant};
+use log::warn;
+
+use crate::unit::UnitError;
+
+
+/// A request may wait for the unit manager and then for a unit, together this stays below the
+/// default timeout of clients
+const LOCK_TIMEOUT: Duration = Duration::from_millis(2000);
+const RETRY_INTERVAL: Duration = Duration::from_millis(5);
+
+
+/// Waits while the unit manager or a unit is used elsewhere, e.g. by the monitor loop, a probe or
+/// another request, instead of failing right away. UnitError::Busy is only returned once the
+/// deadline passed.
+pub fn lock<T>(mutex: &Mutex<T>) -> Result<MutexGuard<'_, T>, UnitError> {
+    return lock_with_timeout(mutex, LOCK_TIMEOUT);
+}
+
+
+fn lock_with_timeout<T>(mutex: &Mutex<T>, timeout: Duration) -> Result<MutexGuard<'_, T>, UnitError> {
+    let deadline = Instant::now() + timeout;
+
+    loop {
+        match mutex.try_lock() {
+            Ok(guard) => return Ok(guard),
+            // the data itself is consistent, whoever panicked only left it halfway through an operation
+            Err(TryLockError::Poisoned(error)) => {
+                warn!("Lock was poisoned by a panic, recovering");
+                return Ok(error.into_inner());
+            },
+            Err(TryLockError::WouldBlock) => {
+                if Instant::now() >= deadline {
+                    return Err(UnitError::Busy(format!("Still in use after {}ms", timeout.as_millis())));
+                }
+                thread::sleep(RETRY_INTERVAL);
+            },
+        };
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use super::*;
+    use crate::unit::{RestartPolicy, Unit, UnitManager, UnitRef};
+
+    fn build_unit_ref() -> UnitRef {
+        return Arc::new(Mutex::new(Unit::new(String::from("test"), String::from("ls"), Vec::new(), RestartPolicy::Never, 0, 0, true)));
+    }
+
+    #[test]
+    fn lock_with_timeout_should_wait_for_release() {
+        let unit = build_unit_ref();
+
+        let holder = unit.clone();
+        let handle = thread::spawn(move || {
+            let _lock = holder.lock().unwrap();
+            thread::sleep(Duration::from_millis(100));
+        });
+        thread::sleep(Duration::from_millis(20));
+
+        assert!(lock_with_timeout(&unit, Duration::from_millis(1000)).is_ok());
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn lock_with_timeout_should_give_up_after_deadline() {
+        let unit = build_unit_ref();
+        let _lock = unit.lock().unwrap();
+
+        assert_eq!(lock_with_timeout(&unit, Duration::from_millis(50)).unwrap_err(), UnitError::Busy(String::from("Still in use after 50ms")));
+    }
+
+    #[test]
+    fn lock_with_timeout_should_recover_poisoned_lock() {
+        let unit_manager = UnitManager::new_ref();
+
+        let holder = unit_manager.clone();
+        let _ = thread::spawn(move || {
+            let _lock = holder.lock().unwrap();
+            panic!("handler failed");
+        }).join();
+
+        assert!(unit_manager.is_poisoned());
+        assert!(lock_with_timeout(&unit_manager, Duration::from_millis(50)).is_ok());
+    }
+}
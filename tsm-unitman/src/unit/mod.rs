@@ -7,6 +7,9 @@ pub use unit::{Unit, UnitRef};
 mod unit_manager;
 pub use unit_manager::{UnitManager, UnitManagerRef};
 
+mod lock;
+pub use lock::lock;
+
 mod restart_policy;
 pub use restart_policy::RestartPolicy;
 
@@ -20,7 +23,85 @@ mod probe_manager;
 use probe_manager::ProbeManager;
 
 mod probe_state;
-use probe_state::ProbeState;
+pub use probe_state::ProbeState;
 
 mod process;
 use process::Process;
+
+mod sandbox;
+pub use sandbox::Sandbox;
+
+mod sandbox_error;
+pub use sandbox_error::SandboxError;
+
+mod unit_error;
+pub use unit_error::UnitError;
+
+mod capabilities;
+
+mod seccomp;
+use seccomp::SyscallFilter;
+
+mod calendar;
+pub use calendar::Calendar;
+
+mod timer;
+pub use timer::Timer;
+
+mod listen_address;
+pub use listen_address::ListenAddress;
+
+mod listen_fds;
+use listen_fds::ListenFds;
+
+mod socket;
+pub use socket::Socket;
+
+mod socket_listener;
+use socket_listener::SocketListener;
+
+mod path_trigger;
+pub use path_trigger::{PathCondition, PathTrigger, PathTriggerState};
+
+mod target;
+pub use target::Target;
+
+mod unit_overrides;
+use unit_overrides::UnitOverrides;
+
+
+mod proc_stat;
+pub use proc_stat::ProcStat;
+
+mod signal;
+pub use signal::Signal;
+
+mod ring_buffer;
+use ring_buffer::RingBuffer;
+
+mod state_transition;
+pub use state_transition::StateTransition;
+
+mod probe_result;
+pub use probe_result::ProbeResult;
+
+mod probe_counters;
+pub use probe_counters::ProbeCounters;
+
+mod output_log;
+pub use output_log::OutputLog;
+
+mod process_tree_entry;
+pub use process_tree_entry::ProcessTreeEntry;
+
+mod runtime_state;
+use runtime_state::{RuntimeState, UnitRuntimeState};
+
+mod unit_event;
+pub use unit_event::{ProbeType, UnitEvent, UnitEventKind};
+
+mod event_sender;
+pub use event_sender::EventSender;
+
+mod wait_condition;
+pub use wait_condition::{WaitCondition, WaitOutcome};
@@ -0,0 +1,125 @@
+use std::fs;
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+
+
+const MAX_SIZE: u64 = 256 * 1024;
+const TAIL_SIZE: u64 = 64 * 1024;
+
+
+/// Captures stdout and stderr of a unit in a file, so that the unit is not affected when
+/// unitman restarts, unlike with a pipe. The file is opened in append mode by the process,
+/// which allows to trim it while the process writes to it. Lines written at the very moment
+/// of trimming may get lost.
+#[derive(Debug, Clone)]
+pub struct OutputLog {
+    path: PathBuf,
+}
+
+
+impl OutputLog {
+    pub fn new(path: PathBuf) -> OutputLog {
+        OutputLog {
+            path,
+        }
+    }
+
+    pub fn get_path(&self) -> PathBuf {
+        self.path.clone()
+    }
+
+    /// Opens the log to be passed as stdout and stderr of a process
+    pub fn open(&self) -> io::Result<File> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        self.trim()?;
+
+        return OpenOptions::new().create(true).append(true).open(&self.path);
+    }
+
+    /// Keeps the newer half of the log once it exceeds its maximum size
+    pub fn trim(&self) -> io::Result<()> {
+        let size = match fs::metadata(&self.path) {
+            Ok(metadata) => metadata.len(),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(error) => return Err(error),
+        };
+
+        if size <= MAX_SIZE {
+            return Ok(());
+        }
+
+        let tail = self.read_tail(MAX_SIZE / 2)?;
+        let tail = match tail.iter().position(|byte| *byte == b'\n') {
+            Some(position) => &tail[position + 1..],
+            None => &tail[..],
+        };
+
+        let mut file = OpenOptions::new().append(true).open(&self.path)?;
+        file.set_len(0)?;
+        return file.write_all(tail);
+    }
+
+    /// The last lines of the log, oldest first
+    pub fn tail(&self, lines: usize) -> Vec<String> {
+        let tail = match self.read_tail(TAIL_SIZE) {
+            Ok(tail) => tail,
+            Err(_) => return Vec::new(),
+        };
+
+        let content = String::from_utf8_lossy(&tail);
+        let all_lines: Vec<&str> = content.lines().collect();
+        let start = all_lines.len().saturating_sub(lines);
+
+        return all_lines[start..].iter().map(|line| line.to_string()).collect();
+    }
+
+    fn read_tail(&self, size: u64) -> io::Result<Vec<u8>> {
+        let mut file = File::open(&self.path)?;
+        let length = file.metadata()?.len();
+
+        file.seek(SeekFrom::Start(length.saturating_sub(size)))?;
+
+        let mut tail = Vec::new();
+        file.read_to_end(&mut tail)?;
+
+        return Ok(tail);
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tail_and_trim_should_work() {
+        let dir = std::env::temp_dir().join(format!("tsm-unitman-output-log-test-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let output_log = OutputLog::new(dir.join("test.log"));
+
+        assert!(output_log.tail(10).is_empty());
+
+        let mut file = output_log.open().unwrap();
+        for line in 0..50000 {
+            writeln!(file, "line {}", line).unwrap();
+        }
+
+        assert_eq!(output_log.tail(2), vec!["line 49998", "line 49999"]);
+
+        output_log.trim().unwrap();
+        let size = fs::metadata(output_log.get_path()).unwrap().len();
+        assert!(size <= MAX_SIZE / 2);
+        assert_eq!(output_log.tail(1), vec!["line 49999"]);
+
+        // the process keeps appending after trimming
+        writeln!(file, "line 50000").unwrap();
+        assert_eq!(fs::metadata(output_log.get_path()).unwrap().len(), size + 11);
+
+        let _ = fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,379 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::fs;
+use std::io::ErrorKind;
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+use chrono::{DateTime, Duration, Local};
+use inotify::{Inotify, WatchMask};
+use log::{debug, warn};
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PathCondition {
+    /// Met as long as the path exists
+    Exists(PathBuf),
+    /// Met once whenever the path is created, written, moved or deleted
+    Changed(PathBuf),
+    /// Met as long as the directory contains at least one entry
+    DirectoryNotEmpty(PathBuf),
+}
+
+
+impl PathCondition {
+    pub fn get_path(&self) -> &Path {
+        return match self {
+            PathCondition::Exists(path) => path,
+            PathCondition::Changed(path) => path,
+            PathCondition::DirectoryNotEmpty(path) => path,
+        };
+    }
+}
+
+
+impl fmt::Display for PathCondition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PathCondition::Exists(path) => write!(f, "exists:{}", path.display()),
+            PathCondition::Changed(path) => write!(f, "changed:{}", path.display()),
+            PathCondition::DirectoryNotEmpty(path) => write!(f, "not-empty:{}", path.display()),
+        }
+    }
+}
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PathTriggerState {
+    /// No condition is met
+    Waiting,
+    /// A condition is met, the unit is started once the paths settled down
+    Pending,
+    /// A condition is met, but the trigger fired too often recently
+    RateLimited,
+}
+
+
+/// Identifies a version of a file, a change of any field means the file changed
+type Fingerprint = (u64, i64, i64, u64);
+
+
+/// Triggers the start of a unit based on paths. Inotify watches on the paths and their nearest
+/// existing ancestors tell when to look at the paths again, the conditions themselves are
+/// checked against the file system, so that nothing is missed while watches are re-armed.
+/// The conditions are checked on every update, so exists and not-empty triggers fire again
+/// after the unit stopped as long as the condition holds, which is what the rate limit is for.
+#[derive(Debug)]
+pub struct PathTrigger {
+    unit_name: String,
+    conditions: Vec<PathCondition>,
+    debounce_s: u64,
+    rate_limit_interval_s: u64,
+    rate_limit_burst: u32,
+    inotify: Option<Inotify>,
+    fingerprints: Vec<Option<Fingerprint>>,
+    state: PathTriggerState,
+    pending_path: Option<PathBuf>,
+    last_activity: Option<DateTime<Local>>,
+    recent_triggers: VecDeque<DateTime<Local>>,
+    last_trigger: Option<DateTime<Local>>,
+    last_path: Option<PathBuf>,
+    trigger_count: u64,
+}
+
+
+impl PathTrigger {
+    pub fn new(
+        unit_name: String,
+        conditions: Vec<PathCondition>,
+        debounce_s: u64,
+        rate_limit_interval_s: u64,
+        rate_limit_burst: u32,
+    ) -> PathTrigger {
+        let inotify = match Inotify::init() {
+            Ok(inotify) => Some(inotify),
+            Err(error) => {
+                warn!("Path trigger for unit {} failed to initialize inotify, falling back to polling: {}", unit_name, error);
+                None
+            }
+        };
+
+        let mut path_trigger = PathTrigger {
+            unit_name,
+            conditions,
+            debounce_s,
+            rate_limit_interval_s,
+            rate_limit_burst,
+            inotify,
+            fingerprints: Vec::new(),
+            state: PathTriggerState::Waiting,
+            pending_path: None,
+            last_activity: None,
+            recent_triggers: VecDeque::new(),
+            last_trigger: None,
+            last_path: None,
+            trigger_count: 0,
+        };
+
+        path_trigger.fingerprints = path_trigger.take_fingerprints();
+        path_trigger.arm_watches();
+
+        return path_trigger;
+    }
+
+    pub fn get_conditions(&self) -> Vec<PathCondition> {
+        self.conditions.clone()
+    }
+
+    pub fn get_state(&self) -> PathTriggerState {
+        self.state.clone()
+    }
+
+    pub fn get_last_trigger(&self) -> Option<DateTime<Local>> {
+        self.last_trigger
+    }
+
+    /// The path whose condition caused the last trigger
+    pub fn get_last_path(&self) -> Option<PathBuf> {
+        self.last_path.clone()
+    }
+
+    pub fn get_trigger_count(&self) -> u64 {
+        self.trigger_count
+    }
+
+    /// Consumes pending inotify events and re-evaluates the conditions
+    pub fn update(&mut self, now: &DateTime<Local>) {
+        if self.consume_events() {
+            self.last_activity = Some(*now);
+            self.arm_watches();
+        }
+
+        self.pending_path = self.find_met_condition();
+        let rate_limited = self.is_rate_limited(now);
+
+        self.state = match self.pending_path {
+            None => PathTriggerState::Waiting,
+            Some(_) if rate_limited => PathTriggerState::RateLimited,
+            Some(_) => PathTriggerState::Pending,
+        };
+    }
+
+    /// Due once a condition is met and no path activity was seen for the debounce period
+    pub fn is_due(&self, now: &DateTime<Local>) -> bool {
+        if self.state != PathTriggerState::Pending {
+            return false;
+        }
+
+        return match self.last_activity {
+            Some(last_activity) => *now - last_activity >= Duration::seconds(self.debounce_s as i64),
+            None => true,
+        };
+    }
+
+    /// Records a trigger. Changes seen so far are consumed by it.
+    pub fn trigger(&mut self, now: &DateTime<Local>) {
+        self.last_trigger = Some(*now);
+        self.last_path = self.pending_path.take();
+        self.trigger_count += 1;
+        self.recent_triggers.push_back(*now);
+        self.fingerprints = self.take_fingerprints();
+        self.state = PathTriggerState::Waiting;
+
+        debug!("Path trigger for unit {} triggered by {:?}", self.unit_name, self.last_path);
+    }
+
+    fn is_rate_limited(&mut self, now: &DateTime<Local>) -> bool {
+        if self.rate_limit_burst == 0 {
+            return false;
+        }
+
+        let window_start = *now - Duration::seconds(self.rate_limit_interval_s as i64);
+        while let Some(oldest) = self.recent_triggers.front() {
+            if *oldest > window_start {
+                break;
+            }
+            self.recent_triggers.pop_front();
+        }
+
+        return self.recent_triggers.len() >= self.rate_limit_burst as usize;
+    }
+
+    fn find_met_condition(&self) -> Option<PathBuf> {
+        for (index, condition) in self.conditions.iter().enumerate() {
+            let met = match condition {
+                PathCondition::Exists(path) => path.exists(),
+                PathCondition::Changed(path) => fingerprint(path) != self.fingerprints[index],
+                PathCondition::DirectoryNotEmpty(path) => {
+                    match fs::read_dir(path) {
+                        Ok(mut entries) => entries.next().is_some(),
+                        Err(_) => false,
+                    }
+                }
+            };
+
+            if met {
+                return Some(condition.get_path().to_path_buf());
+            }
+        }
+
+        None
+    }
+
+    fn take_fingerprints(&self) -> Vec<Option<Fingerprint>> {
+        return self.conditions.iter().map(|condition| fingerprint(condition.get_path())).collect();
+    }
+
+    /// Returns true if there were any events
+    fn consume_events(&mut self) -> bool {
+        let inotify = match self.inotify {
+            Some(ref mut inotify) => inotify,
+            None => return false,
+        };
+
+        let mut buffer = [0u8; 4096];
+        let mut has_events = false;
+
+        loop {
+            match inotify.read_events(&mut buffer) {
+                Ok(mut events) => {
+                    if events.next().is_none() {
+                        break;
+                    }
+                    has_events = true;
+                },
+                Err(error) if error.kind() == ErrorKind::WouldBlock => break,
+                Err(error) => {
+                    warn!("Path trigger for unit {} failed to read inotify events: {}", self.unit_name, error);
+                    break;
+                }
+            }
+        }
+
+        return has_events;
+    }
+
+    /// Watches every path and its nearest existing ancestor, which reports the path appearing.
+    /// Adding a watch twice just updates it, so this is called again whenever something happened.
+    fn arm_watches(&mut self) {
+        let inotify = match self.inotify {
+            Some(ref mut inotify) => inotify,
+            None => return,
+        };
+
+        let path_mask = WatchMask::MODIFY | WatchMask::CLOSE_WRITE | WatchMask::ATTRIB | WatchMask::CREATE
+            | WatchMask::DELETE | WatchMask::MOVE | WatchMask::DELETE_SELF | WatchMask::MOVE_SELF;
+        let ancestor_mask = WatchMask::CREATE | WatchMask::DELETE | WatchMask::MOVE
+            | WatchMask::DELETE_SELF | WatchMask::MOVE_SELF;
+
+        for condition in &self.conditions {
+            let path = condition.get_path();
+
+            if path.exists() {
+                if let Err(error) = inotify.watches().add(path, path_mask) {
+                    debug!("Path trigger for unit {} cannot watch {:?}: {}", self.unit_name, path, error);
+                }
+            }
+
+            let ancestor = path.ancestors().skip(1).find(|ancestor| ancestor.is_dir());
+            if let Some(ancestor) = ancestor {
+                if let Err(error) = inotify.watches().add(ancestor, ancestor_mask) {
+                    debug!("Path trigger for unit {} cannot watch {:?}: {}", self.unit_name, ancestor, error);
+                }
+            }
+        }
+    }
+}
+
+
+fn fingerprint(path: &Path) -> Option<Fingerprint> {
+    return match fs::metadata(path) {
+        Ok(metadata) => Some((metadata.ino(), metadata.mtime(), metadata.mtime_nsec(), metadata.size())),
+        Err(_) => None,
+    };
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("tsm-unitman-path-test-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        return dir;
+    }
+
+    #[test]
+    fn on_path_exists_should_trigger_after_debounce() {
+        let dir = test_dir("exists");
+        let path = dir.join("update").join("firmware.bin");
+        let mut trigger = PathTrigger::new(String::from("test"), vec![PathCondition::Exists(path.clone())], 2, 10, 5);
+        let now = Local::now();
+
+        trigger.update(&now);
+        assert_eq!(trigger.get_state(), PathTriggerState::Waiting);
+
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, "firmware").unwrap();
+
+        trigger.update(&now);
+        assert_eq!(trigger.get_state(), PathTriggerState::Pending);
+        assert!(!trigger.is_due(&now));
+        assert!(trigger.is_due(&(now + Duration::seconds(2))));
+
+        trigger.trigger(&(now + Duration::seconds(2)));
+        assert_eq!(trigger.get_last_path(), Some(path));
+        assert_eq!(trigger.get_trigger_count(), 1);
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn on_path_changed_should_trigger_once_per_change() {
+        let dir = test_dir("changed");
+        let path = dir.join("config");
+        fs::write(&path, "a").unwrap();
+
+        let mut trigger = PathTrigger::new(String::from("test"), vec![PathCondition::Changed(path.clone())], 0, 10, 5);
+        let now = Local::now();
+
+        trigger.update(&now);
+        assert!(!trigger.is_due(&now));
+
+        fs::write(&path, "ab").unwrap();
+        trigger.update(&now);
+        assert!(trigger.is_due(&now));
+
+        trigger.trigger(&now);
+        trigger.update(&now);
+        assert!(!trigger.is_due(&now));
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn on_directory_not_empty_should_be_rate_limited() {
+        let dir = test_dir("not-empty");
+        fs::write(dir.join("file"), "").unwrap();
+
+        let mut trigger = PathTrigger::new(String::from("test"), vec![PathCondition::DirectoryNotEmpty(dir.clone())], 0, 10, 2);
+        let now = Local::now();
+
+        for _ in 0..2 {
+            trigger.update(&now);
+            assert!(trigger.is_due(&now));
+            trigger.trigger(&now);
+        }
+
+        trigger.update(&now);
+        assert_eq!(trigger.get_state(), PathTriggerState::RateLimited);
+        assert!(!trigger.is_due(&now));
+
+        let later = now + Duration::seconds(11);
+        trigger.update(&later);
+        assert!(trigger.is_due(&later));
+
+        let _ = fs::remove_dir_all(dir);
+    }
+}
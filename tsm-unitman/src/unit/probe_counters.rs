@@ -0,0 +1,65 @@
+use std::time::Duration;
+
+use crate::unit::{ProbeResult, ProbeState};
+
+
+/// Totals over all probes since unitman started, other than the results these are never dropped
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ProbeCounters {
+    successes: u64,
+    failures: u64,
+    duration_total: Duration,
+}
+
+
+impl ProbeCounters {
+    pub fn new() -> ProbeCounters {
+        ProbeCounters::default()
+    }
+
+    /// Only an alive probe counts as success
+    pub fn record(&mut self, result: &ProbeResult) {
+        match result.get_state() {
+            ProbeState::Alive => self.successes += 1,
+            _ => self.failures += 1,
+        };
+
+        self.duration_total += result.get_duration();
+    }
+
+    pub fn get_successes(&self) -> u64 {
+        self.successes
+    }
+
+    pub fn get_failures(&self) -> u64 {
+        self.failures
+    }
+
+    pub fn get_count(&self) -> u64 {
+        self.successes + self.failures
+    }
+
+    pub fn get_duration_total(&self) -> Duration {
+        self.duration_total
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use chrono::Local;
+    use super::*;
+
+    #[test]
+    fn record_should_count_anything_but_alive_as_failure() {
+        let mut counters = ProbeCounters::new();
+        counters.record(&ProbeResult::new(Local::now(), ProbeState::Alive, Duration::from_millis(100), Some(0), String::new()));
+        counters.record(&ProbeResult::new(Local::now(), ProbeState::Dead, Duration::from_millis(200), Some(1), String::new()));
+        counters.record(&ProbeResult::new(Local::now(), ProbeState::Undefined, Duration::ZERO, None, String::new()));
+
+        assert_eq!(counters.get_successes(), 1);
+        assert_eq!(counters.get_failures(), 2);
+        assert_eq!(counters.get_count(), 3);
+        assert_eq!(counters.get_duration_total(), Duration::from_millis(300));
+    }
+}
@@ -1,6 +1,6 @@
 use log::{debug};
 
-use crate::unit::{ProbeState, LivenessProbe, ProcessProbe};
+use crate::unit::{ProbeState, LivenessProbe, ProcessProbe, EventSender};
 
 
 #[derive(Debug)]
@@ -9,6 +9,7 @@ pub struct ProbeManager {
     liveness_probe: Option<LivenessProbe>,
     process_probe: Option<ProcessProbe>,
     is_running: bool,
+    events: EventSender,
 }
 
 
@@ -19,17 +20,37 @@ impl ProbeManager {
             liveness_probe: None,
             process_probe: None,
             is_running: false,
+            events: EventSender::default(),
         };
     }
 
-    pub fn set_liveness_probe(&mut self, probe: LivenessProbe) {
+    pub fn set_liveness_probe(&mut self, mut probe: LivenessProbe) {
+        probe.set_event_sender(self.events.clone());
         self.liveness_probe = Some(probe);
     }
 
-    pub fn set_process_probe(&mut self, probe: ProcessProbe) {
+    pub fn set_process_probe(&mut self, mut probe: ProcessProbe) {
+        probe.set_event_sender(self.events.clone());
         self.process_probe = Some(probe);
     }
 
+    /// Applies to the probes set so far and to those set later on
+    pub fn set_event_sender(&mut self, events: EventSender) {
+        if let Some(ref mut liveness_probe) = self.liveness_probe {
+            liveness_probe.set_event_sender(events.clone());
+        }
+
+        if let Some(ref mut process_probe) = self.process_probe {
+            process_probe.set_event_sender(events.clone());
+        }
+
+        self.events = events;
+    }
+
+    pub fn get_liveness_probe(&self) -> Option<LivenessProbe> {
+        self.liveness_probe.clone()
+    }
+
     pub fn get_process_probe_state(&self) -> ProbeState {
         if !self.is_running {
             return ProbeState::Undefined;
@@ -65,7 +86,7 @@ impl ProbeManager {
     pub fn start_probes(&mut self) {
         // Start process probe
         match self.process_probe {
-            Some(ref process_probe) => {
+            Some(ref mut process_probe) => {
                 process_probe.run();
             },
             None => {
@@ -75,7 +96,7 @@ impl ProbeManager {
 
         // Start liveness probe
         match self.liveness_probe {
-            Some(ref liveness_probe) => {
+            Some(ref mut liveness_probe) => {
                 liveness_probe.run();
             },
             None => {
@@ -0,0 +1,54 @@
+use std::time::Duration;
+use chrono::{DateTime, Local};
+
+use crate::unit::ProbeState;
+
+
+#[derive(Debug, Clone)]
+pub struct ProbeResult {
+    timestamp: DateTime<Local>,
+    state: ProbeState,
+    duration: Duration,
+    exit_code: Option<i32>,
+    message: String,
+}
+
+
+impl ProbeResult {
+    pub fn new(
+        timestamp: DateTime<Local>,
+        state: ProbeState,
+        duration: Duration,
+        exit_code: Option<i32>,
+        message: String,
+    ) -> ProbeResult {
+        ProbeResult {
+            timestamp,
+            state,
+            duration,
+            exit_code,
+            message,
+        }
+    }
+
+    pub fn get_timestamp(&self) -> DateTime<Local> {
+        self.timestamp
+    }
+
+    pub fn get_state(&self) -> ProbeState {
+        self.state.clone()
+    }
+
+    pub fn get_duration(&self) -> Duration {
+        self.duration
+    }
+
+    /// None if the probe timed out, was killed by a signal or could not be executed
+    pub fn get_exit_code(&self) -> Option<i32> {
+        self.exit_code
+    }
+
+    pub fn get_message(&self) -> String {
+        self.message.clone()
+    }
+}
@@ -0,0 +1,112 @@
+use std::fs;
+
+
+/// The fields of /proc/<pid>/stat needed to recognize a process again, and its resource usage
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProcStat {
+    state: char,
+    ppid: u32,
+    start_time: u64,
+    cpu_time: u64,
+    rss_pages: u64,
+}
+
+
+impl ProcStat {
+    pub fn read(pid: u32) -> Option<ProcStat> {
+        let content = fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+        return ProcStat::parse(&content);
+    }
+
+    /// The command name may contain spaces and parentheses, so fields are counted after the last ')'
+    fn parse(content: &str) -> Option<ProcStat> {
+        let fields: Vec<&str> = content[content.rfind(')')? + 1..].split_whitespace().collect();
+
+        // field 3 is the state, field 4 the parent pid, fields 14 and 15 the user and system time,
+        // field 22 the start time and field 24 the resident set size, counted from the pid as field 1
+        let state = fields.first()?.chars().next()?;
+        let ppid = fields.get(1)?.parse().ok()?;
+        let utime: u64 = fields.get(11)?.parse().ok()?;
+        let stime: u64 = fields.get(12)?.parse().ok()?;
+        let start_time = fields.get(19)?.parse().ok()?;
+        let rss_pages = fields.get(21)?.parse().ok()?;
+
+        Some(ProcStat { state, ppid, start_time, cpu_time: utime + stime, rss_pages })
+    }
+
+    pub fn get_ppid(&self) -> u32 {
+        self.ppid
+    }
+
+    /// Clock ticks after boot at which the process was started. Together with the pid this
+    /// identifies a process, since pids are reused.
+    pub fn get_start_time(&self) -> u64 {
+        self.start_time
+    }
+
+    /// Seconds the process spent on the CPU in user and kernel mode, without its children
+    pub fn get_cpu_time_s(&self) -> Option<f64> {
+        let ticks_per_s = unsafe { libc::sysconf(libc::_SC_CLK_TCK) };
+
+        if ticks_per_s <= 0 {
+            return None;
+        }
+
+        return Some(self.cpu_time as f64 / ticks_per_s as f64);
+    }
+
+    pub fn get_rss_bytes(&self) -> Option<u64> {
+        let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+
+        if page_size <= 0 {
+            return None;
+        }
+
+        return Some(self.rss_pages * page_size as u64);
+    }
+
+    pub fn is_zombie(&self) -> bool {
+        self.state == 'Z' || self.state == 'X'
+    }
+
+    /// Seconds the process has been running
+    pub fn get_uptime_s(&self) -> Option<u64> {
+        let uptime = fs::read_to_string("/proc/uptime").ok()?;
+        let system_uptime_s: f64 = uptime.split_whitespace().next()?.parse().ok()?;
+        let ticks_per_s = unsafe { libc::sysconf(libc::_SC_CLK_TCK) };
+
+        if ticks_per_s <= 0 {
+            return None;
+        }
+
+        let started_s = self.start_time as f64 / ticks_per_s as f64;
+        return Some((system_uptime_s - started_s).max(0.0) as u64);
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_should_handle_odd_command_names() {
+        let content = "1234 (my (odd) cmd) S 1 1234 1234 0 -1 4194560 100 0 0 0 1 2 0 0 20 0 1 0 987654 1000 10";
+        let stat = ProcStat::parse(content).unwrap();
+
+        assert_eq!(stat.get_start_time(), 987654);
+        assert_eq!(stat.get_ppid(), 1);
+        assert_eq!(stat.is_zombie(), false);
+        assert_eq!(stat.cpu_time, 3);
+        assert_eq!(stat.rss_pages, 10);
+    }
+
+    #[test]
+    fn read_should_find_own_process() {
+        let stat = ProcStat::read(std::process::id()).unwrap();
+
+        assert!(stat.get_start_time() > 0);
+        assert!(stat.get_uptime_s().is_some());
+        assert!(stat.get_rss_bytes().unwrap() > 0);
+    }
+}
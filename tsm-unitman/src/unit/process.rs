@@ -1,9 +1,15 @@
 use std::io::ErrorKind;
 use std::process::{Child, Command, Stdio, ExitStatus};
-use std::os::unix::process::CommandExt;
+use std::os::unix::process::{CommandExt, ExitStatusExt};
+use std::thread;
 use std::time::{Duration, Instant};
 use log::{warn, debug};
 
+use crate::unit::{ListenFds, OutputLog, ProcStat, Sandbox, Signal, UnitError};
+
+
+const ADOPTED_STOP_TIMEOUT: Duration = Duration::from_secs(5);
+
 
 #[derive(Debug)]
 pub struct Process {
@@ -11,8 +17,14 @@ pub struct Process {
     arguments: Vec<String>,
     uid: u32,
     gid: u32,
+    sandbox: Sandbox,
+    listen_fds: ListenFds,
+    output_log: Option<OutputLog>,
     child: Option<Child>,
+    adopted_pid: Option<u32>,
+    start_time: Option<u64>,
     start_timestamp: Option<Instant>,
+    last_exit_status: Option<ExitStatus>,
 }
 
 
@@ -28,11 +40,54 @@ impl Process {
             arguments,
             uid,
             gid,
+            sandbox: Sandbox::new(),
+            listen_fds: ListenFds::new(),
+            output_log: None,
             child: None,
+            adopted_pid: None,
+            start_time: None,
             start_timestamp: None,
+            last_exit_status: None,
         };
     }
 
+    pub fn set_sandbox(&mut self, sandbox: Sandbox) {
+        self.sandbox = sandbox;
+    }
+
+    /// The fds are passed on every start, the process is expected to serve them
+    pub fn set_listen_fds(&mut self, listen_fds: ListenFds) {
+        self.listen_fds = listen_fds;
+    }
+
+    pub fn get_sandbox(&self) -> Sandbox {
+        self.sandbox.clone()
+    }
+
+    /// Without an output log, stdout and stderr are discarded
+    pub fn set_output_log(&mut self, output_log: OutputLog) {
+        self.output_log = Some(output_log);
+    }
+
+    pub fn get_output_log(&self) -> Option<OutputLog> {
+        self.output_log.clone()
+    }
+
+    /// Returns a new, not yet started process with the same configuration
+    pub fn duplicate(&self) -> Process {
+        let mut process = Process::new(
+            self.executable.clone(),
+            self.arguments.clone(),
+            self.uid,
+            self.gid,
+        );
+        process.set_sandbox(self.sandbox.clone());
+        process.set_listen_fds(self.listen_fds.clone());
+        process.output_log = self.output_log.clone();
+
+        return process;
+    }
+
     pub fn get_executable(&self) -> String {
         self.executable.clone()
     }
@@ -52,10 +107,99 @@ impl Process {
     pub fn get_pid(&self) -> Option<u32> {
         return match self.child {
             Some(ref child) => Some(child.id()),
-            None => None,
+            None => self.adopted_pid,
+        };
+    }
+
+    /// Start time of the running process in clock ticks after boot, see ProcStat
+    pub fn get_start_time(&self) -> Option<u64> {
+        self.start_time
+    }
+
+    /// How the process exited the last time it was not stopped by us
+    pub fn get_last_exit_status(&self) -> Option<ExitStatus> {
+        self.last_exit_status
+    }
+
+    /// The process exited on its own with a non-zero exit code or by a signal
+    pub fn has_failed(&self) -> bool {
+        return match self.last_exit_status {
+            Some(exit_status) => !exit_status.success(),
+            None => false,
+        };
+    }
+
+    pub fn reset_failed(&mut self) {
+        self.last_exit_status = None;
+    }
+
+    /// Sends a signal to the process, or to its whole process group
+    pub fn signal(&mut self, signal: Signal, group: bool) -> Result<bool, UnitError> {
+        if !self.is_running() {
+            return Err(UnitError::NotRunning(format!("Process {} is not running", self.executable)));
+        }
+
+        let pid = match self.get_pid() {
+            Some(pid) => pid as libc::pid_t,
+            None => return Err(UnitError::NotRunning(format!("Process {} is not running", self.executable))),
+        };
+
+        let result = match group {
+            // processes adopted from older versions of unitman may still share its process group
+            true if unsafe { libc::getpgid(pid) } != pid => {
+                return Err(UnitError::InvalidArgument(format!("Process {} does not lead its own process group", self.executable)));
+            },
+            true => unsafe { libc::killpg(pid, signal.get_number()) },
+            false => unsafe { libc::kill(pid, signal.get_number()) },
+        };
+
+        return match result {
+            0 => {
+                debug!("Sent {} to process {} (pid={}, group={})", signal, self.executable, pid, group);
+                Ok(true)
+            },
+            _ => {
+                let error = std::io::Error::last_os_error();
+                let message = format!("Process {} failed to receive {}: {}", self.executable, signal, error);
+
+                match error.kind() {
+                    ErrorKind::PermissionDenied => Err(UnitError::PermissionDenied(message)),
+                    _ => Err(UnitError::Failed(message)),
+                }
+            },
         };
     }
 
+    /// Takes over a process which was started by a previous instance of unitman. The start time
+    /// makes sure that the pid was not reused by an unrelated process in the meantime.
+    /// Returns false if the process is gone.
+    pub fn adopt(&mut self, pid: u32, start_time: u64) -> bool {
+        if self.is_running() {
+            debug!("Cannot adopt process {} because it is already running", self.executable);
+            return false;
+        }
+
+        let stat = match ProcStat::read(pid) {
+            Some(stat) if stat.get_start_time() == start_time => stat,
+            _ => return false,
+        };
+
+        if stat.is_zombie() {
+            // reaps the process in case it exited while unitman re-executed itself
+            unsafe { libc::waitpid(pid as i32, std::ptr::null_mut(), libc::WNOHANG) };
+            return false;
+        }
+
+        let uptime = Duration::from_secs(stat.get_uptime_s().unwrap_or(0));
+
+        debug!("Process {} was adopted (pid={})", self.executable, pid);
+        self.adopted_pid = Some(pid);
+        self.start_time = Some(start_time);
+        self.start_timestamp = Instant::now().checked_sub(uptime).or(Some(Instant::now()));
+
+        return true;
+    }
+
     pub fn get_uptime(&self) -> Option<Duration> {
         return match self.start_timestamp {
             Some(timestamp) => {
@@ -78,16 +222,22 @@ impl Process {
             return false;
         }
 
-        return true;
+        // The exit code of an adopted process is unknown if it is not our child
+        return self.get_pid().is_some();
     }
 
     pub fn exit_code(&mut self) -> Option<ExitStatus> {
+        if let Some(pid) = self.adopted_pid {
+            return self.adopted_exit_code(pid);
+        }
+
         return match self.child {
             Some(ref mut child) => {
                 match child.try_wait() {
                     Ok(Some(exit_code)) => {
                         // Process is not running anymore
                         self.cleanup();
+                        self.last_exit_status = Some(exit_code);
                         debug!("Process {} exited with code {}", self.executable, exit_code);
                         Some(ExitStatus::from(exit_code))
                     }
@@ -98,44 +248,160 @@ impl Process {
         };
     }
 
+    /// Adopted processes are still our children after a re-exec, but not after unitman crashed.
+    /// In that case they are reaped by someone else and only their disappearance is noticed.
+    fn adopted_exit_code(&mut self, pid: u32) -> Option<ExitStatus> {
+        let mut status = 0;
+        let result = unsafe { libc::waitpid(pid as i32, &mut status, libc::WNOHANG) };
+
+        if result == pid as i32 {
+            self.cleanup();
+            let exit_code = ExitStatus::from_raw(status);
+            self.last_exit_status = Some(exit_code);
+            debug!("Process {} exited with code {}", self.executable, exit_code);
+            return Some(exit_code);
+        }
+
+        if result < 0 && !self.is_adopted_process_alive(pid) {
+            debug!("Process {} exited", self.executable);
+            self.cleanup();
+        }
+
+        None
+    }
+
+    fn is_adopted_process_alive(&self, pid: u32) -> bool {
+        return match ProcStat::read(pid) {
+            Some(stat) => Some(stat.get_start_time()) == self.start_time && !stat.is_zombie(),
+            None => false,
+        };
+    }
+
     /// Starts the child process
-    pub fn start(&mut self) -> Result<bool, String> {
+    pub fn start(&mut self) -> Result<bool, UnitError> {
         if self.is_running() {
             debug!("Cannot start process {} because it is already running", self.executable);
             return Ok(false);
         }
 
-        let child = Command::new(&self.executable)
+        let (stdout, stderr) = self.open_output();
+
+        // Every process leads its own process group, so that it can be signalled as a whole
+        let mut command = Command::new(&self.executable);
+        command
+            .process_group(0)
             .args(&self.arguments)
-            .stdout(Stdio::null())
-            .stderr(Stdio::null())
-            .uid(self.uid)
-            .gid(self.gid)
-            .spawn();
+            .stdout(stdout)
+            .stderr(stderr);
+
+        let mut error_channel = None;
+
+        if self.sandbox.is_empty() {
+            command.uid(self.uid).gid(self.gid);
+        } else {
+            // The sandbox switches uid/gid itself since namespaces, mounts, chroot and the bounding
+            // set must be set up while still privileged, and std runs pre_exec hooks after setuid.
+            let (mut prepared_sandbox, channel) = match self.sandbox.prepare(self.uid, self.gid) {
+                Ok(prepared) => prepared,
+                Err(error) => {
+                    return Err(UnitError::Failed(format!("Process {} failed to prepare sandbox: {}", self.executable, error)));
+                }
+            };
 
+            unsafe {
+                command.pre_exec(move || prepared_sandbox.apply());
+            }
+
+            error_channel = Some(channel);
+        }
+
+        // Registered last, since it execs the process itself to be able to set LISTEN_PID
+        if !self.listen_fds.is_empty() {
+            let mut prepared_exec = match self.listen_fds.prepare(&self.executable, &self.arguments) {
+                Ok(prepared) => prepared,
+                Err(error) => {
+                    return Err(UnitError::Failed(format!("Process {} failed to prepare listen fds: {}", self.executable, error)));
+                }
+            };
+
+            unsafe {
+                command.pre_exec(move || Err(prepared_exec.exec()));
+            }
+        }
+
+        let child = command.spawn();
+
+        if child.is_err() {
+            match error_channel.and_then(|channel| channel.receive()) {
+                Some(report) => {
+                    self.cleanup();
+                    return Err(UnitError::Failed(format!("Process {} failed to apply sandbox: {}", self.executable, self.sandbox.to_error(report))));
+                }
+                None => {}
+            }
+        }
+
+        self.handle_spawn_result(child)
+    }
+
+    fn open_output(&self) -> (Stdio, Stdio) {
+        let output_log = match self.output_log {
+            Some(ref output_log) => output_log,
+            None => return (Stdio::null(), Stdio::null()),
+        };
+
+        let files = output_log.open().and_then(|file| Ok((file.try_clone()?, file)));
+
+        return match files {
+            Ok((stdout, stderr)) => (Stdio::from(stdout), Stdio::from(stderr)),
+            Err(error) => {
+                warn!("Process {} cannot capture its output in {:?}: {}", self.executable, output_log.get_path(), error);
+                (Stdio::null(), Stdio::null())
+            }
+        };
+    }
+
+    fn handle_spawn_result(&mut self, child: std::io::Result<Child>) -> Result<bool, UnitError> {
         match child {
             Ok(child) => {
                 debug!("Process {} was started", self.executable);
+                self.start_time = ProcStat::read(child.id()).map(|stat| stat.get_start_time());
                 self.child = Some(child);
                 self.start_timestamp = Some(Instant::now());
+                self.last_exit_status = None;
                 Ok(true)
             }
             Err(error) => {
                 self.cleanup();
-                Err(format!("Process {} failed to start: {}", self.executable, error))
+                let message = format!("Process {} failed to start: {}", self.executable, error);
+
+                match error.kind() {
+                    ErrorKind::PermissionDenied => Err(UnitError::PermissionDenied(message)),
+                    _ => Err(UnitError::Failed(message)),
+                }
             }
         }
     }
 
     /// Stops the child process
-    pub fn stop(&mut self) -> Result<bool, String> {
+    pub fn stop(&mut self) -> Result<bool, UnitError> {
         if !self.is_running() {
             debug!("Cannot stop process {} because it is not running", self.executable);
             return Ok(false);
         }
 
+        if let Some(pid) = self.adopted_pid {
+            return self.stop_adopted(pid);
+        }
+
         match self.child {
             Some(ref mut child) => {
+                // takes down the processes the unit started itself, they share its process group
+                let pid = child.id() as libc::pid_t;
+                if unsafe { libc::getpgid(pid) } == pid {
+                    unsafe { libc::killpg(pid, libc::SIGKILL) };
+                }
+
                 match child.kill() {
                     Ok(_) => {
                         match child.wait() {
@@ -145,7 +411,7 @@ impl Process {
                                 Ok(true)
                             }
                             Err(error) => {
-                                Err(format!("Process {} failed to wait: {}", self.executable, error))
+                                Err(UnitError::Failed(format!("Process {} failed to wait: {}", self.executable, error)))
                             }
                         }
                     }
@@ -156,7 +422,7 @@ impl Process {
                             return Ok(true);
                         }
 
-                        Err(format!("Process {} failed to stop: {}", self.executable, error))
+                        Err(UnitError::Failed(format!("Process {} failed to stop: {}", self.executable, error)))
                     }
                 }
             }
@@ -167,7 +433,38 @@ impl Process {
         }
     }
 
-    pub fn restart(&mut self) -> Result<bool, String> {
+    fn stop_adopted(&mut self, pid: u32) -> Result<bool, UnitError> {
+        if unsafe { libc::getpgid(pid as i32) } == pid as i32 {
+            unsafe { libc::killpg(pid as i32, libc::SIGKILL) };
+        }
+
+        if unsafe { libc::kill(pid as i32, libc::SIGKILL) } < 0 {
+            let error = std::io::Error::last_os_error();
+
+            if error.raw_os_error() == Some(libc::ESRCH) {
+                debug!("Process {} has already stopped", self.executable);
+                self.cleanup();
+                return Ok(true);
+            }
+
+            return Err(UnitError::Failed(format!("Process {} failed to stop: {}", self.executable, error)));
+        }
+
+        let deadline = Instant::now() + ADOPTED_STOP_TIMEOUT;
+
+        while self.is_running() {
+            if Instant::now() > deadline {
+                return Err(UnitError::Timeout(format!("Process {} did not stop within {:?}", self.executable, ADOPTED_STOP_TIMEOUT)));
+            }
+
+            thread::sleep(Duration::from_millis(50));
+        }
+
+        debug!("Process {} was stopped", self.executable);
+        Ok(true)
+    }
+
+    pub fn restart(&mut self) -> Result<bool, UnitError> {
         debug!("Restarting process {}", self.executable);
         match self.stop() {
             Ok(_) => {
@@ -187,6 +484,8 @@ impl Process {
     }
 
     fn cleanup(&mut self) {
+        self.adopted_pid = None;
+        self.start_time = None;
         self.start_timestamp = None;
         self.cleanup_process_handles();
     }
@@ -1,11 +1,12 @@
 use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::thread;
 use std::thread::JoinHandle;
 use std::time::{Duration, Instant};
 use sysinfo::{Pid, PidExt, ProcessRefreshKind, System, SystemExt};
 use log::{debug, warn, error};
 
-use crate::unit::ProbeState;
+use crate::unit::{EventSender, ProbeState, ProbeType, UnitEventKind};
 
 
 #[derive(Clone, Debug)]
@@ -15,8 +16,9 @@ pub struct ProcessProbe {
     interval_s: i32,
     system_info: Arc<Mutex<System>>,
     state: Arc<Mutex<ProbeState>>,
-    stop_requested: bool,
+    stop_requested: Arc<AtomicBool>,
     probe_timestamp: Instant,
+    events: EventSender,
 }
 
 
@@ -32,8 +34,9 @@ impl ProcessProbe {
             interval_s,
             system_info: Arc::new(Mutex::new(System::new())),
             state: Arc::new(Mutex::new(ProbeState::Undefined)),
-            stop_requested: false,
+            stop_requested: Arc::new(AtomicBool::new(false)),
             probe_timestamp: Instant::now(),
+            events: EventSender::default(),
         };
     }
 
@@ -47,9 +50,23 @@ impl ProcessProbe {
         }
     }
 
+    pub fn set_event_sender(&mut self, events: EventSender) {
+        self.events = events;
+    }
+
     fn set_state(&mut self, new_state: ProbeState) {
         match self.state.try_lock() {
-            Ok(mut state) => *state = new_state.clone(),
+            Ok(mut state) => {
+                if *state != new_state {
+                    self.events.send(self.name.clone(), UnitEventKind::ProbeChanged {
+                        probe: ProbeType::Process,
+                        from: state.clone(),
+                        to: new_state.clone(),
+                    });
+                }
+
+                *state = new_state.clone();
+            },
             Err(e) => {
                 error!("Liveness probe for unit {} failed to lock state: {}", self.name, e)
             },
@@ -58,10 +75,14 @@ impl ProcessProbe {
 
     /// Set stop_requested flag to true
     pub fn request_stop(&mut self) {
-        self.stop_requested = true;
+        self.stop_requested.store(true, Ordering::Relaxed);
     }
 
-    pub fn run(&self) -> JoinHandle<()> {
+    /// Every run gets its own stop flag, so that a stopped run never picks up a later start.
+    /// A previous run which was not stopped is stopped now.
+    pub fn run(&mut self) -> JoinHandle<()> {
+        self.request_stop();
+        self.stop_requested = Arc::new(AtomicBool::new(false));
         let mut self_clone = self.clone();
         return thread::spawn(move || self_clone.run_loop());
     }
@@ -71,7 +92,7 @@ impl ProcessProbe {
         debug!("Process probe for unit {} starting", self.name);
 
         loop {
-            if self.stop_requested {
+            if self.stop_requested.load(Ordering::Relaxed) {
                 debug!("Process probe for unit {} stop requested", self.name);
                 break;
             }
@@ -85,7 +106,6 @@ impl ProcessProbe {
         }
 
         self.set_state(ProbeState::Dead);
-        self.stop_requested = false;
 
         debug!("Process probe for unit {} stopped", self.name);
     }
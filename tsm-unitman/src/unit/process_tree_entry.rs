@@ -0,0 +1,116 @@
+use std::fs;
+
+use crate::unit::ProcStat;
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProcessTreeEntry {
+    pid: u32,
+    depth: usize,
+    command: String,
+}
+
+
+impl ProcessTreeEntry {
+    /// The process and all of its descendants in depth-first order, the process itself has depth 0
+    pub fn collect(pid: u32) -> Vec<ProcessTreeEntry> {
+        let mut parents: Vec<(u32, u32)> = Vec::new();
+
+        if let Ok(entries) = fs::read_dir("/proc") {
+            for entry in entries.flatten() {
+                let child_pid = match entry.file_name().to_string_lossy().parse::<u32>() {
+                    Ok(child_pid) => child_pid,
+                    Err(_) => continue,
+                };
+
+                if let Some(stat) = ProcStat::read(child_pid) {
+                    parents.push((child_pid, stat.get_ppid()));
+                }
+            }
+        }
+
+        let mut tree = Vec::new();
+
+        if ProcStat::read(pid).is_some() {
+            ProcessTreeEntry::collect_children(pid, 0, &parents, &mut tree);
+        }
+
+        return tree;
+    }
+
+    fn collect_children(pid: u32, depth: usize, parents: &Vec<(u32, u32)>, tree: &mut Vec<ProcessTreeEntry>) {
+        tree.push(ProcessTreeEntry {
+            pid,
+            depth,
+            command: read_command(pid),
+        });
+
+        let mut children: Vec<u32> = parents.iter()
+            .filter(|(_, parent)| *parent == pid)
+            .map(|(child, _)| *child)
+            .collect();
+        children.sort();
+
+        for child in children {
+            ProcessTreeEntry::collect_children(child, depth + 1, parents, tree);
+        }
+    }
+
+    pub fn get_pid(&self) -> u32 {
+        self.pid
+    }
+
+    pub fn get_depth(&self) -> usize {
+        self.depth
+    }
+
+    pub fn get_command(&self) -> String {
+        self.command.clone()
+    }
+}
+
+
+/// Kernel threads and zombies have no command line, their name is used instead
+fn read_command(pid: u32) -> String {
+    if let Ok(cmdline) = fs::read(format!("/proc/{}/cmdline", pid)) {
+        let arguments: Vec<String> = cmdline.split(|byte| *byte == 0)
+            .filter(|argument| !argument.is_empty())
+            .map(|argument| String::from_utf8_lossy(argument).to_string())
+            .collect();
+
+        if !arguments.is_empty() {
+            return arguments.join(" ");
+        }
+    }
+
+    return match fs::read_to_string(format!("/proc/{}/comm", pid)) {
+        Ok(comm) => format!("[{}]", comm.trim()),
+        Err(_) => String::new(),
+    };
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn collect_should_find_descendants() {
+        let mut child = std::process::Command::new("sh")
+            .args(["-c", "sleep 5 & wait"])
+            .spawn()
+            .unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(300));
+
+        let tree = ProcessTreeEntry::collect(child.id());
+
+        assert_eq!(tree.len(), 2);
+        assert_eq!(tree[0].get_pid(), child.id());
+        assert_eq!(tree[0].get_command(), "sh -c sleep 5 & wait");
+        assert_eq!(tree[1].get_depth(), 1);
+        assert_eq!(tree[1].get_command(), "sleep 5");
+
+        unsafe { libc::kill(tree[1].get_pid() as i32, libc::SIGKILL) };
+        child.wait().unwrap();
+    }
+}
@@ -1,5 +1,5 @@
 use std::str::FromStr;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use serde::de::Error;
 
 
@@ -22,6 +22,21 @@ impl<'de> Deserialize<'de> for RestartPolicy {
 }
 
 
+impl Serialize for RestartPolicy {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: serde::Serializer,
+    {
+        let policy = match self {
+            RestartPolicy::Always => "always",
+            RestartPolicy::Never => "never",
+            RestartPolicy::DisabledTemporarily => "disabled*",
+        };
+        serializer.serialize_str(policy)
+    }
+}
+
+
 impl FromStr for RestartPolicy {
     type Err = String;
 
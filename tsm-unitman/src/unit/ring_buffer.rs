@@ -0,0 +1,49 @@
+use std::collections::VecDeque;
+
+
+/// Keeps the last items pushed, the oldest ones are dropped once the capacity is reached
+#[derive(Debug, Clone)]
+pub struct RingBuffer<T> {
+    capacity: usize,
+    items: VecDeque<T>,
+}
+
+
+impl<T: Clone> RingBuffer<T> {
+    pub fn new(capacity: usize) -> RingBuffer<T> {
+        RingBuffer {
+            capacity,
+            items: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    pub fn push(&mut self, item: T) {
+        if self.items.len() == self.capacity {
+            self.items.pop_front();
+        }
+
+        self.items.push_back(item);
+    }
+
+    /// Oldest item first
+    pub fn to_vec(&self) -> Vec<T> {
+        self.items.iter().cloned().collect()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn push_should_drop_oldest_items() {
+        let mut buffer = RingBuffer::new(3);
+
+        for item in 0..5 {
+            buffer.push(item);
+        }
+
+        assert_eq!(buffer.to_vec(), vec![2, 3, 4]);
+    }
+}
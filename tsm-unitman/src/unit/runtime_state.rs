@@ -0,0 +1,149 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+
+use crate::unit::RestartPolicy;
+
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct UnitRuntimeState {
+    pid: Option<u32>,
+    start_time: Option<u64>,
+    #[serde(default)]
+    restart_count: u32,
+    restart_policy: Option<RestartPolicy>,
+}
+
+
+impl UnitRuntimeState {
+    pub fn new(
+        pid: Option<u32>,
+        start_time: Option<u64>,
+        restart_count: u32,
+        restart_policy: Option<RestartPolicy>,
+    ) -> UnitRuntimeState {
+        UnitRuntimeState {
+            pid,
+            start_time,
+            restart_count,
+            restart_policy,
+        }
+    }
+
+    pub fn get_pid(&self) -> Option<u32> {
+        self.pid
+    }
+
+    /// Clock ticks after boot at which the process was started, see ProcStat
+    pub fn get_start_time(&self) -> Option<u64> {
+        self.start_time
+    }
+
+    pub fn get_restart_count(&self) -> u32 {
+        self.restart_count
+    }
+
+    /// Only set if the restart policy was changed at runtime
+    pub fn get_restart_policy(&self) -> Option<RestartPolicy> {
+        self.restart_policy.clone()
+    }
+}
+
+
+/// What unitman needs to know to pick up its units again after a crash, update or re-exec.
+/// Stored as toml in the state directory and rewritten whenever it changes.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct RuntimeState {
+    active_target: Option<String>,
+    #[serde(default)]
+    units: BTreeMap<String, UnitRuntimeState>,
+}
+
+
+impl RuntimeState {
+    /// A missing file means there is nothing to pick up
+    pub fn load(path: &Path) -> Result<RuntimeState, String> {
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(RuntimeState::default()),
+            Err(error) => return Err(format!("Error reading runtime state {:?}: {}", path, error)),
+        };
+
+        return match toml::from_str(&content) {
+            Ok(state) => Ok(state),
+            Err(error) => Err(format!("Error parsing runtime state {:?}: {}", path, error)),
+        };
+    }
+
+    /// Writes to a temporary file first, so that a crash never leaves a truncated file behind
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let content = match toml::to_string(self) {
+            Ok(content) => content,
+            Err(error) => return Err(format!("Error serializing runtime state: {}", error)),
+        };
+
+        if let Some(parent) = path.parent() {
+            if let Err(error) = fs::create_dir_all(parent) {
+                return Err(format!("Error creating state directory {:?}: {}", parent, error));
+            }
+        }
+
+        let temporary_path = path.with_extension("tmp");
+
+        if let Err(error) = fs::write(&temporary_path, content) {
+            return Err(format!("Error writing runtime state {:?}: {}", temporary_path, error));
+        }
+
+        return match fs::rename(&temporary_path, path) {
+            Ok(_) => Ok(()),
+            Err(error) => Err(format!("Error writing runtime state {:?}: {}", path, error)),
+        };
+    }
+
+    pub fn get_active_target(&self) -> Option<String> {
+        self.active_target.clone()
+    }
+
+    pub fn set_active_target(&mut self, active_target: Option<String>) {
+        self.active_target = active_target;
+    }
+
+    pub fn get(&self, name: &str) -> Option<&UnitRuntimeState> {
+        self.units.get(name)
+    }
+
+    pub fn set(&mut self, name: String, state: UnitRuntimeState) {
+        self.units.insert(name, state);
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn save_and_load_should_work() {
+        let dir = std::env::temp_dir().join(format!("tsm-unitman-runtime-state-test-{}", std::process::id()));
+        let path = dir.join("runtime.toml");
+        let _ = fs::remove_dir_all(&dir);
+
+        let state = RuntimeState::load(&path).unwrap();
+        assert_eq!(state, RuntimeState::default());
+
+        let mut state = RuntimeState::default();
+        state.set_active_target(Some(String::from("maintenance")));
+        state.set(String::from("foo"), UnitRuntimeState::new(Some(1234), Some(987654), 3, Some(RestartPolicy::DisabledTemporarily)));
+        state.set(String::from("bar"), UnitRuntimeState::new(None, None, 0, None));
+        state.save(&path).unwrap();
+
+        let loaded = RuntimeState::load(&path).unwrap();
+        assert_eq!(loaded, state);
+        assert_eq!(loaded.get("foo").unwrap().get_pid(), Some(1234));
+        assert_eq!(loaded.get("foo").unwrap().get_restart_policy(), Some(RestartPolicy::DisabledTemporarily));
+        assert_eq!(loaded.get("bar").unwrap().get_start_time(), None);
+
+        let _ = fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,699 @@
+use std::ffi::CString;
+use std::fs::File;
+use std::io;
+use std::io::Read;
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd, RawFd};
+use users::get_group_by_name;
+
+use crate::unit::{SandboxError, SyscallFilter, capabilities, seccomp};
+
+
+/// Opt-in hardening of a unit process. Everything is disabled by default, in which case the
+/// process is started with the privileges of unitman apart from uid/gid.
+#[derive(Debug, Clone)]
+pub struct Sandbox {
+    supplementary_groups: Vec<String>,
+    ambient_capabilities: Vec<String>,
+    capability_bounding_set: Option<Vec<String>>,
+    no_new_privileges: bool,
+    private_mounts: bool,
+    private_network: bool,
+    private_pid: bool,
+    private_ipc: bool,
+    private_tmp: bool,
+    read_only_paths: Vec<String>,
+    root_directory: Option<String>,
+    syscall_deny_list: Vec<String>,
+}
+
+
+impl Sandbox {
+    pub fn new() -> Sandbox {
+        Sandbox {
+            supplementary_groups: Vec::new(),
+            ambient_capabilities: Vec::new(),
+            capability_bounding_set: None,
+            no_new_privileges: false,
+            private_mounts: false,
+            private_network: false,
+            private_pid: false,
+            private_ipc: false,
+            private_tmp: false,
+            read_only_paths: Vec::new(),
+            root_directory: None,
+            syscall_deny_list: Vec::new(),
+        }
+    }
+
+    pub fn set_supplementary_groups(&mut self, groups: Vec<String>) {
+        self.supplementary_groups = groups;
+    }
+
+    pub fn set_ambient_capabilities(&mut self, capabilities: Vec<String>) {
+        self.ambient_capabilities = capabilities;
+    }
+
+    /// None leaves the bounding set untouched, Some drops every capability not in the list
+    pub fn set_capability_bounding_set(&mut self, capabilities: Option<Vec<String>>) {
+        self.capability_bounding_set = capabilities;
+    }
+
+    pub fn set_no_new_privileges(&mut self, no_new_privileges: bool) {
+        self.no_new_privileges = no_new_privileges;
+    }
+
+    pub fn set_private_mounts(&mut self, private_mounts: bool) {
+        self.private_mounts = private_mounts;
+    }
+
+    pub fn set_private_network(&mut self, private_network: bool) {
+        self.private_network = private_network;
+    }
+
+    /// Note that the unit process is started as pid 1 of the new namespace. It is reaped by an
+    /// intermediate process which keeps the pid unitman knows about.
+    pub fn set_private_pid(&mut self, private_pid: bool) {
+        self.private_pid = private_pid;
+    }
+
+    pub fn set_private_ipc(&mut self, private_ipc: bool) {
+        self.private_ipc = private_ipc;
+    }
+
+    pub fn set_private_tmp(&mut self, private_tmp: bool) {
+        self.private_tmp = private_tmp;
+    }
+
+    pub fn set_read_only_paths(&mut self, paths: Vec<String>) {
+        self.read_only_paths = paths;
+    }
+
+    pub fn set_root_directory(&mut self, root_directory: Option<String>) {
+        self.root_directory = root_directory;
+    }
+
+    /// Denied syscalls fail with EPERM. Implies no_new_privileges.
+    pub fn set_syscall_deny_list(&mut self, syscalls: Vec<String>) {
+        self.syscall_deny_list = syscalls;
+    }
+
+    /// True if no sandbox option is configured
+    pub fn is_empty(&self) -> bool {
+        return self.supplementary_groups.is_empty()
+            && self.ambient_capabilities.is_empty()
+            && self.capability_bounding_set.is_none()
+            && !self.no_new_privileges
+            && !self.private_mounts
+            && !self.private_network
+            && !self.private_pid
+            && !self.private_ipc
+            && !self.private_tmp
+            && self.read_only_paths.is_empty()
+            && self.root_directory.is_none()
+            && self.syscall_deny_list.is_empty();
+    }
+
+    /// The configured options, e.g. "private_tmp" or "read_only_paths=/etc,/usr"
+    pub fn describe(&self) -> Vec<String> {
+        let mut options = Vec::new();
+
+        let lists = [
+            ("supplementary_groups", &self.supplementary_groups),
+            ("ambient_capabilities", &self.ambient_capabilities),
+            ("read_only_paths", &self.read_only_paths),
+            ("syscall_deny_list", &self.syscall_deny_list),
+        ];
+        for (name, values) in lists {
+            if !values.is_empty() {
+                options.push(format!("{}={}", name, values.join(",")));
+            }
+        }
+
+        if let Some(ref capabilities) = self.capability_bounding_set {
+            options.push(format!("capability_bounding_set={}", capabilities.join(",")));
+        }
+
+        let flags = [
+            ("no_new_privileges", self.no_new_privileges),
+            ("private_mounts", self.private_mounts),
+            ("private_network", self.private_network),
+            ("private_pid", self.private_pid),
+            ("private_ipc", self.private_ipc),
+            ("private_tmp", self.private_tmp),
+        ];
+        for (name, enabled) in flags {
+            if enabled {
+                options.push(String::from(name));
+            }
+        }
+
+        if let Some(ref root_directory) = self.root_directory {
+            options.push(format!("root_directory={}", root_directory));
+        }
+
+        return options;
+    }
+
+    /// Resolves names and allocates everything the child needs, since only async-signal-safe
+    /// calls are allowed between fork and exec.
+    pub fn prepare(&self, uid: u32, gid: u32) -> Result<(PreparedSandbox, ErrorChannel), SandboxError> {
+        let mut groups = Vec::new();
+        for group_name in &self.supplementary_groups {
+            match get_group_by_name(group_name) {
+                Some(group) => groups.push(group.gid()),
+                None => return Err(SandboxError::UnknownGroup(group_name.clone())),
+            }
+        }
+
+        let ambient_capabilities = Self::resolve_capabilities(&self.ambient_capabilities)?;
+
+        let capability_bounding_set = match &self.capability_bounding_set {
+            Some(capabilities) => Some(Self::resolve_capabilities(capabilities)?),
+            None => None,
+        };
+
+        let mut denied_syscalls = Vec::new();
+        for syscall_name in &self.syscall_deny_list {
+            match seccomp::syscall_from_name(syscall_name) {
+                Some(syscall) => denied_syscalls.push(syscall),
+                None => return Err(SandboxError::UnknownSyscall(syscall_name.clone())),
+            }
+        }
+
+        let syscall_filter = match denied_syscalls.is_empty() {
+            true => None,
+            false => Some(SyscallFilter::new(&denied_syscalls)),
+        };
+
+        let mut read_only_paths = Vec::new();
+        for path in &self.read_only_paths {
+            read_only_paths.push(Self::to_c_string(path)?);
+        }
+
+        let root_directory = match &self.root_directory {
+            Some(path) => Some(Self::to_c_string(path)?),
+            None => None,
+        };
+
+        let private_mounts = self.private_mounts || self.private_tmp || !read_only_paths.is_empty();
+
+        let mut namespaces = 0;
+        if private_mounts {
+            namespaces |= libc::CLONE_NEWNS;
+        }
+        if self.private_network {
+            namespaces |= libc::CLONE_NEWNET;
+        }
+        if self.private_ipc {
+            namespaces |= libc::CLONE_NEWIPC;
+        }
+        if self.private_pid {
+            namespaces |= libc::CLONE_NEWPID;
+        }
+
+        let error_channel = ErrorChannel::open().map_err(SandboxError::ErrorChannel)?;
+
+        let prepared = PreparedSandbox {
+            uid,
+            gid,
+            groups,
+            ambient_capabilities,
+            capability_bounding_set,
+            last_capability: capabilities::last_capability(),
+            namespaces,
+            private_pid: self.private_pid,
+            private_mounts,
+            private_tmp: self.private_tmp,
+            tmp_path: CString::new("/tmp").unwrap(),
+            tmp_type: CString::new("tmpfs").unwrap(),
+            tmp_options: CString::new("mode=1777").unwrap(),
+            root_path: CString::new("/").unwrap(),
+            read_only_paths,
+            root_directory,
+            no_new_privileges: self.no_new_privileges || syscall_filter.is_some(),
+            syscall_filter,
+            error_fd: error_channel.write_fd(),
+        };
+
+        Ok((prepared, error_channel))
+    }
+
+    /// Translates a failure reported by the child into an error naming the option that failed
+    pub fn to_error(&self, report: SandboxReport) -> SandboxError {
+        let error = io::Error::from_raw_os_error(report.errno);
+        let index = report.index as usize;
+
+        return match report.step {
+            Step::Namespaces => SandboxError::Namespaces(error),
+            Step::PrivatePid => SandboxError::PrivatePid(error),
+            Step::MountPropagation => SandboxError::MountPropagation(error),
+            Step::PrivateTmp => SandboxError::PrivateTmp(error),
+            Step::ReadOnlyPath => SandboxError::ReadOnlyPath(self.read_only_paths.get(index).cloned().unwrap_or_default(), error),
+            Step::RootDirectory => SandboxError::RootDirectory(self.root_directory.clone().unwrap_or_default(), error),
+            Step::SupplementaryGroups => SandboxError::SupplementaryGroups(error),
+            Step::SwitchGroup => SandboxError::SwitchGroup(report.index, error),
+            Step::KeepCapabilities => SandboxError::KeepCapabilities(error),
+            Step::CapabilityBoundingSet => SandboxError::CapabilityBoundingSet(error),
+            Step::SwitchUser => SandboxError::SwitchUser(report.index, error),
+            Step::Capabilities => SandboxError::Capabilities(error),
+            Step::AmbientCapabilities => SandboxError::AmbientCapabilities(capabilities::capability_name(report.index), error),
+            Step::NoNewPrivileges => SandboxError::NoNewPrivileges(error),
+            Step::SyscallFilter => SandboxError::SyscallFilter(error),
+        };
+    }
+
+    fn resolve_capabilities(names: &Vec<String>) -> Result<Vec<u32>, SandboxError> {
+        let mut resolved = Vec::new();
+        for name in names {
+            match capabilities::capability_from_name(name) {
+                Some(capability) => resolved.push(capability),
+                None => return Err(SandboxError::UnknownCapability(name.clone())),
+            }
+        }
+        Ok(resolved)
+    }
+
+    fn to_c_string(path: &String) -> Result<CString, SandboxError> {
+        return CString::new(path.as_str()).map_err(|_| SandboxError::InvalidPath(path.clone()));
+    }
+}
+
+
+#[repr(u32)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Step {
+    Namespaces,
+    PrivatePid,
+    MountPropagation,
+    PrivateTmp,
+    ReadOnlyPath,
+    RootDirectory,
+    SupplementaryGroups,
+    SwitchGroup,
+    KeepCapabilities,
+    CapabilityBoundingSet,
+    SwitchUser,
+    Capabilities,
+    AmbientCapabilities,
+    NoNewPrivileges,
+    SyscallFilter,
+}
+
+
+impl Step {
+    const ALL: [Step; 15] = [
+        Step::Namespaces,
+        Step::PrivatePid,
+        Step::MountPropagation,
+        Step::PrivateTmp,
+        Step::ReadOnlyPath,
+        Step::RootDirectory,
+        Step::SupplementaryGroups,
+        Step::SwitchGroup,
+        Step::KeepCapabilities,
+        Step::CapabilityBoundingSet,
+        Step::SwitchUser,
+        Step::Capabilities,
+        Step::AmbientCapabilities,
+        Step::NoNewPrivileges,
+        Step::SyscallFilter,
+    ];
+
+    fn from_u32(value: u32) -> Option<Step> {
+        return Step::ALL.get(value as usize).copied();
+    }
+}
+
+
+/// Failure report sent from the child to the parent. index is the position of the failing
+/// path or capability, or the uid/gid that could not be switched to.
+#[derive(Debug)]
+pub struct SandboxReport {
+    step: Step,
+    errno: i32,
+    index: u32,
+}
+
+
+const REPORT_SIZE: usize = 12;
+
+
+impl SandboxReport {
+    fn to_bytes(&self) -> [u8; REPORT_SIZE] {
+        let mut buffer = [0u8; REPORT_SIZE];
+        buffer[0..4].copy_from_slice(&(self.step as u32).to_ne_bytes());
+        buffer[4..8].copy_from_slice(&self.errno.to_ne_bytes());
+        buffer[8..12].copy_from_slice(&self.index.to_ne_bytes());
+        return buffer;
+    }
+
+    fn from_bytes(buffer: &[u8; REPORT_SIZE]) -> Option<SandboxReport> {
+        let step = u32::from_ne_bytes([buffer[0], buffer[1], buffer[2], buffer[3]]);
+        let errno = i32::from_ne_bytes([buffer[4], buffer[5], buffer[6], buffer[7]]);
+        let index = u32::from_ne_bytes([buffer[8], buffer[9], buffer[10], buffer[11]]);
+
+        return match Step::from_u32(step) {
+            Some(step) => Some(SandboxReport { step, errno, index }),
+            None => None,
+        };
+    }
+}
+
+
+/// A close-on-exec pipe the child uses to tell the parent which sandbox step failed.
+/// std only forwards the errno of a failed pre_exec hook, which is not enough to name the option.
+pub struct ErrorChannel {
+    read_end: OwnedFd,
+    write_end: Option<OwnedFd>,
+}
+
+
+impl ErrorChannel {
+    fn open() -> io::Result<ErrorChannel> {
+        let mut fds: [libc::c_int; 2] = [-1, -1];
+
+        if unsafe { libc::pipe2(fds.as_mut_ptr(), libc::O_CLOEXEC) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(ErrorChannel {
+            read_end: unsafe { OwnedFd::from_raw_fd(fds[0]) },
+            write_end: Some(unsafe { OwnedFd::from_raw_fd(fds[1]) }),
+        })
+    }
+
+    fn write_fd(&self) -> RawFd {
+        return match self.write_end {
+            Some(ref fd) => fd.as_raw_fd(),
+            None => -1,
+        };
+    }
+
+    /// Closes the parent's write end and reads the report of the child, if any.
+    /// Must only be called after spawn() returned, otherwise it blocks until the child execs.
+    pub fn receive(mut self) -> Option<SandboxReport> {
+        self.write_end = None;
+
+        let mut buffer = [0u8; REPORT_SIZE];
+        let mut file = File::from(self.read_end);
+
+        if file.read_exact(&mut buffer).is_err() {
+            return None;
+        }
+
+        return SandboxReport::from_bytes(&buffer);
+    }
+}
+
+
+/// Everything needed to apply a sandbox in the forked child
+pub struct PreparedSandbox {
+    uid: u32,
+    gid: u32,
+    groups: Vec<libc::gid_t>,
+    ambient_capabilities: Vec<u32>,
+    capability_bounding_set: Option<Vec<u32>>,
+    last_capability: u32,
+    namespaces: libc::c_int,
+    private_pid: bool,
+    private_mounts: bool,
+    private_tmp: bool,
+    tmp_path: CString,
+    tmp_type: CString,
+    tmp_options: CString,
+    root_path: CString,
+    read_only_paths: Vec<CString>,
+    root_directory: Option<CString>,
+    no_new_privileges: bool,
+    syscall_filter: Option<SyscallFilter>,
+    error_fd: RawFd,
+}
+
+
+impl PreparedSandbox {
+    /// Runs in the child between fork and exec, replacing Command::uid() and Command::gid()
+    pub fn apply(&mut self) -> io::Result<()> {
+        return match self.apply_steps() {
+            Ok(_) => Ok(()),
+            Err((step, index, error)) => {
+                self.report(step, index, &error);
+                Err(error)
+            }
+        };
+    }
+
+    fn apply_steps(&mut self) -> Result<(), (Step, u32, io::Error)> {
+        if self.namespaces != 0 {
+            check(unsafe { libc::unshare(self.namespaces) }).map_err(|e| (Step::Namespaces, 0, e))?;
+        }
+
+        if self.private_pid {
+            self.enter_pid_namespace()?;
+        }
+
+        if self.private_mounts {
+            check(unsafe {
+                libc::mount(std::ptr::null(), self.root_path.as_ptr(), std::ptr::null(), libc::MS_REC | libc::MS_PRIVATE, std::ptr::null())
+            }).map_err(|e| (Step::MountPropagation, 0, e))?;
+        }
+
+        // /tmp and the read-only paths are those of the new root, so it is entered first
+        if let Some(ref root_directory) = self.root_directory {
+            check(unsafe { libc::chroot(root_directory.as_ptr()) }).map_err(|e| (Step::RootDirectory, 0, e))?;
+            check(unsafe { libc::chdir(self.root_path.as_ptr()) }).map_err(|e| (Step::RootDirectory, 0, e))?;
+        }
+
+        if self.private_tmp {
+            check(unsafe {
+                libc::mount(self.tmp_type.as_ptr(), self.tmp_path.as_ptr(), self.tmp_type.as_ptr(), libc::MS_NOSUID | libc::MS_NODEV, self.tmp_options.as_ptr() as *const libc::c_void)
+            }).map_err(|e| (Step::PrivateTmp, 0, e))?;
+        }
+
+        for (index, path) in self.read_only_paths.iter().enumerate() {
+            check(unsafe {
+                libc::mount(path.as_ptr(), path.as_ptr(), std::ptr::null(), libc::MS_BIND | libc::MS_REC, std::ptr::null())
+            }).map_err(|e| (Step::ReadOnlyPath, index as u32, e))?;
+
+            check(unsafe {
+                libc::mount(std::ptr::null(), path.as_ptr(), std::ptr::null(), libc::MS_BIND | libc::MS_REMOUNT | libc::MS_RDONLY, std::ptr::null())
+            }).map_err(|e| (Step::ReadOnlyPath, index as u32, e))?;
+        }
+
+        match check(unsafe { libc::setgroups(self.groups.len(), self.groups.as_ptr()) }) {
+            Ok(_) => {},
+            // Dropping groups without CAP_SETGID is not possible, which is fine as long as
+            // no groups were asked for. std's Command::uid() behaves the same.
+            Err(error) if self.groups.is_empty() && error.raw_os_error() == Some(libc::EPERM) => {},
+            Err(error) => return Err((Step::SupplementaryGroups, 0, error)),
+        }
+
+        check(unsafe { libc::setgid(self.gid) }).map_err(|e| (Step::SwitchGroup, self.gid, e))?;
+
+        if !self.ambient_capabilities.is_empty() {
+            check(unsafe { libc::prctl(libc::PR_SET_KEEPCAPS, 1, 0, 0, 0) }).map_err(|e| (Step::KeepCapabilities, 0, e))?;
+        }
+
+        if let Some(ref keep) = self.capability_bounding_set {
+            capabilities::drop_bounding_set(keep, self.last_capability).map_err(|e| (Step::CapabilityBoundingSet, 0, e))?;
+        }
+
+        check(unsafe { libc::setuid(self.uid) }).map_err(|e| (Step::SwitchUser, self.uid, e))?;
+
+        if !self.ambient_capabilities.is_empty() {
+            capabilities::set_capabilities(&self.ambient_capabilities).map_err(|e| (Step::Capabilities, 0, e))?;
+
+            for capability in &self.ambient_capabilities {
+                capabilities::raise_ambient_capability(*capability).map_err(|e| (Step::AmbientCapabilities, *capability, e))?;
+            }
+        }
+
+        if self.no_new_privileges {
+            check(unsafe { libc::prctl(libc::PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0) }).map_err(|e| (Step::NoNewPrivileges, 0, e))?;
+        }
+
+        if let Some(ref mut syscall_filter) = self.syscall_filter {
+            syscall_filter.install().map_err(|e| (Step::SyscallFilter, 0, e))?;
+        }
+
+        Ok(())
+    }
+
+    /// unshare(CLONE_NEWPID) only affects children of the caller, so fork once more. The forked
+    /// child becomes pid 1 of the namespace and goes on to exec the unit, while this process
+    /// stays behind, waits for it and exits with its status.
+    /// The child reports failures of the later steps to this process, which only lets spawn()
+    /// return once the child exec'd or failed, and then forwards the report.
+    fn enter_pid_namespace(&mut self) -> Result<(), (Step, u32, io::Error)> {
+        let mut fds: [libc::c_int; 2] = [-1, -1];
+        check(unsafe { libc::pipe2(fds.as_mut_ptr(), libc::O_CLOEXEC) }).map_err(|e| (Step::PrivatePid, 0, e))?;
+        let [read_fd, write_fd] = fds;
+
+        let pid = unsafe { libc::fork() };
+
+        if pid < 0 {
+            let error = io::Error::last_os_error();
+            unsafe {
+                libc::close(read_fd);
+                libc::close(write_fd);
+            }
+            return Err((Step::PrivatePid, 0, error));
+        }
+
+        if pid == 0 {
+            unsafe { libc::close(read_fd) };
+            self.error_fd = write_fd;
+
+            // the unit must not outlive the process unitman is tracking
+            return check(unsafe { libc::prctl(libc::PR_SET_PDEATHSIG, libc::SIGKILL, 0, 0, 0) }).map_err(|e| (Step::PrivatePid, 0, e));
+        }
+
+        unsafe { libc::close(write_fd) };
+
+        // the pipe is closed by the exec, so nothing is read if the child got that far
+        let mut buffer = [0u8; REPORT_SIZE];
+        let report = match read_fully(read_fd, &mut buffer) {
+            true => SandboxReport::from_bytes(&buffer),
+            false => None,
+        };
+        unsafe { libc::close(read_fd) };
+
+        if let Some(report) = report {
+            wait_for(pid);
+            return Err((report.step, report.index, io::Error::from_raw_os_error(report.errno)));
+        }
+
+        // Close everything but stdio, otherwise std's exec status pipe stays open and spawn()
+        // would block until the unit exits.
+        if unsafe { libc::syscall(libc::SYS_close_range, 3, libc::c_uint::MAX, 0) } != 0 {
+            for fd in 3..1024 {
+                unsafe { libc::close(fd) };
+            }
+        }
+
+        let status = wait_for(pid);
+
+        let code = match libc::WIFEXITED(status) {
+            true => libc::WEXITSTATUS(status),
+            false => 128 + libc::WTERMSIG(status),
+        };
+
+        unsafe { libc::_exit(code) };
+    }
+
+    fn report(&self, step: Step, index: u32, error: &io::Error) {
+        let report = SandboxReport { step, errno: error.raw_os_error().unwrap_or(0), index };
+        let buffer = report.to_bytes();
+
+        unsafe {
+            libc::write(self.error_fd, buffer.as_ptr() as *const libc::c_void, buffer.len());
+        }
+    }
+}
+
+
+/// Reads until the buffer is full, false if the pipe was closed before
+fn read_fully(fd: RawFd, buffer: &mut [u8]) -> bool {
+    let mut received = 0;
+
+    while received < buffer.len() {
+        let result = unsafe { libc::read(fd, buffer[received..].as_mut_ptr() as *mut libc::c_void, buffer.len() - received) };
+
+        if result > 0 {
+            received += result as usize;
+        } else if result == 0 || io::Error::last_os_error().raw_os_error() != Some(libc::EINTR) {
+            return false;
+        }
+    }
+
+    return true;
+}
+
+
+/// Returns the wait status of the child, the process exits if it can't be waited for
+fn wait_for(pid: libc::pid_t) -> libc::c_int {
+    let mut status: libc::c_int = 0;
+
+    loop {
+        let result = unsafe { libc::waitpid(pid, &mut status, 0) };
+        if result == pid {
+            return status;
+        }
+        if result < 0 && io::Error::last_os_error().raw_os_error() != Some(libc::EINTR) {
+            unsafe { libc::_exit(1) };
+        }
+    }
+}
+
+
+fn check(result: libc::c_int) -> io::Result<()> {
+    return match result {
+        -1 => Err(io::Error::last_os_error()),
+        _ => Ok(()),
+    };
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use users::{get_current_gid, get_current_uid};
+
+    #[test]
+    fn new_sandbox_is_empty() {
+        let sandbox = Sandbox::new();
+
+        assert!(sandbox.is_empty());
+    }
+
+    #[test]
+    fn prepare_with_unknown_capability_should_fail() {
+        let mut sandbox = Sandbox::new();
+        sandbox.set_ambient_capabilities(vec![String::from("CAP_DOES_NOT_EXIST")]);
+
+        let result = sandbox.prepare(get_current_uid(), get_current_gid());
+
+        assert!(matches!(result, Err(SandboxError::UnknownCapability(_))));
+    }
+
+    #[test]
+    fn prepare_with_unknown_syscall_should_fail() {
+        let mut sandbox = Sandbox::new();
+        sandbox.set_syscall_deny_list(vec![String::from("mount"), String::from("foobar")]);
+
+        let result = sandbox.prepare(get_current_uid(), get_current_gid());
+
+        assert!(matches!(result, Err(SandboxError::UnknownSyscall(name)) if name == "foobar"));
+    }
+
+    #[test]
+    fn prepare_should_imply_no_new_privileges_for_syscall_filter() {
+        let mut sandbox = Sandbox::new();
+        sandbox.set_syscall_deny_list(vec![String::from("reboot")]);
+
+        let (prepared, _) = sandbox.prepare(get_current_uid(), get_current_gid()).unwrap();
+
+        assert!(prepared.no_new_privileges);
+        assert!(prepared.syscall_filter.is_some());
+    }
+
+    #[test]
+    fn to_error_should_name_the_failing_path() {
+        let mut sandbox = Sandbox::new();
+        sandbox.set_read_only_paths(vec![String::from("/etc"), String::from("/usr")]);
+
+        let report = SandboxReport { step: Step::ReadOnlyPath, errno: libc::EPERM, index: 1 };
+
+        assert!(matches!(sandbox.to_error(report), SandboxError::ReadOnlyPath(path, _) if path == "/usr"));
+    }
+
+    #[test]
+    fn report_should_survive_the_pipe() {
+        let report = SandboxReport { step: Step::SwitchUser, errno: libc::EPERM, index: 1000 };
+        let received = SandboxReport::from_bytes(&report.to_bytes()).unwrap();
+
+        assert_eq!(received.step, Step::SwitchUser);
+        assert_eq!(received.errno, libc::EPERM);
+        assert_eq!(received.index, 1000);
+        assert!(SandboxReport::from_bytes(&[0xff; REPORT_SIZE]).is_none());
+    }
+}
@@ -0,0 +1,57 @@
+use std::fmt;
+use std::io;
+
+
+/// Errors that can happen while preparing or applying a unit sandbox.
+/// Every sandbox option has its own variant so that the log tells exactly which option failed.
+#[derive(Debug)]
+pub enum SandboxError {
+    UnknownGroup(String),
+    UnknownCapability(String),
+    UnknownSyscall(String),
+    InvalidPath(String),
+    ErrorChannel(io::Error),
+    Namespaces(io::Error),
+    PrivatePid(io::Error),
+    MountPropagation(io::Error),
+    PrivateTmp(io::Error),
+    ReadOnlyPath(String, io::Error),
+    RootDirectory(String, io::Error),
+    SupplementaryGroups(io::Error),
+    SwitchGroup(u32, io::Error),
+    KeepCapabilities(io::Error),
+    CapabilityBoundingSet(io::Error),
+    SwitchUser(u32, io::Error),
+    Capabilities(io::Error),
+    AmbientCapabilities(String, io::Error),
+    NoNewPrivileges(io::Error),
+    SyscallFilter(io::Error),
+}
+
+
+impl fmt::Display for SandboxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SandboxError::UnknownGroup(group) => write!(f, "unknown supplementary group {}", group),
+            SandboxError::UnknownCapability(capability) => write!(f, "unknown capability {}", capability),
+            SandboxError::UnknownSyscall(syscall) => write!(f, "unknown syscall {}", syscall),
+            SandboxError::InvalidPath(path) => write!(f, "invalid path {}", path),
+            SandboxError::ErrorChannel(error) => write!(f, "failed to create sandbox error channel: {}", error),
+            SandboxError::Namespaces(error) => write!(f, "failed to create private namespaces: {}", error),
+            SandboxError::PrivatePid(error) => write!(f, "failed to enter private pid namespace: {}", error),
+            SandboxError::MountPropagation(error) => write!(f, "failed to make mounts private: {}", error),
+            SandboxError::PrivateTmp(error) => write!(f, "failed to mount private /tmp: {}", error),
+            SandboxError::ReadOnlyPath(path, error) => write!(f, "failed to make {} read-only: {}", path, error),
+            SandboxError::RootDirectory(path, error) => write!(f, "failed to change root directory to {}: {}", path, error),
+            SandboxError::SupplementaryGroups(error) => write!(f, "failed to set supplementary groups: {}", error),
+            SandboxError::SwitchGroup(gid, error) => write!(f, "failed to switch to gid {}: {}", gid, error),
+            SandboxError::KeepCapabilities(error) => write!(f, "failed to keep capabilities across user switch: {}", error),
+            SandboxError::CapabilityBoundingSet(error) => write!(f, "failed to drop capability bounding set: {}", error),
+            SandboxError::SwitchUser(uid, error) => write!(f, "failed to switch to uid {}: {}", uid, error),
+            SandboxError::Capabilities(error) => write!(f, "failed to set capabilities: {}", error),
+            SandboxError::AmbientCapabilities(capability, error) => write!(f, "failed to raise ambient capability {}: {}", capability, error),
+            SandboxError::NoNewPrivileges(error) => write!(f, "failed to set no_new_privileges: {}", error),
+            SandboxError::SyscallFilter(error) => write!(f, "failed to install syscall filter: {}", error),
+        }
+    }
+}
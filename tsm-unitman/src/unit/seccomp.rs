@@ -0,0 +1,129 @@
+use std::io;
+
+
+#[cfg(target_arch = "x86_64")]
+const AUDIT_ARCH: u32 = 0xC000003E;
+#[cfg(target_arch = "x86")]
+const AUDIT_ARCH: u32 = 0x40000003;
+#[cfg(target_arch = "aarch64")]
+const AUDIT_ARCH: u32 = 0xC00000B7;
+#[cfg(target_arch = "arm")]
+const AUDIT_ARCH: u32 = 0x40000028;
+#[cfg(target_arch = "riscv64")]
+const AUDIT_ARCH: u32 = 0xC00000F3;
+
+// offsets into struct seccomp_data
+const SECCOMP_DATA_NR_OFFSET: u32 = 0;
+const SECCOMP_DATA_ARCH_OFFSET: u32 = 4;
+
+
+/// Syscalls that can be put on a unit's deny-list. Only syscalls that exist on all supported
+/// architectures are listed here. execve is left out on purpose, the filter is installed before the
+/// unit executable itself is executed.
+const SYSCALLS: [(&str, libc::c_long); 39] = [
+    ("acct", libc::SYS_acct),
+    ("add_key", libc::SYS_add_key),
+    ("adjtimex", libc::SYS_adjtimex),
+    ("bind", libc::SYS_bind),
+    ("bpf", libc::SYS_bpf),
+    ("chroot", libc::SYS_chroot),
+    ("clock_adjtime", libc::SYS_clock_adjtime),
+    ("clock_settime", libc::SYS_clock_settime),
+    ("clone", libc::SYS_clone),
+    ("connect", libc::SYS_connect),
+    ("delete_module", libc::SYS_delete_module),
+    ("fanotify_init", libc::SYS_fanotify_init),
+    ("finit_module", libc::SYS_finit_module),
+    ("init_module", libc::SYS_init_module),
+    ("kcmp", libc::SYS_kcmp),
+    ("kexec_load", libc::SYS_kexec_load),
+    ("keyctl", libc::SYS_keyctl),
+    ("kill", libc::SYS_kill),
+    ("listen", libc::SYS_listen),
+    ("mkdirat", libc::SYS_mkdirat),
+    ("mknodat", libc::SYS_mknodat),
+    ("mount", libc::SYS_mount),
+    ("open_by_handle_at", libc::SYS_open_by_handle_at),
+    ("perf_event_open", libc::SYS_perf_event_open),
+    ("personality", libc::SYS_personality),
+    ("pivot_root", libc::SYS_pivot_root),
+    ("process_vm_readv", libc::SYS_process_vm_readv),
+    ("process_vm_writev", libc::SYS_process_vm_writev),
+    ("ptrace", libc::SYS_ptrace),
+    ("quotactl", libc::SYS_quotactl),
+    ("reboot", libc::SYS_reboot),
+    ("request_key", libc::SYS_request_key),
+    ("setdomainname", libc::SYS_setdomainname),
+    ("sethostname", libc::SYS_sethostname),
+    ("setns", libc::SYS_setns),
+    ("settimeofday", libc::SYS_settimeofday),
+    ("socket", libc::SYS_socket),
+    ("swapoff", libc::SYS_swapoff),
+    ("swapon", libc::SYS_swapon),
+];
+
+
+pub fn syscall_from_name(name: &str) -> Option<libc::c_long> {
+    let lower = name.to_lowercase();
+
+    return SYSCALLS.iter()
+        .find(|(syscall, _)| *syscall == lower)
+        .map(|(_, number)| *number);
+}
+
+
+/// A seccomp BPF program that makes the listed syscalls fail with EPERM and allows everything else.
+/// The program is assembled in the parent so that the child only has to install it.
+pub struct SyscallFilter {
+    program: Vec<libc::sock_filter>,
+}
+
+
+impl SyscallFilter {
+    pub fn new(denied_syscalls: &[libc::c_long]) -> SyscallFilter {
+        let mut program = Vec::new();
+
+        // kill processes that use a different syscall ABI, their syscall numbers would not match
+        program.push(statement(libc::BPF_LD | libc::BPF_W | libc::BPF_ABS, SECCOMP_DATA_ARCH_OFFSET));
+        program.push(jump(libc::BPF_JMP | libc::BPF_JEQ | libc::BPF_K, AUDIT_ARCH, 1, 0));
+        program.push(statement(libc::BPF_RET | libc::BPF_K, libc::SECCOMP_RET_KILL_PROCESS));
+
+        program.push(statement(libc::BPF_LD | libc::BPF_W | libc::BPF_ABS, SECCOMP_DATA_NR_OFFSET));
+        for syscall in denied_syscalls {
+            program.push(jump(libc::BPF_JMP | libc::BPF_JEQ | libc::BPF_K, *syscall as u32, 0, 1));
+            program.push(statement(libc::BPF_RET | libc::BPF_K, libc::SECCOMP_RET_ERRNO | (libc::EPERM as u32 & libc::SECCOMP_RET_DATA)));
+        }
+        program.push(statement(libc::BPF_RET | libc::BPF_K, libc::SECCOMP_RET_ALLOW));
+
+        SyscallFilter {
+            program,
+        }
+    }
+
+    /// Installs the filter for the calling thread. Requires no_new_privs or CAP_SYS_ADMIN.
+    pub fn install(&mut self) -> io::Result<()> {
+        let program = libc::sock_fprog {
+            len: self.program.len() as libc::c_ushort,
+            filter: self.program.as_mut_ptr(),
+        };
+
+        let result = unsafe {
+            libc::prctl(libc::PR_SET_SECCOMP, libc::SECCOMP_MODE_FILTER as libc::c_ulong, &program as *const libc::sock_fprog)
+        };
+
+        return match result {
+            0 => Ok(()),
+            _ => Err(io::Error::last_os_error()),
+        };
+    }
+}
+
+
+fn statement(code: u32, k: u32) -> libc::sock_filter {
+    libc::sock_filter { code: code as u16, jt: 0, jf: 0, k }
+}
+
+
+fn jump(code: u32, k: u32, jt: u8, jf: u8) -> libc::sock_filter {
+    libc::sock_filter { code: code as u16, jt, jf, k }
+}
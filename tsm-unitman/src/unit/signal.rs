@@ -0,0 +1,114 @@
+use std::fmt;
+use std::str::FromStr;
+use serde::Deserialize;
+use serde::de::Error;
+
+
+const SIGNALS: [(&str, libc::c_int); 30] = [
+    ("HUP", libc::SIGHUP),
+    ("INT", libc::SIGINT),
+    ("QUIT", libc::SIGQUIT),
+    ("ILL", libc::SIGILL),
+    ("TRAP", libc::SIGTRAP),
+    ("ABRT", libc::SIGABRT),
+    ("BUS", libc::SIGBUS),
+    ("FPE", libc::SIGFPE),
+    ("KILL", libc::SIGKILL),
+    ("USR1", libc::SIGUSR1),
+    ("SEGV", libc::SIGSEGV),
+    ("USR2", libc::SIGUSR2),
+    ("PIPE", libc::SIGPIPE),
+    ("ALRM", libc::SIGALRM),
+    ("TERM", libc::SIGTERM),
+    ("STKFLT", libc::SIGSTKFLT),
+    ("CHLD", libc::SIGCHLD),
+    ("CONT", libc::SIGCONT),
+    ("STOP", libc::SIGSTOP),
+    ("TSTP", libc::SIGTSTP),
+    ("TTIN", libc::SIGTTIN),
+    ("TTOU", libc::SIGTTOU),
+    ("URG", libc::SIGURG),
+    ("XCPU", libc::SIGXCPU),
+    ("XFSZ", libc::SIGXFSZ),
+    ("VTALRM", libc::SIGVTALRM),
+    ("PROF", libc::SIGPROF),
+    ("WINCH", libc::SIGWINCH),
+    ("IO", libc::SIGIO),
+    ("PWR", libc::SIGPWR),
+];
+
+
+/// A signal given by name, with or without SIG prefix, or by number
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Signal(libc::c_int);
+
+
+impl Signal {
+    /// For signals reported by the kernel, which are valid by definition
+    pub fn from_number(number: libc::c_int) -> Signal {
+        Signal(number)
+    }
+
+    pub fn get_number(&self) -> libc::c_int {
+        self.0
+    }
+}
+
+
+impl FromStr for Signal {
+    type Err = String;
+
+    fn from_str(signal: &str) -> Result<Self, Self::Err> {
+        if let Ok(number) = signal.parse::<libc::c_int>() {
+            return match number > 0 && number <= libc::SIGRTMAX() {
+                true => Ok(Signal(number)),
+                false => Err(format!("Invalid signal number: {}", signal)),
+            };
+        }
+
+        let name = signal.to_uppercase();
+        let name = name.strip_prefix("SIG").unwrap_or(&name);
+
+        return match SIGNALS.iter().find(|(signal_name, _)| *signal_name == name) {
+            Some((_, number)) => Ok(Signal(*number)),
+            None => Err(format!("Invalid signal: {}", signal)),
+        };
+    }
+}
+
+
+impl<'de> Deserialize<'de> for Signal {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: serde::Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        Signal::from_str(&s).map_err(Error::custom)
+    }
+}
+
+
+impl fmt::Display for Signal {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match SIGNALS.iter().find(|(_, number)| *number == self.0) {
+            Some((name, _)) => write!(f, "SIG{}", name),
+            None => write!(f, "{}", self.0),
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_str_should_accept_names_and_numbers() {
+        assert_eq!(Signal::from_str("SIGHUP").unwrap().get_number(), libc::SIGHUP);
+        assert_eq!(Signal::from_str("term").unwrap().get_number(), libc::SIGTERM);
+        assert_eq!(Signal::from_str("10").unwrap().get_number(), 10);
+        assert_eq!(Signal::from_str("USR1").unwrap().to_string(), "SIGUSR1");
+        assert!(Signal::from_str("SIGFOO").is_err());
+        assert!(Signal::from_str("0").is_err());
+    }
+}
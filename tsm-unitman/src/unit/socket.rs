@@ -0,0 +1,159 @@
+use std::io;
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+use std::time::Duration;
+use log::debug;
+
+use crate::unit::{ListenAddress, ListenFds};
+
+
+/// Listening sockets bound by unitman on behalf of a unit, which is started on the first
+/// connection. In accept mode unitman accepts the connections itself and spawns one instance
+/// of the unit per connection instead.
+#[derive(Debug)]
+pub struct Socket {
+    name: String,
+    unit_name: String,
+    accept: bool,
+    listeners: Vec<(ListenAddress, OwnedFd)>,
+}
+
+
+impl Socket {
+    /// Binds all addresses right away, so that connections are queued before the unit runs
+    pub fn new(
+        name: String,
+        unit_name: String,
+        addresses: Vec<ListenAddress>,
+        accept: bool,
+    ) -> Result<Socket, String> {
+        if addresses.is_empty() {
+            return Err(format!("Socket {} has no listen addresses", name));
+        }
+
+        let mut listeners = Vec::new();
+
+        for address in addresses {
+            if accept && !address.is_stream() {
+                return Err(format!("Socket {} cannot accept connections on {}", name, address));
+            }
+
+            match address.bind() {
+                Ok(fd) => {
+                    debug!("Socket {} listening on {}", name, address);
+                    listeners.push((address, fd));
+                },
+                Err(error) => return Err(format!("Socket {} failed to bind {}: {}", name, address, error)),
+            }
+        }
+
+        return Ok(Socket {
+            name,
+            unit_name,
+            accept,
+            listeners,
+        });
+    }
+
+    pub fn get_name(&self) -> String {
+        self.name.clone()
+    }
+
+    pub fn get_unit_name(&self) -> String {
+        self.unit_name.clone()
+    }
+
+    pub fn is_accept(&self) -> bool {
+        self.accept
+    }
+
+    pub fn get_addresses(&self) -> Vec<ListenAddress> {
+        return self.listeners.iter().map(|(address, _)| address.clone()).collect();
+    }
+
+    /// All listening fds, named after the socket
+    pub fn get_listen_fds(&self) -> ListenFds {
+        let mut listen_fds = ListenFds::new();
+
+        for (_, fd) in &self.listeners {
+            listen_fds.push(fd.as_raw_fd(), self.name.clone());
+        }
+
+        return listen_fds;
+    }
+
+    /// Waits until one of the listeners becomes readable and returns its index
+    pub fn poll(&self, timeout: Duration) -> io::Result<Option<usize>> {
+        let mut poll_fds: Vec<libc::pollfd> = self.listeners.iter()
+            .map(|(_, fd)| libc::pollfd { fd: fd.as_raw_fd(), events: libc::POLLIN, revents: 0 })
+            .collect();
+
+        let result = unsafe { libc::poll(poll_fds.as_mut_ptr(), poll_fds.len() as libc::nfds_t, timeout.as_millis() as libc::c_int) };
+
+        if result < 0 {
+            let error = io::Error::last_os_error();
+            return match error.kind() {
+                io::ErrorKind::Interrupted => Ok(None),
+                _ => Err(error),
+            };
+        }
+
+        return Ok(poll_fds.iter().position(|poll_fd| poll_fd.revents != 0));
+    }
+
+    /// Accepts a pending connection on the listener with the given index
+    pub fn accept(&self, index: usize) -> io::Result<OwnedFd> {
+        let fd = match self.listeners.get(index) {
+            Some((_, fd)) => fd.as_raw_fd(),
+            None => return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("Socket {} has no listener {}", self.name, index))),
+        };
+
+        let connection = unsafe { libc::accept4(fd, std::ptr::null_mut(), std::ptr::null_mut(), libc::SOCK_CLOEXEC) };
+        if connection < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        return Ok(unsafe { OwnedFd::from_raw_fd(connection) });
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpStream;
+    use std::str::FromStr;
+
+    fn build_socket(accept: bool) -> Socket {
+        let address = ListenAddress::from_str("tcp://127.0.0.1:0").unwrap();
+        return Socket::new(String::from("test"), String::from("test"), vec![address], accept).unwrap();
+    }
+
+    fn local_address(socket: &Socket) -> std::net::SocketAddr {
+        let listener = std::net::TcpListener::from(socket.listeners[0].1.try_clone().unwrap());
+        return listener.local_addr().unwrap();
+    }
+
+    #[test]
+    fn poll_should_report_pending_connection() {
+        let socket = build_socket(false);
+        assert_eq!(socket.poll(Duration::from_millis(10)).unwrap(), None);
+
+        let _client = TcpStream::connect(local_address(&socket)).unwrap();
+        assert_eq!(socket.poll(Duration::from_secs(1)).unwrap(), Some(0));
+    }
+
+    #[test]
+    fn accept_should_work() {
+        let socket = build_socket(true);
+
+        let _client = TcpStream::connect(local_address(&socket)).unwrap();
+        assert!(socket.accept(0).is_ok());
+        assert_eq!(socket.poll(Duration::from_millis(10)).unwrap(), None);
+    }
+
+    #[test]
+    fn accept_mode_with_datagram_socket_should_fail() {
+        let address = ListenAddress::from_str("udp://127.0.0.1:0").unwrap();
+        assert!(Socket::new(String::from("test"), String::from("test"), vec![address], true).is_err());
+    }
+}
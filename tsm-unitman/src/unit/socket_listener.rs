@@ -0,0 +1,150 @@
+use std::os::fd::AsRawFd;
+use std::sync::Arc;
+use std::thread;
+use std::thread::JoinHandle;
+use std::time::Duration;
+use log::{debug, error, info, warn};
+
+use crate::unit::{lock, ListenFds, Process, Socket, UnitManager};
+
+
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+const RETRY_INTERVAL: Duration = Duration::from_secs(1);
+
+
+/// Watches the listeners of a socket until unitman is asked to stop.
+/// Without accept mode the unit is started as soon as a listener becomes readable and is left
+/// alone while it runs, it is expected to serve the listeners itself.
+/// In accept mode every connection is accepted here and handed to a new instance of the unit.
+pub struct SocketListener {
+    socket: Arc<Socket>,
+    manager: UnitManager,
+    instances: Vec<Process>,
+}
+
+
+impl SocketListener {
+    pub fn new(socket: Arc<Socket>, manager: UnitManager) -> SocketListener {
+        SocketListener {
+            socket,
+            manager,
+            instances: Vec::new(),
+        }
+    }
+
+    pub fn run(mut self) -> JoinHandle<()> {
+        return thread::spawn(move || {
+            match self.socket.is_accept() {
+                true => self.accept_loop(),
+                false => self.activation_loop(),
+            }
+        });
+    }
+
+    fn activation_loop(&mut self) {
+        let unit_name = self.socket.get_unit_name();
+
+        while !self.manager.stop_requested() {
+            if self.manager.is_unit_running(&unit_name) || !self.manager.is_unit_wanted(&unit_name) {
+                thread::sleep(POLL_INTERVAL);
+                continue;
+            }
+
+            match self.socket.poll(POLL_INTERVAL) {
+                Ok(Some(_)) => {
+                    info!("Socket {} activates unit {}", self.socket.get_name(), unit_name);
+
+                    match self.manager.activate_unit(unit_name.clone()) {
+                        Ok(_) => debug!("Unit {} started by socket {}", unit_name, self.socket.get_name()),
+                        Err(e) => {
+                            warn!("Error starting unit {} by socket {}: {}", unit_name, self.socket.get_name(), e);
+                            thread::sleep(RETRY_INTERVAL);
+                        },
+                    }
+                },
+                Ok(None) => {},
+                Err(e) => {
+                    error!("Error polling socket {}: {}", self.socket.get_name(), e);
+                    thread::sleep(RETRY_INTERVAL);
+                },
+            }
+        }
+    }
+
+    fn accept_loop(&mut self) {
+        let unit_name = self.socket.get_unit_name();
+
+        while !self.manager.stop_requested() {
+            self.reap_instances();
+
+            // connections stay queued while the unit is not part of the active target
+            if !self.manager.is_unit_wanted(&unit_name) {
+                thread::sleep(POLL_INTERVAL);
+                continue;
+            }
+
+            match self.socket.poll(POLL_INTERVAL) {
+                Ok(Some(index)) => self.accept_connection(index),
+                Ok(None) => {},
+                Err(e) => {
+                    error!("Error polling socket {}: {}", self.socket.get_name(), e);
+                    thread::sleep(RETRY_INTERVAL);
+                },
+            }
+        }
+
+        self.stop_instances();
+    }
+
+    fn accept_connection(&mut self, index: usize) {
+        let unit_name = self.socket.get_unit_name();
+
+        // the connection is closed in unitman as soon as it is dropped, the instance keeps its own copy
+        let connection = match self.socket.accept(index) {
+            Ok(connection) => connection,
+            Err(e) => {
+                warn!("Error accepting connection on socket {}: {}", self.socket.get_name(), e);
+                return;
+            },
+        };
+
+        let unit = match self.manager.get_unit(&unit_name) {
+            Some(unit) => unit,
+            None => {
+                warn!("Socket {} cannot find unit {}", self.socket.get_name(), unit_name);
+                return;
+            },
+        };
+
+        let mut listen_fds = ListenFds::new();
+        listen_fds.push(connection.as_raw_fd(), String::from("connection"));
+
+        // waits for a probe or a request using the unit, instead of dropping the connection
+        let instance = lock(&unit).and_then(|unit| unit.spawn_instance(listen_fds));
+
+        match instance {
+            Ok(instance) => {
+                debug!("Socket {} spawned instance {:?} of unit {}", self.socket.get_name(), instance.get_pid(), unit_name);
+                self.instances.push(instance);
+            },
+            Err(e) => warn!("Error spawning instance of unit {} by socket {}: {}", unit_name, self.socket.get_name(), e),
+        }
+    }
+
+    fn reap_instances(&mut self) {
+        self.instances.retain_mut(|instance| instance.is_running());
+    }
+
+    fn stop_instances(&mut self) {
+        for instance in &mut self.instances {
+            let pid = instance.get_pid();
+
+            match instance.stop() {
+                Ok(_) => debug!("Stopped instance {:?} of unit {}", pid, self.socket.get_unit_name()),
+                Err(e) => warn!("Error stopping instance of unit {}: {}", self.socket.get_unit_name(), e),
+            }
+        }
+
+        self.instances.clear();
+    }
+}
@@ -0,0 +1,40 @@
+use chrono::{DateTime, Local};
+
+use crate::unit::UnitState;
+
+
+#[derive(Debug, Clone)]
+pub struct StateTransition {
+    timestamp: DateTime<Local>,
+    from: UnitState,
+    to: UnitState,
+    reason: String,
+}
+
+
+impl StateTransition {
+    pub fn new(from: UnitState, to: UnitState, reason: String) -> StateTransition {
+        StateTransition {
+            timestamp: Local::now(),
+            from,
+            to,
+            reason,
+        }
+    }
+
+    pub fn get_timestamp(&self) -> DateTime<Local> {
+        self.timestamp
+    }
+
+    pub fn get_from(&self) -> UnitState {
+        self.from.clone()
+    }
+
+    pub fn get_to(&self) -> UnitState {
+        self.to.clone()
+    }
+
+    pub fn get_reason(&self) -> String {
+        self.reason.clone()
+    }
+}
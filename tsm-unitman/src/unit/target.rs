@@ -0,0 +1,32 @@
+/// A named group of units, e.g. a mode the device runs in. A target wants units and other
+/// targets, units can also add themselves to a target with wanted_by.
+#[derive(Debug, Clone)]
+pub struct Target {
+    name: String,
+    wants: Vec<String>,
+}
+
+
+impl Target {
+    pub fn new(name: String, wants: Vec<String>) -> Target {
+        Target {
+            name,
+            wants,
+        }
+    }
+
+    pub fn get_name(&self) -> String {
+        self.name.clone()
+    }
+
+    /// Names of the wanted units and targets
+    pub fn get_wants(&self) -> Vec<String> {
+        self.wants.clone()
+    }
+
+    pub fn add_want(&mut self, name: String) {
+        if !self.wants.contains(&name) {
+            self.wants.push(name);
+        }
+    }
+}
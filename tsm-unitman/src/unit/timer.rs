@@ -0,0 +1,253 @@
+use std::collections::hash_map::RandomState;
+use std::fs;
+use std::hash::{BuildHasher, Hasher};
+use std::path::PathBuf;
+use chrono::{DateTime, Duration, Local, TimeZone};
+use log::{debug, warn};
+
+use crate::unit::Calendar;
+
+
+/// Triggers the start of a unit. All configured triggers are combined, the earliest one wins.
+/// on_boot_s: seconds after unitman started, fires once
+/// on_unit_active_s: seconds after the unit was last started, or after the timer was created
+/// on_calendar: cron expression
+/// persistent: the last trigger time is stored on disk, so that a calendar elapse missed while
+/// the device was off is caught up right after the next start
+#[derive(Debug, Clone)]
+pub struct Timer {
+    unit_name: String,
+    on_calendar: Option<Calendar>,
+    on_boot_s: Option<u64>,
+    on_unit_active_s: Option<u64>,
+    randomized_delay_s: u64,
+    state_file: Option<PathBuf>,
+    boot_timestamp: DateTime<Local>,
+    boot_triggered: bool,
+    last_trigger: Option<DateTime<Local>>,
+    last_activation: Option<DateTime<Local>>,
+    next_trigger: Option<DateTime<Local>>,
+}
+
+
+impl Timer {
+    pub fn new(
+        unit_name: String,
+        on_calendar: Option<Calendar>,
+        on_boot_s: Option<u64>,
+        on_unit_active_s: Option<u64>,
+        randomized_delay_s: u64,
+        persistent: bool,
+        state_dir: String,
+    ) -> Timer {
+        let state_file = match persistent {
+            true => Some(PathBuf::from(state_dir).join("timers").join(&unit_name)),
+            false => None,
+        };
+
+        let mut timer = Timer {
+            unit_name,
+            on_calendar,
+            on_boot_s,
+            on_unit_active_s,
+            randomized_delay_s,
+            state_file,
+            boot_timestamp: Local::now(),
+            boot_triggered: false,
+            last_trigger: None,
+            last_activation: None,
+            next_trigger: None,
+        };
+
+        timer.last_trigger = timer.load_last_trigger();
+        timer.schedule_next();
+
+        return timer;
+    }
+
+    pub fn get_on_calendar(&self) -> Option<String> {
+        return match self.on_calendar {
+            Some(ref calendar) => Some(calendar.get_expression()),
+            None => None,
+        };
+    }
+
+    pub fn get_next_trigger(&self) -> Option<DateTime<Local>> {
+        self.next_trigger
+    }
+
+    pub fn get_last_trigger(&self) -> Option<DateTime<Local>> {
+        self.last_trigger
+    }
+
+    pub fn is_due(&self, now: &DateTime<Local>) -> bool {
+        return match self.next_trigger {
+            Some(next_trigger) => next_trigger <= *now,
+            None => false,
+        };
+    }
+
+    /// Records a trigger and schedules the next one
+    pub fn trigger(&mut self, now: &DateTime<Local>) {
+        if let Some(on_boot_s) = self.on_boot_s {
+            if *now >= self.boot_timestamp + Duration::seconds(on_boot_s as i64) {
+                self.boot_triggered = true;
+            }
+        }
+
+        self.last_trigger = Some(*now);
+        self.store_last_trigger();
+        self.schedule_next();
+
+        debug!("Timer for unit {} triggered, next trigger at {:?}", self.unit_name, self.next_trigger);
+    }
+
+    /// The unit was started, by this timer or otherwise
+    pub fn set_activated(&mut self, now: &DateTime<Local>) {
+        self.last_activation = Some(*now);
+        self.schedule_next();
+    }
+
+    fn schedule_next(&mut self) {
+        let mut candidates: Vec<DateTime<Local>> = Vec::new();
+
+        if let Some(on_boot_s) = self.on_boot_s {
+            if !self.boot_triggered {
+                candidates.push(self.boot_timestamp + Duration::seconds(on_boot_s as i64));
+            }
+        }
+
+        if let Some(on_unit_active_s) = self.on_unit_active_s {
+            // a trigger whose start failed counts as activation, otherwise it would trigger again right away
+            let reference = [self.last_activation, self.last_trigger].into_iter().flatten().max().unwrap_or(self.boot_timestamp);
+            candidates.push(reference + Duration::seconds(on_unit_active_s as i64));
+        }
+
+        if let Some(ref on_calendar) = self.on_calendar {
+            // With a persisted last trigger this may lie in the past, which catches up missed runs
+            let reference = self.last_trigger.unwrap_or(self.boot_timestamp);
+            if let Some(elapse) = on_calendar.next_after(&reference) {
+                candidates.push(elapse);
+            }
+        }
+
+        self.next_trigger = candidates.into_iter().min().map(|next| next + self.random_delay());
+    }
+
+    fn random_delay(&self) -> Duration {
+        if self.randomized_delay_s == 0 {
+            return Duration::zero();
+        }
+
+        let random = RandomState::new().build_hasher().finish();
+        return Duration::seconds((random % (self.randomized_delay_s + 1)) as i64);
+    }
+
+    fn load_last_trigger(&self) -> Option<DateTime<Local>> {
+        let state_file = match self.state_file {
+            Some(ref state_file) => state_file,
+            None => return None,
+        };
+
+        let content = match fs::read_to_string(state_file) {
+            Ok(content) => content,
+            Err(_) => return None,
+        };
+
+        return match content.trim().parse::<i64>() {
+            Ok(timestamp) => Local.timestamp_opt(timestamp, 0).single(),
+            Err(error) => {
+                warn!("Timer for unit {} has an invalid state file {:?}: {}", self.unit_name, state_file, error);
+                None
+            }
+        };
+    }
+
+    fn store_last_trigger(&self) {
+        let (state_file, last_trigger) = match (&self.state_file, self.last_trigger) {
+            (Some(state_file), Some(last_trigger)) => (state_file, last_trigger),
+            _ => return,
+        };
+
+        if let Some(parent) = state_file.parent() {
+            if let Err(error) = fs::create_dir_all(parent) {
+                warn!("Timer for unit {} failed to create state directory {:?}: {}", self.unit_name, parent, error);
+                return;
+            }
+        }
+
+        if let Err(error) = fs::write(state_file, last_trigger.timestamp().to_string()) {
+            warn!("Timer for unit {} failed to write state file {:?}: {}", self.unit_name, state_file, error);
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn state_dir(name: &str) -> String {
+        let dir = std::env::temp_dir().join(format!("tsm-unitman-timer-test-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        return dir.to_string_lossy().to_string();
+    }
+
+    #[test]
+    fn on_boot_should_trigger_once() {
+        let mut timer = Timer::new(String::from("test"), None, Some(0), None, 0, false, state_dir("boot"));
+        let now = Local::now();
+
+        assert!(timer.is_due(&now));
+        timer.trigger(&now);
+        assert!(!timer.is_due(&now));
+        assert_eq!(timer.get_next_trigger(), None);
+        assert_eq!(timer.get_last_trigger(), Some(now));
+    }
+
+    #[test]
+    fn on_unit_active_should_trigger_relative_to_last_activation() {
+        let mut timer = Timer::new(String::from("test"), None, None, Some(60), 0, false, state_dir("active"));
+        let created = timer.get_next_trigger().unwrap() - Duration::seconds(60);
+        assert!(created <= Local::now());
+
+        let now = Local::now();
+        timer.trigger(&now);
+        assert_eq!(timer.get_next_trigger(), Some(now + Duration::seconds(60)));
+
+        let started = now + Duration::seconds(30);
+        timer.set_activated(&started);
+        assert_eq!(timer.get_next_trigger(), Some(started + Duration::seconds(60)));
+    }
+
+    #[test]
+    fn on_calendar_should_schedule_next_elapse() {
+        let calendar = Calendar::from_str("*/5 * * * *").unwrap();
+        let timer = Timer::new(String::from("test"), Some(calendar), None, None, 0, false, state_dir("calendar"));
+
+        let next = timer.get_next_trigger().unwrap();
+        assert!(next > Local::now());
+        assert!(next <= Local::now() + Duration::minutes(5));
+    }
+
+    #[test]
+    fn persistent_timer_should_catch_up_missed_elapse() {
+        let dir = state_dir("persistent");
+        let calendar = Calendar::from_str("@hourly").unwrap();
+
+        let mut timer = Timer::new(String::from("test"), Some(calendar.clone()), None, None, 0, true, dir.clone());
+        timer.trigger(&(Local::now() - Duration::hours(3)));
+
+        // simulates a restart of unitman
+        let timer = Timer::new(String::from("test"), Some(calendar), None, None, 0, true, dir.clone());
+        assert!(timer.is_due(&Local::now()));
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn invalid_calendar_should_fail() {
+        assert!(Calendar::from_str("every tuesday").is_err());
+    }
+}
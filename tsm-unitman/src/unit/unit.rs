@@ -1,8 +1,17 @@
+use std::io::Error;
+use std::process::{Command, Stdio};
+use std::os::unix::process::{CommandExt, ExitStatusExt};
 use std::sync::{Arc, Mutex};
 use std::time::Duration;
-use log::{debug, warn};
+use chrono::{DateTime, Local};
+use process_control::{ChildExt, Control, ExitStatus};
+use log::{debug, info, warn};
 
-use crate::unit::{RestartPolicy, ProcessProbe, LivenessProbe, ProbeState, Process, ProbeManager, UnitState};
+use crate::unit::{RestartPolicy, ProcessProbe, LivenessProbe, ProbeState, Process, ProbeManager, UnitState, Sandbox, Timer, ListenFds, PathTrigger, UnitRuntimeState, Signal, RingBuffer, StateTransition, ProbeResult, ProbeCounters, OutputLog, EventSender, UnitEventKind, UnitError};
+
+
+const RELOAD_TIMEOUT: Duration = Duration::from_secs(30);
+const TRANSITIONS_CAPACITY: usize = 20;
 
 
 pub type UnitRef = Arc<Mutex<Unit>>;
@@ -13,10 +22,21 @@ pub struct Unit {
     name: String,
     dependencies: Vec<UnitRef>,
     restart_policy: RestartPolicy,
+    configured_restart_policy: RestartPolicy,
     enabled: bool,
+    masked: bool,
+    wanted: bool,
     process: Process,
     probe_manager: ProbeManager,
     state: UnitState,
+    timer: Option<Timer>,
+    path_trigger: Option<PathTrigger>,
+    socket_activated: bool,
+    restart_count: u32,
+    reload_signal: Option<Signal>,
+    reload_command: Vec<String>,
+    transitions: RingBuffer<StateTransition>,
+    events: EventSender,
 }
 
 
@@ -41,11 +61,22 @@ impl Unit {
         Unit {
             name: name.clone(),
             dependencies: Vec::new(),
-            restart_policy,
+            restart_policy: restart_policy.clone(),
+            configured_restart_policy: restart_policy,
             enabled,
+            masked: false,
+            wanted: true,
             process,
             probe_manager: ProbeManager::new(name.clone()),
             state: UnitState::Stopped,
+            timer: None,
+            path_trigger: None,
+            socket_activated: false,
+            restart_count: 0,
+            reload_signal: None,
+            reload_command: Vec::new(),
+            transitions: RingBuffer::new(TRANSITIONS_CAPACITY),
+            events: EventSender::default(),
         }
     }
 
@@ -53,6 +84,171 @@ impl Unit {
         self.probe_manager.set_liveness_probe(probe);
     }
 
+    pub fn set_sandbox(&mut self, sandbox: Sandbox) {
+        self.process.set_sandbox(sandbox);
+    }
+
+    /// Events of the unit and its probes are sent here
+    pub fn set_event_sender(&mut self, events: EventSender) {
+        self.probe_manager.set_event_sender(events.clone());
+        self.events = events;
+    }
+
+    pub fn get_liveness_probe(&self) -> Option<LivenessProbe> {
+        self.probe_manager.get_liveness_probe()
+    }
+
+    /// The last results of the liveness probe, oldest first
+    pub fn get_probe_results(&self) -> Vec<ProbeResult> {
+        return match self.probe_manager.get_liveness_probe() {
+            Some(probe) => probe.get_results(),
+            None => Vec::new(),
+        };
+    }
+
+    /// None if the unit has no liveness probe
+    pub fn get_probe_counters(&self) -> Option<ProbeCounters> {
+        self.probe_manager.get_liveness_probe().map(|probe| probe.get_counters())
+    }
+
+    pub fn get_sandbox(&self) -> Sandbox {
+        self.process.get_sandbox()
+    }
+
+    /// Captures stdout and stderr of the unit, otherwise they are discarded
+    pub fn set_output_log(&mut self, output_log: OutputLog) {
+        self.process.set_output_log(output_log);
+    }
+
+    /// The last lines written to stdout and stderr, oldest first
+    pub fn get_output(&self, lines: usize) -> Vec<String> {
+        return match self.process.get_output_log() {
+            Some(output_log) => output_log.tail(lines),
+            None => Vec::new(),
+        };
+    }
+
+    /// Keeps the output log from growing without bounds while the unit is running
+    pub fn trim_output(&self) {
+        if let Some(output_log) = self.process.get_output_log() {
+            if let Err(error) = output_log.trim() {
+                warn!("Unit {} failed to trim output log: {}", self.name, error);
+            }
+        }
+    }
+
+    pub fn set_reload_signal(&mut self, signal: Signal) {
+        self.reload_signal = Some(signal);
+    }
+
+    pub fn get_reload_signal(&self) -> Option<Signal> {
+        self.reload_signal
+    }
+
+    /// Executable and arguments, takes precedence over the reload signal
+    pub fn set_reload_command(&mut self, command: Vec<String>) {
+        self.reload_command = command;
+    }
+
+    pub fn get_reload_command(&self) -> Vec<String> {
+        self.reload_command.clone()
+    }
+
+    /// Units with a timer are only started when the timer elapses, never at boot
+    pub fn set_timer(&mut self, timer: Timer) {
+        self.timer = Some(timer);
+    }
+
+    pub fn get_timer(&self) -> Option<Timer> {
+        self.timer.clone()
+    }
+
+    pub fn has_timer(&self) -> bool {
+        self.timer.is_some()
+    }
+
+    pub fn is_timer_due(&self, now: &DateTime<Local>) -> bool {
+        return match self.timer {
+            Some(ref timer) => timer.is_due(now),
+            None => false,
+        };
+    }
+
+    pub fn trigger_timer(&mut self, now: &DateTime<Local>) {
+        match self.timer {
+            Some(ref mut timer) => timer.trigger(now),
+            None => {},
+        }
+    }
+
+    /// Units with a path trigger are only started when one of its conditions is met, never at boot
+    pub fn set_path_trigger(&mut self, path_trigger: PathTrigger) {
+        self.path_trigger = Some(path_trigger);
+    }
+
+    pub fn get_path_trigger(&self) -> Option<&PathTrigger> {
+        self.path_trigger.as_ref()
+    }
+
+    pub fn has_path_trigger(&self) -> bool {
+        self.path_trigger.is_some()
+    }
+
+    pub fn update_path_trigger(&mut self, now: &DateTime<Local>) {
+        match self.path_trigger {
+            Some(ref mut path_trigger) => path_trigger.update(now),
+            None => {},
+        }
+    }
+
+    pub fn is_path_trigger_due(&self, now: &DateTime<Local>) -> bool {
+        return match self.path_trigger {
+            Some(ref path_trigger) => path_trigger.is_due(now),
+            None => false,
+        };
+    }
+
+    pub fn trigger_path_trigger(&mut self, now: &DateTime<Local>) {
+        match self.path_trigger {
+            Some(ref mut path_trigger) => path_trigger.trigger(now),
+            None => {},
+        }
+    }
+
+    /// Socket activated units are only started by their socket, never at boot
+    pub fn set_socket_activated(&mut self, socket_activated: bool) {
+        self.socket_activated = socket_activated;
+    }
+
+    pub fn set_listen_fds(&mut self, listen_fds: ListenFds) {
+        self.process.set_listen_fds(listen_fds);
+    }
+
+    /// Whether the unit is started by a timer, path trigger or socket instead of at boot
+    pub fn is_started_on_demand(&self) -> bool {
+        self.has_timer() || self.has_path_trigger() || self.socket_activated
+    }
+
+    /// Starts a separate instance of the unit process which is not tracked by the unit.
+    /// Used by sockets in accept mode, which pass one connection to each instance.
+    pub fn spawn_instance(&self, listen_fds: ListenFds) -> Result<Process, UnitError> {
+        if self.masked {
+            return Err(UnitError::Masked(self.name.clone()));
+        }
+
+        if !self.enabled {
+            return Err(UnitError::Disabled(self.name.clone()));
+        }
+
+        let mut process = self.process.duplicate();
+        process.set_listen_fds(listen_fds);
+
+        return match process.start() {
+            Ok(_) => Ok(process),
+            Err(error) => Err(error.context(format!("Unit {} failed to start instance", self.name))),
+        };
+    }
+
     pub fn get_name(&self) -> String {
         self.name.clone()
     }
@@ -81,6 +277,32 @@ impl Unit {
         self.restart_policy = policy;
     }
 
+    /// Reverts temporary changes of the restart policy to the configured one
+    pub fn reset_restart_policy(&mut self) {
+        self.restart_policy = self.configured_restart_policy.clone();
+    }
+
+    /// The restart policy if it was changed at runtime
+    pub fn get_restart_policy_override(&self) -> Option<RestartPolicy> {
+        return match self.restart_policy == self.configured_restart_policy {
+            true => None,
+            false => Some(self.restart_policy.clone()),
+        };
+    }
+
+    /// Number of restarts done because of the restart policy
+    pub fn get_restart_count(&self) -> u32 {
+        self.restart_count
+    }
+
+    pub fn set_restart_count(&mut self, restart_count: u32) {
+        self.restart_count = restart_count;
+    }
+
+    pub fn increment_restart_count(&mut self) {
+        self.restart_count += 1;
+    }
+
     pub fn get_uid(&self) -> u32 {
         self.process.get_uid()
     }
@@ -93,10 +315,56 @@ impl Unit {
         self.enabled
     }
 
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    /// A masked unit cannot be started by any means until it is unmasked
+    pub fn set_masked(&mut self, masked: bool) {
+        self.masked = masked;
+    }
+
+    pub fn is_masked(&self) -> bool {
+        self.masked
+    }
+
+    /// Units not wanted by the active target are neither started at boot, restarted nor triggered
+    pub fn set_wanted(&mut self, wanted: bool) {
+        self.wanted = wanted;
+    }
+
+    pub fn is_wanted(&self) -> bool {
+        self.wanted
+    }
+
     pub fn get_pid(&self) -> Option<u32> {
         self.process.get_pid()
     }
 
+    /// What is needed to pick up the unit again after unitman restarted
+    pub fn get_runtime_state(&self) -> UnitRuntimeState {
+        return UnitRuntimeState::new(
+            self.process.get_pid(),
+            self.process.get_start_time(),
+            self.restart_count,
+            self.get_restart_policy_override(),
+        );
+    }
+
+    /// Takes over the still running process of a previous unitman instance instead of starting
+    /// a new one. Returns false if the process is gone. This does not start the probes!
+    pub fn adopt(&mut self, pid: u32, start_time: u64) -> bool {
+        if !self.process.adopt(pid, start_time) {
+            return false;
+        }
+
+        debug!("Unit {} adopted process {}", self.name, pid);
+        self.init_process_probe();
+        self.set_state(UnitState::Running, format!("process adopted (pid={})", pid));
+
+        return true;
+    }
+
     pub fn get_state(&self) -> UnitState {
         return match self.state {
             UnitState::Running => {
@@ -110,6 +378,31 @@ impl Unit {
         }
     }
 
+    /// Only actual changes of the state are recorded
+    fn set_state(&mut self, state: UnitState, reason: String) {
+        if self.state != state {
+            debug!("Unit {} changed from {:?} to {:?}: {}", self.name, self.state, state, reason);
+
+            if state == UnitState::Failed {
+                self.events.send(self.name.clone(), UnitEventKind::Failed { reason: reason.clone() });
+            }
+
+            self.transitions.push(StateTransition::new(self.state.clone(), state.clone(), reason));
+        }
+
+        self.state = state;
+    }
+
+    /// The last state changes, oldest first
+    pub fn get_transitions(&self) -> Vec<StateTransition> {
+        self.transitions.to_vec()
+    }
+
+    /// Exit status of the last run of the process, if it exited
+    pub fn get_last_exit_status(&self) -> Option<std::process::ExitStatus> {
+        self.process.get_last_exit_status()
+    }
+
     pub fn get_process_probe_state(&self) -> ProbeState {
         self.probe_manager.get_process_probe_state()
     }
@@ -129,19 +422,24 @@ impl Unit {
 
     /// Starts the child process
     /// This does not start the probes!
-    pub fn start(&mut self) -> Result<bool, String> {
+    pub fn start(&mut self) -> Result<bool, UnitError> {
         if !self.can_start() {
-            return Err(format!("Cannot start unit {}", self.name));
+            return Err(match (self.masked, self.enabled) {
+                (true, _) => UnitError::Masked(self.name.clone()),
+                (false, false) => UnitError::Disabled(self.name.clone()),
+                (false, true) => UnitError::AlreadyRunning(self.name.clone()),
+            });
         }
 
         debug!("Starting unit {}", self.name);
 
-        self.state = UnitState::Starting;
+        self.set_state(UnitState::Starting, String::from("start requested"));
 
         match self.start_dependencies() {
             Ok(_) => {}
             Err(error) => {
-                return Err(format!("Unit {} failed to start dependencies: {}", self.name, error));
+                self.set_state(UnitState::Failed, format!("dependencies failed to start: {}", error));
+                return Err(UnitError::DependencyFailed(self.name.clone(), Box::new(error)));
             }
         }
 
@@ -149,12 +447,19 @@ impl Unit {
             Ok(_) => {
                 debug!("Unit {} was started", self.name);
                 self.init_process_probe();
-                self.state = UnitState::Running;
+                let pid = self.process.get_pid().unwrap_or_default();
+                self.set_state(UnitState::Running, format!("process started (pid={})", pid));
+                self.events.send(self.name.clone(), UnitEventKind::Started { pid });
+
+                if let Some(ref mut timer) = self.timer {
+                    timer.set_activated(&Local::now());
+                }
+
                 Ok(true)
             }
             Err(error) => {
-                self.state = UnitState::Stopped;
-                Err(format!("Unit {} failed to start: {}", self.name, error))
+                self.set_state(UnitState::Failed, format!("process failed to start: {}", error));
+                Err(error.context(format!("Unit {} failed to start", self.name)))
             }
         }
     }
@@ -173,7 +478,7 @@ impl Unit {
         }
     }
 
-    fn start_dependencies(&mut self) -> Result<bool, String> {
+    fn start_dependencies(&mut self) -> Result<bool, UnitError> {
         for dependency in &self.dependencies {
             match dependency.try_lock() {
                 Ok(mut unit) => {
@@ -182,7 +487,7 @@ impl Unit {
                     }
                 },
                 Err(error) => {
-                    return Err(format!("Unit {} failed to start: {}", self.name, error));
+                    return Err(UnitError::Busy(format!("Unit {} failed to start: {}", self.name, error)));
                 }
             }
         }
@@ -191,27 +496,68 @@ impl Unit {
     }
 
     /// Stops the child process
-    pub fn stop(&mut self) -> Result<bool, String> {
+    pub fn stop(&mut self) -> Result<bool, UnitError> {
         debug!("Stopping unit {}", self.name);
 
         let current_state = self.get_state();
-        self.state = UnitState::Stopping;
+        let raw_state = self.state.clone();
+
+        // a process that already exited is not stopped by us, so it is not stopping either
+        let exited = match self.process.get_last_exit_status() {
+            Some(exit_status) => Some(format!("process exited, {}", exit_status)),
+            None => None,
+        };
+        if exited.is_none() {
+            self.set_state(UnitState::Stopping, String::from("stop requested"));
+        }
+
+        // the monitor stops units whose process is gone over and over again, but it exited only once
+        let was_active = match raw_state {
+            UnitState::Starting | UnitState::Running | UnitState::Stopping => true,
+            _ => false,
+        };
 
         match self.process.stop() {
             Ok(success) => { // true if process was running, false if process was not running
                 self.stop_probes();
-                self.state = UnitState::Stopped;
+
+                if was_active {
+                    self.send_exited_event(success);
+                }
+
+                let reason = exited.unwrap_or(String::from("process stopped"));
+                match self.process.get_last_exit_status() {
+                    Some(exit_status) if self.process.has_failed() => {
+                        if current_state != UnitState::Failed {
+                            warn!("Unit {} failed: {}", self.name, exit_status);
+                        }
+                        self.set_state(UnitState::Failed, reason);
+                    },
+                    _ => self.set_state(UnitState::Stopped, reason),
+                };
+
                 debug!("Unit {} was stopped", self.name);
                 Ok(success)
             }
             Err(error) => {
-                self.state = current_state;
-                Err(format!("Unit {} failed to stop: {}", self.name, error))
+                self.state = raw_state;
+                Err(error.context(format!("Unit {} failed to stop", self.name)))
             }
         }
     }
 
-    pub fn restart(&mut self) -> Result<bool, String> {
+    /// A process stopped by us was killed, its exit status is not kept
+    fn send_exited_event(&self, stopped: bool) {
+        let (exit_code, signal) = match self.process.get_last_exit_status() {
+            Some(exit_status) => (exit_status.code(), exit_status.signal().map(Signal::from_number)),
+            None if stopped => (None, Some(Signal::from_number(libc::SIGKILL))),
+            None => (None, None),
+        };
+
+        self.events.send(self.name.clone(), UnitEventKind::Exited { exit_code, signal });
+    }
+
+    pub fn restart(&mut self) -> Result<bool, UnitError> {
         debug!("Restarting unit {}", self.name);
 
         if self.is_running() {
@@ -221,8 +567,87 @@ impl Unit {
         self.start()
     }
 
+    /// Runs the reload command, or sends the reload signal to the unit process
+    pub fn reload(&mut self) -> Result<bool, UnitError> {
+        if !self.is_running() {
+            return Err(UnitError::NotRunning(format!("Unit {} is not running", self.name)));
+        }
+
+        if !self.reload_command.is_empty() {
+            return self.run_reload_command();
+        }
+
+        return match self.reload_signal {
+            Some(signal) => {
+                info!("Reloading unit {} with {}", self.name, signal);
+                self.process.signal(signal, false)
+            },
+            None => Err(UnitError::InvalidArgument(format!("Unit {} has neither a reload signal nor a reload command", self.name))),
+        };
+    }
+
+    /// Runs as the user and group of the unit, MAINPID is set to the pid of the unit process
+    fn run_reload_command(&mut self) -> Result<bool, UnitError> {
+        info!("Reloading unit {} with {:?}", self.name, self.reload_command);
+
+        let main_pid = self.get_pid().map(|pid| pid.to_string()).unwrap_or_default();
+
+        let child = Command::new(&self.reload_command[0])
+            .args(&self.reload_command[1..])
+            .env("MAINPID", main_pid)
+            .uid(self.get_uid())
+            .gid(self.get_gid())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn();
+
+        let mut child = match child {
+            Ok(child) => child,
+            Err(error) => return Err(UnitError::Failed(format!("Unit {} failed to run reload command: {}", self.name, error))),
+        };
+
+        let result: Result<Option<ExitStatus>, Error> = child
+            .controlled()
+            .time_limit(RELOAD_TIMEOUT)
+            .terminate_for_timeout()
+            .wait();
+
+        return match result {
+            Ok(Some(exit_status)) if exit_status.success() => Ok(true),
+            Ok(Some(exit_status)) => Err(UnitError::Failed(format!("Unit {} reload command failed with exit status {}", self.name, exit_status))),
+            Ok(None) => Err(UnitError::Timeout(format!("Unit {} reload command timed out after {:?}", self.name, RELOAD_TIMEOUT))),
+            Err(error) => Err(UnitError::Failed(format!("Unit {} failed to run reload command: {}", self.name, error))),
+        };
+    }
+
+    /// Sends a signal to the unit process, or to all processes of the unit. What happens if the
+    /// process exits is up to the restart policy.
+    pub fn kill(&mut self, signal: Signal, group: bool) -> Result<bool, UnitError> {
+        info!("Sending {} to unit {}", signal, self.name);
+
+        return match self.process.signal(signal, group) {
+            Ok(result) => Ok(result),
+            Err(error) => Err(error.context(format!("Unit {} failed to receive signal", self.name))),
+        };
+    }
+
+    /// Forgets that the unit failed and resets its restart counter
+    pub fn reset_failed(&mut self) {
+        if self.state == UnitState::Failed {
+            self.set_state(UnitState::Stopped, String::from("failure reset"));
+        }
+
+        self.process.reset_failed();
+        self.restart_count = 0;
+    }
+
     /// A unit is allowed to start if it is enabled and all dependencies are running
     fn can_start(&mut self) -> bool {
+        if self.masked {
+            warn!("Unit {} is masked", self.name);
+            return false;
+        }
+
         if !self.enabled {
             warn!("Unit {} is not enabled", self.name);
             return false;
@@ -385,12 +810,178 @@ mod tests {
         assert_eq!(unit.is_running(), false);
     }
 
+    #[test]
+    fn start_with_sandbox_should_work() {
+        let mut unit = build_unit();
+        let mut sandbox = Sandbox::new();
+        sandbox.set_no_new_privileges(true);
+        sandbox.set_syscall_deny_list(vec![String::from("reboot")]);
+        unit.set_sandbox(sandbox);
+
+        unit.start().unwrap();
+        assert_eq!(unit.is_running(), true);
+    }
+
+    #[test]
+    fn start_with_failing_sandbox_option_should_name_it() {
+        let mut unit = build_unit();
+        let mut sandbox = Sandbox::new();
+        sandbox.set_root_directory(Some(String::from("/does/not/exist")));
+        unit.set_sandbox(sandbox);
+
+        let error = unit.start().unwrap_err();
+        assert!(error.to_string().contains("failed to change root directory to /does/not/exist"));
+        assert_eq!(unit.is_running(), false);
+    }
+
+    #[test]
+    fn start_with_listen_fds_should_pass_them() {
+        let output = std::env::temp_dir().join(format!("tsm-unitman-listen-fds-test-{}", std::process::id()));
+        let address = crate::unit::ListenAddress::Tcp("127.0.0.1:0".parse().unwrap());
+        let socket = crate::unit::Socket::new(String::from("web"), String::from("test"), vec![address], false).unwrap();
+
+        let script = format!(
+            "echo \"$LISTEN_FDS $LISTEN_FDNAMES $((LISTEN_PID == $$))\" > {0}; readlink /proc/$$/fd/3 >> {0}",
+            output.display(),
+        );
+
+        let mut unit = Unit::new(
+            String::from("test"),
+            String::from("sh"),
+            vec![String::from("-c"), script],
+            RestartPolicy::Never,
+            get_current_uid(),
+            get_current_gid(),
+            true,
+        );
+        unit.set_listen_fds(socket.get_listen_fds());
+
+        unit.start().unwrap();
+        std::thread::sleep(Duration::from_millis(500));
+
+        let content = std::fs::read_to_string(&output).unwrap();
+        let _ = std::fs::remove_file(&output);
+
+        assert!(content.starts_with("1 web 1\nsocket:["));
+    }
+
+    #[test]
+    fn adopt_should_take_over_running_process() {
+        let mut unit = build_unit();
+        let mut child = std::process::Command::new("sleep").arg("5").spawn().unwrap();
+        let pid = child.id();
+        let start_time = crate::unit::ProcStat::read(pid).unwrap().get_start_time();
+
+        assert_eq!(unit.adopt(pid, start_time + 1), false);
+        assert_eq!(unit.adopt(pid, start_time), true);
+        assert_eq!(unit.is_running(), true);
+        assert_eq!(unit.get_pid(), Some(pid));
+        assert_eq!(unit.get_runtime_state().get_start_time(), Some(start_time));
+
+        unit.stop().unwrap();
+        assert_eq!(unit.is_running(), false);
+        assert!(child.try_wait().is_err()); // already reaped by the unit
+    }
+
+    #[test]
+    fn unit_exiting_with_error_should_fail_until_reset() {
+        let mut unit = Unit::new(
+            String::from("test"),
+            String::from("sh"),
+            vec![String::from("-c"), String::from("exit 3")],
+            RestartPolicy::Never,
+            get_current_uid(),
+            get_current_gid(),
+            true,
+        );
+
+        unit.start().unwrap();
+        std::thread::sleep(Duration::from_millis(300));
+        assert_eq!(unit.is_running(), false);
+
+        unit.stop().unwrap();
+        assert_eq!(unit.get_state(), UnitState::Failed);
+
+        unit.reset_failed();
+        assert_eq!(unit.get_state(), UnitState::Stopped);
+
+        let reasons: Vec<String> = unit.get_transitions().iter().map(|transition| transition.get_reason()).collect();
+        assert_eq!(reasons.len(), 4);
+        assert_eq!(reasons[0], "start requested");
+        assert!(reasons[1].starts_with("process started (pid="));
+        assert!(reasons[2].starts_with("process exited, "));
+        assert_eq!(reasons[3], "failure reset");
+        assert_eq!(unit.get_transitions()[2].get_to(), UnitState::Failed);
+    }
+
+    #[test]
+    fn unit_should_send_events() {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let mut unit = Unit::new(
+            String::from("test"),
+            String::from("sh"),
+            vec![String::from("-c"), String::from("exit 3")],
+            RestartPolicy::Never,
+            get_current_uid(),
+            get_current_gid(),
+            true,
+        );
+        unit.set_event_sender(EventSender::new(sender));
+
+        unit.start().unwrap();
+        let pid = unit.get_pid().unwrap();
+        std::thread::sleep(Duration::from_millis(300));
+        assert_eq!(unit.is_running(), false);
+
+        // stopping again must not report the exit twice
+        unit.stop().unwrap();
+        unit.stop().unwrap();
+
+        let events: Vec<UnitEventKind> = receiver.try_iter().map(|event| event.get_kind()).collect();
+        assert_eq!(events.len(), 3);
+        assert_eq!(events[0], UnitEventKind::Started { pid });
+        assert_eq!(events[1], UnitEventKind::Exited { exit_code: Some(3), signal: None });
+        assert!(matches!(events[2], UnitEventKind::Failed { .. }));
+    }
+
+    #[test]
+    fn reload_should_send_reload_signal() {
+        let output = std::env::temp_dir().join(format!("tsm-unitman-reload-test-{}", std::process::id()));
+        let script = format!("trap 'echo reloaded > {}' HUP; while true; do sleep 0.1; done", output.display());
+
+        let mut unit = Unit::new(
+            String::from("test"),
+            String::from("sh"),
+            vec![String::from("-c"), script],
+            RestartPolicy::Never,
+            get_current_uid(),
+            get_current_gid(),
+            true,
+        );
+        assert!(unit.reload().is_err());
+
+        unit.set_reload_signal("SIGHUP".parse().unwrap());
+        unit.start().unwrap();
+        std::thread::sleep(Duration::from_millis(300));
+        unit.reload().unwrap();
+        std::thread::sleep(Duration::from_millis(300));
+
+        assert_eq!(std::fs::read_to_string(&output).unwrap(), "reloaded\n");
+        assert_eq!(unit.is_running(), true);
+
+        unit.kill("SIGKILL".parse().unwrap(), true).unwrap();
+        std::thread::sleep(Duration::from_millis(300));
+        assert_eq!(unit.is_running(), false);
+
+        let _ = std::fs::remove_file(&output);
+    }
+
     #[test]
     fn cannot_start_if_already_started() {
         let mut unit = build_unit();
 
         unit.start().unwrap();
-        assert!(unit.start().is_err());
+        assert_eq!(unit.start().unwrap_err(), UnitError::AlreadyRunning(String::from("test")));
     }
 
     #[test]
@@ -0,0 +1,81 @@
+use std::fmt;
+
+
+/// Errors of units and the unit manager. The variant tells RPC clients what went wrong,
+/// Context and Multiple only add to the message.
+#[derive(Debug, Clone, PartialEq)]
+pub enum UnitError {
+    UnitNotFound(String),
+    TargetNotFound(String),
+    Masked(String),
+    Disabled(String),
+    AlreadyRunning(String),
+    NotRunning(String),
+    InvalidArgument(String),
+    PermissionDenied(String),
+    DependencyFailed(String, Box<UnitError>),
+    Timeout(String),
+    Busy(String),
+    Failed(String),
+    Context(String, Box<UnitError>),
+    Multiple(String, Vec<UnitError>),
+}
+
+
+impl UnitError {
+    /// Prefixes the message, the kind of error stays the same
+    pub fn context(self, context: String) -> UnitError {
+        UnitError::Context(context, Box::new(self))
+    }
+
+    /// The error which determines the kind, with all context stripped
+    pub fn root_cause(&self) -> &UnitError {
+        return match self {
+            UnitError::Context(_, error) => error.root_cause(),
+            UnitError::Multiple(_, errors) if !errors.is_empty() => errors[0].root_cause(),
+            _ => self,
+        };
+    }
+}
+
+
+impl fmt::Display for UnitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UnitError::UnitNotFound(name) => write!(f, "Unit {} not found", name),
+            UnitError::TargetNotFound(name) => write!(f, "Target {} not found", name),
+            UnitError::Masked(name) => write!(f, "Unit {} is masked", name),
+            UnitError::Disabled(name) => write!(f, "Unit {} is not enabled", name),
+            UnitError::AlreadyRunning(name) => write!(f, "Unit {} is already running", name),
+            UnitError::NotRunning(message) => write!(f, "{}", message),
+            UnitError::InvalidArgument(message) => write!(f, "{}", message),
+            UnitError::PermissionDenied(message) => write!(f, "{}", message),
+            UnitError::DependencyFailed(name, error) => write!(f, "Unit {} failed to start dependencies: {}", name, error),
+            UnitError::Timeout(message) => write!(f, "{}", message),
+            UnitError::Busy(message) => write!(f, "{}", message),
+            UnitError::Failed(message) => write!(f, "{}", message),
+            UnitError::Context(context, error) => write!(f, "{}: {}", context, error),
+            UnitError::Multiple(context, errors) => {
+                let errors: Vec<String> = errors.iter().map(|error| error.to_string()).collect();
+                write!(f, "{}: {}", context, errors.join(", "))
+            },
+        }
+    }
+}
+
+
+impl std::error::Error for UnitError {}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn context_should_keep_root_cause() {
+        let error = UnitError::Masked(String::from("foo")).context(String::from("Error starting unit foo"));
+
+        assert_eq!(error.to_string(), "Error starting unit foo: Unit foo is masked");
+        assert_eq!(error.root_cause(), &UnitError::Masked(String::from("foo")));
+    }
+}
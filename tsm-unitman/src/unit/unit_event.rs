@@ -0,0 +1,53 @@
+use chrono::{DateTime, Local};
+
+use crate::unit::{ProbeState, Signal};
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ProbeType {
+    Process,
+    Liveness,
+}
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum UnitEventKind {
+    Started { pid: u32 },
+    /// Neither exit code nor signal are known if an adopted process was reaped by someone else
+    Exited { exit_code: Option<i32>, signal: Option<Signal> },
+    ProbeChanged { probe: ProbeType, from: ProbeState, to: ProbeState },
+    Failed { reason: String },
+    ConfigReloaded { unit_count: usize },
+}
+
+
+#[derive(Debug, Clone)]
+pub struct UnitEvent {
+    timestamp: DateTime<Local>,
+    unit_name: String,
+    kind: UnitEventKind,
+}
+
+
+impl UnitEvent {
+    /// unit_name is empty for events not about a single unit
+    pub fn new(unit_name: String, kind: UnitEventKind) -> UnitEvent {
+        UnitEvent {
+            timestamp: Local::now(),
+            unit_name,
+            kind,
+        }
+    }
+
+    pub fn get_timestamp(&self) -> DateTime<Local> {
+        self.timestamp
+    }
+
+    pub fn get_unit_name(&self) -> String {
+        self.unit_name.clone()
+    }
+
+    pub fn get_kind(&self) -> UnitEventKind {
+        self.kind.clone()
+    }
+}
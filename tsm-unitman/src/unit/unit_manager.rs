@@ -1,10 +1,14 @@
+use std::os::unix::process::CommandExt;
+use std::path::PathBuf;
+use std::process::Command;
 use std::sync::{Arc, Mutex};
 use std::thread;
 use std::thread::JoinHandle;
 use std::time::Duration;
+use chrono::Local;
 use log::{debug, error, warn, info};
 
-use crate::unit::{RestartPolicy, UnitRef};
+use crate::unit::{lock, EventSender, RestartPolicy, RuntimeState, Signal, Socket, SocketListener, Target, UnitError, UnitEventKind, UnitOverrides, UnitRef};
 
 
 pub type UnitManagerRef = Arc<Mutex<UnitManager>>;
@@ -13,7 +17,18 @@ pub type UnitManagerRef = Arc<Mutex<UnitManager>>;
 #[derive(Clone, Debug)]
 pub struct UnitManager {
     units: Vec<UnitRef>,
+    /// Same order as units, so that a unit is found without locking the others
+    unit_names: Vec<String>,
+    sockets: Vec<Arc<Socket>>,
+    targets: Vec<Target>,
+    default_target: Option<String>,
+    active_target: Arc<Mutex<Option<String>>>,
+    state_dir: Option<String>,
     stop_requested: Arc<Mutex<bool>>,
+    reexec_requested: Arc<Mutex<Option<PathBuf>>>,
+    /// What was written last, shared by all clones so that any of them can save a change
+    saved_runtime_state: Arc<Mutex<RuntimeState>>,
+    events: EventSender,
 }
 
 
@@ -21,7 +36,16 @@ impl UnitManager {
     pub fn new() -> UnitManager {
         UnitManager {
             units: Vec::new(),
+            unit_names: Vec::new(),
+            sockets: Vec::new(),
+            targets: Vec::new(),
+            default_target: None,
+            active_target: Arc::new(Mutex::new(None)),
+            state_dir: None,
             stop_requested: Arc::new(Mutex::new(false)),
+            reexec_requested: Arc::new(Mutex::new(None)),
+            saved_runtime_state: Arc::new(Mutex::new(RuntimeState::default())),
+            events: EventSender::default(),
         }
     }
 
@@ -31,9 +55,11 @@ impl UnitManager {
 
     pub fn add_unit(&mut self, unit: UnitRef) {
         match unit.try_lock() {
-            Ok(unit_unlocked) => {
+            Ok(mut unit_unlocked) => {
                 debug!("Adding unit {:?}", unit_unlocked);
+                unit_unlocked.set_event_sender(self.events.clone());
                 self.units.push(unit.clone());
+                self.unit_names.push(unit_unlocked.get_name());
             }
             Err(e) => {
                 error!("Failed to lock unit: {}", e);
@@ -45,41 +71,665 @@ impl UnitManager {
         &self.units
     }
 
-    pub fn start_unit(&self, name: String) -> Result<bool, String> {
+    pub fn get_unit(&self, name: &str) -> Option<UnitRef> {
+        return self.unit_names.iter()
+            .position(|unit_name| unit_name == name)
+            .map(|index| self.units[index].clone());
+    }
+
+    pub fn is_unit_running(&self, name: &str) -> bool {
+        return match self.get_unit(name) {
+            Some(unit) => {
+                match unit.try_lock() {
+                    Ok(mut unit) => unit.is_running(),
+                    Err(e) => {
+                        error!("Error acquiring lock while checking unit: {}", e);
+                        false
+                    },
+                }
+            },
+            None => false,
+        };
+    }
+
+    /// Marks the target unit as socket activated. Without accept mode, the listening fds are
+    /// passed to the unit on every start.
+    pub fn add_socket(&mut self, socket: Socket) {
+        let unit = match self.get_unit(&socket.get_unit_name()) {
+            Some(unit) => unit,
+            None => {
+                error!("Socket {} refers to unknown unit {}", socket.get_name(), socket.get_unit_name());
+                return;
+            },
+        };
+
+        match lock(&unit) {
+            Ok(mut unit) => {
+                debug!("Adding socket {} listening on {:?}", socket.get_name(), socket.get_addresses());
+                unit.set_socket_activated(true);
+
+                if !socket.is_accept() {
+                    unit.set_listen_fds(socket.get_listen_fds());
+                }
+            },
+            Err(e) => {
+                error!("Failed to add socket {}: {}", socket.get_name(), e);
+                return;
+            },
+        };
+
+        self.sockets.push(Arc::new(socket));
+    }
+
+    /// Events of all units and of the manager itself are sent here
+    pub fn set_event_sender(&mut self, events: EventSender) {
+        for unit in &self.units {
+            match unit.try_lock() {
+                Ok(mut unit) => unit.set_event_sender(events.clone()),
+                Err(e) => error!("Failed to lock unit: {}", e),
+            }
+        }
+
+        self.events = events;
+    }
+
+    pub fn set_state_dir(&mut self, state_dir: String) {
+        self.state_dir = Some(state_dir);
+    }
+
+    fn get_overrides_path(&self) -> Option<PathBuf> {
+        return match self.state_dir {
+            Some(ref state_dir) => Some(PathBuf::from(state_dir).join("units.toml")),
+            None => None,
+        };
+    }
+
+    /// Applies the persisted enable, disable and mask operations on top of the configuration
+    pub fn apply_unit_overrides(&self) {
+        let path = match self.get_overrides_path() {
+            Some(path) => path,
+            None => return,
+        };
+
+        let overrides = match UnitOverrides::load(&path) {
+            Ok(overrides) => overrides,
+            Err(e) => {
+                error!("{}", e);
+                return;
+            }
+        };
+
         for unit in &self.units {
             match unit.try_lock() {
                 Ok(mut unit) => {
-                    if unit.get_name() == name {
-                        if unit.is_running() {
-                            debug!("Unit {} is already running", unit.get_name());
-                            return Ok(true);
-                        }
+                    let unit_override = match overrides.get(&unit.get_name()) {
+                        Some(unit_override) => unit_override.clone(),
+                        None => continue,
+                    };
+
+                    if let Some(enabled) = unit_override.get_enabled() {
+                        debug!("Unit {} is {} by override", unit.get_name(), if enabled { "enabled" } else { "disabled" });
+                        unit.set_enabled(enabled);
+                    }
 
-                        debug!("Starting unit {}", unit.get_name());
+                    if let Some(masked) = unit_override.get_masked() {
+                        debug!("Unit {} is {} by override", unit.get_name(), if masked { "masked" } else { "unmasked" });
+                        unit.set_masked(masked);
+                    }
+                },
+                Err(e) => error!("Error acquiring lock while applying unit overrides: {}", e),
+            }
+        }
+    }
 
-                        match unit.start() {
-                            Ok(_) => {
-                                unit.set_restart_policy(RestartPolicy::DisabledTemporarily);
-                                unit.start_probes();
+    fn get_runtime_state_path(&self) -> Option<PathBuf> {
+        return match self.state_dir {
+            Some(ref state_dir) => Some(PathBuf::from(state_dir).join("runtime.toml")),
+            None => None,
+        };
+    }
 
-                                debug!("Started unit {}", unit.get_name());
-                                return Ok(true);
-                            },
-                            Err(e) => {
-                                warn!("Error starting unit {}: {}", unit.get_name(), e);
-                                return Err(format!("Error starting unit {}: {}", unit.get_name(), e));
-                            },
+    /// Waits for busy units, a state missing a unit would make the next unitman lose its process
+    fn collect_runtime_state(&self) -> Result<RuntimeState, UnitError> {
+        let mut runtime_state = RuntimeState::default();
+        runtime_state.set_active_target(self.get_active_target());
+
+        for unit in &self.units {
+            let unit = lock(unit)?;
+            runtime_state.set(unit.get_name(), unit.get_runtime_state());
+        }
+
+        return Ok(runtime_state);
+    }
+
+    /// Called right after every change of the units, so that a process started or stopped is
+    /// never missed by a crash or re-exec. Units must not be locked by the caller.
+    fn save_runtime_state(&self) {
+        if let Err(e) = self.write_runtime_state(false) {
+            error!("{}", e);
+        }
+    }
+
+    /// Without force the runtime state is only written if it differs from the last written one
+    fn write_runtime_state(&self, force: bool) -> Result<(), String> {
+        let path = match self.get_runtime_state_path() {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+
+        // held while collecting, so that an older state never overwrites a newer one
+        let mut saved_runtime_state = match self.saved_runtime_state.lock() {
+            Ok(saved_runtime_state) => saved_runtime_state,
+            Err(e) => return Err(format!("Failed to lock saved runtime state: {}", e)),
+        };
+
+        let runtime_state = match self.collect_runtime_state() {
+            Ok(runtime_state) => runtime_state,
+            Err(e) => return Err(format!("Error collecting runtime state: {}", e)),
+        };
+
+        if !force && runtime_state == *saved_runtime_state {
+            return Ok(());
+        }
+
+        runtime_state.save(&path)?;
+        *saved_runtime_state = runtime_state;
+
+        return Ok(());
+    }
+
+    /// Picks up the units of a previous unitman instance. Processes which are still running are
+    /// adopted instead of being started again, restart counters and policy overrides are restored.
+    fn restore_runtime_state(&self) {
+        let path = match self.get_runtime_state_path() {
+            Some(path) => path,
+            None => return,
+        };
+
+        let runtime_state = match RuntimeState::load(&path) {
+            Ok(runtime_state) => runtime_state,
+            Err(e) => {
+                error!("{}", e);
+                return;
+            }
+        };
+
+        self.set_active_target(runtime_state.get_active_target());
+
+        for unit in &self.units {
+            match unit.try_lock() {
+                Ok(mut unit) => {
+                    let unit_state = match runtime_state.get(&unit.get_name()) {
+                        Some(unit_state) => unit_state.clone(),
+                        None => continue,
+                    };
+
+                    unit.set_restart_count(unit_state.get_restart_count());
+
+                    if let Some(policy) = unit_state.get_restart_policy() {
+                        unit.set_restart_policy(policy);
+                    }
+
+                    if let (Some(pid), Some(start_time)) = (unit_state.get_pid(), unit_state.get_start_time()) {
+                        match unit.adopt(pid, start_time) {
+                            true => info!("Adopted unit {} (pid={})", unit.get_name(), pid),
+                            false => info!("Process of unit {} (pid={}) is gone", unit.get_name(), pid),
                         }
                     }
+                },
+                Err(e) => error!("Error acquiring lock while restoring runtime state: {}", e),
+            }
+        }
+
+        match self.saved_runtime_state.lock() {
+            Ok(mut saved_runtime_state) => *saved_runtime_state = runtime_state,
+            Err(e) => error!("Failed to lock saved runtime state: {}", e),
+        };
+    }
+
+    /// Hands over to a new unitman binary, by default the one currently running. The units keep
+    /// running and are adopted by the new binary, which is executed with the same arguments.
+    /// Sockets are bound again by the new binary, which fails for addresses still in use by
+    /// units serving them.
+    pub fn request_reexec(&self, executable: Option<String>) -> Result<bool, UnitError> {
+        let executable = match executable {
+            Some(executable) => PathBuf::from(executable),
+            None => match std::env::current_exe() {
+                // the binary was replaced on disk, e.g. by a package update
+                Ok(path) => PathBuf::from(path.to_string_lossy().trim_end_matches(" (deleted)")),
+                Err(e) => return Err(UnitError::Failed(format!("Error determining current executable: {}", e))),
+            },
+        };
+
+        if !executable.is_file() {
+            return Err(UnitError::InvalidArgument(format!("Executable {:?} not found", executable)));
+        }
+
+        if self.state_dir.is_none() {
+            return Err(UnitError::Failed(String::from("Cannot re-exec without a state directory")));
+        }
+
+        return match self.reexec_requested.try_lock() {
+            Ok(mut reexec_requested) => {
+                info!("Re-exec into {:?} requested", executable);
+                *reexec_requested = Some(executable);
+                Ok(true)
+            },
+            Err(e) => Err(UnitError::Busy(format!("Failed to lock reexec_requested: {}", e))),
+        };
+    }
+
+    fn take_reexec_request(&self) -> Option<PathBuf> {
+        return match self.reexec_requested.try_lock() {
+            Ok(mut reexec_requested) => reexec_requested.take(),
+            Err(e) => {
+                error!("Failed to lock reexec_requested: {}", e);
+                None
+            }
+        };
+    }
+
+    /// Only returns if the exec failed, or the runtime state could not be saved, since the new
+    /// binary could not adopt the units without it
+    fn reexec(&self, executable: PathBuf) {
+        // gives the rpc server time to send the reply to the re-exec request
+        thread::sleep(Duration::from_millis(500));
+
+        // written right before the exec, even if unchanged, so the new binary sees every unit
+        if let Err(e) = self.write_runtime_state(true) {
+            error!("Not re-executing {:?}, saving the runtime state failed: {}", executable, e);
+            return;
+        }
+
+        info!("Re-executing {:?}", executable);
+        let error = Command::new(&executable).args(std::env::args_os().skip(1)).exec();
+        error!("Error re-executing {:?}: {}", executable, error);
+    }
+
+    /// Persists the change first, so that the unit is never changed without surviving a restart
+    fn update_unit_overrides<F>(&self, update: F) -> Result<(), UnitError>
+        where F: FnOnce(&mut UnitOverrides)
+    {
+        let path = match self.get_overrides_path() {
+            Some(path) => path,
+            None => {
+                warn!("No state directory set, unit changes will not be persisted");
+                return Ok(());
+            }
+        };
+
+        let mut overrides = UnitOverrides::load(&path).map_err(UnitError::Failed)?;
+        update(&mut overrides);
+        return overrides.save(&path).map_err(UnitError::Failed);
+    }
+
+    pub fn enable_unit(&self, name: String, now: bool) -> Result<bool, UnitError> {
+        let unit = match self.get_unit(&name) {
+            Some(unit) => unit,
+            None => return Err(UnitError::UnitNotFound(name)),
+        };
+
+        {
+            let mut unit = lock(&unit)?;
+
+            if unit.is_masked() {
+                return Err(UnitError::Masked(name));
+            }
+
+            self.update_unit_overrides(|overrides| overrides.set_enabled(name.clone(), true))?;
+            unit.set_enabled(true);
+            info!("Enabled unit {}", name);
+        }
+
+        return match now {
+            true => self.start_unit(name),
+            false => Ok(true),
+        };
+    }
+
+    pub fn disable_unit(&self, name: String, now: bool) -> Result<bool, UnitError> {
+        let unit = match self.get_unit(&name) {
+            Some(unit) => unit,
+            None => return Err(UnitError::UnitNotFound(name)),
+        };
+
+        {
+            let mut unit = lock(&unit)?;
+            self.update_unit_overrides(|overrides| overrides.set_enabled(name.clone(), false))?;
+            unit.set_enabled(false);
+            info!("Disabled unit {}", name);
+        }
+
+        return match now {
+            true => self.stop_unit(name, false),
+            false => Ok(true),
+        };
+    }
+
+    pub fn mask_unit(&self, name: String, now: bool) -> Result<bool, UnitError> {
+        let unit = match self.get_unit(&name) {
+            Some(unit) => unit,
+            None => return Err(UnitError::UnitNotFound(name)),
+        };
+
+        {
+            let mut unit = lock(&unit)?;
+            self.update_unit_overrides(|overrides| overrides.set_masked(name.clone(), true))?;
+            unit.set_masked(true);
+            info!("Masked unit {}", name);
+        }
+
+        return match now {
+            true => self.stop_unit(name, false),
+            false => Ok(true),
+        };
+    }
+
+    pub fn unmask_unit(&self, name: String) -> Result<bool, UnitError> {
+        let unit = match self.get_unit(&name) {
+            Some(unit) => unit,
+            None => return Err(UnitError::UnitNotFound(name)),
+        };
+
+        let mut unit = lock(&unit)?;
+        self.update_unit_overrides(|overrides| overrides.set_masked(name.clone(), false))?;
+        unit.set_masked(false);
+        info!("Unmasked unit {}", name);
+
+        return Ok(true);
+    }
+
+    pub fn is_unit_wanted(&self, name: &str) -> bool {
+        return match self.get_unit(name) {
+            Some(unit) => {
+                match unit.try_lock() {
+                    Ok(unit) => unit.is_wanted(),
+                    Err(e) => {
+                        error!("Error acquiring lock while checking unit: {}", e);
+                        false
+                    },
+                }
+            },
+            None => false,
+        };
+    }
+
+    pub fn add_target(&mut self, target: Target) {
+        debug!("Adding target {:?}", target);
+        self.targets.push(target);
+    }
+
+    pub fn get_targets(&self) -> &Vec<Target> {
+        &self.targets
+    }
+
+    /// Without a default target all units are started at boot
+    pub fn set_default_target(&mut self, name: Option<String>) {
+        self.default_target = name;
+    }
+
+    pub fn get_default_target(&self) -> Option<String> {
+        self.default_target.clone()
+    }
+
+    /// The target which was isolated last, or the default target
+    pub fn get_active_target(&self) -> Option<String> {
+        return match self.active_target.try_lock() {
+            Ok(active_target) => active_target.clone(),
+            Err(e) => {
+                error!("Failed to lock active_target: {}", e);
+                None
+            }
+        };
+    }
+
+    fn set_active_target(&self, name: Option<String>) {
+        match self.active_target.try_lock() {
+            Ok(mut active_target) => *active_target = name,
+            Err(e) => error!("Failed to lock active_target: {}", e),
+        };
+    }
+
+    /// Units wanted by the target, directly or through the targets it wants
+    pub fn get_target_units(&self, name: &str) -> Result<Vec<String>, UnitError> {
+        if !self.targets.iter().any(|target| target.get_name() == name) {
+            return Err(UnitError::TargetNotFound(name.to_string()));
+        }
+
+        let mut unit_names: Vec<String> = Vec::new();
+        let mut visited_targets = vec![name.to_string()];
+        let mut target_queue = vec![name.to_string()];
+
+        while let Some(target_name) = target_queue.pop() {
+            let target = match self.targets.iter().find(|target| target.get_name() == target_name) {
+                Some(target) => target,
+                None => continue,
+            };
+
+            for want in target.get_wants() {
+                if self.targets.iter().any(|target| target.get_name() == want) {
+                    if !visited_targets.contains(&want) {
+                        visited_targets.push(want.clone());
+                        target_queue.push(want);
+                    }
+                } else if self.get_unit(&want).is_some() {
+                    if !unit_names.contains(&want) {
+                        unit_names.push(want);
+                    }
+                } else {
+                    warn!("Target {} wants unknown unit or target {}", target_name, want);
                 }
-                Err(e) => {
-                    warn!("Error acquiring lock while starting unit: {}", e);
-                    return Err(format!("Error acquiring lock while starting unit: {}", e));
+            }
+        }
+
+        return Ok(unit_names);
+    }
+
+    /// Units wanted by the target plus all of their dependencies
+    pub fn resolve_target(&self, name: &str) -> Result<Vec<String>, UnitError> {
+        let mut unit_names = self.get_target_units(name)?;
+        let mut index = 0;
+
+        while index < unit_names.len() {
+            let unit = match self.get_unit(&unit_names[index]) {
+                Some(unit) => unit,
+                None => return Err(UnitError::UnitNotFound(unit_names[index].clone())),
+            };
+
+            let dependencies = lock(&unit)?.get_dependencies();
+
+            for dependency in dependencies {
+                let dependency_name = lock(&dependency)?.get_name();
+
+                if !unit_names.contains(&dependency_name) {
+                    unit_names.push(dependency_name);
+                }
+            }
+
+            index += 1;
+        }
+
+        return Ok(unit_names);
+    }
+
+    /// Starts all units of the target and lets them be restarted and triggered again
+    pub fn start_target(&self, name: String) -> Result<bool, UnitError> {
+        let unit_names = self.resolve_target(&name)?;
+        let mut errors = Vec::new();
+
+        info!("Starting target {}", name);
+
+        for unit_name in unit_names {
+            let unit = match self.get_unit(&unit_name) {
+                Some(unit) => unit,
+                None => continue,
+            };
+
+            match lock(&unit) {
+                Ok(mut unit) => {
+                    unit.set_wanted(true);
+                    unit.reset_restart_policy();
+
+                    if unit.is_started_on_demand() || unit.is_running() {
+                        continue;
+                    }
+
+                    match unit.start() {
+                        Ok(_) => {
+                            unit.start_probes();
+                            debug!("Started unit {} of target {}", unit.get_name(), name);
+                        },
+                        Err(e) => errors.push(e),
+                    }
                 },
+                Err(e) => errors.push(e.context(format!("Error starting unit {}", unit_name))),
+            };
+        }
+
+        self.save_runtime_state();
+
+        return match errors.is_empty() {
+            true => Ok(true),
+            false => Err(UnitError::Multiple(format!("Error starting target {}", name), errors)),
+        };
+    }
+
+    /// Stops the units wanted by the target. Their dependencies are left alone, since other
+    /// units may still need them.
+    pub fn stop_target(&self, name: String) -> Result<bool, UnitError> {
+        let unit_names = self.get_target_units(&name)?;
+        let mut errors = Vec::new();
+
+        info!("Stopping target {}", name);
+
+        for unit_name in unit_names {
+            let unit = match self.get_unit(&unit_name) {
+                Some(unit) => unit,
+                None => continue,
+            };
+
+            match lock(&unit) {
+                Ok(mut unit) => {
+                    unit.set_wanted(false);
+
+                    match unit.stop() {
+                        Ok(_) => debug!("Stopped unit {} of target {}", unit.get_name(), name),
+                        Err(e) => errors.push(e),
+                    }
+                },
+                Err(e) => errors.push(e.context(format!("Error stopping unit {}", unit_name))),
+            };
+        }
+
+        self.save_runtime_state();
+
+        return match errors.is_empty() {
+            true => Ok(true),
+            false => Err(UnitError::Multiple(format!("Error stopping target {}", name), errors)),
+        };
+    }
+
+    /// Stops every unit which is not part of the target, then starts the target
+    pub fn isolate_target(&self, name: String) -> Result<bool, UnitError> {
+        let unit_names = self.resolve_target(&name)?;
+
+        info!("Isolating target {}", name);
+
+        for unit in &self.units {
+            let mut unit = lock(unit).map_err(|e| e.context(format!("Error isolating target {}", name)))?;
+            let wanted = unit_names.contains(&unit.get_name());
+            unit.set_wanted(wanted);
+
+            if !wanted && unit.is_running() {
+                match unit.stop() {
+                    Ok(_) => info!("Stopped unit {}", unit.get_name()),
+                    Err(e) => return Err(e.context(format!("Error isolating target {}", name))),
+                }
+            }
+        }
+
+        self.set_active_target(Some(name.clone()));
+        return self.start_target(name);
+    }
+
+    /// Restricts the units started at boot to the target which was active before unitman
+    /// restarted, or to the default target
+    fn apply_default_target(&self) {
+        let name = match self.get_active_target().or(self.default_target.clone()) {
+            Some(name) => name,
+            None => return,
+        };
+
+        let unit_names = match self.resolve_target(&name) {
+            Ok(unit_names) => unit_names,
+            Err(e) => {
+                error!("Error resolving default target, starting all units: {}", e);
+                self.set_active_target(None);
+                return;
+            }
+        };
+
+        for unit in &self.units {
+            match unit.try_lock() {
+                Ok(mut unit) => {
+                    let wanted = unit_names.contains(&unit.get_name());
+                    unit.set_wanted(wanted);
+                },
+                Err(e) => error!("Error acquiring lock while applying default target: {}", e),
             }
         }
 
-        Err(format!("Unit {} not found", name))
+        info!("Active target is {}", name);
+        self.set_active_target(Some(name));
+    }
+
+    /// Started by hand, the unit is not restarted by its restart policy until it is restarted or
+    /// its target is started again
+    pub fn start_unit(&self, name: String) -> Result<bool, UnitError> {
+        return self.start_unit_with_policy(name, Some(RestartPolicy::DisabledTemporarily));
+    }
+
+    /// Starts a unit on behalf of its timer, path trigger or socket. Its restart policy is left
+    /// alone, so that the unit is not kept from being restarted by what started it.
+    pub fn activate_unit(&self, name: String) -> Result<bool, UnitError> {
+        return self.start_unit_with_policy(name, None);
+    }
+
+    fn start_unit_with_policy(&self, name: String, restart_policy: Option<RestartPolicy>) -> Result<bool, UnitError> {
+        let unit = match self.get_unit(&name) {
+            Some(unit) => unit,
+            None => return Err(UnitError::UnitNotFound(name)),
+        };
+
+        let result = {
+            let mut unit = lock(&unit)?;
+
+            if unit.is_running() {
+                debug!("Unit {} is already running", name);
+                return Ok(true);
+            }
+
+            debug!("Starting unit {}", name);
+
+            match unit.start() {
+                Ok(_) => {
+                    if let Some(restart_policy) = restart_policy {
+                        unit.set_restart_policy(restart_policy);
+                    }
+                    unit.start_probes();
+
+                    debug!("Started unit {}", name);
+                    Ok(true)
+                },
+                Err(e) => {
+                    warn!("Error starting unit {}: {}", name, e);
+                    Err(e.context(format!("Error starting unit {}", name)))
+                },
+            }
+        };
+
+        self.save_runtime_state();
+        return result;
     }
 
     /// Iterate over all units and try to start them
@@ -95,6 +745,21 @@ impl UnitManager {
                         continue;
                     }
 
+                    if !unit.is_wanted() {
+                        debug!("Unit {} is not part of the active target", unit.get_name());
+                        continue;
+                    }
+
+                    if !unit.is_enabled() || unit.is_masked() {
+                        debug!("Unit {} is disabled or masked", unit.get_name());
+                        continue;
+                    }
+
+                    if unit.is_started_on_demand() {
+                        debug!("Unit {} is started by its timer, path trigger or socket", unit.get_name());
+                        continue;
+                    }
+
                     debug!("Starting unit {}", unit.get_name());
 
                     match unit.start() {
@@ -107,43 +772,101 @@ impl UnitManager {
         }
     }
 
-    pub fn stop_unit(&self, name: String, restart: bool) -> Result<bool, String> {
-        for unit in &self.units {
-            match unit.try_lock() {
-                Ok(mut unit) => {
-                    if unit.get_name() == name {
-                        if !unit.is_running() {
-                            debug!("Unit {} is already stopped", unit.get_name());
-                            return Ok(true);
-                        }
+    pub fn stop_unit(&self, name: String, restart: bool) -> Result<bool, UnitError> {
+        let unit = match self.get_unit(&name) {
+            Some(unit) => unit,
+            None => return Err(UnitError::UnitNotFound(name)),
+        };
 
-                        info!("Stopping unit {}", unit.get_name());
+        let result = {
+            let mut unit = lock(&unit)?;
 
-                        // stopping unit will automatically stop its probes and cleanup its resources
-                        return match unit.stop() {
-                            Ok(_) => {
-                                if !restart {
-                                    unit.set_restart_policy(RestartPolicy::DisabledTemporarily);
-                                }
+            if !unit.is_running() {
+                debug!("Unit {} is already stopped", name);
+                return Ok(true);
+            }
 
-                                info!("Stopped unit {}", unit.get_name());
-                                Ok(true)
-                            },
-                            Err(e) => {
-                                warn!("Error stopping unit {}: {}", unit.get_name(), e);
-                                Err(format!("Error stopping unit {}: {}", unit.get_name(), e))
-                            },
-                        }
+            info!("Stopping unit {}", name);
+
+            // stopping unit will automatically stop its probes and cleanup its resources
+            match unit.stop() {
+                Ok(_) => {
+                    if !restart {
+                        unit.set_restart_policy(RestartPolicy::DisabledTemporarily);
                     }
-                }
+
+                    info!("Stopped unit {}", name);
+                    Ok(true)
+                },
                 Err(e) => {
-                    warn!("Error acquiring lock while stopping unit: {}", e);
-                    return Err(format!("Error acquiring lock while stopping unit: {}", e));
+                    warn!("Error stopping unit {}: {}", name, e);
+                    Err(e.context(format!("Error stopping unit {}", name)))
                 },
             }
-        }
+        };
+
+        self.save_runtime_state();
+        return result;
+    }
+
+    /// Stops the unit if it is running and starts it again, the restart policy is kept
+    pub fn restart_unit(&self, name: String) -> Result<bool, UnitError> {
+        let unit = match self.get_unit(&name) {
+            Some(unit) => unit,
+            None => return Err(UnitError::UnitNotFound(name)),
+        };
 
-        Err(format!("Unit {} not found", name))
+        let result = {
+            let mut unit = lock(&unit)?;
+            info!("Restarting unit {}", name);
+
+            match unit.restart() {
+                Ok(_) => {
+                    unit.start_probes();
+                    info!("Restarted unit {}", name);
+                    Ok(true)
+                },
+                Err(e) => {
+                    warn!("Error restarting unit {}: {}", name, e);
+                    Err(e.context(format!("Error restarting unit {}", name)))
+                },
+            }
+        };
+
+        self.save_runtime_state();
+        return result;
+    }
+
+    pub fn reload_unit(&self, name: String) -> Result<bool, UnitError> {
+        let unit = match self.get_unit(&name) {
+            Some(unit) => unit,
+            None => return Err(UnitError::UnitNotFound(name)),
+        };
+
+        return lock(&unit)?.reload();
+    }
+
+    /// Sends a signal to the unit process, or with group to all processes of the unit
+    pub fn kill_unit(&self, name: String, signal: Signal, group: bool) -> Result<bool, UnitError> {
+        let unit = match self.get_unit(&name) {
+            Some(unit) => unit,
+            None => return Err(UnitError::UnitNotFound(name)),
+        };
+
+        return lock(&unit)?.kill(signal, group);
+    }
+
+    pub fn reset_failed_unit(&self, name: String) -> Result<bool, UnitError> {
+        let unit = match self.get_unit(&name) {
+            Some(unit) => unit,
+            None => return Err(UnitError::UnitNotFound(name)),
+        };
+
+        lock(&unit)?.reset_failed();
+        info!("Reset failed state of unit {}", name);
+        self.save_runtime_state();
+
+        return Ok(true);
     }
 
     /// Iterate over all units and try to stop them
@@ -167,7 +890,7 @@ impl UnitManager {
         thread::sleep(Duration::from_secs(1));
     }
 
-    fn stop_requested(&self) -> bool {
+    pub fn stop_requested(&self) -> bool {
         return match self.stop_requested.try_lock() {
             Ok(stop_requested) => *stop_requested,
             Err(e) => {
@@ -201,11 +924,21 @@ impl UnitManager {
     }
 
     pub fn run_loop(&mut self) {
+        self.restore_runtime_state();
+        self.apply_default_target();
+
+        // the configuration is read at startup and again on every re-exec
+        self.events.send(String::new(), UnitEventKind::ConfigReloaded { unit_count: self.units.len() });
+
         info!("Starting units");
         self.start_units();
 
         info!("Starting unit probes");
         self.start_units_probes();
+        self.save_runtime_state();
+
+        info!("Listening on sockets");
+        let socket_listeners = self.start_socket_listeners();
 
         info!("Monitoring units");
         loop {
@@ -215,18 +948,44 @@ impl UnitManager {
             }
 
             self.monitor();
+            self.trigger_timers();
+            self.trigger_paths();
+            self.save_runtime_state();
+
+            if let Some(executable) = self.take_reexec_request() {
+                self.reexec(executable);
+            }
+
             thread::sleep(Duration::from_secs(1));
         }
 
+        for socket_listener in socket_listeners {
+            socket_listener.join().expect("Error joining socket listener thread");
+        }
+
         info!("Shutting down units and their probes");
         self.stop_units();
+        self.save_runtime_state();
         self.reset_stop_request();
     }
 
+    fn start_socket_listeners(&self) -> Vec<JoinHandle<()>> {
+        return self.sockets.iter()
+            .map(|socket| SocketListener::new(socket.clone(), self.clone()).run())
+            .collect();
+    }
+
     fn start_units_probes(&self) {
         for unit in &self.units {
             match unit.try_lock() {
-                Ok(mut unit) => unit.start_probes(),
+                Ok(mut unit) => {
+                    // these were not started by start_units()
+                    if !unit.is_wanted() || !unit.is_enabled() || unit.is_masked() || unit.is_started_on_demand() {
+                        continue;
+                    }
+
+                    unit.start_probes();
+                },
                 Err(e) => error!("Error acquiring lock while starting unit probes: {}", e),
             }
         }
@@ -238,6 +997,10 @@ impl UnitManager {
                 Ok(mut unit) => {
                     let is_running = unit.is_running();
 
+                    if is_running {
+                        unit.trim_output();
+                    }
+
                     if !is_running {
                         debug!("Force stopping unit {} to make sure resources are cleaned up", unit.get_name());
                         match unit.stop() {
@@ -246,8 +1009,10 @@ impl UnitManager {
                         }
                     }
 
-                    if !is_running && unit.get_restart_policy() == RestartPolicy::Always {
+                    if !is_running && unit.get_restart_policy() == RestartPolicy::Always && unit.is_wanted() && unit.is_enabled() && !unit.is_masked() && !unit.is_started_on_demand() {
                         debug!("Unit {} is not running, restarting because restart policy was set to Always.", unit.get_name());
+                        unit.increment_restart_count();
+
                         match unit.restart() {
                             Ok(_) => {
                                 unit.start_probes();
@@ -263,6 +1028,68 @@ impl UnitManager {
             }
         }
     }
+
+    /// Starts the units whose timers elapsed
+    fn trigger_timers(&self) {
+        let now = Local::now();
+        let mut due_units = Vec::new();
+
+        for unit in &self.units {
+            match unit.try_lock() {
+                Ok(mut unit) => {
+                    if unit.is_wanted() && unit.is_timer_due(&now) {
+                        unit.trigger_timer(&now);
+                        due_units.push(unit.get_name());
+                    }
+                }
+                Err(e) => error!("Error acquiring lock while checking timers: {}", e),
+            }
+        }
+
+        // activate_unit() locks the units itself, so all locks must be released by now
+        for name in due_units {
+            info!("Timer of unit {} elapsed", name);
+
+            match self.activate_unit(name.clone()) {
+                Ok(_) => debug!("Unit {} started by timer", name),
+                Err(e) => warn!("Error starting unit {} by timer: {}", name, e),
+            }
+        }
+    }
+
+    /// Starts the stopped units whose path conditions are met
+    fn trigger_paths(&self) {
+        let now = Local::now();
+        let mut due_units = Vec::new();
+
+        for unit in &self.units {
+            match unit.try_lock() {
+                Ok(mut unit) => {
+                    if !unit.has_path_trigger() {
+                        continue;
+                    }
+
+                    unit.update_path_trigger(&now);
+
+                    if unit.is_wanted() && unit.is_path_trigger_due(&now) && !unit.is_running() {
+                        unit.trigger_path_trigger(&now);
+                        due_units.push(unit.get_name());
+                    }
+                }
+                Err(e) => error!("Error acquiring lock while checking path triggers: {}", e),
+            }
+        }
+
+        // activate_unit() locks the units itself, so all locks must be released by now
+        for name in due_units {
+            info!("Path trigger of unit {} fired", name);
+
+            match self.activate_unit(name.clone()) {
+                Ok(_) => debug!("Unit {} started by path trigger", name),
+                Err(e) => warn!("Error starting unit {} by path trigger: {}", name, e),
+            }
+        }
+    }
 }
 
 
@@ -371,5 +1198,108 @@ mod tests {
         assert_eq!(unit1.lock().unwrap().is_running(), false);
         assert_eq!(unit2.lock().unwrap().is_running(), false);
     }
-}
 
+    #[test]
+    fn resolve_target_should_include_dependencies() {
+        let mut manager = UnitManager::new();
+        let (unit1, unit2) = build_unitrefs();
+
+        manager.add_unit(unit1.clone());
+        manager.add_unit(unit2.clone());
+        manager.add_target(Target::new(String::from("normal"), vec![String::from("test2")]));
+        manager.add_target(Target::new(String::from("maintenance"), vec![String::from("normal")]));
+
+        assert_eq!(manager.get_target_units("maintenance").unwrap(), vec!["test2"]);
+        assert_eq!(manager.resolve_target("maintenance").unwrap(), vec!["test2", "test1"]);
+        assert_eq!(manager.resolve_target("factory-test").unwrap_err(), UnitError::TargetNotFound(String::from("factory-test")));
+    }
+
+    #[test]
+    fn isolate_target_should_stop_other_units() {
+        let mut manager = UnitManager::new();
+        let (unit1, unit2) = build_unitrefs();
+
+        manager.add_unit(unit1.clone());
+        manager.add_unit(unit2.clone());
+        manager.add_target(Target::new(String::from("base"), vec![String::from("test1")]));
+
+        manager.start_units();
+        assert_eq!(unit2.lock().unwrap().is_running(), true);
+
+        manager.isolate_target(String::from("base")).unwrap();
+        assert_eq!(unit1.lock().unwrap().is_running(), true);
+        assert_eq!(unit2.lock().unwrap().is_running(), false);
+        assert_eq!(unit2.lock().unwrap().is_wanted(), false);
+        assert_eq!(manager.get_active_target(), Some(String::from("base")));
+
+        manager.stop_units();
+    }
+
+    #[test]
+    fn activate_unit_should_keep_restart_policy() {
+        let mut manager = UnitManager::new();
+        let (unit1, unit2) = build_unitrefs();
+        manager.add_unit(unit1.clone());
+        manager.add_unit(unit2.clone());
+
+        manager.activate_unit(String::from("test1")).unwrap();
+        assert_eq!(unit1.lock().unwrap().get_restart_policy_override(), None);
+
+        manager.start_unit(String::from("test2")).unwrap();
+        assert_eq!(unit2.lock().unwrap().get_restart_policy_override(), Some(RestartPolicy::DisabledTemporarily));
+
+        manager.stop_units();
+    }
+
+    #[test]
+    fn start_unit_should_save_runtime_state() {
+        let state_dir = std::env::temp_dir().join(format!("tsm-unitman-runtime-test-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&state_dir);
+        std::fs::create_dir_all(&state_dir).unwrap();
+
+        let mut manager = UnitManager::new();
+        let (unit1, _) = build_unitrefs();
+        manager.add_unit(unit1.clone());
+        manager.set_state_dir(state_dir.to_string_lossy().to_string());
+
+        manager.start_unit(String::from("test1")).unwrap();
+        let pid = unit1.lock().unwrap().get_pid();
+
+        let runtime_state = RuntimeState::load(&state_dir.join("runtime.toml")).unwrap();
+        assert!(pid.is_some());
+        assert_eq!(runtime_state.get("test1").unwrap().get_pid(), pid);
+
+        manager.stop_units();
+        let _ = std::fs::remove_dir_all(state_dir);
+    }
+
+    #[test]
+    fn mask_unit_should_persist_across_managers() {
+        let state_dir = std::env::temp_dir().join(format!("tsm-unitman-mask-test-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&state_dir);
+
+        let mut manager = UnitManager::new();
+        let (unit1, _) = build_unitrefs();
+        manager.add_unit(unit1.clone());
+        manager.set_state_dir(state_dir.to_string_lossy().to_string());
+
+        manager.mask_unit(String::from("test1"), false).unwrap();
+        assert_eq!(unit1.lock().unwrap().is_masked(), true);
+        assert_eq!(manager.start_unit(String::from("test1")).unwrap_err().root_cause(), &UnitError::Masked(String::from("test1")));
+        assert_eq!(manager.enable_unit(String::from("test1"), false).unwrap_err(), UnitError::Masked(String::from("test1")));
+
+        // simulates a restart of unitman
+        let mut manager = UnitManager::new();
+        let (unit1, _) = build_unitrefs();
+        manager.add_unit(unit1.clone());
+        manager.set_state_dir(state_dir.to_string_lossy().to_string());
+        manager.apply_unit_overrides();
+        assert_eq!(unit1.lock().unwrap().is_masked(), true);
+
+        manager.unmask_unit(String::from("test1")).unwrap();
+        manager.disable_unit(String::from("test1"), false).unwrap();
+        assert_eq!(unit1.lock().unwrap().is_enabled(), false);
+
+        let _ = std::fs::remove_dir_all(state_dir);
+    }
+}
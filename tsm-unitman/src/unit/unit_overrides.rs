@@ -0,0 +1,112 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct UnitOverride {
+    enabled: Option<bool>,
+    masked: Option<bool>,
+}
+
+
+impl UnitOverride {
+    pub fn get_enabled(&self) -> Option<bool> {
+        self.enabled
+    }
+
+    pub fn get_masked(&self) -> Option<bool> {
+        self.masked
+    }
+}
+
+
+/// Runtime changes to units which take precedence over the configuration file, so that they
+/// survive a restart of unitman. Stored as toml in the state directory.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct UnitOverrides {
+    #[serde(default)]
+    units: BTreeMap<String, UnitOverride>,
+}
+
+
+impl UnitOverrides {
+    /// A missing file means there are no overrides
+    pub fn load(path: &Path) -> Result<UnitOverrides, String> {
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(UnitOverrides::default()),
+            Err(error) => return Err(format!("Error reading unit overrides {:?}: {}", path, error)),
+        };
+
+        return match toml::from_str(&content) {
+            Ok(overrides) => Ok(overrides),
+            Err(error) => Err(format!("Error parsing unit overrides {:?}: {}", path, error)),
+        };
+    }
+
+    /// Writes to a temporary file first, so that a crash never leaves a truncated file behind
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let content = match toml::to_string(self) {
+            Ok(content) => content,
+            Err(error) => return Err(format!("Error serializing unit overrides: {}", error)),
+        };
+
+        if let Some(parent) = path.parent() {
+            if let Err(error) = fs::create_dir_all(parent) {
+                return Err(format!("Error creating state directory {:?}: {}", parent, error));
+            }
+        }
+
+        let temporary_path = path.with_extension("tmp");
+
+        if let Err(error) = fs::write(&temporary_path, content) {
+            return Err(format!("Error writing unit overrides {:?}: {}", temporary_path, error));
+        }
+
+        return match fs::rename(&temporary_path, path) {
+            Ok(_) => Ok(()),
+            Err(error) => Err(format!("Error writing unit overrides {:?}: {}", path, error)),
+        };
+    }
+
+    pub fn get(&self, name: &str) -> Option<&UnitOverride> {
+        self.units.get(name)
+    }
+
+    pub fn set_enabled(&mut self, name: String, enabled: bool) {
+        self.units.entry(name).or_default().enabled = Some(enabled);
+    }
+
+    pub fn set_masked(&mut self, name: String, masked: bool) {
+        self.units.entry(name).or_default().masked = Some(masked);
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn save_and_load_should_work() {
+        let dir = std::env::temp_dir().join(format!("tsm-unitman-overrides-test-{}", std::process::id()));
+        let path = dir.join("units.toml");
+        let _ = fs::remove_dir_all(&dir);
+
+        let mut overrides = UnitOverrides::load(&path).unwrap();
+        assert!(overrides.get("foo").is_none());
+
+        overrides.set_enabled(String::from("foo"), false);
+        overrides.set_masked(String::from("bar"), true);
+        overrides.save(&path).unwrap();
+
+        let overrides = UnitOverrides::load(&path).unwrap();
+        assert_eq!(overrides.get("foo").unwrap().get_enabled(), Some(false));
+        assert_eq!(overrides.get("foo").unwrap().get_masked(), None);
+        assert_eq!(overrides.get("bar").unwrap().get_masked(), Some(true));
+
+        let _ = fs::remove_dir_all(dir);
+    }
+}
@@ -11,6 +11,7 @@ pub enum UnitState {
     RunningButDegraded,
     Stopping,
     Stopped,
+    Failed,
 }
 
 
@@ -36,6 +37,7 @@ impl FromStr for UnitState {
             "running (degraded)" => Ok(UnitState::RunningButDegraded),
             "stopping" => Ok(UnitState::Stopping),
             "stopped" => Ok(UnitState::Stopped),
+            "failed" => Ok(UnitState::Failed),
             _ => Err(format!("Invalid unit state: {}", unit_state)),
         }
     }
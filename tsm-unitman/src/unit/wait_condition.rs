@@ -0,0 +1,117 @@
+use std::sync::TryLockError;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::unit::{ProbeState, Unit, UnitError, UnitRef, UnitState};
+
+
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum WaitCondition {
+    /// Running matches a running unit regardless of its health
+    State(UnitState),
+    LivenessProbeState(ProbeState),
+}
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum WaitOutcome {
+    Reached,
+    TimedOut,
+    /// The unit stopped or failed before the condition was met
+    Failed,
+}
+
+
+impl WaitCondition {
+    pub fn is_met(&self, unit: &Unit) -> bool {
+        return match self {
+            WaitCondition::State(UnitState::Running) => match unit.get_state() {
+                UnitState::Running | UnitState::RunningAndHealthy | UnitState::RunningButDegraded => true,
+                _ => false,
+            },
+            WaitCondition::State(state) => unit.get_state() == *state,
+            WaitCondition::LivenessProbeState(state) => unit.get_liveness_probe_state() == *state,
+        };
+    }
+
+    /// Polls the unit until the condition is met, the unit is stopped or failed, or the timeout
+    /// elapses. The unit is not locked in between, so that it can change meanwhile.
+    /// Returns the outcome along with the unit state and liveness probe state at that moment.
+    pub fn wait(&self, unit: &UnitRef, timeout: Duration) -> Result<(WaitOutcome, UnitState, ProbeState), UnitError> {
+        let deadline = Instant::now() + timeout;
+
+        loop {
+            match unit.try_lock() {
+                Ok(unit) => {
+                    let state = unit.get_state();
+                    let probe_state = unit.get_liveness_probe_state();
+
+                    if self.is_met(&unit) {
+                        return Ok((WaitOutcome::Reached, state, probe_state));
+                    }
+
+                    if state == UnitState::Stopped || state == UnitState::Failed {
+                        return Ok((WaitOutcome::Failed, state, probe_state));
+                    }
+
+                    if Instant::now() >= deadline {
+                        return Ok((WaitOutcome::TimedOut, state, probe_state));
+                    }
+                },
+                Err(TryLockError::WouldBlock) => {
+                    if Instant::now() >= deadline {
+                        return Err(UnitError::Busy(String::from("Unit stayed locked until the timeout elapsed")));
+                    }
+                },
+                Err(TryLockError::Poisoned(error)) => {
+                    return Err(UnitError::Busy(format!("Failed to lock unit: {}", error)));
+                },
+            }
+
+            thread::sleep(POLL_INTERVAL);
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+    use users::{get_current_gid, get_current_uid};
+    use super::*;
+    use crate::unit::RestartPolicy;
+
+    fn build_unit_ref(arguments: &str) -> UnitRef {
+        return Arc::new(Mutex::new(Unit::new(
+            String::from("test"),
+            String::from("sh"),
+            vec![String::from("-c"), String::from(arguments)],
+            RestartPolicy::Never,
+            get_current_uid(),
+            get_current_gid(),
+            true,
+        )));
+    }
+
+    #[test]
+    fn wait_should_reach_running_and_fail_on_stop() {
+        let unit = build_unit_ref("sleep 0.5");
+        unit.lock().unwrap().start().unwrap();
+
+        let condition = WaitCondition::State(UnitState::Running);
+        let (outcome, _, _) = condition.wait(&unit, Duration::from_secs(1)).unwrap();
+        assert_eq!(outcome, WaitOutcome::Reached);
+
+        let condition = WaitCondition::State(UnitState::RunningAndHealthy);
+        let (outcome, _, _) = condition.wait(&unit, Duration::from_millis(200)).unwrap();
+        assert_eq!(outcome, WaitOutcome::TimedOut);
+
+        unit.lock().unwrap().stop().unwrap();
+        let (outcome, state, _) = condition.wait(&unit, Duration::from_secs(1)).unwrap();
+        assert_eq!(outcome, WaitOutcome::Failed);
+        assert_eq!(state, UnitState::Stopped);
+    }
+}